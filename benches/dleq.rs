@@ -0,0 +1,55 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use curve25519_dalek::edwards::EdwardsPoint;
+use ecdsa_fun::fun::Point;
+
+use farcaster_core::crypto::dleq::DLEQProof;
+use farcaster_core::crypto::ProveCrossGroupDleq;
+use farcaster_core::swap::btcxmr::KeyManager;
+
+fn proofs(n: u32) -> Vec<(DLEQProof, EdwardsPoint, Point)> {
+    (0..n)
+        .map(|i| {
+            let mut key_manager = KeyManager::new([i as u8; 32], i).unwrap();
+            let (spend, encryption_key, proof) = key_manager.generate_proof().unwrap();
+            (
+                proof,
+                spend.point.decompress().unwrap(),
+                Point::from(encryption_key),
+            )
+        })
+        .collect()
+}
+
+fn verify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dleq");
+    group.sample_size(10);
+
+    let batch = proofs(16);
+    let (proof, spend, encryption_key) = &batch[0];
+    group.bench_function("verify", |b| {
+        b.iter(|| black_box(proof.verify(*spend, *encryption_key)))
+    });
+
+    for size in [1, 4, 16] {
+        let batch = &batch[..size];
+        group.bench_with_input(BenchmarkId::new("verify_each", size), batch, |b, batch| {
+            b.iter(|| {
+                batch
+                    .iter()
+                    .map(|(proof, spend, encryption_key)| {
+                        black_box(proof.verify(*spend, *encryption_key))
+                    })
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("verify_batch", size), batch, |b, batch| {
+            b.iter(|| black_box(DLEQProof::verify_batch(batch)))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, verify);
+criterion_main!(benches);
@@ -0,0 +1,397 @@
+//! Derive macros for the Farcaster core consensus encoding traits `Encodable`, `Decodable` and
+//! `CanonicalBytes`, re-exported by `farcaster_core::consensus`.
+//!
+//! Structs are encoded field by field in declaration order. Fields marked with `#[canonical]` are
+//! encoded as a length prefixed vector of their canonical bytes, all other fields must implement
+//! `Encodable` and `Decodable`. Enums are encoded with a one byte tag followed by the variant
+//! fields, each variant declares its tag with `#[consensus(tag = 0x01)]`.
+//!
+//! Adding `#[consensus(strict_encoding)]` on the type also implements `StrictEncode` and
+//! `StrictDecode` on top of the consensus encoding, as `impl_strict_encoding!` does.
+//!
+//! A field marked with `#[consensus(when = "<condition>")]` is encoded and decoded only if the
+//! condition, a constant expression evaluated in the impl, holds. Otherwise the field is not part
+//! of the encoding and is decoded as its `Default` value, e.g. a field used by some swap contexts
+//! only.
+//!
+//! ```ignore
+//! #[derive(Encodable, Decodable)]
+//! #[consensus(strict_encoding)]
+//! pub struct RevealProof<Ctx: Swap> {
+//!     pub swap_id: SwapId,
+//!     #[canonical]
+//!     pub proof: Ctx::Proof,
+//! }
+//! ```
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Expr, Fields, Lit, Meta, NestedMeta,
+    Result,
+};
+
+/// Derive `Encodable` on a struct or an enum, see the crate documentation for the encoding.
+#[proc_macro_derive(Encodable, attributes(canonical, consensus))]
+pub fn derive_encodable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    encodable(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Derive `Decodable` on a struct or an enum, see the crate documentation for the encoding.
+#[proc_macro_derive(Decodable, attributes(canonical, consensus))]
+pub fn derive_decodable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    decodable(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Derive `CanonicalBytes` from the consensus encoding of the type, the type must implement
+/// `Encodable` and `Decodable`.
+#[proc_macro_derive(CanonicalBytes)]
+pub fn derive_canonical_bytes(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    canonical_bytes(&input).into()
+}
+
+// Options set with `#[consensus(...)]` on the type, on an enum variant or on a field.
+#[derive(Default)]
+struct ConsensusAttrs {
+    strict_encoding: bool,
+    tag: Option<u8>,
+    when: Option<Expr>,
+}
+
+fn consensus_attrs(attrs: &[Attribute]) -> Result<ConsensusAttrs> {
+    let mut res = ConsensusAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("consensus")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new(meta.span(), "expected #[consensus(...)]")),
+        };
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("strict_encoding") => {
+                    res.strict_encoding = true;
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("tag") =>
+                {
+                    match &name_value.lit {
+                        Lit::Int(int) => res.tag = Some(int.base10_parse()?),
+                        lit => return Err(Error::new(lit.span(), "expected a u8 tag")),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(name_value))
+                    if name_value.path.is_ident("when") =>
+                {
+                    match &name_value.lit {
+                        Lit::Str(condition) => res.when = Some(condition.parse()?),
+                        lit => return Err(Error::new(lit.span(), "expected a condition string")),
+                    }
+                }
+                _ => return Err(Error::new(nested.span(), "unknown consensus attribute")),
+            }
+        }
+    }
+    Ok(res)
+}
+
+fn is_canonical(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("canonical"))
+}
+
+// Names used to bind the fields when matching on `self`, the field names for named fields.
+fn bindings(fields: &Fields) -> Vec<Ident> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => ident.clone(),
+            None => format_ident!("__field{}", i),
+        })
+        .collect()
+}
+
+// Pattern destructuring the fields into their bindings.
+fn pattern(fields: &Fields, bindings: &[Ident]) -> TokenStream2 {
+    match fields {
+        Fields::Named(_) => quote! { { #(#bindings),* } },
+        Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+        Fields::Unit => quote! {},
+    }
+}
+
+// Statements encoding each field from its binding, a reference to the field.
+fn encode_fields(fields: &Fields, bindings: &[Ident]) -> Result<Vec<TokenStream2>> {
+    fields
+        .iter()
+        .zip(bindings)
+        .map(|(field, binding)| {
+            let encode = if is_canonical(&field.attrs) {
+                quote! {
+                    __len += ::farcaster_core::consensus::Encodable::consensus_encode(
+                        &::farcaster_core::consensus::CanonicalBytes::as_canonical_bytes(#binding),
+                        __encoder,
+                    )?;
+                }
+            } else {
+                quote! {
+                    __len += ::farcaster_core::consensus::Encodable::consensus_encode(
+                        #binding,
+                        __encoder,
+                    )?;
+                }
+            };
+            Ok(match consensus_attrs(&field.attrs)?.when {
+                Some(condition) => quote! { if #condition { #encode } },
+                None => encode,
+            })
+        })
+        .collect()
+}
+
+// Constructor decoding each field in declaration order with the `Decodable` method `decode`.
+fn decode_fields(fields: &Fields, decode: &Ident) -> Result<TokenStream2> {
+    let values = fields
+        .iter()
+        .map(|field| {
+            let ty = &field.ty;
+            let value = if is_canonical(&field.attrs) {
+                quote! {
+                    <#ty as ::farcaster_core::consensus::CanonicalBytes>::from_canonical_bytes(
+                        <::std::vec::Vec<u8> as ::farcaster_core::consensus::Decodable>::#decode(
+                            __decoder,
+                        )?
+                        .as_ref(),
+                    )?
+                }
+            } else {
+                quote! {
+                    <#ty as ::farcaster_core::consensus::Decodable>::#decode(__decoder)?
+                }
+            };
+            Ok(match consensus_attrs(&field.attrs)?.when {
+                Some(condition) => quote! {
+                    if #condition { #value } else { <#ty as ::std::default::Default>::default() }
+                },
+                None => value,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(match fields {
+        Fields::Named(_) => {
+            let names = fields.iter().map(|field| &field.ident);
+            quote! { { #(#names: #values),* } }
+        }
+        Fields::Unnamed(_) => quote! { ( #(#values),* ) },
+        Fields::Unit => quote! {},
+    })
+}
+
+// Return the variants of an enum with their tags, tags must be declared and unique.
+fn tagged_variants(data: &syn::DataEnum) -> Result<Vec<(&syn::Variant, u8)>> {
+    let mut variants: Vec<(&syn::Variant, u8)> = Vec::new();
+    for variant in data.variants.iter() {
+        let tag = consensus_attrs(&variant.attrs)?.tag.ok_or_else(|| {
+            Error::new(
+                variant.span(),
+                "missing variant tag, add #[consensus(tag = ...)]",
+            )
+        })?;
+        if variants.iter().any(|(_, other)| *other == tag) {
+            return Err(Error::new(variant.span(), "duplicate variant tag"));
+        }
+        variants.push((variant, tag));
+    }
+    Ok(variants)
+}
+
+fn encodable(input: &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let attrs = consensus_attrs(&input.attrs)?;
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let bindings = bindings(&data.fields);
+            let pattern = pattern(&data.fields, &bindings);
+            let encode = encode_fields(&data.fields, &bindings)?;
+            quote! {
+                let Self #pattern = self;
+                let mut __len = 0;
+                #(#encode)*
+                Ok(__len)
+            }
+        }
+        Data::Enum(data) => {
+            let arms = tagged_variants(data)?
+                .into_iter()
+                .map(|(variant, tag)| {
+                    let ident = &variant.ident;
+                    let bindings = bindings(&variant.fields);
+                    let pattern = pattern(&variant.fields, &bindings);
+                    let encode = encode_fields(&variant.fields, &bindings)?;
+                    Ok(quote! {
+                        Self::#ident #pattern => {
+                            let mut __len =
+                                ::farcaster_core::consensus::Encodable::consensus_encode(
+                                    &#tag,
+                                    __encoder,
+                                )?;
+                            #(#encode)*
+                            Ok(__len)
+                        }
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "unions cannot derive Encodable",
+            ))
+        }
+    };
+
+    let strict_encode = if attrs.strict_encoding {
+        quote! {
+            impl #impl_generics ::strict_encoding::StrictEncode for #name #ty_generics #where_clause {
+                fn strict_encode<E: ::std::io::Write>(
+                    &self,
+                    mut e: E,
+                ) -> ::std::result::Result<usize, ::strict_encoding::Error> {
+                    ::farcaster_core::consensus::Encodable::consensus_encode(self, &mut e)
+                        .map_err(::strict_encoding::Error::from)
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::farcaster_core::consensus::Encodable for #name #ty_generics #where_clause {
+            #[allow(unused_mut, unused_variables)]
+            fn consensus_encode<W: ::std::io::Write>(
+                &self,
+                __encoder: &mut W,
+            ) -> ::std::result::Result<usize, ::std::io::Error> {
+                #body
+            }
+        }
+
+        #strict_encode
+    })
+}
+
+fn decodable(input: &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let attrs = consensus_attrs(&input.attrs)?;
+
+    // The same body decodes with `consensus_decode` and with `consensus_decode_with_limits`, the
+    // latter forwarding the limited reader to the fields.
+    let body = |decode: &Ident| -> Result<TokenStream2> {
+        Ok(match &input.data {
+            Data::Struct(data) => {
+                let fields = decode_fields(&data.fields, decode)?;
+                quote! { Ok(Self #fields) }
+            }
+            Data::Enum(data) => {
+                let arms = tagged_variants(data)?
+                    .into_iter()
+                    .map(|(variant, tag)| {
+                        let ident = &variant.ident;
+                        let fields = decode_fields(&variant.fields, decode)?;
+                        Ok(quote! { #tag => Ok(Self::#ident #fields), })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                quote! {
+                    match <u8 as ::farcaster_core::consensus::Decodable>::consensus_decode(__decoder)? {
+                        #(#arms)*
+                        _ => Err(::farcaster_core::consensus::Error::UnknownType),
+                    }
+                }
+            }
+            Data::Union(_) => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "unions cannot derive Decodable",
+                ))
+            }
+        })
+    };
+    let decode = body(&format_ident!("consensus_decode"))?;
+    let decode_with_limits = body(&format_ident!("consensus_decode_with_limits"))?;
+
+    let strict_decode = if attrs.strict_encoding {
+        quote! {
+            impl #impl_generics ::strict_encoding::StrictDecode for #name #ty_generics #where_clause {
+                fn strict_decode<D: ::std::io::Read>(
+                    d: D,
+                ) -> ::std::result::Result<Self, ::strict_encoding::Error> {
+                    let limits = ::farcaster_core::consensus::DecodingLimits::default();
+                    ::farcaster_core::consensus::decode_with_limits(d, limits)
+                        .map_err(|e| ::strict_encoding::Error::DataIntegrityError(e.to_string()))
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::farcaster_core::consensus::Decodable for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn consensus_decode<D: ::std::io::Read>(
+                __decoder: &mut D,
+            ) -> ::std::result::Result<Self, ::farcaster_core::consensus::Error> {
+                #decode
+            }
+
+            #[allow(unused_variables)]
+            fn consensus_decode_with_limits<R: ::std::io::Read>(
+                __decoder: &mut ::farcaster_core::consensus::LimitedReader<R>,
+            ) -> ::std::result::Result<Self, ::farcaster_core::consensus::Error> {
+                #decode_with_limits
+            }
+        }
+
+        #strict_decode
+    })
+}
+
+fn canonical_bytes(input: &DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::farcaster_core::consensus::CanonicalBytes for #name #ty_generics #where_clause {
+            fn as_canonical_bytes(&self) -> ::std::vec::Vec<u8> {
+                let mut bytes = ::std::vec::Vec::new();
+                ::farcaster_core::consensus::Encodable::consensus_encode(self, &mut bytes)
+                    .expect("Encoding in a vector does not fail");
+                bytes
+            }
+
+            fn from_canonical_bytes(
+                bytes: &[u8],
+            ) -> ::std::result::Result<Self, ::farcaster_core::consensus::Error> {
+                ::farcaster_core::consensus::deserialize(bytes)
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Decode arbitrary bytes as a cross-group discrete logarithm equality proof and verify it against
+//! arbitrary keys, neither step must panic.
+//!
+//! Input: `monero public spend key (32 bytes) | bitcoin public key (33 bytes) | proof`
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use farcaster_core::consensus::CanonicalBytes;
+use farcaster_core::crypto::dleq::{DLEQProof, DLEQProofFormat, DLEQ_PROOF_COMPACT_VERSION};
+use farcaster_core::crypto::ProveCrossGroupDleq;
+use farcaster_core::swap::btcxmr::KeyManager;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(proof) = DLEQProof::from_canonical_bytes(data) {
+        // a decoded proof must re-encode to the same bytes in its format
+        let format = match data[0] {
+            DLEQ_PROOF_COMPACT_VERSION => DLEQProofFormat::Compact,
+            _ => DLEQProofFormat::Legacy,
+        };
+        assert_eq!(proof.to_bytes_with_format(format), data);
+    }
+
+    if data.len() < 65 {
+        return;
+    }
+    let (keys, proof) = data.split_at(65);
+    let public_spend = match monero::PublicKey::from_slice(&keys[..32]) {
+        Ok(key) => key,
+        Err(_) => return,
+    };
+    let encryption_key = match bitcoin::secp256k1::PublicKey::from_slice(&keys[32..]) {
+        Ok(key) => key,
+        Err(_) => return,
+    };
+    if let Ok(proof) = DLEQProof::from_canonical_bytes(proof) {
+        let mut key_manager = KeyManager::new([0u8; 32], 0).expect("Valid key manager");
+        let _ = key_manager.verify_proof(&public_spend, &encryption_key, proof);
+    }
+});
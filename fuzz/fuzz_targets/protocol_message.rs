@@ -0,0 +1,54 @@
+//! Decode arbitrary bytes as a protocol message payload and as a framed protocol message under
+//! the decoding limits, no step must panic and decoded messages must re-encode deterministically.
+//!
+//! Input: `message type selector (1 byte) | message`
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use farcaster_core::consensus::{
+    deserialize_with_limits, serialize, Decodable, DecodingLimits, Encodable,
+};
+use farcaster_core::protocol_message::{
+    Abort, BuyProcedureSignature, CommitAliceParameters, CommitBobParameters, CoreArbitratingSetup,
+    MessageFrame, RefundProcedureSignatures, RevealAliceParameters, RevealBobParameters,
+    RevealProof, TakerAmount,
+};
+use farcaster_core::swap::btcxmr::BtcXmr;
+
+use std::fmt::Debug;
+
+fn decode<T: Decodable + Encodable + Debug>(data: &[u8]) {
+    if let Ok(msg) = deserialize_with_limits::<T>(data, DecodingLimits::default()) {
+        // re-encoding a decoded message is stable, the input itself may not be canonical
+        let bytes = serialize(&msg);
+        let msg = deserialize_with_limits::<T>(&bytes, DecodingLimits::default())
+            .expect("Re-encoded message must decode");
+        assert_eq!(serialize(&msg), bytes);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (selector, msg) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    match selector % 12 {
+        0 => decode::<CommitAliceParameters<BtcXmr>>(msg),
+        1 => decode::<CommitBobParameters<BtcXmr>>(msg),
+        2 => decode::<RevealAliceParameters<BtcXmr>>(msg),
+        3 => decode::<RevealBobParameters<BtcXmr>>(msg),
+        4 => decode::<RevealProof<BtcXmr>>(msg),
+        5 => decode::<TakerAmount<BtcXmr>>(msg),
+        6 => decode::<CoreArbitratingSetup<BtcXmr>>(msg),
+        7 => decode::<RefundProcedureSignatures<BtcXmr>>(msg),
+        8 => decode::<BuyProcedureSignature<BtcXmr>>(msg),
+        9 => decode::<Abort>(msg),
+        10 => decode::<MessageFrame<BtcXmr>>(msg),
+        _ => {
+            // decode a stream of frames until the input is exhausted or invalid
+            let mut reader = std::io::Cursor::new(msg);
+            while MessageFrame::<BtcXmr>::decode_frame(&mut reader).is_ok() {}
+        }
+    }
+});
@@ -0,0 +1,19 @@
+//! Decode arbitrary bytes as a syncer event under the decoding limits, decoding must not panic
+//! and decoded events must re-encode deterministically.
+//!
+//! Input: `event`
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use farcaster_core::consensus::{deserialize_with_limits, serialize, DecodingLimits};
+use farcaster_core::syncer::Event;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(event) = deserialize_with_limits::<Event>(data, DecodingLimits::default()) {
+        let bytes = serialize(&event);
+        let event = deserialize_with_limits::<Event>(&bytes, DecodingLimits::default())
+            .expect("Re-encoded event must decode");
+        assert_eq!(serialize(&event), bytes);
+    }
+});
@@ -0,0 +1,19 @@
+//! Decode arbitrary bytes as a syncer task under the decoding limits, decoding must not panic
+//! and decoded tasks must re-encode deterministically.
+//!
+//! Input: `task`
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use farcaster_core::consensus::{deserialize_with_limits, serialize, DecodingLimits};
+use farcaster_core::syncer::Task;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(task) = deserialize_with_limits::<Task>(data, DecodingLimits::default()) {
+        let bytes = serialize(&task);
+        let task = deserialize_with_limits::<Task>(&bytes, DecodingLimits::default())
+            .expect("Re-encoded task must decode");
+        assert_eq!(serialize(&task), bytes);
+    }
+});
@@ -87,14 +87,35 @@ impl<S: Strategy> Asset for Bitcoin<S> {
     fn to_u32(&self) -> u32 {
         0x80000000
     }
+
+    fn dust_limit() -> Amount {
+        // Dust limit of a P2WSH output, such as the lock output, with the default dust relay fee
+        Amount::from_sat(330)
+    }
 }
 
 impl<S: Strategy> blockchain::Address for Bitcoin<S> {
     type Address = Address;
+
+    fn is_valid_address(address: &Address, network: blockchain::Network) -> bool {
+        // Regtest legacy addresses share the testnet prefixes and are parsed as testnet addresses
+        match network {
+            blockchain::Network::Mainnet => address.network == bitcoin::Network::Bitcoin,
+            blockchain::Network::Testnet => address.network == bitcoin::Network::Testnet,
+            blockchain::Network::Local => matches!(
+                address.network,
+                bitcoin::Network::Regtest | bitcoin::Network::Testnet
+            ),
+        }
+    }
 }
 
 impl<S: Strategy> Timelock for Bitcoin<S> {
     type Timelock = timelock::CSVTimelock;
+
+    fn is_valid_timelock(timelock: &timelock::CSVTimelock) -> bool {
+        timelock.is_bip68_valid()
+    }
 }
 
 impl<S: Strategy> Onchain for Bitcoin<S> {
@@ -1,6 +1,19 @@
+use crate::blockchain::{BaseUnit, Rounding};
 use crate::consensus::{self, CanonicalBytes};
 use bitcoin::Amount;
 
+impl BaseUnit for Amount {
+    const ROUNDING: Rounding = Rounding::Down;
+
+    fn as_base_units(&self) -> u64 {
+        self.as_sat()
+    }
+
+    fn from_base_units(units: u64) -> Self {
+        Amount::from_sat(units)
+    }
+}
+
 impl CanonicalBytes for Amount {
     fn as_canonical_bytes(&self) -> Vec<u8> {
         bitcoin::consensus::encode::serialize(&self.as_sat())
@@ -7,7 +7,7 @@ use std::str::FromStr;
 
 use crate::bitcoin::{Bitcoin, BitcoinTaproot, Btc, Strategy};
 use crate::consensus::{self, CanonicalBytes};
-use crate::crypto::{Keys, SharedKeyId, SharedSecretKeys, Signatures};
+use crate::crypto::{Keys, SharedKeyId, SharedSecretKeys, Signatures, REDACTED};
 //use crate::role::Arbitrating;
 
 use bitcoin::hashes::sha256d::Hash as Sha256dHash;
@@ -15,6 +15,7 @@ use bitcoin::secp256k1::{
     constants::SECRET_KEY_SIZE,
     schnorrsig::{KeyPair, PublicKey, Signature},
 };
+use zeroize::Zeroize;
 
 /// Inner type for the Taproot strategy with on-chain scripts.
 #[derive(Clone, Debug, Copy, Eq, PartialEq)]
@@ -81,10 +82,29 @@ impl CanonicalBytes for PublicKey {
     }
 }
 
-/// Schnorr secret key shareable over the network if needed by the protocol.
-#[derive(Clone, Debug, Copy, Eq, PartialEq)]
+/// Schnorr secret key shareable over the network if needed by the protocol. The key is zeroized
+/// on drop and redacted from the `Debug` output.
+#[derive(Clone, Eq, PartialEq)]
 pub struct SecretSharedKey([u8; SECRET_KEY_SIZE]);
 
+impl Zeroize for SecretSharedKey {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecretSharedKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretSharedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("SecretSharedKey").field(&REDACTED).finish()
+    }
+}
+
 impl SecretSharedKey {
     /// Return a slice to the secret key bytes.
     pub fn as_bytes(&self) -> &[u8] {
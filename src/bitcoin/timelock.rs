@@ -16,6 +16,10 @@ impl FromStr for CSVTimelock {
     }
 }
 
+/// Maximum number of blocks a relative timelock can enforce as defined in BIP68, the value is
+/// encoded in the 16 low bits of the sequence.
+pub const BIP68_MAX_BLOCKS: u32 = 0xffff;
+
 /// An `OP_CSV` value (32-bits integer) to use in transactions and scripts.
 #[derive(PartialEq, Eq, PartialOrd, Clone, Debug, Copy, Display)]
 #[display("{0} blocks")]
@@ -32,6 +36,12 @@ impl CSVTimelock {
         self.0
     }
 
+    /// Return `true` if the value is a relative timelock in blocks enforceable as defined in
+    /// BIP68, i.e. the disable and type flags are not set and the value fits in 16 bits.
+    pub fn is_bip68_valid(&self) -> bool {
+        self.0 <= BIP68_MAX_BLOCKS
+    }
+
     /// Return the value of nSequence that disable `CHECK_SEQUENCE_VERIFY`.
     pub fn disable() -> u32 {
         (1 << 31) as u32
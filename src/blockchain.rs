@@ -5,6 +5,7 @@
 //! 44](https://github.com/satoshilabs/slips/blob/master/slip-0044.md#slip-0044--registered-coin-types-for-bip-0044)
 //! or must not conflict with any registered entity.
 
+use std::convert::TryFrom;
 use std::error;
 use std::fmt::{self, Debug, Display};
 use std::io;
@@ -25,20 +26,32 @@ pub enum Blockchain {
 pub trait Address {
     /// Defines the address format for the arbitrating blockchain.
     type Address: Clone + Debug + CanonicalBytes;
+
+    /// Return `true` if the address can be used on the given swap network. Default implementation
+    /// accepts all addresses.
+    fn is_valid_address(_address: &Self::Address, _network: Network) -> bool {
+        true
+    }
 }
 
 /// Defines the type for a blockchain timelock, this type is used when manipulating transactions
 /// and is carried in the [`Offer`](crate::negotiation::Offer) to fix the two timelocks.
 pub trait Timelock {
     /// Defines the type of timelock used for the arbitrating transactions.
-    type Timelock: Copy + PartialEq + Eq + Debug + Display + CanonicalBytes;
+    type Timelock: Copy + PartialEq + Eq + PartialOrd + Debug + Display + CanonicalBytes;
+
+    /// Return `true` if the timelock can be enforced on-chain, i.e. the value is within the
+    /// blockchain consensus limits. Default implementation accepts all timelocks.
+    fn is_valid_timelock(_timelock: &Self::Timelock) -> bool {
+        true
+    }
 }
 
 /// Defines the asset identifier for a blockchain and its associated asset unit type, it is carried
 /// in the [`Offer`](crate::negotiation::Offer) to fix exchanged amounts.
 pub trait Asset: Copy + Debug {
     /// Type for the traded asset unit for a blockchain.
-    type AssetUnit: Copy + Eq + Debug + Display + CanonicalBytes;
+    type AssetUnit: Copy + Eq + Debug + Display + CanonicalBytes + BaseUnit;
 
     /// Parse an 32 bits identifier as defined in [SLIP
     /// 44](https://github.com/satoshilabs/slips/blob/master/slip-0044.md#slip-0044--registered-coin-types-for-bip-0044)
@@ -48,6 +61,61 @@ pub trait Asset: Copy + Debug {
     /// Return the 32 bits identifier for the blockchain as defined in [SLIP
     /// 44](https://github.com/satoshilabs/slips/blob/master/slip-0044.md#slip-0044--registered-coin-types-for-bip-0044).
     fn to_u32(&self) -> u32;
+
+    /// Return the smallest amount that can be transferred on-chain, smaller amounts are considered
+    /// as dust. Default implementation returns one base unit.
+    fn dust_limit() -> Self::AssetUnit {
+        Self::AssetUnit::from_base_units(1)
+    }
+}
+
+/// Defines how an amount is rounded when an exact computation, e.g. applying a price, falls
+/// between two base units of an asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[display(Debug)]
+pub enum Rounding {
+    /// Round towards zero, the result is never greater than the exact value.
+    Down,
+    /// Round away from zero, the result is never smaller than the exact value.
+    Up,
+    /// Round to the nearest base unit, ties are rounded up.
+    Nearest,
+}
+
+impl Rounding {
+    /// Compute `amount * numerator / denominator` with exact integer arithmetic and round the
+    /// result with the rounding rule. Returns `None` if the denominator is zero or if the result
+    /// overflows a `u64`.
+    pub fn apply(&self, amount: u64, numerator: u64, denominator: u64) -> Option<u64> {
+        if denominator == 0 {
+            return None;
+        }
+        let product = amount as u128 * numerator as u128;
+        let denominator = denominator as u128;
+        let (quotient, remainder) = (product / denominator, product % denominator);
+        let res = match self {
+            Self::Down => quotient,
+            Self::Up if remainder > 0 => quotient + 1,
+            Self::Up => quotient,
+            Self::Nearest if remainder * 2 >= denominator => quotient + 1,
+            Self::Nearest => quotient,
+        };
+        u64::try_from(res).ok()
+    }
+}
+
+/// Defines the smallest indivisible unit of an [`Asset::AssetUnit`], used to compute exact prices
+/// between assets without relying on floating point arithmetic.
+pub trait BaseUnit: Sized {
+    /// The rounding rule applied when an amount of this unit is computed from a price.
+    const ROUNDING: Rounding;
+
+    /// Return the amount expressed in the smallest indivisible unit of the asset, e.g. satoshis
+    /// for Bitcoin.
+    fn as_base_units(&self) -> u64;
+
+    /// Create an amount from a number of the smallest indivisible unit of the asset.
+    fn from_base_units(units: u64) -> Self;
 }
 
 /// Defines the types a blockchain needs to interact on-chain, i.e. the transaction exchanged
@@ -368,6 +436,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn rounding_rules() {
+        // 10 * 2 / 3 = 6.666...
+        assert_eq!(Rounding::Down.apply(10, 2, 3), Some(6));
+        assert_eq!(Rounding::Up.apply(10, 2, 3), Some(7));
+        assert_eq!(Rounding::Nearest.apply(10, 2, 3), Some(7));
+        // 10 * 1 / 4 = 2.5
+        assert_eq!(Rounding::Down.apply(10, 1, 4), Some(2));
+        assert_eq!(Rounding::Up.apply(10, 1, 4), Some(3));
+        assert_eq!(Rounding::Nearest.apply(10, 1, 4), Some(3));
+        // exact results are not affected
+        assert_eq!(Rounding::Up.apply(9, 1, 3), Some(3));
+        assert_eq!(Rounding::Down.apply(1, 1, 0), None);
+        assert_eq!(Rounding::Down.apply(u64::MAX, 2, 1), None);
+    }
+
     #[test]
     fn parse_network() {
         for s in ["Mainnet", "mainnet", "Testnet", "testnet", "Local", "local"].iter() {
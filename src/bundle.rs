@@ -7,41 +7,138 @@
 //! and forward it to its client, completing a full round of communication between between Alice
 //! and Bob clients.
 
-use std::io;
-
-use crate::blockchain::{Address, Fee, FeeStrategy, Onchain, Timelock};
-use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
-use crate::crypto::{Keys, SharedKeyId, SharedSecretKeys, Signatures, TaggedElement};
+use thiserror::Error;
+
+use crate::blockchain::{Address, Fee, FeeStrategy, Network, Onchain, Timelock};
+use crate::consensus::{Decodable, Encodable};
+use crate::crypto::{
+    CommitmentField, Keys, SharedKeyId, SharedSecretKeys, Signatures, TaggedElement,
+};
+use crate::negotiation::PublicOffer;
 use crate::protocol_message;
 use crate::swap::Swap;
 
-#[derive(Debug, Clone, Display)]
-#[display(Debug)]
-pub struct Proof<Ctx: Swap> {
-    pub proof: Ctx::Proof,
+/// Errors raised when validating the parameters of a participant against the agreed public offer.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// The cancel timelock does not match the public offer.
+    #[error("The cancel timelock does not match the public offer")]
+    CancelTimelockMismatch,
+    /// The punish timelock does not match the public offer.
+    #[error("The punish timelock does not match the public offer")]
+    PunishTimelockMismatch,
+    /// The fee strategy does not match the public offer.
+    #[error("The fee strategy does not match the public offer")]
+    FeeStrategyMismatch,
+    /// The address is not valid on the network of the public offer.
+    #[error("The address is not valid on {0}")]
+    InvalidAddressNetwork(Network),
+    /// The public key is not valid, e.g. it is the identity point.
+    #[error("The public key of {0} is invalid")]
+    InvalidPublicKey(CommitmentField),
+    /// The same public key is used for two different fields.
+    #[error("The public key of {1} is the same as {0}")]
+    DuplicatePublicKey(CommitmentField, CommitmentField),
+    /// A public key of Alice is also used by Bob, the first field is Alice's and the second Bob's.
+    #[error("Alice's public key of {0} is the same as Bob's public key of {1}")]
+    CounterpartyPublicKey(CommitmentField, CommitmentField),
+}
+
+// Validates the swap terms carried in the parameters, when present, and the address network. The
+// reveal messages do not carry the timelocks and the fee strategy, the parameters received from
+// the counterparty have none and the public offer is the only source for them.
+fn validate_terms<Ctx: Swap>(
+    cancel_timelock: &Option<<Ctx::Ar as Timelock>::Timelock>,
+    punish_timelock: &Option<<Ctx::Ar as Timelock>::Timelock>,
+    fee_strategy: &Option<FeeStrategy<<Ctx::Ar as Fee>::FeeUnit>>,
+    address: &<Ctx::Ar as Address>::Address,
+    public_offer: &PublicOffer<Ctx>,
+) -> Result<(), Error> {
+    let offer = &public_offer.offer;
+    if matches!(cancel_timelock, Some(timelock) if *timelock != offer.cancel_timelock) {
+        return Err(Error::CancelTimelockMismatch);
+    }
+    if matches!(punish_timelock, Some(timelock) if *timelock != offer.punish_timelock) {
+        return Err(Error::PunishTimelockMismatch);
+    }
+    if matches!(fee_strategy, Some(strategy) if *strategy != offer.fee_strategy) {
+        return Err(Error::FeeStrategyMismatch);
+    }
+    if !<Ctx::Ar as Address>::is_valid_address(address, offer.network) {
+        return Err(Error::InvalidAddressNetwork(offer.network));
+    }
+    Ok(())
+}
+
+// Pairs of fields allowed to carry the same public key, the buy and cancel keys and the refund
+// and punish keys can be derived from the same path.
+const SHARED_PUBLIC_KEYS: [(CommitmentField, CommitmentField); 2] = [
+    (CommitmentField::Buy, CommitmentField::Cancel),
+    (CommitmentField::Refund, CommitmentField::Punish),
+];
+
+// Validates each public key and ensures that no public key is used twice, except for the pairs
+// listed in `SHARED_PUBLIC_KEYS`.
+fn validate_keys<K: Keys>(keys: &[(CommitmentField, &K::PublicKey)]) -> Result<(), Error> {
+    for (i, (field, key)) in keys.iter().enumerate() {
+        if !K::is_valid_public_key(key) {
+            return Err(Error::InvalidPublicKey(*field));
+        }
+        if let Some((other, _)) = keys[..i].iter().find(|(other, other_key)| {
+            other_key == key && !SHARED_PUBLIC_KEYS.contains(&(*other, *field))
+        }) {
+            return Err(Error::DuplicatePublicKey(*other, *field));
+        }
+    }
+    Ok(())
 }
 
-impl<Ctx> Encodable for Proof<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        self.proof.as_canonical_bytes().consensus_encode(s)
+// Ensures that no public key is used by both participants.
+fn validate_distinct_keys<K: Keys>(
+    alice_keys: &[(CommitmentField, &K::PublicKey)],
+    bob_keys: &[(CommitmentField, &K::PublicKey)],
+) -> Result<(), Error> {
+    for (field, key) in alice_keys {
+        if let Some((other, _)) = bob_keys.iter().find(|(_, other_key)| other_key == key) {
+            return Err(Error::CounterpartyPublicKey(*field, *other));
+        }
     }
+    Ok(())
 }
 
-impl<Ctx> Decodable for Proof<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            proof: Ctx::Proof::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-        })
-    }
+/// Validate that Alice and Bob do not share any arbitrating or accordant public key, e.g. a
+/// counterparty replaying our own keys. Used by [`Alice`] and [`Bob`] once the parameters of both
+/// participants are known.
+///
+/// [`Alice`]: crate::role::Alice
+/// [`Bob`]: crate::role::Bob
+pub fn validate_participants<Ctx: Swap>(
+    alice_parameters: &AliceParameters<Ctx>,
+    bob_parameters: &BobParameters<Ctx>,
+) -> Result<(), Error> {
+    validate_distinct_keys::<Ctx::Ar>(
+        &alice_parameters.arbitrating_keys(),
+        &bob_parameters.arbitrating_keys(),
+    )?;
+    validate_distinct_keys::<Ctx::Ac>(
+        &alice_parameters.accordant_keys(),
+        &bob_parameters.accordant_keys(),
+    )
 }
 
-impl_strict_encoding!(Proof<Ctx>, Ctx: Swap);
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
+pub struct Proof<Ctx: Swap> {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
+    pub proof: Ctx::Proof,
+}
 
 /// Alice parameters required for the initialization step of a swap and used to generate the
 /// [`CommitAliceParameters`] and [`RevealAliceParameters`] protocol messages in the commit/reveal
@@ -49,87 +146,103 @@ impl_strict_encoding!(Proof<Ctx>, Ctx: Swap);
 ///
 /// [`CommitAliceParameters`]: protocol_message::CommitAliceParameters
 /// [`RevealAliceParameters`]: protocol_message::RevealAliceParameters
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct AliceParameters<Ctx: Swap> {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy: <Ctx::Ar as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub cancel: <Ctx::Ar as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund: <Ctx::Ar as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub punish: <Ctx::Ar as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub adaptor: <Ctx::Ar as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_arbitrating_keys: Vec<TaggedElement<u16, <Ctx::Ar as Keys>::PublicKey>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub arbitrating_shared_keys:
         Vec<TaggedElement<SharedKeyId, <Ctx::Ar as SharedSecretKeys>::SharedSecretKey>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub spend: <Ctx::Ac as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_accordant_keys: Vec<TaggedElement<u16, <Ctx::Ac as Keys>::PublicKey>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub accordant_shared_keys:
         Vec<TaggedElement<SharedKeyId, <Ctx::Ac as SharedSecretKeys>::SharedSecretKey>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub destination_address: <Ctx::Ar as Address>::Address,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::option"))]
     pub cancel_timelock: Option<<Ctx::Ar as Timelock>::Timelock>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::option"))]
     pub punish_timelock: Option<<Ctx::Ar as Timelock>::Timelock>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::option"))]
     pub fee_strategy: Option<FeeStrategy<<Ctx::Ar as Fee>::FeeUnit>>,
 }
 
-impl<Ctx> Encodable for AliceParameters<Ctx>
+impl<Ctx> AliceParameters<Ctx>
 where
     Ctx: Swap,
 {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.buy.as_canonical_bytes().consensus_encode(s)?;
-        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
-        len += self.refund.as_canonical_bytes().consensus_encode(s)?;
-        len += self.punish.as_canonical_bytes().consensus_encode(s)?;
-        len += self.adaptor.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_arbitrating_keys.consensus_encode(s)?;
-        len += self.arbitrating_shared_keys.consensus_encode(s)?;
-        len += self.spend.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_accordant_keys.consensus_encode(s)?;
-        len += self.accordant_shared_keys.consensus_encode(s)?;
-        len += self
-            .destination_address
-            .as_canonical_bytes()
-            .consensus_encode(s)?;
-        len += self.cancel_timelock.consensus_encode(s)?;
-        len += self.punish_timelock.consensus_encode(s)?;
-        Ok(len + self.fee_strategy.consensus_encode(s)?)
+    /// Validate the parameters against the agreed public offer. The timelocks and fee strategy,
+    /// when present, must match the offer, the destination address must be valid on the offer's
+    /// network, and the public keys must be valid and distinct, buy and cancel or refund and
+    /// punish keys may be shared. Parameters built from [`RevealAliceParameters`] carry no
+    /// timelocks nor fee strategy, the offer is the only source for them.
+    ///
+    /// [`RevealAliceParameters`]: protocol_message::RevealAliceParameters
+    pub fn validate_against(&self, public_offer: &PublicOffer<Ctx>) -> Result<(), Error> {
+        validate_terms(
+            &self.cancel_timelock,
+            &self.punish_timelock,
+            &self.fee_strategy,
+            &self.destination_address,
+            public_offer,
+        )?;
+        validate_keys::<Ctx::Ar>(&self.arbitrating_keys())?;
+        validate_keys::<Ctx::Ac>(&self.accordant_keys())
     }
-}
 
-impl<Ctx> Decodable for AliceParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            buy: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            cancel: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            refund: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            punish: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            adaptor: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            extra_arbitrating_keys: Decodable::consensus_decode(d)?,
-            arbitrating_shared_keys: Decodable::consensus_decode(d)?,
-            spend: <Ctx::Ac as Keys>::PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            extra_accordant_keys: Decodable::consensus_decode(d)?,
-            accordant_shared_keys: Decodable::consensus_decode(d)?,
-            destination_address: <Ctx::Ar as Address>::Address::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            cancel_timelock: Decodable::consensus_decode(d)?,
-            punish_timelock: Decodable::consensus_decode(d)?,
-            fee_strategy: Decodable::consensus_decode(d)?,
-        })
+    fn arbitrating_keys(&self) -> Vec<(CommitmentField, &<Ctx::Ar as Keys>::PublicKey)> {
+        let mut keys = vec![
+            (CommitmentField::Buy, &self.buy),
+            (CommitmentField::Cancel, &self.cancel),
+            (CommitmentField::Refund, &self.refund),
+            (CommitmentField::Punish, &self.punish),
+            (CommitmentField::Adaptor, &self.adaptor),
+        ];
+        keys.extend(
+            self.extra_arbitrating_keys
+                .iter()
+                .map(|key| (CommitmentField::ExtraArbitratingKey(*key.tag()), key.elem())),
+        );
+        keys
     }
-}
 
-impl_strict_encoding!(AliceParameters<Ctx>, Ctx: Swap);
+    fn accordant_keys(&self) -> Vec<(CommitmentField, &<Ctx::Ac as Keys>::PublicKey)> {
+        let mut keys = vec![(CommitmentField::Spend, &self.spend)];
+        keys.extend(
+            self.extra_accordant_keys
+                .iter()
+                .map(|key| (CommitmentField::ExtraAccordantKey(*key.tag()), key.elem())),
+        );
+        keys
+    }
+}
 
 impl<Ctx> From<protocol_message::RevealAliceParameters<Ctx>> for AliceParameters<Ctx>
 where
@@ -148,6 +261,7 @@ where
             extra_accordant_keys: msg.extra_accordant_keys,
             accordant_shared_keys: msg.accordant_shared_keys,
             destination_address: msg.address,
+            // not revealed, taken from the public offer
             cancel_timelock: None,
             punish_timelock: None,
             fee_strategy: None,
@@ -161,82 +275,99 @@ where
 ///
 /// [`CommitBobParameters`]: protocol_message::CommitBobParameters
 /// [`RevealBobParameters`]: protocol_message::RevealBobParameters
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct BobParameters<Ctx: Swap> {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy: <Ctx::Ar as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub cancel: <Ctx::Ar as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund: <Ctx::Ar as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub adaptor: <Ctx::Ar as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_arbitrating_keys: Vec<TaggedElement<u16, <Ctx::Ar as Keys>::PublicKey>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub arbitrating_shared_keys:
         Vec<TaggedElement<SharedKeyId, <Ctx::Ar as SharedSecretKeys>::SharedSecretKey>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub spend: <Ctx::Ac as Keys>::PublicKey,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_accordant_keys: Vec<TaggedElement<u16, <Ctx::Ac as Keys>::PublicKey>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub accordant_shared_keys:
         Vec<TaggedElement<SharedKeyId, <Ctx::Ac as SharedSecretKeys>::SharedSecretKey>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund_address: <Ctx::Ar as Address>::Address,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::option"))]
     pub cancel_timelock: Option<<Ctx::Ar as Timelock>::Timelock>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::option"))]
     pub punish_timelock: Option<<Ctx::Ar as Timelock>::Timelock>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::option"))]
     pub fee_strategy: Option<FeeStrategy<<Ctx::Ar as Fee>::FeeUnit>>,
 }
 
-impl<Ctx> Encodable for BobParameters<Ctx>
+impl<Ctx> BobParameters<Ctx>
 where
     Ctx: Swap,
 {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.buy.as_canonical_bytes().consensus_encode(s)?;
-        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
-        len += self.refund.as_canonical_bytes().consensus_encode(s)?;
-        len += self.adaptor.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_arbitrating_keys.consensus_encode(s)?;
-        len += self.arbitrating_shared_keys.consensus_encode(s)?;
-        len += self.spend.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_accordant_keys.consensus_encode(s)?;
-        len += self.accordant_shared_keys.consensus_encode(s)?;
-        len += self
-            .refund_address
-            .as_canonical_bytes()
-            .consensus_encode(s)?;
-        len += self.cancel_timelock.consensus_encode(s)?;
-        len += self.punish_timelock.consensus_encode(s)?;
-        Ok(len + self.fee_strategy.consensus_encode(s)?)
+    /// Validate the parameters against the agreed public offer. The timelocks and fee strategy,
+    /// when present, must match the offer, the refund address must be valid on the offer's
+    /// network, and the public keys must be valid and distinct, buy and cancel or refund and
+    /// punish keys may be shared. Parameters built from [`RevealBobParameters`] carry no
+    /// timelocks nor fee strategy, the offer is the only source for them.
+    ///
+    /// [`RevealBobParameters`]: protocol_message::RevealBobParameters
+    pub fn validate_against(&self, public_offer: &PublicOffer<Ctx>) -> Result<(), Error> {
+        validate_terms(
+            &self.cancel_timelock,
+            &self.punish_timelock,
+            &self.fee_strategy,
+            &self.refund_address,
+            public_offer,
+        )?;
+        validate_keys::<Ctx::Ar>(&self.arbitrating_keys())?;
+        validate_keys::<Ctx::Ac>(&self.accordant_keys())
     }
-}
 
-impl<Ctx> Decodable for BobParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            buy: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            cancel: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            refund: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            adaptor: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            extra_arbitrating_keys: Decodable::consensus_decode(d)?,
-            arbitrating_shared_keys: Decodable::consensus_decode(d)?,
-            spend: <Ctx::Ac as Keys>::PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            extra_accordant_keys: Decodable::consensus_decode(d)?,
-            accordant_shared_keys: Decodable::consensus_decode(d)?,
-            refund_address: <Ctx::Ar as Address>::Address::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            cancel_timelock: Decodable::consensus_decode(d)?,
-            punish_timelock: Decodable::consensus_decode(d)?,
-            fee_strategy: Decodable::consensus_decode(d)?,
-        })
+    fn arbitrating_keys(&self) -> Vec<(CommitmentField, &<Ctx::Ar as Keys>::PublicKey)> {
+        let mut keys = vec![
+            (CommitmentField::Buy, &self.buy),
+            (CommitmentField::Cancel, &self.cancel),
+            (CommitmentField::Refund, &self.refund),
+            (CommitmentField::Adaptor, &self.adaptor),
+        ];
+        keys.extend(
+            self.extra_arbitrating_keys
+                .iter()
+                .map(|key| (CommitmentField::ExtraArbitratingKey(*key.tag()), key.elem())),
+        );
+        keys
     }
-}
 
-impl_strict_encoding!(BobParameters<Ctx>, Ctx: Swap);
+    fn accordant_keys(&self) -> Vec<(CommitmentField, &<Ctx::Ac as Keys>::PublicKey)> {
+        let mut keys = vec![(CommitmentField::Spend, &self.spend)];
+        keys.extend(
+            self.extra_accordant_keys
+                .iter()
+                .map(|key| (CommitmentField::ExtraAccordantKey(*key.tag()), key.elem())),
+        );
+        keys
+    }
+}
 
 impl<Ctx> From<protocol_message::RevealBobParameters<Ctx>> for BobParameters<Ctx>
 where
@@ -254,6 +385,7 @@ where
             extra_accordant_keys: msg.extra_accordant_keys,
             accordant_shared_keys: msg.accordant_shared_keys,
             refund_address: msg.address,
+            // not revealed, taken from the public offer
             cancel_timelock: None,
             punish_timelock: None,
             fee_strategy: None,
@@ -268,37 +400,23 @@ where
 /// [`CoreArbitratingSetup`]: protocol_message::CoreArbitratingSetup
 /// [`RefundProcedureSignatures`]: protocol_message::RefundProcedureSignatures
 /// [`Cancelable`]: crate::transaction::Cancelable
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display("Cancel signature: {cancel_sig}")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct CosignedArbitratingCancel<S>
 where
     S: Signatures,
 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub cancel_sig: S::Signature,
 }
 
-impl<S> Encodable for CosignedArbitratingCancel<S>
-where
-    S: Signatures,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        self.cancel_sig.as_canonical_bytes().consensus_encode(s)
-    }
-}
-
-impl<S> Decodable for CosignedArbitratingCancel<S>
-where
-    S: Signatures,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            cancel_sig: S::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-        })
-    }
-}
-
-impl_strict_encoding!(CosignedArbitratingCancel<S>, S: Signatures);
-
 impl<Ctx> From<protocol_message::CoreArbitratingSetup<Ctx>> for CosignedArbitratingCancel<Ctx::Ar>
 where
     Ctx: Swap,
@@ -326,12 +444,20 @@ where
 /// [`CoreArbitratingTransactions`] bundle, later used to create protocol messages.
 ///
 /// [`Fundable`]: crate::transaction::Fundable
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display(funding_tx_fmt)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct FundingTransaction<T>
 where
     T: Onchain,
 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub funding: T::Transaction,
 }
 
@@ -342,40 +468,30 @@ where
     format!("Funding transaction: {:?}", b.funding)
 }
 
-impl<T> Encodable for FundingTransaction<T>
-where
-    T: Onchain,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        self.funding.as_canonical_bytes().consensus_encode(s)
-    }
-}
-
-impl<T> Decodable for FundingTransaction<T>
-where
-    T: Onchain,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            funding: T::Transaction::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-        })
-    }
-}
-
-impl_strict_encoding!(FundingTransaction<T>, T: Onchain);
-
 /// Provides Bob's daemon or Alice's clients the core set of arbritrating transactions present in
 /// [`CoreArbitratingSetup`].
 ///
 /// [`CoreArbitratingSetup`]: protocol_message::CoreArbitratingSetup
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display(core_arbitrating_tx_fmt)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct CoreArbitratingTransactions<T>
 where
     T: Onchain,
 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_base64"))]
+    #[canonical]
     pub lock: T::PartialTransaction,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_base64"))]
+    #[canonical]
     pub cancel: T::PartialTransaction,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_base64"))]
+    #[canonical]
     pub refund: T::PartialTransaction,
 }
 
@@ -389,32 +505,6 @@ where
     )
 }
 
-impl<T> Encodable for CoreArbitratingTransactions<T>
-where
-    T: Onchain,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.lock.as_canonical_bytes().consensus_encode(s)?;
-        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len + self.refund.as_canonical_bytes().consensus_encode(s)?)
-    }
-}
-
-impl<T> Decodable for CoreArbitratingTransactions<T>
-where
-    T: Onchain,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            lock: T::PartialTransaction::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            cancel: T::PartialTransaction::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            refund: T::PartialTransaction::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-        })
-    }
-}
-
-impl_strict_encoding!(CoreArbitratingTransactions<T>, T: Onchain);
-
 impl<Ctx> From<protocol_message::CoreArbitratingSetup<Ctx>> for CoreArbitratingTransactions<Ctx::Ar>
 where
     Ctx: Swap,
@@ -433,13 +523,23 @@ where
 /// [`FullySignedBuy`] bundle containing the adapted (i.e. decrypted) signature.
 ///
 /// [`Buyable`]: crate::transaction::Buyable
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display(signed_adaptor_buy_fmt)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct SignedAdaptorBuy<T>
 where
     T: Signatures + Onchain,
 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_base64"))]
+    #[canonical]
     pub buy: T::PartialTransaction,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy_adaptor_sig: T::EncryptedSignature,
 }
 
@@ -453,119 +553,53 @@ where
     )
 }
 
-impl<T> Encodable for SignedAdaptorBuy<T>
-where
-    T: Signatures + Onchain,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let len = self.buy.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len
-            + self
-                .buy_adaptor_sig
-                .as_canonical_bytes()
-                .consensus_encode(s)?)
-    }
-}
-
-impl<T> Decodable for SignedAdaptorBuy<T>
-where
-    T: Signatures + Onchain,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            buy: T::PartialTransaction::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            buy_adaptor_sig: T::EncryptedSignature::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-        })
-    }
-}
-
-impl_strict_encoding!(SignedAdaptorBuy<T>, T: Signatures + Onchain);
-
 /// Provides Alice's daemon or Bob's daemon/client with the two signatures on the unsigned
 /// [`Buyable`] transaction. Alice's standard signature and the adapted (i.e. decrypted) version of
 /// Bob's adaptor (i.e. encrypted) signature with Alice's key.
 ///
 /// [`Buyable`]: crate::transaction::Buyable
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display("Buy signature: {buy_sig}, Buy adapted: {buy_adapted_sig}")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct FullySignedBuy<S>
 where
     S: Signatures,
 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy_sig: S::Signature,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy_adapted_sig: S::Signature,
 }
 
-impl<S> Encodable for FullySignedBuy<S>
-where
-    S: Signatures,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let len = self.buy_sig.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len
-            + self
-                .buy_adapted_sig
-                .as_canonical_bytes()
-                .consensus_encode(s)?)
-    }
-}
-
-impl<S> Decodable for FullySignedBuy<S>
-where
-    S: Signatures,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            buy_sig: S::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            buy_adapted_sig: S::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-        })
-    }
-}
-
-impl_strict_encoding!(FullySignedBuy<S>, S: Signatures);
-
 /// Provides Alice's daemon or Bob's daemon/client with an adaptor (i.e. encrypted) signature on
 /// the unsigned [`Refundable`] transaction.. After verification, Bob will return a
 /// [`FullySignedRefund`] bundle containing the adapted (i.e. decrypted) signature.
 ///
 /// [`Refundable`]: crate::transaction::Refundable
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display("Refund adaptor: {refund_adaptor_sig}")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct SignedAdaptorRefund<S>
 where
     S: Signatures,
 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund_adaptor_sig: S::EncryptedSignature,
 }
 
-impl<S> Encodable for SignedAdaptorRefund<S>
-where
-    S: Signatures,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        self.refund_adaptor_sig
-            .as_canonical_bytes()
-            .consensus_encode(s)
-    }
-}
-
-impl<S> Decodable for SignedAdaptorRefund<S>
-where
-    S: Signatures,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            refund_adaptor_sig: S::EncryptedSignature::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-        })
-    }
-}
-
-impl_strict_encoding!(SignedAdaptorRefund<S>, S: Signatures);
-
 impl<Ctx> From<protocol_message::RefundProcedureSignatures<Ctx>> for SignedAdaptorRefund<Ctx::Ar>
 where
     Ctx: Swap,
@@ -582,90 +616,68 @@ where
 /// Alice's adaptor (i.e. encrypted) signature with Bob's key.
 ///
 /// [`Refundable`]: crate::transaction::Refundable
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display("Refund signature: {refund_sig}, Refund adapted: {refund_adapted_sig}")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct FullySignedRefund<S>
 where
     S: Signatures,
 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund_sig: S::Signature,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund_adapted_sig: S::Signature,
 }
 
-impl<S> Encodable for FullySignedRefund<S>
-where
-    S: Signatures,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let len = self.refund_sig.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len
-            + self
-                .refund_adapted_sig
-                .as_canonical_bytes()
-                .consensus_encode(s)?)
-    }
-}
-
-impl<S> Decodable for FullySignedRefund<S>
-where
-    S: Signatures,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            refund_sig: S::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            refund_adapted_sig: S::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-        })
-    }
-}
-
-impl_strict_encoding!(FullySignedRefund<S>, S: Signatures);
-
 /// Provides Bob's daemon with the signature on the unsigned [`Lockable`] transaction present in
 /// [`CoreArbitratingTransactions`].
 ///
 /// [`Lockable`]: crate::transaction::Lockable
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display("Lock signature: {lock_sig}")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct SignedArbitratingLock<S>
 where
     S: Signatures,
 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub lock_sig: S::Signature,
 }
 
-impl<S> Encodable for SignedArbitratingLock<S>
-where
-    S: Signatures,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        self.lock_sig.as_canonical_bytes().consensus_encode(s)
-    }
-}
-
-impl<S> Decodable for SignedArbitratingLock<S>
-where
-    S: Signatures,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            lock_sig: S::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-        })
-    }
-}
-
-impl_strict_encoding!(SignedArbitratingLock<S>, S: Signatures);
-
 /// Provides Alice's daemon with the signature on the unsigned [`Punishable`] transaction, ready
 /// for broadcast.
 ///
 /// [`Punishable`]: crate::transaction::Punishable
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display(fully_signed_punish_fmt)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct FullySignedPunish<T>
 where
     T: Signatures + Onchain,
 {
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_base64"))]
+    #[canonical]
     pub punish: T::PartialTransaction,
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub punish_sig: T::Signature,
 }
 
@@ -678,27 +690,3 @@ where
         b.punish, b.punish_sig
     )
 }
-
-impl<T> Encodable for FullySignedPunish<T>
-where
-    T: Signatures + Onchain,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let len = self.punish.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len + self.punish_sig.as_canonical_bytes().consensus_encode(s)?)
-    }
-}
-
-impl<T> Decodable for FullySignedPunish<T>
-where
-    T: Signatures + Onchain,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            punish: T::PartialTransaction::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            punish_sig: T::Signature::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-        })
-    }
-}
-
-impl_strict_encoding!(FullySignedPunish<T>, T: Signatures + Onchain);
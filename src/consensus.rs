@@ -8,8 +8,14 @@
 use hex::encode as hex_encode;
 use thiserror::Error;
 
+/// Derive macros for [`Encodable`], [`Decodable`] and [`CanonicalBytes`], fields marked with
+/// `#[canonical]` are encoded as length prefixed canonical bytes. See the
+/// [`farcaster_core_derive`] crate for the details.
+pub use farcaster_core_derive::{CanonicalBytes, Decodable, Encodable};
+
 use std::error;
 use std::io;
+use std::mem;
 use std::str;
 
 /// Encoding and decoding errors and data transformation errors (when converting data from one
@@ -31,6 +37,18 @@ pub enum Error {
     /// A generic parsing error.
     #[error("Parsing error: {0}")]
     ParseFailed(&'static str),
+    /// The message type identifier is not known.
+    #[error("Unknown message type {0}")]
+    UnknownMessageType(u16),
+    /// The message type identifier is odd and not known, the message was skipped.
+    #[error("Ignored unknown odd message type {0}")]
+    IgnoredMessageType(u16),
+    /// The extension type is even and not known, even extensions must be understood.
+    #[error("Unknown even extension type {0}")]
+    UnknownExtensionType(u16),
+    /// The data exceeds the decoding limits.
+    #[error("Decoding limit exceeded: {0}")]
+    LimitExceeded(&'static str),
     /// Any Consensus error not part of this list.
     #[error("Consensus error: {0}")]
     Other(Box<dyn error::Error + Send + Sync>),
@@ -115,6 +133,128 @@ pub fn deserialize_partial<T: Decodable>(data: &[u8]) -> Result<(T, usize), Erro
     Ok((rv, consumed))
 }
 
+/// Default maximum number of bytes decoded with [`DecodingLimits::default`].
+pub const DEFAULT_MAX_DECODING_BYTES: usize = 1 << 20;
+
+/// Default maximum number of vector elements decoded with [`DecodingLimits::default`].
+pub const DEFAULT_MAX_DECODING_ELEMENTS: usize = 1 << 20;
+
+// Maximum number of bytes preallocated when decoding a vector, the announced length is not trusted
+// and the vector grows as elements are decoded.
+const MAX_VEC_PREALLOCATION: usize = 4096;
+
+/// Limits applied when decoding untrusted data with [`deserialize_with_limits`], they bound the
+/// amount of memory a peer can make the decoder allocate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodingLimits {
+    /// The maximum number of bytes to decode.
+    pub max_bytes: usize,
+    /// The maximum number of vector elements to decode, summed over all nested vectors including
+    /// vectors of bytes.
+    pub max_elements: usize,
+}
+
+impl DecodingLimits {
+    /// Create new decoding limits.
+    pub const fn new(max_bytes: usize, max_elements: usize) -> Self {
+        Self {
+            max_bytes,
+            max_elements,
+        }
+    }
+}
+
+impl Default for DecodingLimits {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_DECODING_BYTES, DEFAULT_MAX_DECODING_ELEMENTS)
+    }
+}
+
+/// Reader enforcing [`DecodingLimits`] while decoding, it counts the bytes read from the inner
+/// reader and holds the budget of vector elements left. Obtained with [`decode_with_limits`] and
+/// passed to [`Decodable::consensus_decode_with_limits`].
+#[derive(Debug)]
+pub struct LimitedReader<R> {
+    inner: R,
+    remaining_bytes: usize,
+    remaining_elements: usize,
+    exceeded: bool,
+}
+
+impl<R: io::Read> LimitedReader<R> {
+    fn new(inner: R, limits: DecodingLimits) -> Self {
+        Self {
+            inner,
+            remaining_bytes: limits.max_bytes,
+            remaining_elements: limits.max_elements,
+            exceeded: false,
+        }
+    }
+
+    /// Consume `count` elements from the element budget, fails if the budget is exhausted.
+    pub fn consume_elements(&mut self, count: usize) -> Result<(), Error> {
+        match self.remaining_elements.checked_sub(count) {
+            Some(left) => {
+                self.remaining_elements = left;
+                Ok(())
+            }
+            None => Err(Error::LimitExceeded("too many elements")),
+        }
+    }
+}
+
+impl<R: io::Read> io::Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining_bytes == 0 {
+            self.exceeded = true;
+            return Err(io::Error::new(io::ErrorKind::Other, "too many bytes"));
+        }
+        let max = buf.len().min(self.remaining_bytes);
+        let read = self.inner.read(&mut buf[..max])?;
+        self.remaining_bytes -= read;
+        Ok(read)
+    }
+}
+
+/// Decode an object from a reader under the given limits, will error if the decoding reads more
+/// bytes or decodes more vector elements than allowed. The reader is not required to be consumed
+/// entirely.
+pub fn decode_with_limits<T: Decodable, R: io::Read>(
+    reader: R,
+    limits: DecodingLimits,
+) -> Result<T, Error> {
+    let mut reader = LimitedReader::new(reader, limits);
+    T::consensus_decode_with_limits(&mut reader).map_err(|e| match reader.exceeded {
+        true => Error::LimitExceeded("too many bytes"),
+        false => e,
+    })
+}
+
+/// Deserialize an object from a vector of bytes under the given limits, will error if said
+/// deserialization doesn't consume the entire vector or exceeds the limits.
+pub fn deserialize_with_limits<T: Decodable>(
+    data: &[u8],
+    limits: DecodingLimits,
+) -> Result<T, Error> {
+    if data.len() > limits.max_bytes {
+        return Err(Error::LimitExceeded("too many bytes"));
+    }
+    let mut decoder = io::Cursor::new(data);
+    let rv = decode_with_limits(&mut decoder, limits)?;
+
+    // Fail if data are not consumed entirely.
+    if decoder.position() as usize == data.len() {
+        Ok(rv)
+    } else {
+        Err(Error::ParseFailed(
+            "data not consumed entirely when explicitly deserializing",
+        ))
+    }
+}
+
 /// Data which can be encoded in a consensus-consistent way. Used to implement `StrictEncode` on
 /// messages passed around by the node.
 pub trait Encodable {
@@ -130,6 +270,13 @@ pub trait Encodable {
 pub trait Decodable: Sized {
     /// Decode an object with a well-defined format
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, Error>;
+
+    /// Decode an object under the limits of the reader, see [`decode_with_limits`]. Defaults to
+    /// [`Decodable::consensus_decode`] bounded in bytes by the reader, types containing vectors
+    /// forward the reader to their fields so the element budget applies to the nested vectors.
+    fn consensus_decode_with_limits<R: io::Read>(d: &mut LimitedReader<R>) -> Result<Self, Error> {
+        Self::consensus_decode(d)
+    }
 }
 
 impl<T> Encodable for Vec<T>
@@ -155,13 +302,30 @@ where
 {
     #[inline]
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, Error> {
-        let len = u16::consensus_decode(d)?;
-        let mut ret = Vec::<T>::with_capacity(len as usize);
-        for _ in 0..len {
-            ret.push(Decodable::consensus_decode(d)?);
-        }
-        Ok(ret)
+        let len = u16::consensus_decode(d)? as usize;
+        decode_elements(len, || T::consensus_decode(d))
+    }
+
+    #[inline]
+    fn consensus_decode_with_limits<R: io::Read>(d: &mut LimitedReader<R>) -> Result<Self, Error> {
+        let len = u16::consensus_decode(d)? as usize;
+        d.consume_elements(len)?;
+        decode_elements(len, || T::consensus_decode_with_limits(d))
+    }
+}
+
+// Decode `len` elements, the announced length is not trusted and the vector grows as elements are
+// decoded.
+fn decode_elements<T>(
+    len: usize,
+    mut decode: impl FnMut() -> Result<T, Error>,
+) -> Result<Vec<T>, Error> {
+    let mut ret =
+        Vec::<T>::with_capacity(len.min(MAX_VEC_PREALLOCATION / mem::size_of::<T>().max(1)));
+    for _ in 0..len {
+        ret.push(decode()?);
     }
+    Ok(ret)
 }
 
 macro_rules! impl_fixed_array {
@@ -186,6 +350,8 @@ macro_rules! impl_fixed_array {
 }
 
 impl_fixed_array!(6);
+impl_fixed_array!(20);
+impl_fixed_array!(24);
 impl_fixed_array!(32);
 impl_fixed_array!(33);
 impl_fixed_array!(64);
@@ -328,6 +494,17 @@ where
             _ => Err(Error::UnknownType),
         }
     }
+
+    #[inline]
+    fn consensus_decode_with_limits<R: io::Read>(d: &mut LimitedReader<R>) -> Result<Self, Error> {
+        match u8::consensus_decode(d)? {
+            1u8 => Ok(Some(T::from_canonical_bytes(
+                Vec::<u8>::consensus_decode_with_limits(d)?.as_ref(),
+            )?)),
+            0u8 => Ok(None),
+            _ => Err(Error::UnknownType),
+        }
+    }
 }
 
 impl CanonicalBytes for String {
@@ -357,6 +534,15 @@ impl Decodable for String {
             .map_err(Error::new)?
             .into())
     }
+
+    #[inline]
+    fn consensus_decode_with_limits<R: io::Read>(d: &mut LimitedReader<R>) -> Result<Self, Error> {
+        Ok(
+            str::from_utf8(Vec::<u8>::consensus_decode_with_limits(d)?.as_ref())
+                .map_err(Error::new)?
+                .into(),
+        )
+    }
 }
 
 macro_rules! impl_strict_encoding {
@@ -372,8 +558,9 @@ macro_rules! impl_strict_encoding {
         }
 
         impl<$($args)*> ::strict_encoding::StrictDecode for $thing {
-            fn strict_decode<D: ::std::io::Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
-                $crate::consensus::Decodable::consensus_decode(&mut d)
+            fn strict_decode<D: ::std::io::Read>(d: D) -> Result<Self, strict_encoding::Error> {
+                let limits = $crate::consensus::DecodingLimits::default();
+                $crate::consensus::decode_with_limits(d, limits)
                     .map_err(|e| strict_encoding::Error::DataIntegrityError(e.to_string()))
             }
         }
@@ -390,14 +577,148 @@ macro_rules! impl_strict_encoding {
         }
 
         impl strict_encoding::StrictDecode for $thing {
-            fn strict_decode<D: ::std::io::Read>(mut d: D) -> Result<Self, strict_encoding::Error> {
-                $crate::consensus::Decodable::consensus_decode(&mut d)
+            fn strict_decode<D: ::std::io::Read>(d: D) -> Result<Self, strict_encoding::Error> {
+                let limits = $crate::consensus::DecodingLimits::default();
+                $crate::consensus::decode_with_limits(d, limits)
                     .map_err(|e| strict_encoding::Error::DataIntegrityError(e.to_string()))
             }
         }
     };
 }
 
+/// Serde helpers serializing a type through its [`CanonicalBytes`] representation encoded in hex,
+/// used with `#[serde(with = "...")]` on keys, signatures, commitments, etc.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_hex {
+    use super::CanonicalBytes;
+    use serde_crate::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: CanonicalBytes,
+        S: Serializer,
+    {
+        serializer.serialize_str(&hex::encode(value.as_canonical_bytes()))
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: CanonicalBytes,
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let bytes = hex::decode(&s).map_err(de::Error::custom)?;
+        T::from_canonical_bytes(&bytes).map_err(de::Error::custom)
+    }
+
+    /// Same as the parent module for optional values.
+    pub(crate) mod option {
+        use super::super::CanonicalBytes;
+        use serde_crate::{de, Deserialize, Deserializer, Serializer};
+
+        pub(crate) fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            T: CanonicalBytes,
+            S: Serializer,
+        {
+            match value {
+                Some(value) => serializer.serialize_some(&hex::encode(value.as_canonical_bytes())),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+        where
+            T: CanonicalBytes,
+            D: Deserializer<'de>,
+        {
+            Option::<String>::deserialize(deserializer)?
+                .map(|s| {
+                    let bytes = hex::decode(&s).map_err(de::Error::custom)?;
+                    T::from_canonical_bytes(&bytes).map_err(de::Error::custom)
+                })
+                .transpose()
+        }
+    }
+
+    /// Same as the parent module for vectors of [`TaggedElement`]s, each element is serialized
+    /// with its tag and the hex of its canonical bytes.
+    ///
+    /// [`TaggedElement`]: crate::crypto::TaggedElement
+    pub(crate) mod tagged {
+        use super::super::CanonicalBytes;
+        use crate::crypto::TaggedElement;
+        use serde_crate::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+        #[derive(Serialize, Deserialize)]
+        #[serde(crate = "serde_crate")]
+        struct HexTaggedElement<T> {
+            tag: T,
+            elem: String,
+        }
+
+        pub(crate) fn serialize<T, E, S>(
+            value: &[TaggedElement<T, E>],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            T: Eq + Serialize,
+            E: CanonicalBytes,
+            S: Serializer,
+        {
+            serializer.collect_seq(value.iter().map(|tagged| HexTaggedElement {
+                tag: tagged.tag(),
+                elem: hex::encode(tagged.elem().as_canonical_bytes()),
+            }))
+        }
+
+        pub(crate) fn deserialize<'de, T, E, D>(
+            deserializer: D,
+        ) -> Result<Vec<TaggedElement<T, E>>, D::Error>
+        where
+            T: Eq + Deserialize<'de>,
+            E: CanonicalBytes,
+            D: Deserializer<'de>,
+        {
+            Vec::<HexTaggedElement<T>>::deserialize(deserializer)?
+                .into_iter()
+                .map(|tagged| {
+                    let bytes = hex::decode(&tagged.elem).map_err(de::Error::custom)?;
+                    let elem = E::from_canonical_bytes(&bytes).map_err(de::Error::custom)?;
+                    Ok(TaggedElement::new(tagged.tag, elem))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Serde helpers serializing a type through its [`CanonicalBytes`] representation encoded in
+/// base64, used with `#[serde(with = "...")]` on partial transactions, e.g. PSBTs in their
+/// standard base64 form.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_base64 {
+    use super::CanonicalBytes;
+    use serde_crate::{de, Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: CanonicalBytes,
+        S: Serializer,
+    {
+        serializer.serialize_str(&base64::encode(value.as_canonical_bytes()))
+    }
+
+    pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: CanonicalBytes,
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let bytes = base64::decode(&s).map_err(de::Error::custom)?;
+        T::from_canonical_bytes(&bytes).map_err(de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -422,4 +743,53 @@ mod tests {
         let vec = vec![0x41; u16::MAX.into()];
         assert_eq!(deserialize::<Vec<u8>>(&serialize(&vec)[..]).unwrap(), vec);
     }
+
+    #[test]
+    fn truncated_vec() {
+        // announce the max number of elements without providing them
+        assert!(matches!(
+            deserialize::<Vec<[u8; 64]>>(&[0xff, 0xff, 0x00]),
+            Err(Error::Io(_))
+        ));
+    }
+
+    #[test]
+    fn decoding_limits() {
+        let vec: Vec<Vec<u8>> = vec![vec![0x41; 10], vec![0x42; 10]];
+        let data = serialize(&vec);
+        assert_eq!(
+            deserialize_with_limits::<Vec<Vec<u8>>>(&data, DecodingLimits::default()).unwrap(),
+            vec
+        );
+        // 2 vectors of 10 bytes are 22 elements
+        assert!(
+            deserialize_with_limits::<Vec<Vec<u8>>>(&data, DecodingLimits::new(26, 22)).is_ok()
+        );
+        assert!(matches!(
+            deserialize_with_limits::<Vec<Vec<u8>>>(&data, DecodingLimits::new(26, 21)),
+            Err(Error::LimitExceeded(_))
+        ));
+        assert!(matches!(
+            deserialize_with_limits::<Vec<Vec<u8>>>(&data, DecodingLimits::new(25, 22)),
+            Err(Error::LimitExceeded(_))
+        ));
+        // nested vectors of bytes are bounded by the element budget
+        let data = serialize(&Some("abc".to_string()));
+        assert!(matches!(
+            deserialize_with_limits::<Option<String>>(&data, DecodingLimits::new(6, 2)),
+            Err(Error::LimitExceeded(_))
+        ));
+        // readers are bounded in bytes, not only the top-level data
+        let mut reader = io::Cursor::new(serialize(&vec));
+        assert!(matches!(
+            decode_with_limits::<Vec<Vec<u8>>, _>(&mut reader, DecodingLimits::new(25, 22)),
+            Err(Error::LimitExceeded(_))
+        ));
+        let mut reader = io::Cursor::new([serialize(&vec), vec![0xff]].concat());
+        assert_eq!(
+            decode_with_limits::<Vec<Vec<u8>>, _>(&mut reader, DecodingLimits::new(26, 22))
+                .unwrap(),
+            vec
+        );
+    }
 }
@@ -11,7 +11,8 @@ use thiserror::Error;
 use tiny_keccak::{Hasher, Keccak};
 
 use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
-use crate::role::Accordant;
+use crate::role::{Accordant, SwapRole};
+use crate::swap::SwapId;
 
 #[cfg(feature = "experimental")]
 #[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
@@ -20,6 +21,9 @@ pub mod dleq;
 #[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
 pub mod slip10;
 
+/// Placeholder printed instead of secret values in the `Debug` output of secret-bearing types.
+pub const REDACTED: &str = "[redacted]";
+
 /// List of cryptographic errors that can be encountered in cryptographic operations such as
 /// signatures, proofs, key derivation, or commitments.
 #[derive(Error, Debug)]
@@ -45,6 +49,12 @@ pub enum Error {
     /// The commitment does not match the given value.
     #[error("The commitment does not match the given value")]
     InvalidCommitment,
+    /// A committed element is not revealed or a revealed element is not committed.
+    #[error("The commitment or the revealed element of {0} is missing")]
+    MissingCommitmentTag(CommitmentField),
+    /// A tag appears more than once in the commitments or in the revealed elements.
+    #[error("The tag of {0} is duplicated")]
+    DuplicateCommitmentTag(CommitmentField),
     /// The Pedersen commitment does not match the given value.
     #[error("The Pedersen commitment does not match the given value")]
     InvalidPedersenCommitment,
@@ -54,6 +64,41 @@ pub enum Error {
     /// The proof of knowledge signature is invalid.
     #[error("The proof of knowledge signature is invalid")]
     InvalidProofOfKnowledge,
+    /// The proof does not contain the expected number of elements.
+    #[error("The proof contains {found} elements, expected {expected}")]
+    InvalidProofSize {
+        /// The number of elements expected in the proof.
+        expected: usize,
+        /// The number of elements found in the proof.
+        found: usize,
+    },
+    /// The secret cannot be proven, e.g. it is zero or too large for the proof system.
+    #[error("The secret cannot be proven")]
+    InvalidProofSecret,
+    /// The point is not a valid curve point.
+    #[error("The point is not a valid curve point")]
+    InvalidPoint,
+    /// The keystore cannot be decrypted, the password is wrong or the keystore has been altered.
+    #[error("The keystore cannot be decrypted, wrong password or altered keystore")]
+    KeystoreDecryption,
+    /// The keystore key derivation parameters are invalid.
+    #[error("Invalid keystore key derivation parameters")]
+    InvalidKeystoreParameters,
+    /// The swap index is already used by another swap.
+    #[error("The swap index {0} is already used")]
+    SwapIndexAlreadyUsed(u32),
+    /// The swap index has not been allocated.
+    #[error("The swap index {0} has not been allocated")]
+    UnallocatedSwapIndex(u32),
+    /// The swap is already bound to another swap index.
+    #[error("The swap is already bound to the swap index {0}")]
+    SwapIdAlreadyBound(u32),
+    /// All the swap indexes are used.
+    #[error("All the swap indexes are used")]
+    SwapIndexesExhausted,
+    /// The master seed does not match the swap index registry.
+    #[error("The master seed does not match the swap index registry")]
+    SeedMismatch,
     /// SLIP10 error when manipulating extended secret keys.
     #[error("SLIP10 error: {0}")]
     Slip10(#[from] slip10::Error),
@@ -326,6 +371,212 @@ impl Commit<KeccakCommitment> for CommitmentEngine {
     }
 }
 
+/// Tag prefixed to the values committed with a [`SaltedCommit`] engine, version 2 of the commitment
+/// scheme.
+pub const SALTED_COMMITMENT_TAG: &[u8] = b"farcaster:commitment:v2";
+
+/// Tag prefixed to the seed when deriving the nonces of salted commitments.
+pub const COMMITMENT_NONCE_TAG: &[u8] = b"farcaster:commitment-nonce";
+
+/// The field of the swap parameters a salted commitment is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum CommitmentField {
+    /// The buy public key.
+    Buy,
+    /// The cancel public key.
+    Cancel,
+    /// The refund public key.
+    Refund,
+    /// The punish public key.
+    Punish,
+    /// The adaptor public key.
+    Adaptor,
+    /// An extra arbitrating public key with its tag.
+    ExtraArbitratingKey(u16),
+    /// An arbitrating shared key with its identifier.
+    ArbitratingSharedKey(SharedKeyId),
+    /// The accordant spend public key.
+    Spend,
+    /// An extra accordant public key with its tag.
+    ExtraAccordantKey(u16),
+    /// An accordant shared key with its identifier.
+    AccordantSharedKey(SharedKeyId),
+}
+
+impl Encodable for CommitmentField {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        match self {
+            Self::Buy => 0x01u8.consensus_encode(s),
+            Self::Cancel => 0x02u8.consensus_encode(s),
+            Self::Refund => 0x03u8.consensus_encode(s),
+            Self::Punish => 0x04u8.consensus_encode(s),
+            Self::Adaptor => 0x05u8.consensus_encode(s),
+            Self::ExtraArbitratingKey(tag) => {
+                Ok(0x06u8.consensus_encode(s)? + tag.consensus_encode(s)?)
+            }
+            Self::ArbitratingSharedKey(id) => {
+                Ok(0x07u8.consensus_encode(s)? + id.consensus_encode(s)?)
+            }
+            Self::Spend => 0x08u8.consensus_encode(s),
+            Self::ExtraAccordantKey(tag) => {
+                Ok(0x09u8.consensus_encode(s)? + tag.consensus_encode(s)?)
+            }
+            Self::AccordantSharedKey(id) => {
+                Ok(0x0au8.consensus_encode(s)? + id.consensus_encode(s)?)
+            }
+        }
+    }
+}
+
+impl Decodable for CommitmentField {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match u8::consensus_decode(d)? {
+            0x01u8 => Ok(Self::Buy),
+            0x02u8 => Ok(Self::Cancel),
+            0x03u8 => Ok(Self::Refund),
+            0x04u8 => Ok(Self::Punish),
+            0x05u8 => Ok(Self::Adaptor),
+            0x06u8 => Ok(Self::ExtraArbitratingKey(Decodable::consensus_decode(d)?)),
+            0x07u8 => Ok(Self::ArbitratingSharedKey(Decodable::consensus_decode(d)?)),
+            0x08u8 => Ok(Self::Spend),
+            0x09u8 => Ok(Self::ExtraAccordantKey(Decodable::consensus_decode(d)?)),
+            0x0au8 => Ok(Self::AccordantSharedKey(Decodable::consensus_decode(d)?)),
+            _ => Err(consensus::Error::UnknownType),
+        }
+    }
+}
+
+/// The domain a salted commitment is bound to: the swap, the role of the committer and the
+/// committed field. A commitment cannot be replayed in another swap, by the other role, or for
+/// another field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitmentDomain {
+    /// The swap identifier.
+    pub swap_id: SwapId,
+    /// The role of the participant committing.
+    pub role: SwapRole,
+    /// The committed field.
+    pub field: CommitmentField,
+}
+
+impl CommitmentDomain {
+    /// Create a new commitment domain.
+    pub fn new(swap_id: SwapId, role: SwapRole, field: CommitmentField) -> Self {
+        Self {
+            swap_id,
+            role,
+            field,
+        }
+    }
+}
+
+impl Encodable for CommitmentDomain {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.swap_id.consensus_encode(s)?;
+        len += self.role.consensus_encode(s)?;
+        Ok(len + self.field.consensus_encode(s)?)
+    }
+}
+
+fixed_hash::construct_fixed_hash!(
+    /// Blinding nonce of a salted commitment, kept secret until the committed value is revealed.
+    pub struct CommitmentNonce(32);
+);
+
+impl CommitmentNonce {
+    /// Derive the nonce of a domain from a secret seed. The seed must be random and used for one
+    /// swap only, e.g. drawn from a cryptographically secure random number generator.
+    pub fn derive(seed: &[u8; 32], domain: &CommitmentDomain) -> Self {
+        let mut out = [0u8; 32];
+        let mut keccak = Keccak::v256();
+        keccak.update(COMMITMENT_NONCE_TAG);
+        keccak.update(seed);
+        keccak.update(&consensus::serialize(domain));
+        keccak.finalize(&mut out);
+        Self(out)
+    }
+}
+
+impl CanonicalBytes for CommitmentNonce {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        (*self).to_fixed_bytes().into()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        Ok(Self(bytes.try_into().map_err(consensus::Error::new)?))
+    }
+}
+
+/// A vector of commitment nonces tagged with the field they blind.
+pub type CommitmentNonces = TaggedElements<CommitmentField, CommitmentNonce>;
+
+fixed_hash::construct_fixed_hash!(
+    /// Result of a salted and domain separated keccak256 commitment.
+    #[cfg_attr(
+        feature = "serde",
+        derive(Serialize, Deserialize),
+        serde(crate = "serde_crate"),
+    )]
+    pub struct SaltedKeccakCommitment(32);
+);
+
+impl SaltedKeccakCommitment {
+    /// Create a null commitment hash with all zeros.
+    pub fn null_hash() -> Self {
+        Self([0u8; 32])
+    }
+
+    /// Create a commitment from its hash.
+    pub fn new(input: [u8; 32]) -> Self {
+        Self(input)
+    }
+}
+
+impl CanonicalBytes for SaltedKeccakCommitment {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        (*self).to_fixed_bytes().into()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        Ok(Self::new(bytes.try_into().map_err(consensus::Error::new)?))
+    }
+}
+
+/// Engine to produce and validate salted and domain separated hash commitments, computed as
+/// `keccak256(SALTED_COMMITMENT_TAG || domain || nonce || value)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaltedCommitmentEngine;
+
+impl SaltedCommit<SaltedKeccakCommitment> for SaltedCommitmentEngine {
+    fn commit_to_salted<T: AsRef<[u8]>>(
+        &self,
+        domain: &CommitmentDomain,
+        nonce: &CommitmentNonce,
+        value: T,
+    ) -> SaltedKeccakCommitment {
+        let mut out = [0u8; 32];
+        let mut keccak = Keccak::v256();
+        keccak.update(SALTED_COMMITMENT_TAG);
+        keccak.update(&consensus::serialize(domain));
+        keccak.update(nonce.as_bytes());
+        keccak.update(value.as_ref());
+        keccak.finalize(&mut out);
+        SaltedKeccakCommitment::new(out)
+    }
+}
+
 /// Required for [`Arbitrating`] and [`Accordant`] blockchains to fix the cryptographic secret key
 /// and public key types. The public key type is shared across the network and used in
 /// transactions, the secret key type is used during signing operation, proofs, etc.
@@ -341,6 +592,12 @@ pub trait Keys {
 
     /// Return a list of extra public key identifiers to use during the setup phase.
     fn extra_keys() -> Vec<u16>;
+
+    /// Return `true` if the public key is acceptable as a swap key, e.g. is not the identity
+    /// point. Default implementation accepts all public keys.
+    fn is_valid_public_key(_key: &Self::PublicKey) -> bool {
+        true
+    }
 }
 
 /// Required for [`Arbitrating`] and [`Accordant`] blockchains to fix the potential shared secret
@@ -493,6 +750,36 @@ pub trait Commit<Commitment: Eq> {
     }
 }
 
+/// Commit to values with a blinding nonce and bound to a [`CommitmentDomain`], the nonce is revealed
+/// with the value to open the commitment.
+pub trait SaltedCommit<Commitment: Eq> {
+    /// Commit to a value referencable as stream of bytes in the given domain and blinded with the
+    /// nonce.
+    fn commit_to_salted<T: AsRef<[u8]>>(
+        &self,
+        domain: &CommitmentDomain,
+        nonce: &CommitmentNonce,
+        value: T,
+    ) -> Commitment;
+
+    /// Validate the equality between a candidate and a commitment in the given domain, return
+    /// `Ok(())` if the value and the nonce open the commitment, return
+    /// [`Error::InvalidCommitment`] otherwise.
+    fn validate_salted<T: AsRef<[u8]>>(
+        &self,
+        domain: &CommitmentDomain,
+        nonce: &CommitmentNonce,
+        candidate: T,
+        commitment: Commitment,
+    ) -> Result<(), Error> {
+        if self.commit_to_salted(domain, nonce, candidate) == commitment {
+            Ok(())
+        } else {
+            Err(Error::InvalidCommitment)
+        }
+    }
+}
+
 /// Proof generator and verifier for the cross-group projection of the accordant public spend key
 /// as an arbitrating key used to encrypt signatures.
 pub trait ProveCrossGroupDleq<EncryptionKey, AccordantSpendKey, Proof> {
@@ -1,6 +1,18 @@
 //! Discrete Logarithm Equality Proof system across the different groups secp256k1 and curve25519.
-
-use std::convert::TryInto;
+//!
+//! All the randomness used to generate a proof, i.e. the Pedersen commitment blinders and the ring
+//! signature nonces, is drawn from the random number generator given to [`DLEQProof::generate`].
+//! Seeding the generator deterministically, as done by the [`KeyManager`], makes the proof
+//! reproducible for a given secret.
+//!
+//! Proofs are serialized in the [`DLEQProofFormat::Compact`] format, proofs serialized by previous
+//! versions in the [`DLEQProofFormat::Legacy`] format are still decoded. Both formats hold the same
+//! proof and verify identically.
+//!
+//! [`KeyManager`]: crate::swap::btcxmr::KeyManager
+
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
 
 use crate::{
     consensus::{self, deserialize, serialize, CanonicalBytes, Decodable, Encodable},
@@ -12,11 +24,16 @@ use bitcoin_hashes::{self, Hash};
 
 use bitvec::{order::Lsb0, prelude::BitSlice};
 use curve25519_dalek::{
-    constants::ED25519_BASEPOINT_POINT as G, edwards::CompressedEdwardsY as ed25519PointCompressed,
-    edwards::EdwardsPoint as ed25519Point, scalar::Scalar as ed25519Scalar,
+    constants::{BASEPOINT_ORDER, ED25519_BASEPOINT_POINT as G},
+    edwards::CompressedEdwardsY as ed25519PointCompressed,
+    edwards::EdwardsPoint as ed25519Point,
+    scalar::Scalar as ed25519Scalar,
+    traits::{IsIdentity, VartimeMultiscalarMul},
 };
 
-const ENTROPY: bool = true;
+/// Number of bits of the secret covered by the proof, i.e. the number of Pedersen commitments and
+/// ring signatures contained in a [`DLEQProof`].
+pub const DLEQ_BITS: usize = 252;
 
 fn _max_ed25519() -> u256 {
     (u256::from(1u32) << 252) + 27742317777372353535851937790883648493u128
@@ -28,24 +45,36 @@ fn reverse_endianness(bytes: &[u8; 32]) -> [u8; 32] {
     bytes_rev
 }
 
-// matches https://github.com/monero-project/monero/blob/9414194b1e47730843e4dbbd4214bf72d3540cf9/src/ringct/rctTypes.h#L454
-// i.e. hash-to-curve of G as in https://github.com/monero-project/mininero/blob/master/mininero.py#L305-L323
-// TODO: this is disgusting and must be removed asap
-#[allow(non_snake_case)]
-fn G_p() -> ed25519Point {
-    let hash_G = monero::cryptonote::hash::keccak_256(G.compress().as_bytes());
-
-    ed25519PointCompressed::from_slice(&hash_G)
-        .decompress()
-        .unwrap()
-        .mul_by_cofactor() // should be in basepoint's subgroup, i.e. 8 * toPoint(hash_G)
-}
+/// Compressed alternate generator of ed25519 used by the Pedersen commitments, i.e. the
+/// hash-to-curve `8 * toPoint(keccak256(G))` of the basepoint as in
+/// [mininero](https://github.com/monero-project/mininero/blob/master/mininero.py#L305-L323).
+/// Matches Monero's `H` from
+/// [rctTypes.h](https://github.com/monero-project/monero/blob/9414194b1e47730843e4dbbd4214bf72d3540cf9/src/ringct/rctTypes.h#L454).
+pub const ALT_GENERATOR_ED25519: [u8; 32] = [
+    0x8b, 0x65, 0x59, 0x70, 0x15, 0x37, 0x99, 0xaf, 0x2a, 0xea, 0xdc, 0x9f, 0xf1, 0xad, 0xd0, 0xea,
+    0x6c, 0x72, 0x51, 0xd5, 0x41, 0x54, 0xcf, 0xa9, 0x2c, 0x17, 0x3a, 0x0d, 0xd3, 0x9c, 0x1f, 0x94,
+];
+
+/// Compressed alternate generator of secp256k1 used by the Pedersen commitments, i.e. the point
+/// with an even `y` and `x = sha256(G)` over the uncompressed basepoint, as explained
+/// [here](https://crypto.stackexchange.com/a/25603). Matches the generator `H` from
+/// [rust-secp256k1-zkp](https://github.com/mimblewimble/rust-secp256k1-zkp/blob/caa49992ae67f131157f6341f4e8b0b0c1e53055/src/constants.rs#L79-L136).
+pub const ALT_GENERATOR_SECP256K1: [u8; 33] = [
+    0x02, 0x50, 0x92, 0x9b, 0x74, 0xc1, 0xa0, 0x49, 0x54, 0xb7, 0x8b, 0x4b, 0x60, 0x35, 0xe9, 0x7a,
+    0x5e, 0x07, 0x8a, 0x5a, 0x0f, 0x28, 0xec, 0x96, 0xd5, 0x47, 0xbf, 0xee, 0x9a, 0xce, 0x80, 0x3a,
+    0xc0,
+];
 
 #[cfg(feature = "experimental")]
 use ecdsa_fun::fun::{Point as secp256k1Point, Scalar as secp256k1Scalar, G as H};
+use rand::{CryptoRng, RngCore};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 #[cfg(feature = "experimental")]
 use secp256kfun::{g, marker::*, s as sc};
 use sha2::Digest;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
 
 fn _max_secp256k1() -> u256 {
     // let order_injected: [u8;32] = [
@@ -66,65 +95,113 @@ fn _max_secp256k1() -> u256 {
     n
 }
 
-// Hash to curve of the generator G as explained over here:
-// https://crypto.stackexchange.com/a/25603
-// Matches the result here:
-// https://github.com/mimblewimble/rust-secp256k1-zkp/blob/caa49992ae67f131157f6341f4e8b0b0c1e53055/src/constants.rs#L79-L136
-// TODO: this is disgusting and must be removed asap (i.e. change to constant)
-#[allow(non_snake_case)]
-fn H_p() -> secp256k1Point {
-    let hash_H: [u8; 32] =
-        bitcoin_hashes::sha256::Hash::hash(&H.to_bytes_uncompressed()).into_inner();
-    let even_y_prepend_hash_H: [u8; 33] = [2u8]
-        .iter()
-        .chain(hash_H.iter())
-        .cloned()
-        .collect::<Vec<u8>>()
-        .try_into()
-        .unwrap();
-    secp256k1Point::from_bytes(even_y_prepend_hash_H).expect("Alternate basepoint is invalid")
-    // secp256k1Point::from_bytes([2, 80, 146, 155, 116, 193, 160, 73, 84, 183, 139, 75, 96, 53, 233, 122, 94, 7, 138, 90, 15, 40, 236, 150, 213, 71, 191, 238, 154, 206, 128, 58, 192])
-    // .expect("Alternate basepoint is invalid")
-}
-
-#[derive(Copy, Clone, Debug)]
-struct PedersenCommitment<Point, Scalar> {
+// The alternate generators of both groups, decompressed once per proof generation or verification
+// and shared by all the bits.
+struct AltGenerators {
+    g_p: ed25519Point,
+    h_p: secp256k1Point,
+}
+
+impl AltGenerators {
+    fn new() -> Self {
+        // both constants are pinned by the tests, decompression cannot fail
+        AltGenerators {
+            g_p: ed25519PointCompressed(ALT_GENERATOR_ED25519)
+                .decompress()
+                .expect("Alternate ed25519 generator is a valid point"),
+            h_p: secp256k1Point::from_bytes(ALT_GENERATOR_SECP256K1)
+                .expect("Alternate secp256k1 generator is a valid point"),
+        }
+    }
+}
+
+// Draw a uniformly distributed ed25519 scalar by reducing 512 random bits.
+fn random_ed25519_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> ed25519Scalar {
+    let mut bytes = [0u8; 64];
+    rng.fill_bytes(&mut bytes);
+    ed25519Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+// Draw a non-zero secp256k1 scalar by reducing 256 random bits, the bias is negligible.
+fn random_secp256k1_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> secp256k1Scalar {
+    loop {
+        let mut bytes = [0u8; 32];
+        rng.fill_bytes(&mut bytes);
+        if let Some(scalar) = secp256k1Scalar::from_bytes_mod_order(bytes).mark::<NonZero>() {
+            return scalar;
+        }
+    }
+}
+
+// A secret Pedersen commitment blinder, erased when the commitment is dropped since the blinders
+// reveal the bits of the secret.
+trait Blinder {
+    fn erase(&mut self);
+}
+
+impl Blinder for ed25519Scalar {
+    fn erase(&mut self) {
+        self.zeroize();
+    }
+}
+
+// The secp256k1 scalar does not give mutable access to its bytes, the blinder is kept as bytes in
+// a storage zeroized on drop and the scalar is rebuilt when used.
+#[derive(Clone)]
+struct Secp256k1Blinder(Zeroizing<[u8; 32]>);
+
+impl Secp256k1Blinder {
+    fn scalar(&self) -> secp256k1Scalar {
+        secp256k1Scalar::from_bytes(*self.0)
+            .and_then(|scalar| scalar.mark::<NonZero>())
+            .expect("Non-zero blinder")
+    }
+}
+
+impl Blinder for Secp256k1Blinder {
+    fn erase(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+#[derive(Clone)]
+struct PedersenCommitment<Point, Scalar: Blinder> {
     commitment: Point,
     blinder: Scalar,
 }
 
-impl From<(bool, usize)> for PedersenCommitment<ed25519Point, ed25519Scalar> {
-    fn from((bit, index): (bool, usize)) -> PedersenCommitment<ed25519Point, ed25519Scalar> {
-        // let mut csprng = rand_alt::rngs::OsRng;
-        // let blinder = ed25519Scalar::random(&mut csprng);
-        let blinder = match ENTROPY {
-            true => ed25519Scalar::random(&mut rand_alt::rngs::OsRng),
-            false => ed25519Scalar::zero(),
-        };
-
-        let order = u256::from(1u32) << index;
+impl<Point, Scalar: Blinder> Zeroize for PedersenCommitment<Point, Scalar> {
+    fn zeroize(&mut self) {
+        self.blinder.erase();
+    }
+}
 
-        let commitment = match bit {
-            false => blinder * G_p(),
-            true => G * ed25519Scalar::from_bits(order.to_le_bytes()) + blinder * G_p(),
-        };
+impl<Point, Scalar: Blinder> Drop for PedersenCommitment<Point, Scalar> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
 
-        PedersenCommitment {
-            commitment,
-            blinder,
-        }
+impl<Point: fmt::Debug, Scalar: Blinder> fmt::Debug for PedersenCommitment<Point, Scalar> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PedersenCommitment")
+            .field("commitment", &self.commitment)
+            .field("blinder", &crypto::REDACTED)
+            .finish()
     }
 }
 
-impl From<(bool, usize, ed25519Scalar)> for PedersenCommitment<ed25519Point, ed25519Scalar> {
+impl From<(bool, usize, ed25519Scalar, &AltGenerators)>
+    for PedersenCommitment<ed25519Point, ed25519Scalar>
+{
     fn from(
-        (bit, index, blinder): (bool, usize, ed25519Scalar),
+        (bit, index, blinder, generators): (bool, usize, ed25519Scalar, &AltGenerators),
     ) -> PedersenCommitment<ed25519Point, ed25519Scalar> {
         let order = u256::from(1u32) << index;
 
         let commitment = match bit {
-            false => blinder * G_p(),
-            true => G * ed25519Scalar::from_bits(order.to_le_bytes()) + blinder * G_p(),
+            false => blinder * generators.g_p,
+            true => G * ed25519Scalar::from_bits(order.to_le_bytes()) + blinder * generators.g_p,
         };
 
         PedersenCommitment {
@@ -134,76 +211,60 @@ impl From<(bool, usize, ed25519Scalar)> for PedersenCommitment<ed25519Point, ed2
     }
 }
 
-impl From<(bool, usize)> for PedersenCommitment<secp256k1Point, secp256k1Scalar> {
-    fn from((bit, index): (bool, usize)) -> PedersenCommitment<secp256k1Point, secp256k1Scalar> {
-        // let blinder = secp256k1Scalar::random(&mut rand::thread_rng());
-        let blinder = match ENTROPY {
-            true => secp256k1Scalar::random(&mut rand::thread_rng()),
-            false => secp256k1Scalar::one(),
-        };
-
-        let order = u256::from(1u32) << index;
-
-        let order_on_curve = secp256k1Scalar::from_bytes(order.to_be_bytes())
-            .expect("integer greater than curve order");
-        #[allow(non_snake_case)]
-        let H_p = H_p();
-        let blinder_point = g!(blinder * H_p).mark::<NonZero>().unwrap();
-
-        let commitment = match bit {
-            true => g!(order_on_curve * H + blinder_point)
-                .mark::<NonZero>()
-                .unwrap(),
-            false => blinder_point,
-        }
-        .mark::<Normal>();
-
-        PedersenCommitment {
-            commitment,
-            blinder,
-        }
+// The value of the bit at `index` on secp256k1, i.e. `2^index`.
+fn order_on_secp256k1(index: usize) -> Option<secp256k1Scalar<Secret, Zero>> {
+    if index >= 256 {
+        return None;
     }
+    let order = u256::from(1u32) << index;
+    secp256k1Scalar::from_bytes(order.to_be_bytes())
 }
 
-impl From<(bool, usize, secp256k1Scalar)> for PedersenCommitment<secp256k1Point, secp256k1Scalar> {
-    fn from(
-        (bit, index, blinder): (bool, usize, secp256k1Scalar),
-    ) -> PedersenCommitment<secp256k1Point, secp256k1Scalar> {
-        let order = u256::from(1u32) << index;
+impl TryFrom<(bool, usize, secp256k1Scalar, &AltGenerators)>
+    for PedersenCommitment<secp256k1Point, Secp256k1Blinder>
+{
+    type Error = crypto::Error;
 
-        let order_on_curve = secp256k1Scalar::from_bytes(order.to_be_bytes())
-            .expect("integer greater than curve order");
+    fn try_from(
+        (bit, index, blinder, generators): (bool, usize, secp256k1Scalar, &AltGenerators),
+    ) -> Result<PedersenCommitment<secp256k1Point, Secp256k1Blinder>, crypto::Error> {
+        let order_on_curve =
+            order_on_secp256k1(index).ok_or(crypto::Error::InvalidPedersenCommitment)?;
 
         #[allow(non_snake_case)]
-        let H_p = H_p();
+        let H_p = &generators.h_p;
         let blinder_point = g!(blinder * H_p);
 
         let commitment = match bit {
             true => g!(order_on_curve * H + blinder_point)
                 .mark::<NonZero>()
-                .unwrap(),
+                .ok_or(crypto::Error::InvalidPedersenCommitment)?,
             false => blinder_point,
         }
         .mark::<Normal>();
 
-        PedersenCommitment {
+        Ok(PedersenCommitment {
             commitment,
-            blinder,
-        }
+            blinder: Secp256k1Blinder(Zeroizing::new(blinder.to_bytes())),
+        })
     }
 }
 
-fn key_commitment(
+fn key_commitment<R: RngCore + CryptoRng>(
     x_bits: &BitSlice<Lsb0, u8>,
     msb_index: usize,
-) -> Vec<PedersenCommitment<ed25519Point, ed25519Scalar>> {
+    generators: &AltGenerators,
+    rng: &mut R,
+) -> Result<Vec<PedersenCommitment<ed25519Point, ed25519Scalar>>, crypto::Error> {
     let mut commitment: Vec<PedersenCommitment<ed25519Point, ed25519Scalar>> = x_bits
         .iter()
         .take(msb_index)
         .enumerate()
-        .map(|(index, bit)| (*bit, index).into())
+        .map(|(index, bit)| (*bit, index, random_ed25519_scalar(rng), generators).into())
         .collect();
-    let commitment_last = x_bits.get(msb_index).unwrap();
+    let commitment_last = x_bits
+        .get(msb_index)
+        .ok_or(crypto::Error::InvalidProofSecret)?;
     let _commitment_last_value = match *commitment_last {
         true => ed25519Scalar::one(),
         false => ed25519Scalar::zero(),
@@ -211,39 +272,48 @@ fn key_commitment(
     let blinder_last = commitment
         .iter()
         .fold(ed25519Scalar::zero(), |acc, x| acc - x.blinder);
-    commitment.push((*commitment_last, msb_index, blinder_last).into());
-    commitment
+    commitment.push((*commitment_last, msb_index, blinder_last, generators).into());
+    Ok(commitment)
 }
 
-fn key_commitment_secp256k1(
+fn key_commitment_secp256k1<R: RngCore + CryptoRng>(
     x_bits: &BitSlice<Lsb0, u8>,
     msb_index: usize,
-) -> Vec<PedersenCommitment<secp256k1Point, secp256k1Scalar>> {
-    let mut commitment: Vec<PedersenCommitment<secp256k1Point, secp256k1Scalar>> = x_bits
+    generators: &AltGenerators,
+    rng: &mut R,
+) -> Result<Vec<PedersenCommitment<secp256k1Point, Secp256k1Blinder>>, crypto::Error> {
+    let mut commitment = x_bits
         .iter()
         .take(msb_index)
         .enumerate()
-        .map(|(index, bit)| (*bit, index).into())
-        .collect();
-    let commitment_last = x_bits.get(msb_index).unwrap();
-    let blinder_last = commitment
-        .iter()
-        .fold(secp256k1Scalar::zero(), |acc, x| sc!(acc - x.blinder));
+        .map(|(index, bit)| (*bit, index, random_secp256k1_scalar(rng), generators).try_into())
+        .collect::<Result<Vec<PedersenCommitment<secp256k1Point, Secp256k1Blinder>>, _>>()?;
+    let commitment_last = x_bits
+        .get(msb_index)
+        .ok_or(crypto::Error::InvalidProofSecret)?;
+    let blinder_last = commitment.iter().fold(secp256k1Scalar::zero(), |acc, x| {
+        let blinder = x.blinder.scalar();
+        sc!(acc - blinder)
+    });
     commitment.push(
         (
             *commitment_last,
             msb_index,
-            blinder_last.mark::<NonZero>().expect("is zero"),
+            blinder_last
+                .mark::<NonZero>()
+                .ok_or(crypto::Error::InvalidPedersenCommitment)?,
+            generators,
         )
-            .into(),
+            .try_into()?,
     );
-    commitment
+    Ok(commitment)
 }
 
+// The challenge `e_0_i` is kept as the raw ring hash, its reductions on both curves are recomputed
+// when needed.
 #[derive(Clone, Debug, PartialEq)]
 struct RingSignature<ScalarCurveA, ScalarCurveB> {
-    e_g_0_i: ScalarCurveA,
-    e_h_0_i: ScalarCurveB,
+    e_0_i: [u8; 32],
     a_0_i: ScalarCurveA,
     b_0_i: ScalarCurveB,
     a_1_i: ScalarCurveA,
@@ -251,44 +321,61 @@ struct RingSignature<ScalarCurveA, ScalarCurveB> {
 }
 
 fn ring_hash(term0: [u8; 32], term1: [u8; 33], term2: [u8; 32], term3: [u8; 33]) -> [u8; 32] {
-    let preimage: [u8; 130] = term0
-        .iter()
-        .chain(term1.iter())
-        .chain(term2.iter())
-        .chain(term3.iter())
-        .cloned()
-        .collect::<Vec<u8>>()
-        .try_into()
-        .unwrap();
+    let mut preimage = [0u8; 130];
+    preimage[..32].copy_from_slice(&term0);
+    preimage[32..65].copy_from_slice(&term1);
+    preimage[65..97].copy_from_slice(&term2);
+    preimage[97..].copy_from_slice(&term3);
     bitcoin_hashes::sha256::Hash::hash(&preimage).into_inner()
 }
 
+impl RingSignature<ed25519Scalar, secp256k1Scalar> {
+    fn e_g_0_i(&self) -> ed25519Scalar {
+        ed25519Scalar::from_bytes_mod_order(self.e_0_i)
+    }
+
+    fn e_h_0_i(&self) -> secp256k1Scalar<Secret, Zero> {
+        secp256k1Scalar::from_bytes_mod_order(self.e_0_i)
+    }
+}
+
+// All inputs are public, the ed25519 terms use variable time multiscalar multiplications.
 fn verify_ring_sig(
     index: usize,
-    c_g_i: ed25519Point,
-    c_h_i: secp256k1Point,
-    ring_sig: RingSignature<ed25519Scalar, secp256k1Scalar>,
+    c_g_i: &ed25519Point,
+    c_h_i: &secp256k1Point,
+    ring_sig: &RingSignature<ed25519Scalar, secp256k1Scalar>,
+    generators: &AltGenerators,
 ) -> bool {
+    let (g_p, h_p) = (&generators.g_p, &generators.h_p);
     let term0: [u8; 32] = *c_g_i.compress().as_bytes();
     let term1: [u8; 33] = c_h_i.to_bytes();
 
     let order = u256::from(1u32) << index;
-    let order_on_secp256k1 =
-        secp256k1Scalar::from_bytes(order.to_be_bytes()).expect("integer greater than curve order");
-    #[allow(non_snake_case)]
-    let H_p = H_p();
+    let order_on_ed25519 = ed25519Scalar::from_bytes_mod_order(order.to_le_bytes());
+    let order_on_secp256k1 = match order_on_secp256k1(index) {
+        Some(order_on_secp256k1) => order_on_secp256k1,
+        None => return false,
+    };
+
+    let e_h_0_i = ring_sig.e_h_0_i();
 
     // compute e_1_i
     let e_1_i = {
-        let term2: [u8; 32] = *(ring_sig.a_1_i * G_p() - ring_sig.e_g_0_i * c_g_i)
-            .compress()
-            .as_bytes();
+        let term2: [u8; 32] = *ed25519Point::vartime_multiscalar_mul(
+            [ring_sig.a_1_i, -ring_sig.e_g_0_i()].iter(),
+            [*g_p, *c_g_i].iter(),
+        )
+        .compress()
+        .as_bytes();
 
-        let term3: [u8; 33] = g!(ring_sig.b_1_i * H_p - ring_sig.e_h_0_i * c_h_i)
+        let term3: [u8; 33] = match g!(ring_sig.b_1_i * h_p - e_h_0_i * c_h_i)
             .mark::<Normal>()
             .mark::<NonZero>()
-            .expect("is zero")
-            .to_bytes();
+        {
+            Some(term3) => term3.to_bytes(),
+            None => return false,
+        };
 
         ring_hash(term0, term1, term2, term3)
     };
@@ -297,101 +384,78 @@ fn verify_ring_sig(
 
     // compute e_0_i
     let e_0_i = {
-        let term2: [u8; 32] = *(ring_sig.a_0_i * G_p()
-            - e_g_1_i * (c_g_i - ed25519Scalar::from_bytes_mod_order(order.to_le_bytes()) * G))
-            .compress()
-            .as_bytes();
+        let term2: [u8; 32] = *ed25519Point::vartime_multiscalar_mul(
+            [ring_sig.a_0_i, -e_g_1_i, e_g_1_i * order_on_ed25519].iter(),
+            [*g_p, *c_g_i, G].iter(),
+        )
+        .compress()
+        .as_bytes();
 
-        let term3: [u8; 33] = g!(ring_sig.b_0_i * H_p - e_h_1_i * (c_h_i - order_on_secp256k1 * H))
-            .mark::<Normal>()
-            .mark::<NonZero>()
-            .expect("is zero")
-            .to_bytes();
+        let term3: [u8; 33] =
+            match g!(ring_sig.b_0_i * h_p - e_h_1_i * (c_h_i - order_on_secp256k1 * H))
+                .mark::<Normal>()
+                .mark::<NonZero>()
+            {
+                Some(term3) => term3.to_bytes(),
+                None => return false,
+            };
 
         ring_hash(term0, term1, term2, term3)
     };
 
-    let e_g_0_i = ed25519Scalar::from_bytes_mod_order(e_0_i);
-    let e_h_0_i = secp256k1Scalar::from_bytes_mod_order(e_0_i);
-
-    // compare computed results with provided values
-    (e_g_0_i == ring_sig.e_g_0_i) && (e_h_0_i == ring_sig.e_h_0_i)
+    e_0_i[..].ct_eq(&ring_sig.e_0_i[..]).into()
 }
 
-impl
-    From<(
-        usize,
-        bool,
-        PedersenCommitment<ed25519Point, ed25519Scalar>,
-        PedersenCommitment<secp256k1Point, secp256k1Scalar>,
-    )> for RingSignature<ed25519Scalar, secp256k1Scalar>
-{
-    fn from(
-        (index, b_i, c_g_i, c_h_i): (
-            usize,
-            bool,
-            PedersenCommitment<ed25519Point, ed25519Scalar>,
-            PedersenCommitment<secp256k1Point, secp256k1Scalar>,
-        ),
-    ) -> Self {
+impl RingSignature<ed25519Scalar, secp256k1Scalar> {
+    fn generate<R: RngCore + CryptoRng>(
+        index: usize,
+        b_i: bool,
+        c_g_i: PedersenCommitment<ed25519Point, ed25519Scalar>,
+        c_h_i: PedersenCommitment<secp256k1Point, Secp256k1Blinder>,
+        generators: &AltGenerators,
+        rng: &mut R,
+    ) -> Result<Self, crypto::Error> {
         // first confirm that the pedersen commitments are correctly calculated
-        assert_eq!(
-            c_g_i.commitment,
-            PedersenCommitment::from((b_i, index, c_g_i.blinder)).commitment,
-            "incorrect pedersen commitment!"
-        );
-        assert_eq!(
-            c_h_i.commitment,
-            PedersenCommitment::from((b_i, index, c_h_i.blinder.clone())).commitment,
-            "incorrect pedersen commitment!"
-        );
+        if c_g_i.commitment
+            != PedersenCommitment::from((b_i, index, c_g_i.blinder, generators)).commitment
+            || c_h_i.commitment
+                != PedersenCommitment::try_from((b_i, index, c_h_i.blinder.scalar(), generators))?
+                    .commitment
+        {
+            return Err(crypto::Error::InvalidPedersenCommitment);
+        }
         let term0: [u8; 32] = *c_g_i.commitment.compress().as_bytes();
         let term1: [u8; 33] = c_h_i.commitment.to_bytes();
 
-        // let j_i = ed25519Scalar::random(&mut csprng);
-        // let k_i = secp256k1Scalar::random(&mut rand::thread_rng());
-        let j_i = match ENTROPY {
-            true => ed25519Scalar::random(&mut rand_alt::rngs::OsRng),
-            false => ed25519Scalar::zero(),
-        };
-        let k_i = match ENTROPY {
-            true => secp256k1Scalar::random(&mut rand::thread_rng()),
-            false => secp256k1Scalar::one(),
-        };
+        let j_i = random_ed25519_scalar(rng);
+        let k_i = random_secp256k1_scalar(rng);
+        let c_h_i_blinder = c_h_i.blinder.scalar();
 
         #[allow(non_snake_case)]
-        let H_p = H_p();
+        let (G_p, H_p) = (generators.g_p, &generators.h_p);
 
-        let term2_generated = *(j_i * G_p()).compress().as_bytes();
+        let term2_generated = *(j_i * G_p).compress().as_bytes();
         let term3_generated = g!(k_i * H_p).mark::<Normal>().to_bytes();
 
         // clippy insists it's better to avoid reuse like this ¯\_(ツ)_/¯
         let e_0_i = ring_hash(term0, term1, term2_generated, term3_generated);
         let e_g_0_i = ed25519Scalar::from_bytes_mod_order(e_0_i);
 
-        let (e_g_0_i, e_h_0_i, a_0_i, a_1_i, b_0_i, b_1_i) = if b_i {
+        let (e_0_i, a_0_i, a_1_i, b_0_i, b_1_i) = if b_i {
             let e_h_0_i = secp256k1Scalar::from_bytes_mod_order(e_0_i)
                 .mark::<NonZero>()
-                .expect("is zero");
+                .ok_or(crypto::Error::InvalidRingSignature)?;
 
-            // let a_1_i = ed25519Scalar::random(&mut csprng);
-            // let b_1_i = secp256k1Scalar::random(&mut rand::thread_rng());
-            let a_1_i = match ENTROPY {
-                true => ed25519Scalar::random(&mut rand_alt::rngs::OsRng),
-                false => ed25519Scalar::zero(),
-            };
-            let b_1_i = match ENTROPY {
-                true => secp256k1Scalar::random(&mut rand::thread_rng()),
-                false => secp256k1Scalar::one(),
-            };
+            let a_1_i = random_ed25519_scalar(rng);
+            let b_1_i = random_secp256k1_scalar(rng);
 
-            let term2 = *(a_1_i * G_p() - e_g_0_i * c_g_i.commitment)
+            let term2 = *(a_1_i * G_p - e_g_0_i * c_g_i.commitment)
                 .compress()
                 .as_bytes();
             let term3 = g!(b_1_i * H_p - e_h_0_i * c_h_i.commitment)
                 .mark::<Normal>()
                 .mark::<NonZero>()
-                .expect("is zero")
+                .ok_or(crypto::Error::InvalidRingSignature)?
                 .to_bytes();
 
             let e_1_i = ring_hash(term0, term1, term2, term3);
@@ -400,15 +464,15 @@ impl
 
             let a_0_i = j_i + e_g_1_i * c_g_i.blinder;
 
-            let b_0_i = sc!(k_i + e_h_1_i * c_h_i.blinder)
+            let b_0_i = sc!(k_i + e_h_1_i * c_h_i_blinder)
                 .mark::<NonZero>()
-                .unwrap();
+                .ok_or(crypto::Error::InvalidRingSignature)?;
 
             let order = u256::from(1u32) << index;
-            let order_on_secp256k1 = secp256k1Scalar::from_bytes(order.to_be_bytes())
-                .expect("integer greater than curve order");
+            let order_on_secp256k1 =
+                order_on_secp256k1(index).ok_or(crypto::Error::InvalidRingSignature)?;
 
-            let term2_calculated: [u8; 32] = *(a_0_i * G_p()
+            let term2_calculated: [u8; 32] = *(a_0_i * G_p
                 - e_g_1_i * (c_g_i.commitment - ed25519Scalar::from_bits(order.to_le_bytes()) * G))
                 .compress()
                 .as_bytes();
@@ -417,43 +481,32 @@ impl
                 g!(b_0_i * H_p - e_h_1_i * (c_h_i.commitment - order_on_secp256k1 * H))
                     .mark::<Normal>()
                     .mark::<NonZero>()
-                    .expect("is zero")
+                    .ok_or(crypto::Error::InvalidRingSignature)?
                     .to_bytes();
 
-            assert_eq!(
-                term2_calculated, term2_generated,
-                "term2 bit=1 should match"
-            );
-            assert_eq!(
-                term3_calculated, term3_generated,
-                "term3 bit=1 should match"
-            );
-
+            // the ring must close, otherwise the bit commitment is invalid
             let e_0_p = ring_hash(term0, term1, term2_calculated, term3_calculated);
-            assert_eq!(e_0_i, e_0_p, "ring hash bit=1 should match");
-
-            (e_g_0_i, e_h_0_i, a_0_i, a_1_i, b_0_i, b_1_i)
+            if term2_calculated != term2_generated
+                || term3_calculated != term3_generated
+                || e_0_i != e_0_p
+            {
+                return Err(crypto::Error::InvalidRingSignature);
+            }
+
+            (e_0_i, a_0_i, a_1_i, b_0_i, b_1_i)
         } else {
             let e_1_i = e_0_i;
             let e_g_1_i = e_g_0_i;
             let e_h_1_i = secp256k1Scalar::from_bytes_mod_order(e_1_i);
 
-            // let a_0_i = ed25519Scalar::random(&mut csprng);
-            // let b_0_i = secp256k1Scalar::random(&mut rand::thread_rng());
-            let a_0_i = match ENTROPY {
-                true => ed25519Scalar::random(&mut rand_alt::rngs::OsRng),
-                false => ed25519Scalar::zero(),
-            };
-            let b_0_i = match ENTROPY {
-                true => secp256k1Scalar::random(&mut rand::thread_rng()),
-                false => secp256k1Scalar::one(),
-            };
+            let a_0_i = random_ed25519_scalar(rng);
+            let b_0_i = random_secp256k1_scalar(rng);
 
             let order = u256::from(1u32) << index;
-            let order_on_secp256k1 = secp256k1Scalar::from_bytes(order.to_be_bytes())
-                .expect("integer greater than curve order");
+            let order_on_secp256k1 =
+                order_on_secp256k1(index).ok_or(crypto::Error::InvalidRingSignature)?;
 
-            let term2 = *(a_0_i * G_p()
+            let term2 = *(a_0_i * G_p
                 - e_g_1_i
                     * (c_g_i.commitment
                         - ed25519Scalar::from_bytes_mod_order(order.to_le_bytes()) * G))
@@ -462,55 +515,50 @@ impl
             let term3 = g!(b_0_i * H_p - e_h_1_i * (c_h_i.commitment - order_on_secp256k1 * H))
                 .mark::<Normal>()
                 .mark::<NonZero>()
-                .expect("is zero")
+                .ok_or(crypto::Error::InvalidRingSignature)?
                 .to_bytes();
 
             let e_0_i = ring_hash(term0, term1, term2, term3);
             let e_g_0_i = ed25519Scalar::from_bytes_mod_order(e_0_i);
             let e_h_0_i = secp256k1Scalar::from_bytes_mod_order(e_0_i)
                 .mark::<NonZero>()
-                .expect("is zero");
+                .ok_or(crypto::Error::InvalidRingSignature)?;
 
             let a_1_i = j_i + e_g_0_i * c_g_i.blinder;
 
-            let b_1_i = sc!(k_i + e_h_0_i * c_h_i.blinder)
+            let b_1_i = sc!(k_i + e_h_0_i * c_h_i_blinder)
                 .mark::<NonZero>()
-                .expect("is zero");
+                .ok_or(crypto::Error::InvalidRingSignature)?;
 
             // verification
-            let term2_calculated: [u8; 32] = *(a_1_i * G_p() - e_g_0_i * c_g_i.commitment)
+            let term2_calculated: [u8; 32] = *(a_1_i * G_p - e_g_0_i * c_g_i.commitment)
                 .compress()
                 .as_bytes();
 
             let term3_calculated: [u8; 33] = g!(b_1_i * H_p - e_h_0_i * c_h_i.commitment)
                 .mark::<Normal>()
                 .mark::<NonZero>()
-                .expect("is zero")
+                .ok_or(crypto::Error::InvalidRingSignature)?
                 .to_bytes();
 
-            assert_eq!(
-                term2_calculated, term2_generated,
-                "term2 bit=0 should match"
-            );
-            assert_eq!(
-                term3_calculated, term3_generated,
-                "term3 bit=0 should match"
-            );
-
             let e_1_p = ring_hash(term0, term1, term2_calculated, term3_calculated);
-            assert_eq!(e_1_i, e_1_p, "ring hash bit=0 should match");
-
-            (e_g_0_i, e_h_0_i, a_0_i, a_1_i, b_0_i, b_1_i)
+            if term2_calculated != term2_generated
+                || term3_calculated != term3_generated
+                || e_1_i != e_1_p
+            {
+                return Err(crypto::Error::InvalidRingSignature);
+            }
+
+            (e_0_i, a_0_i, a_1_i, b_0_i, b_1_i)
         };
 
-        RingSignature {
-            e_g_0_i,
-            e_h_0_i,
+        Ok(RingSignature {
+            e_0_i,
             a_0_i,
             b_0_i,
             a_1_i,
             b_1_i,
-        }
+        })
     }
 }
 
@@ -534,9 +582,14 @@ impl Encodable for ed25519Point {
 impl Decodable for ed25519Point {
     fn consensus_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         let bytes: [u8; 32] = Decodable::consensus_decode(d)?;
-        Ok(ed25519PointCompressed::from_slice(&bytes)
+        let point = ed25519PointCompressed(bytes)
             .decompress()
-            .unwrap())
+            .ok_or(consensus::Error::ParseFailed("Invalid ed25519 point"))?;
+        // reject non-canonical encodings and points outside of the prime order subgroup
+        if point.compress().to_bytes() != bytes || !point.is_torsion_free() {
+            return Err(consensus::Error::ParseFailed("Non-canonical ed25519 point"));
+        }
+        Ok(point)
     }
 }
 
@@ -549,7 +602,8 @@ impl Encodable for secp256k1Point {
 impl Decodable for secp256k1Point {
     fn consensus_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         let bytes: [u8; 33] = Decodable::consensus_decode(d)?;
-        Ok(secp256k1Point::from_bytes(bytes).unwrap())
+        secp256k1Point::from_bytes(bytes)
+            .ok_or(consensus::Error::ParseFailed("Invalid secp256k1 point"))
     }
 }
 
@@ -562,7 +616,9 @@ impl Encodable for ed25519Scalar {
 impl Decodable for ed25519Scalar {
     fn consensus_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         let bytes: [u8; 32] = Decodable::consensus_decode(d)?;
-        Ok(ed25519Scalar::from_bytes_mod_order(bytes))
+        ed25519Scalar::from_canonical_bytes(bytes).ok_or(consensus::Error::ParseFailed(
+            "Non-canonical ed25519 scalar",
+        ))
     }
 }
 
@@ -575,9 +631,11 @@ impl Encodable for secp256k1Scalar {
 impl Decodable for secp256k1Scalar {
     fn consensus_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         let bytes: [u8; 32] = Decodable::consensus_decode(d)?;
-        Ok(secp256k1Scalar::from_bytes_mod_order(bytes)
-            .mark::<NonZero>()
-            .unwrap())
+        secp256k1Scalar::from_bytes(bytes)
+            .and_then(|scalar| scalar.mark::<NonZero>())
+            .ok_or(consensus::Error::ParseFailed(
+                "Non-canonical or zero secp256k1 scalar",
+            ))
     }
 }
 
@@ -605,15 +663,75 @@ impl Encodable for ecdsa_fun::Signature {
 impl Decodable for ecdsa_fun::Signature {
     fn consensus_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         let bytes: [u8; 64] = Decodable::consensus_decode(d)?;
-        Ok(ecdsa_fun::Signature::from_bytes(bytes).unwrap())
+        ecdsa_fun::Signature::from_bytes(bytes)
+            .ok_or(consensus::Error::ParseFailed("Invalid ECDSA signature"))
+    }
+}
+
+/// Version byte of the compact encoding of a [`DLEQProof`], see [`DLEQProofFormat::Compact`].
+pub const DLEQ_PROOF_COMPACT_VERSION: u8 = 0x01;
+
+/// First byte of the legacy encoding of a [`DLEQProof`]. The legacy encoding has no version
+/// byte, it starts with the number of Pedersen commitments as a little endian `u16` whose low byte,
+/// i.e. [`DLEQ_BITS`], plays the role of the version byte.
+pub const DLEQ_PROOF_LEGACY_VERSION: u8 = DLEQ_BITS as u8;
+
+/// Encoding formats of a [`DLEQProof`], told apart by the first byte of the serialized proof.
+/// Decoding accepts both formats, encoding with [`CanonicalBytes`] produces the compact one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DLEQProofFormat {
+    /// Length prefixed vectors of full points and scalars, with both reductions of every ring
+    /// signature challenge. Kept to exchange proofs with peers running previous versions.
+    Legacy,
+    /// Version byte followed by a fixed number of elements without length prefixes, raw ring
+    /// signature challenges, and secp256k1 commitments as x coordinates with their parities
+    /// packed in a bit field.
+    Compact,
+}
+
+impl DLEQProofFormat {
+    /// The first byte of a proof serialized in this format.
+    pub fn version_byte(&self) -> u8 {
+        match self {
+            DLEQProofFormat::Legacy => DLEQ_PROOF_LEGACY_VERSION,
+            DLEQProofFormat::Compact => DLEQ_PROOF_COMPACT_VERSION,
+        }
+    }
+}
+
+// Add two 256 bits little endian integers, `None` on overflow.
+fn checked_add_le(a: &[u8; 32], b: &[u8; 32]) -> Option<[u8; 32]> {
+    let mut sum = [0u8; 32];
+    let mut carry = 0u16;
+    for ((s, a), b) in sum.iter_mut().zip(a.iter()).zip(b.iter()) {
+        let res = *a as u16 + *b as u16 + carry;
+        *s = res as u8;
+        carry = res >> 8;
+    }
+    match carry {
+        0 => Some(sum),
+        _ => None,
+    }
+}
+
+// Recover the raw ring hash from its two reductions found in the legacy encoding, i.e. the hash
+// read in little endian modulo l and read in big endian modulo n. The hash is one of the at most
+// 16 values `e_g + k * l` below 2^256 whose big endian reduction is `e_h`.
+fn recover_ring_hash(e_g: &ed25519Scalar, e_h: &secp256k1Scalar) -> Option<[u8; 32]> {
+    let order = BASEPOINT_ORDER.to_bytes();
+    let mut candidate = e_g.to_bytes();
+    loop {
+        if secp256k1Scalar::from_bytes_mod_order(candidate) == *e_h {
+            return Some(candidate);
+        }
+        candidate = checked_add_le(&candidate, &order)?;
     }
 }
 
 impl Encodable for RingSignature<ed25519Scalar, secp256k1Scalar> {
     fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
         let mut len = 0usize;
-        len += self.e_g_0_i.consensus_encode(writer)?;
-        len += self.e_h_0_i.consensus_encode(writer)?;
+        len += self.e_0_i.consensus_encode(writer)?;
         len += self.a_0_i.consensus_encode(writer)?;
         len += self.b_0_i.consensus_encode(writer)?;
         len += self.a_1_i.consensus_encode(writer)?;
@@ -624,40 +742,197 @@ impl Encodable for RingSignature<ed25519Scalar, secp256k1Scalar> {
 
 impl Decodable for RingSignature<ed25519Scalar, secp256k1Scalar> {
     fn consensus_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(RingSignature {
+            e_0_i: Decodable::consensus_decode(d)?,
+            a_0_i: Decodable::consensus_decode(d)?,
+            b_0_i: Decodable::consensus_decode(d)?,
+            a_1_i: Decodable::consensus_decode(d)?,
+            b_1_i: Decodable::consensus_decode(d)?,
+        })
+    }
+}
+
+impl RingSignature<ed25519Scalar, secp256k1Scalar> {
+    fn legacy_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
+        let mut len = 0usize;
+        len += self.e_g_0_i().consensus_encode(writer)?;
+        len += self.e_h_0_i().to_bytes().consensus_encode(writer)?;
+        len += self.a_0_i.consensus_encode(writer)?;
+        len += self.b_0_i.consensus_encode(writer)?;
+        len += self.a_1_i.consensus_encode(writer)?;
+        len += self.b_1_i.consensus_encode(writer)?;
+        Ok(len)
+    }
+
+    fn legacy_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
         let e_g_0_i = Decodable::consensus_decode(d)?;
         let e_h_0_i = Decodable::consensus_decode(d)?;
-        let a_0_i = Decodable::consensus_decode(d)?;
-        let b_0_i = Decodable::consensus_decode(d)?;
-        let a_1_i = Decodable::consensus_decode(d)?;
-        let b_1_i = Decodable::consensus_decode(d)?;
+        let e_0_i = recover_ring_hash(&e_g_0_i, &e_h_0_i).ok_or(consensus::Error::ParseFailed(
+            "Inconsistent ring signature challenge",
+        ))?;
         Ok(RingSignature {
-            e_g_0_i,
-            e_h_0_i,
-            a_0_i,
-            b_0_i,
-            a_1_i,
-            b_1_i,
+            e_0_i,
+            a_0_i: Decodable::consensus_decode(d)?,
+            b_0_i: Decodable::consensus_decode(d)?,
+            a_1_i: Decodable::consensus_decode(d)?,
+            b_1_i: Decodable::consensus_decode(d)?,
         })
     }
 }
 
 impl Encodable for DLEQProof {
     fn consensus_encode<W: std::io::Write>(&self, writer: &mut W) -> Result<usize, std::io::Error> {
+        self.encode_with_format(DLEQProofFormat::Compact, writer)
+    }
+}
+
+// Decode the little endian `u16` length of a legacy vector, it must match the number of bits.
+fn decode_bits_len<D: std::io::Read>(d: &mut D) -> Result<(), consensus::Error> {
+    match u16::consensus_decode(d)? as usize {
+        DLEQ_BITS => Ok(()),
+        _ => Err(consensus::Error::ParseFailed(
+            "Invalid number of elements in DLEQ proof",
+        )),
+    }
+}
+
+// Decode one element per bit with `decode`, the number of elements is fixed so the vector is
+// never oversized.
+fn decode_bits<T, D: std::io::Read>(
+    d: &mut D,
+    mut decode: impl FnMut(&mut D) -> Result<T, consensus::Error>,
+) -> Result<Vec<T>, consensus::Error> {
+    let mut ret = Vec::with_capacity(DLEQ_BITS);
+    for _ in 0..DLEQ_BITS {
+        ret.push(decode(d)?);
+    }
+    Ok(ret)
+}
+
+impl Decodable for DLEQProof {
+    fn consensus_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match u8::consensus_decode(d)? {
+            DLEQ_PROOF_COMPACT_VERSION => Self::compact_decode(d),
+            DLEQ_PROOF_LEGACY_VERSION => {
+                // second byte of the length of the first vector
+                if u8::consensus_decode(d)? != 0 {
+                    return Err(consensus::Error::ParseFailed(
+                        "Invalid number of elements in DLEQ proof",
+                    ));
+                }
+                Self::legacy_decode(d)
+            }
+            _ => Err(consensus::Error::ParseFailed("Unknown DLEQ proof version")),
+        }
+    }
+}
+
+impl DLEQProof {
+    /// Serialize the proof in the given format. [`CanonicalBytes::as_canonical_bytes`] uses the
+    /// compact format, the legacy format is only needed by peers running previous versions.
+    pub fn to_bytes_with_format(&self, format: DLEQProofFormat) -> Vec<u8> {
+        let mut encoder = Vec::new();
+        let len = self.encode_with_format(format, &mut encoder).unwrap();
+        debug_assert_eq!(len, encoder.len());
+        encoder
+    }
+
+    fn encode_with_format<W: std::io::Write>(
+        &self,
+        format: DLEQProofFormat,
+        writer: &mut W,
+    ) -> Result<usize, std::io::Error> {
         let mut len = 0usize;
-        len += self.c_g.consensus_encode(writer)?;
-        len += self.c_h.consensus_encode(writer)?;
-        len += self.ring_signatures.consensus_encode(writer)?;
+        match format {
+            DLEQProofFormat::Legacy => {
+                len += self.c_g.consensus_encode(writer)?;
+                len += self.c_h.consensus_encode(writer)?;
+                len += (self.ring_signatures.len() as u16).consensus_encode(writer)?;
+                for ring_sig in self.ring_signatures.iter() {
+                    len += ring_sig.legacy_encode(writer)?;
+                }
+            }
+            DLEQProofFormat::Compact => {
+                if self.c_g.len() != DLEQ_BITS
+                    || self.c_h.len() != DLEQ_BITS
+                    || self.ring_signatures.len() != DLEQ_BITS
+                {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "Invalid number of elements in DLEQ proof",
+                    ));
+                }
+                len += DLEQ_PROOF_COMPACT_VERSION.consensus_encode(writer)?;
+                for c_g_i in self.c_g.iter() {
+                    len += c_g_i.consensus_encode(writer)?;
+                }
+                // the prefixes of the compressed secp256k1 commitments are packed in a bit field
+                let c_h: Vec<[u8; 33]> = self.c_h.iter().map(|c_h_i| c_h_i.to_bytes()).collect();
+                let mut parities = [0u8; 32];
+                for (index, c_h_i) in c_h.iter().enumerate() {
+                    parities[index / 8] |= (c_h_i[0] & 1) << (index % 8);
+                }
+                len += parities.consensus_encode(writer)?;
+                for c_h_i in c_h.iter() {
+                    writer.write_all(&c_h_i[1..])?;
+                    len += 32;
+                }
+                for ring_sig in self.ring_signatures.iter() {
+                    len += ring_sig.consensus_encode(writer)?;
+                }
+            }
+        }
         len += self.pok_0.consensus_encode(writer)?;
         len += self.pok_1.consensus_encode(writer)?;
         Ok(len)
     }
-}
 
-impl Decodable for DLEQProof {
-    fn consensus_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        let c_g = Decodable::consensus_decode(d)?;
-        let c_h = Decodable::consensus_decode(d)?;
-        let ring_signatures = Decodable::consensus_decode(d)?;
+    // Decode the legacy format after its first two bytes, i.e. the length of the first vector.
+    fn legacy_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let c_g = decode_bits(d, ed25519Point::consensus_decode)?;
+        decode_bits_len(d)?;
+        let c_h = decode_bits(d, secp256k1Point::consensus_decode)?;
+        decode_bits_len(d)?;
+        let ring_signatures = decode_bits(
+            d,
+            RingSignature::<ed25519Scalar, secp256k1Scalar>::legacy_decode,
+        )?;
+        let pok_0 = Decodable::consensus_decode(d)?;
+        let pok_1 = Decodable::consensus_decode(d)?;
+
+        Ok(DLEQProof {
+            c_g,
+            c_h,
+            ring_signatures,
+            pok_0,
+            pok_1,
+        })
+    }
+
+    // Decode the compact format after its version byte.
+    fn compact_decode<D: std::io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let c_g = decode_bits(d, ed25519Point::consensus_decode)?;
+        let parities: [u8; 32] = Decodable::consensus_decode(d)?;
+        // the bits past the last commitment must be unset for the encoding to be unique
+        if parities[DLEQ_BITS / 8] >> (DLEQ_BITS % 8) != 0 {
+            return Err(consensus::Error::ParseFailed(
+                "Non-canonical secp256k1 commitment parities",
+            ));
+        }
+        let mut index = 0;
+        let c_h = decode_bits(d, |d| {
+            let x: [u8; 32] = Decodable::consensus_decode(d)?;
+            let mut bytes = [0u8; 33];
+            bytes[0] = 0x02 | ((parities[index / 8] >> (index % 8)) & 1);
+            bytes[1..].copy_from_slice(&x);
+            index += 1;
+            secp256k1Point::from_bytes(bytes)
+                .ok_or(consensus::Error::ParseFailed("Invalid secp256k1 point"))
+        })?;
+        let ring_signatures = decode_bits(
+            d,
+            RingSignature::<ed25519Scalar, secp256k1Scalar>::consensus_decode,
+        )?;
         let pok_0 = Decodable::consensus_decode(d)?;
         let pok_1 = Decodable::consensus_decode(d)?;
 
@@ -702,13 +977,23 @@ fn _zeroize_highest_bits(x: [u8; 32], highest_bit: usize) -> [u8; 32] {
 }
 
 impl DLEQProof {
-    pub(crate) fn generate(x: [u8; 32]) -> Self {
+    /// Generate a proof for the 252 bits secret `x`, encoded in little endian, drawing all the
+    /// blinders and nonces from `rng`. The proof is deterministic for a given secret and random
+    /// number generator state. Returns an error if the secret is zero or does not fit in
+    /// [`DLEQ_BITS`] bits.
+    pub(crate) fn generate<R: RngCore + CryptoRng>(
+        x: [u8; 32],
+        rng: &mut R,
+    ) -> Result<Self, crypto::Error> {
         // convention: start count at 0
-        let msb_index = 251;
+        let msb_index = DLEQ_BITS - 1;
 
-        let x_bits = BitSlice::<Lsb0, u8>::from_slice(&x).unwrap();
+        let x_bits =
+            BitSlice::<Lsb0, u8>::from_slice(&x).map_err(|_| crypto::Error::InvalidProofSecret)?;
 
-        assert!(x_bits[msb_index + 1..].iter().all(|bit| !(*bit)));
+        if x_bits[msb_index + 1..].iter().any(|bit| *bit) {
+            return Err(crypto::Error::InvalidProofSecret);
+        }
 
         let x_ed25519 = ed25519Scalar::from_bytes_mod_order(x);
         #[allow(non_snake_case)]
@@ -718,21 +1003,24 @@ impl DLEQProof {
         let x_secp256k1: secp256k1Scalar<_> =
             secp256k1Scalar::from_bytes_mod_order(reverse_endianness(&x))
                 .mark::<NonZero>()
-                .expect("x is zero");
+                .ok_or(crypto::Error::InvalidProofSecret)?;
         #[allow(non_snake_case)]
         let xH_p = g!(x_secp256k1 * H).mark::<Normal>();
 
-        let c_g = key_commitment(x_bits, msb_index);
-        let c_h = key_commitment_secp256k1(x_bits, msb_index);
+        let generators = AltGenerators::new();
+        let c_g = key_commitment(x_bits, msb_index, &generators, rng)?;
+        let c_h = key_commitment_secp256k1(x_bits, msb_index, &generators, rng)?;
 
-        let ring_signatures: Vec<RingSignature<ed25519Scalar, secp256k1Scalar>> = x_bits
+        let ring_signatures = x_bits
             .iter()
             .take(msb_index + 1)
             .enumerate()
             .zip(c_g.clone())
             .zip(c_h.clone())
-            .map(|(((index, b_i), c_g_i), c_h_i)| RingSignature::from((index, *b_i, c_g_i, c_h_i)))
-            .collect();
+            .map(|(((index, b_i), c_g_i), c_h_i)| {
+                RingSignature::generate(index, *b_i, c_g_i, c_h_i, &generators, rng)
+            })
+            .collect::<Result<Vec<RingSignature<ed25519Scalar, secp256k1Scalar>>, _>>()?;
 
         let c_g: Vec<ed25519Point> = c_g.iter().map(|pc| pc.commitment).collect();
         let c_h: Vec<secp256k1Point> = c_h.iter().map(|pc| pc.commitment).collect();
@@ -763,36 +1051,48 @@ impl DLEQProof {
 
         let pok_0 = (alpha_G, response);
 
-        // Proof of Knowledge secp256k1 (ECDSA)
-        let nonce_gen = ecdsa_fun::nonce::Synthetic::<
-            sha2::Sha256,
-            ecdsa_fun::nonce::GlobalRng<rand::rngs::ThreadRng>,
-        >::default();
+        // Proof of Knowledge secp256k1 (ECDSA), the nonce is derived from the secret key and the
+        // commitments which already contain the randomness
+        let nonce_gen = ecdsa_fun::nonce::Deterministic::<sha2::Sha256>::default();
         let pok_1_message = serialize(&c_h);
-        let pok_1_message_hash: [u8; 32] = sha2::Sha256::digest(&pok_1_message).try_into().unwrap();
+        let pok_1_message_hash: [u8; 32] = sha2::Sha256::digest(&pok_1_message).into();
         let ecdsa = ecdsa_fun::ECDSA::new(nonce_gen);
 
         let pok_1 = ecdsa.sign(&x_secp256k1, &pok_1_message_hash);
 
-        assert!(ecdsa.verify(&xH_p, &pok_1_message_hash, &pok_1));
+        if !ecdsa.verify(&xH_p, &pok_1_message_hash, &pok_1) {
+            return Err(crypto::Error::InvalidProofOfKnowledge);
+        }
 
-        DLEQProof {
+        Ok(DLEQProof {
             c_g,
             c_h,
             ring_signatures,
             pok_0,
             pok_1,
-        }
+        })
     }
 
-    pub(crate) fn verify(
+    /// Verify the proof against the ed25519 point `xG_p` and the secp256k1 point `xH_p`. Never
+    /// panics, a malformed proof, e.g. received from a remote peer, returns an error.
+    ///
+    /// The ring signature challenges are compared in constant time. The proof and the points are
+    /// public, they are sent by the counterparty, so the checks rejecting malformed proofs, e.g.
+    /// an identity intermediate point or a wrong commitment sum, return early without leaking
+    /// anything about the secret.
+    pub fn verify(
         &self,
         #[allow(non_snake_case)] xG_p: ed25519Point,
         #[allow(non_snake_case)] xH_p: secp256k1Point,
     ) -> Result<(), crypto::Error> {
-        assert_eq!(252, self.c_g.len());
-        assert_eq!(252, self.c_h.len());
-        assert_eq!(252, self.ring_signatures.len());
+        for found in [self.c_g.len(), self.c_h.len(), self.ring_signatures.len()] {
+            if found != DLEQ_BITS {
+                return Err(crypto::Error::InvalidProofSize {
+                    expected: DLEQ_BITS,
+                    found,
+                });
+            }
+        }
 
         // Commitments
         let commitment_agg_ed25519 = self.c_g.iter().sum();
@@ -813,18 +1113,8 @@ impl DLEQProof {
         }
 
         // Ring signatures
-        let valid_ring_signatures = self
-            .c_g
-            .clone()
-            .iter()
-            .enumerate()
-            .zip(self.c_h.clone())
-            .zip(self.ring_signatures.clone())
-            .all(|(((index, c_g_i), c_h_i), ring_sig)| {
-                verify_ring_sig(index, *c_g_i, c_h_i, ring_sig)
-            });
-
-        if !(valid_ring_signatures) {
+        let generators = AltGenerators::new();
+        if !self.ring_signatures_are_valid(&generators) {
             return Err(crypto::Error::InvalidRingSignature);
         }
 
@@ -832,29 +1122,166 @@ impl DLEQProof {
         // ed25519 (edDSA)
         #[allow(non_snake_case)]
         let (alpha_G, r) = self.pok_0;
-        let mut challenge_preimage = vec![];
-        challenge_preimage.extend_from_slice(alpha_G.compress().as_bytes());
-        challenge_preimage.extend_from_slice(xG_p.compress().as_bytes());
-        challenge_preimage.extend_from_slice(serialize(&self.c_g).as_slice());
-        let challenge = monero::cryptonote::hash::keccak_256(&challenge_preimage);
-
-        if r * G != alpha_G + ed25519Scalar::from_bytes_mod_order(challenge) * xG_p {
+        if r * G != alpha_G + self.pok_0_challenge(&xG_p) * xG_p {
             return Err(crypto::Error::InvalidProofOfKnowledge);
         }
 
         // secp256k1 (ECDSA)
-        let ecdsa = ecdsa_fun::ECDSA::verify_only();
-        let pok_1_message = serialize(&self.c_h);
-        let pok_1_message_hash: [u8; 32] = sha2::Sha256::digest(pok_1_message.as_slice())
-            .try_into()
-            .unwrap();
-        if !ecdsa_fun::ECDSA::verify(&ecdsa, &xH_p, &pok_1_message_hash, &self.pok_1) {
+        if !self.verify_pok_1(&xH_p) {
             return Err(crypto::Error::InvalidProofOfKnowledge);
         }
 
         // Everything ok
         Ok(())
     }
+
+    /// Verify a batch of proofs against their respective `xG_p` and `xH_p` points and return the
+    /// result of each proof, in order.
+    ///
+    /// The linear checks of all the proofs, i.e. the sums of the ed25519 Pedersen commitments and
+    /// the ed25519 proofs of knowledge, are folded into a single multiscalar multiplication with
+    /// random 128 bits weights. The ed25519 points `xG_p` must be in the prime order subgroup,
+    /// like every decoded point, otherwise a torsion component could vanish under the random
+    /// weights and the batch would accept what [`Self::verify`] rejects.
+    ///
+    /// The ring signatures are not folded: a proof only carries the challenges of its rings, which
+    /// are hashes of the intermediate points, so every point must be recomputed and hashed and
+    /// there is no linear equation to combine. Batching them requires a proof format carrying the
+    /// intermediate points. They are verified one by one with variable time multiscalar
+    /// multiplications instead, in parallel if the `rayon` feature is enabled. All the data is
+    /// public, the verification runs in variable time.
+    ///
+    /// If the batch does not verify, each proof is verified individually with [`Self::verify`]
+    /// to report which proofs are invalid.
+    #[allow(non_snake_case)]
+    pub fn verify_batch(
+        batch: &[(DLEQProof, ed25519Point, secp256k1Point)],
+    ) -> Vec<Result<(), crypto::Error>> {
+        if Self::batch_is_valid(batch, &mut rand::thread_rng()) {
+            batch.iter().map(|_| Ok(())).collect()
+        } else {
+            batch
+                .iter()
+                .map(|(proof, xG_p, xH_p)| proof.verify(*xG_p, *xH_p))
+                .collect()
+        }
+    }
+
+    #[allow(non_snake_case)]
+    fn batch_is_valid<R: RngCore + CryptoRng>(
+        batch: &[(DLEQProof, ed25519Point, secp256k1Point)],
+        rng: &mut R,
+    ) -> bool {
+        let generators = AltGenerators::new();
+
+        let mut scalars: Vec<ed25519Scalar> = vec![];
+        let mut points: Vec<ed25519Point> = vec![];
+        let mut g_scalar = ed25519Scalar::zero();
+
+        for (proof, xG_p, xH_p) in batch {
+            if proof.c_g.len() != DLEQ_BITS
+                || proof.c_h.len() != DLEQ_BITS
+                || proof.ring_signatures.len() != DLEQ_BITS
+            {
+                return false;
+            }
+            // the commitments and alpha_G are decoded in the prime order subgroup, xG_p must be
+            // too for the weighted sums to be equivalent to the individual checks
+            if !xG_p.is_torsion_free() {
+                return false;
+            }
+
+            // z * (sum(c_g) - xG_p) = 0
+            let z = random_weight(rng);
+            scalars.extend(std::iter::repeat(z).take(DLEQ_BITS));
+            points.extend(proof.c_g.iter());
+            scalars.push(-z);
+            points.push(*xG_p);
+
+            // w * (r * G - alpha_G - challenge * xG_p) = 0
+            let w = random_weight(rng);
+            let (alpha_G, r) = proof.pok_0;
+            g_scalar += w * r;
+            scalars.push(-w);
+            points.push(alpha_G);
+            scalars.push(-(w * proof.pok_0_challenge(xG_p)));
+            points.push(*xG_p);
+
+            // secp256k1 commitments only need additions
+            let commitment_agg_secp256k1 = proof
+                .c_h
+                .iter()
+                .fold(secp256k1Point::zero(), |acc, bit_commitment| {
+                    g!(acc + bit_commitment).mark::<Normal>()
+                });
+            if !(*xH_p == commitment_agg_secp256k1) || !proof.verify_pok_1(xH_p) {
+                return false;
+            }
+        }
+
+        scalars.push(g_scalar);
+        points.push(G);
+        if !ed25519Point::vartime_multiscalar_mul(scalars, points).is_identity() {
+            return false;
+        }
+
+        batch
+            .iter()
+            .all(|(proof, _, _)| proof.ring_signatures_are_valid(&generators))
+    }
+
+    fn ring_signatures_are_valid(&self, generators: &AltGenerators) -> bool {
+        all_bits(|index| {
+            verify_ring_sig(
+                index,
+                &self.c_g[index],
+                &self.c_h[index],
+                &self.ring_signatures[index],
+                generators,
+            )
+        })
+    }
+
+    // Challenge of the ed25519 proof of knowledge.
+    #[allow(non_snake_case)]
+    fn pok_0_challenge(&self, xG_p: &ed25519Point) -> ed25519Scalar {
+        #[allow(non_snake_case)]
+        let (alpha_G, _) = self.pok_0;
+        let mut challenge_preimage = vec![];
+        challenge_preimage.extend_from_slice(alpha_G.compress().as_bytes());
+        challenge_preimage.extend_from_slice(xG_p.compress().as_bytes());
+        challenge_preimage.extend_from_slice(serialize(&self.c_g).as_slice());
+        ed25519Scalar::from_bytes_mod_order(monero::cryptonote::hash::keccak_256(
+            &challenge_preimage,
+        ))
+    }
+
+    // Verify the secp256k1 proof of knowledge, an ECDSA signature of the commitments.
+    #[allow(non_snake_case)]
+    fn verify_pok_1(&self, xH_p: &secp256k1Point) -> bool {
+        let ecdsa = ecdsa_fun::ECDSA::verify_only();
+        let pok_1_message = serialize(&self.c_h);
+        let pok_1_message_hash: [u8; 32] = sha2::Sha256::digest(pok_1_message.as_slice()).into();
+        ecdsa.verify(xH_p, &pok_1_message_hash, &self.pok_1)
+    }
+}
+
+// Check the predicate on every bit index, in parallel if the `rayon` feature is enabled.
+#[cfg(feature = "rayon")]
+fn all_bits(predicate: impl Fn(usize) -> bool + Sync + Send) -> bool {
+    (0..DLEQ_BITS).into_par_iter().all(predicate)
+}
+
+#[cfg(not(feature = "rayon"))]
+fn all_bits(predicate: impl Fn(usize) -> bool) -> bool {
+    (0..DLEQ_BITS).all(predicate)
+}
+
+// Draw a random 128 bits scalar used to weight the checks of a batch verification.
+fn random_weight<R: RngCore + CryptoRng>(rng: &mut R) -> ed25519Scalar {
+    let mut bytes = [0u8; 32];
+    rng.fill_bytes(&mut bytes[..16]);
+    ed25519Scalar::from_bytes_mod_order(bytes)
 }
 
 #[cfg(test)]
@@ -868,7 +1295,8 @@ mod tests {
         // ensure 256th bit is 0
         x[31] &= 0b0111_1111;
         let x_bits = BitSlice::<Lsb0, u8>::from_slice(&x).unwrap();
-        let key_commitment = key_commitment(x_bits, 255);
+        let key_commitment =
+            key_commitment(x_bits, 255, &AltGenerators::new(), &mut rand::thread_rng()).unwrap();
         let commitment_acc = key_commitment.iter().map(|pc| pc.commitment).sum();
         assert_eq!(ed25519Scalar::from_bytes_mod_order(x) * G, commitment_acc);
     }
@@ -881,7 +1309,9 @@ mod tests {
         // ensure 256th bit is 0
         // x[31] &= 0b0111_1111;
         let x_bits = BitSlice::<Lsb0, u8>::from_slice(&x).unwrap();
-        let key_commitment = key_commitment_secp256k1(x_bits, 255);
+        let key_commitment =
+            key_commitment_secp256k1(x_bits, 255, &AltGenerators::new(), &mut rand::thread_rng())
+                .unwrap();
         // let commitment_acc: secp256k1Point<Jacobian, Public, Zero> = key_commitment
         let commitment_acc = key_commitment.iter().fold(
             secp256k1Point::zero(),
@@ -897,7 +1327,7 @@ mod tests {
         use rand::Rng;
         let x: [u8; 32] = rand::thread_rng().gen();
         let x_shaved = _zeroize_highest_bits(x, 252);
-        let dleq = DLEQProof::generate(x_shaved);
+        let dleq = DLEQProof::generate(x_shaved, &mut rand::thread_rng()).unwrap();
 
         let xG_p = ed25519Scalar::from_bytes_mod_order(x_shaved) * G;
         let xH_p_secp256k1 = secp256k1Scalar::from_bytes_mod_order(reverse_endianness(&x_shaved))
@@ -911,12 +1341,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn zeroize_erases_blinders() {
+        let generators = AltGenerators::new();
+        let mut rng = rand::thread_rng();
+        let mut c_g: PedersenCommitment<ed25519Point, ed25519Scalar> =
+            (true, 3, random_ed25519_scalar(&mut rng), &generators).into();
+        c_g.zeroize();
+        assert_eq!(c_g.blinder, ed25519Scalar::zero());
+        let mut c_h: PedersenCommitment<secp256k1Point, Secp256k1Blinder> =
+            (true, 3, random_secp256k1_scalar(&mut rng), &generators)
+                .try_into()
+                .unwrap();
+        c_h.zeroize();
+        assert_eq!(*c_h.blinder.0, [0u8; 32]);
+    }
+
     #[test]
     fn blinders_sum_to_zero() {
         use rand::Rng;
         let x: [u8; 32] = rand::thread_rng().gen();
         let x_bits = BitSlice::<Lsb0, u8>::from_slice(&x).unwrap();
-        let key_commitment = key_commitment(x_bits, 255);
+        let key_commitment =
+            key_commitment(x_bits, 255, &AltGenerators::new(), &mut rand::thread_rng()).unwrap();
         let blinder_acc = key_commitment
             .iter()
             .fold(ed25519Scalar::zero(), |acc, bit_commitment| {
@@ -928,7 +1375,456 @@ mod tests {
     #[test]
     #[allow(non_snake_case)]
     fn alt_ed25519_generator_is_correct() {
-        assert_eq!(G_p(), monero::util::key::H.point.decompress().unwrap())
+        let G_p = AltGenerators::new().g_p;
+        assert_eq!(G_p, monero::util::key::H.point.decompress().unwrap());
+        assert_eq!(
+            hex::encode(ALT_GENERATOR_ED25519),
+            "8b655970153799af2aeadc9ff1add0ea6c7251d54154cfa92c173a0dd39c1f94"
+        );
+        // hash-to-curve of the basepoint, in the prime order subgroup
+        let hash_G = monero::cryptonote::hash::keccak_256(G.compress().as_bytes());
+        let hash_to_curve = ed25519PointCompressed(hash_G)
+            .decompress()
+            .unwrap()
+            .mul_by_cofactor();
+        assert_eq!(G_p, hash_to_curve);
+        assert!(G_p.is_torsion_free());
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn alt_secp256k1_generator_is_correct() {
+        let H_p = AltGenerators::new().h_p;
+        assert_eq!(
+            hex::encode(ALT_GENERATOR_SECP256K1),
+            "0250929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0"
+        );
+        // x coordinate is the hash of the uncompressed basepoint, y is even
+        let hash_H: [u8; 32] =
+            bitcoin_hashes::sha256::Hash::hash(&H.to_bytes_uncompressed()).into_inner();
+        let mut even_y_prepend_hash_H = [2u8; 33];
+        even_y_prepend_hash_H[1..].copy_from_slice(&hash_H);
+        assert_eq!(
+            H_p,
+            secp256k1Point::from_bytes(even_y_prepend_hash_H).unwrap()
+        );
+        assert_eq!(H_p.to_bytes(), ALT_GENERATOR_SECP256K1);
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn dleq_proof_is_deterministic() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+
+        let x: [u8; 32] =
+            hex::decode("0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f00")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let dleq = DLEQProof::generate(x, &mut ChaCha20Rng::from_seed([0x42; 32])).unwrap();
+        let dleq_bis = DLEQProof::generate(x, &mut ChaCha20Rng::from_seed([0x42; 32])).unwrap();
+        assert_eq!(dleq, dleq_bis);
+        assert_eq!(dleq.as_canonical_bytes(), dleq_bis.as_canonical_bytes());
+
+        let dleq_other = DLEQProof::generate(x, &mut ChaCha20Rng::from_seed([0x43; 32])).unwrap();
+        assert_ne!(dleq, dleq_other);
+
+        let xG_p = ed25519Scalar::from_bytes_mod_order(x) * G;
+        let x_secp256k1 = secp256k1Scalar::from_bytes_mod_order(reverse_endianness(&x))
+            .mark::<NonZero>()
+            .unwrap();
+        let xH_p = g!(x_secp256k1 * H).mark::<Normal>();
+        assert!(dleq.verify(xG_p, xH_p).is_ok());
+        assert!(dleq_other.verify(xG_p, xH_p).is_ok());
+    }
+
+    #[test]
+    fn dleq_proof_known_answer() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+
+        let x: [u8; 32] =
+            hex::decode("0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f00")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let dleq = DLEQProof::generate(x, &mut ChaCha20Rng::from_seed([0x42; 32])).unwrap();
+
+        // Pedersen commitments of the first and last bits
+        assert_eq!(
+            hex::encode(dleq.c_g[0].compress().as_bytes()),
+            "ea3a2f5a85d1f53384395bd12d475ffc18fddf5a92be9095fdc2e7a51ffda840"
+        );
+        assert_eq!(
+            hex::encode(dleq.c_h[0].to_bytes()),
+            "02f9edf8a8f420668887e841dbf24a7e6faa653319839832fc8b14c7a538ab7850"
+        );
+        assert_eq!(
+            hex::encode(dleq.c_g[251].compress().as_bytes()),
+            "3b1a5597200c1c668023e0d93156941c64c202caf429c3ee94d0e647b0d8ff6d"
+        );
+        assert_eq!(
+            hex::encode(dleq.c_h[251].to_bytes()),
+            "029f08b63f7e7c761b50abe9f2400e1acf90f52f6989a0d09fadcdfba11da71f5b"
+        );
+
+        // Ring signature of the first bit
+        let ring_sig = &dleq.ring_signatures[0];
+        assert_eq!(
+            hex::encode(ring_sig.e_0_i),
+            "94e112c9184ace54307a2b4c70ccaa2cb6fb1b71bcbc6ffd7f2295f310ed165b"
+        );
+        assert_eq!(
+            hex::encode(ring_sig.e_g_0_i().to_bytes()),
+            "f3bd45f8945a729c006a551d17eb4fc4b5fb1b71bcbc6ffd7f2295f310ed160b"
+        );
+        assert_eq!(
+            hex::encode(ring_sig.e_h_0_i().to_bytes()),
+            "94e112c9184ace54307a2b4c70ccaa2cb6fb1b71bcbc6ffd7f2295f310ed165b"
+        );
+        assert_eq!(
+            hex::encode(ring_sig.a_0_i.to_bytes()),
+            "9a7e84925173a4ae3fa60f81f11b350e41fac14e0e97d90b8abc6b4198a45003"
+        );
+        assert_eq!(
+            hex::encode(ring_sig.b_0_i.to_bytes()),
+            "6cb25e5e30e23a3e9f2b2d7e191f0c2d2d602d991cf8d24e730aea151e225f77"
+        );
+    }
+
+    #[test]
+    fn generate_fails_on_invalid_secret() {
+        let mut x = [0xffu8; 32];
+        x[31] = 0b0001_0000;
+        assert!(matches!(
+            DLEQProof::generate(x, &mut rand::thread_rng()),
+            Err(crypto::Error::InvalidProofSecret)
+        ));
+        assert!(matches!(
+            DLEQProof::generate([0u8; 32], &mut rand::thread_rng()),
+            Err(crypto::Error::InvalidProofSecret)
+        ));
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn verify_malformed_proof_fails() {
+        use rand::Rng;
+        let x: [u8; 32] = rand::thread_rng().gen();
+        let x_shaved = _zeroize_highest_bits(x, 252);
+        let dleq = DLEQProof::generate(x_shaved, &mut rand::thread_rng()).unwrap();
+
+        let xG_p = ed25519Scalar::from_bytes_mod_order(x_shaved) * G;
+        let x_secp256k1 = secp256k1Scalar::from_bytes_mod_order(reverse_endianness(&x_shaved))
+            .mark::<NonZero>()
+            .unwrap();
+        let xH_p = g!(x_secp256k1 * H).mark::<Normal>();
+
+        // missing elements
+        let mut truncated = dleq.clone();
+        truncated.c_h.pop();
+        assert!(matches!(
+            truncated.verify(xG_p, xH_p),
+            Err(crypto::Error::InvalidProofSize {
+                expected: DLEQ_BITS,
+                found: 251
+            })
+        ));
+        let mut truncated = dleq.clone();
+        truncated.ring_signatures.clear();
+        assert!(truncated.verify(xG_p, xH_p).is_err());
+
+        // swapped commitments keep the sums but break the ring signatures
+        let mut swapped = dleq.clone();
+        swapped.c_g.swap(0, 1);
+        assert!(matches!(
+            swapped.verify(xG_p, xH_p),
+            Err(crypto::Error::InvalidRingSignature)
+        ));
+
+        // tampered ring signature
+        let mut tampered = dleq.clone();
+        tampered.ring_signatures[7].b_0_i = tampered.ring_signatures[7].b_1_i.clone();
+        assert!(tampered.verify(xG_p, xH_p).is_err());
+
+        // wrong public keys
+        assert!(matches!(
+            dleq.verify(xG_p + G, xH_p),
+            Err(crypto::Error::InvalidPedersenCommitment)
+        ));
+        assert!(matches!(
+            dleq.verify(
+                xG_p,
+                g!(xH_p + H).mark::<Normal>().mark::<NonZero>().unwrap()
+            ),
+            Err(crypto::Error::InvalidPedersenCommitment)
+        ));
+    }
+
+    #[test]
+    fn decoding_malformed_proof_fails() {
+        use rand::Rng;
+        let x: [u8; 32] = rand::thread_rng().gen();
+        let x_shaved = _zeroize_highest_bits(x, 252);
+        let dleq = DLEQProof::generate(x_shaved, &mut rand::thread_rng()).unwrap();
+        let bytes = dleq.to_bytes_with_format(DLEQProofFormat::Legacy);
+
+        // truncated
+        assert!(DLEQProof::from_canonical_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(DLEQProof::from_canonical_bytes(&[]).is_err());
+
+        // oversized vector length is rejected before reading the elements
+        let mut oversized = bytes.clone();
+        oversized[..2].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(matches!(
+            DLEQProof::from_canonical_bytes(&oversized),
+            Err(consensus::Error::ParseFailed(_))
+        ));
+        let mut oversized = bytes.clone();
+        oversized[1] = 0x01;
+        assert!(matches!(
+            DLEQProof::from_canonical_bytes(&oversized),
+            Err(consensus::Error::ParseFailed(_))
+        ));
+        let offset = 2 + 32 * DLEQ_BITS;
+        let mut oversized = bytes.clone();
+        oversized[offset..offset + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+        assert!(matches!(
+            DLEQProof::from_canonical_bytes(&oversized),
+            Err(consensus::Error::ParseFailed(_))
+        ));
+
+        // first commitment is not a point
+        let mut invalid_point = bytes.clone();
+        invalid_point[2..34].copy_from_slice(&[0xff; 32]);
+        assert!(DLEQProof::from_canonical_bytes(&invalid_point).is_err());
+
+        // first secp256k1 commitment is not a point
+        let offset = 2 + 32 * DLEQ_BITS;
+        let mut invalid_point = bytes.clone();
+        invalid_point[offset + 2] = 0x05;
+        assert!(DLEQProof::from_canonical_bytes(&invalid_point).is_err());
+
+        // first ring signature scalar is not canonical
+        let offset = offset + 2 + 33 * DLEQ_BITS + 2;
+        let mut invalid_scalar = bytes.clone();
+        invalid_scalar[offset..offset + 32].copy_from_slice(&[0xff; 32]);
+        assert!(DLEQProof::from_canonical_bytes(&invalid_scalar).is_err());
+
+        // reductions of the first ring signature challenge don't come from the same hash
+        let mut inconsistent = bytes;
+        inconsistent[offset + 32] ^= 0x01;
+        assert!(matches!(
+            DLEQProof::from_canonical_bytes(&inconsistent),
+            Err(consensus::Error::ParseFailed(_))
+        ));
+
+        let bytes = dleq.as_canonical_bytes();
+
+        // truncated
+        assert!(DLEQProof::from_canonical_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(DLEQProof::from_canonical_bytes(&bytes[..1]).is_err());
+
+        // unknown version
+        for version in [0x00, 0x02, 0xfd, 0xff] {
+            let mut unknown = bytes.clone();
+            unknown[0] = version;
+            assert!(matches!(
+                DLEQProof::from_canonical_bytes(&unknown),
+                Err(consensus::Error::ParseFailed(_))
+            ));
+        }
+
+        // parity bits set past the last secp256k1 commitment
+        let offset = 1 + 32 * DLEQ_BITS;
+        let mut non_canonical = bytes.clone();
+        non_canonical[offset + 31] |= 0x80;
+        assert!(matches!(
+            DLEQProof::from_canonical_bytes(&non_canonical),
+            Err(consensus::Error::ParseFailed(_))
+        ));
+
+        // first secp256k1 commitment is not on the curve with its parity
+        let offset = offset + 32;
+        let mut invalid_point = bytes.clone();
+        invalid_point[offset..offset + 32].copy_from_slice(&[0xff; 32]);
+        assert!(DLEQProof::from_canonical_bytes(&invalid_point).is_err());
+
+        // first ring signature scalar is not canonical
+        let offset = offset + 32 * DLEQ_BITS + 32;
+        let mut invalid_scalar = bytes;
+        invalid_scalar[offset..offset + 32].copy_from_slice(&[0xff; 32]);
+        assert!(DLEQProof::from_canonical_bytes(&invalid_scalar).is_err());
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn legacy_and_compact_formats_verify_identically() {
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha20Rng;
+
+        let x: [u8; 32] =
+            hex::decode("0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f00")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let dleq = DLEQProof::generate(x, &mut ChaCha20Rng::from_seed([0x42; 32])).unwrap();
+        let xG_p = ed25519Scalar::from_bytes_mod_order(x) * G;
+        let x_secp256k1 = secp256k1Scalar::from_bytes_mod_order(reverse_endianness(&x))
+            .mark::<NonZero>()
+            .unwrap();
+        let xH_p = g!(x_secp256k1 * H).mark::<Normal>();
+
+        let legacy = dleq.to_bytes_with_format(DLEQProofFormat::Legacy);
+        let compact = dleq.to_bytes_with_format(DLEQProofFormat::Compact);
+        assert_eq!(compact, dleq.as_canonical_bytes());
+        assert_eq!(legacy[0], DLEQProofFormat::Legacy.version_byte());
+        assert_eq!(compact[0], DLEQProofFormat::Compact.version_byte());
+        assert_eq!(legacy.len(), 64898);
+        assert_eq!(compact.len(), 56609);
+
+        // both formats decode to the same proof and re-encode to the same bytes
+        let from_legacy = DLEQProof::from_canonical_bytes(&legacy).unwrap();
+        let from_compact = DLEQProof::from_canonical_bytes(&compact).unwrap();
+        assert_eq!(from_legacy, dleq);
+        assert_eq!(from_compact, dleq);
+        assert_eq!(
+            from_legacy.to_bytes_with_format(DLEQProofFormat::Legacy),
+            legacy
+        );
+        assert_eq!(from_legacy.as_canonical_bytes(), compact);
+
+        // and verify identically
+        for (xG_p, xH_p) in [(xG_p, xH_p), (xG_p + G, xH_p)] {
+            let res = dleq.verify(xG_p, xH_p).map_err(|e| e.to_string());
+            assert_eq!(
+                from_legacy.verify(xG_p, xH_p).map_err(|e| e.to_string()),
+                res
+            );
+            assert_eq!(
+                from_compact.verify(xG_p, xH_p).map_err(|e| e.to_string()),
+                res
+            );
+        }
+        assert!(from_legacy.verify(xG_p, xH_p).is_ok());
+        assert!(
+            DLEQProof::verify_batch(&[(from_legacy, xG_p, xH_p), (from_compact, xG_p, xH_p)])
+                .iter()
+                .all(|r| r.is_ok())
+        );
+    }
+
+    #[test]
+    fn decoding_non_canonical_points_fails() {
+        // small order point of order 8, valid encoding but outside of the prime order subgroup
+        let torsion: [u8; 32] = curve25519_dalek::constants::EIGHT_TORSION[1]
+            .compress()
+            .to_bytes();
+        assert!(deserialize::<ed25519Point>(&torsion).is_err());
+        // non-canonical encoding of the identity, y = p + 1
+        let mut non_canonical = [0xffu8; 32];
+        non_canonical[0] = 0xee;
+        non_canonical[31] = 0x7f;
+        assert!(deserialize::<ed25519Point>(&non_canonical).is_err());
+        // valid point
+        let point = deserialize::<ed25519Point>(G.compress().as_bytes()).unwrap();
+        assert_eq!(point, G);
+    }
+
+    #[allow(non_snake_case)]
+    fn random_proof() -> (DLEQProof, ed25519Point, secp256k1Point) {
+        use rand::Rng;
+        let x: [u8; 32] = rand::thread_rng().gen();
+        let x_shaved = _zeroize_highest_bits(x, 252);
+        let dleq = DLEQProof::generate(x_shaved, &mut rand::thread_rng()).unwrap();
+        let xG_p = ed25519Scalar::from_bytes_mod_order(x_shaved) * G;
+        let x_secp256k1 = secp256k1Scalar::from_bytes_mod_order(reverse_endianness(&x_shaved))
+            .mark::<NonZero>()
+            .unwrap();
+        (dleq, xG_p, g!(x_secp256k1 * H).mark::<Normal>())
+    }
+
+    #[test]
+    fn batch_verification_works() {
+        let batch: Vec<_> = (0..3).map(|_| random_proof()).collect();
+        let res = DLEQProof::verify_batch(&batch);
+        assert_eq!(res.len(), 3);
+        assert!(res.iter().all(|r| r.is_ok()));
+        assert!(DLEQProof::verify_batch(&[]).is_empty());
+
+        // a tampered ring signature is rejected
+        let generators = AltGenerators::new();
+        let (dleq, _, _) = &batch[0];
+        let mut tampered = dleq.ring_signatures[3].clone();
+        tampered.a_0_i = tampered.a_1_i;
+        assert!(verify_ring_sig(
+            3,
+            &dleq.c_g[3],
+            &dleq.c_h[3],
+            &dleq.ring_signatures[3],
+            &generators
+        ));
+        assert!(!verify_ring_sig(
+            3,
+            &dleq.c_g[3],
+            &dleq.c_h[3],
+            &tampered,
+            &generators
+        ));
+    }
+
+    #[test]
+    fn batch_verification_reports_invalid_proofs() {
+        let batch: Vec<_> = (0..3).map(|_| random_proof()).collect();
+
+        // broken ring signatures
+        let mut invalid = batch.clone();
+        invalid[1].0.c_g.swap(0, 1);
+        let res = DLEQProof::verify_batch(&invalid);
+        assert!(res[0].is_ok());
+        assert!(matches!(res[1], Err(crypto::Error::InvalidRingSignature)));
+        assert!(res[2].is_ok());
+
+        // proof verified against the wrong keys
+        let mut invalid = batch.clone();
+        invalid[2].1 = batch[0].1;
+        let res = DLEQProof::verify_batch(&invalid);
+        assert!(res[0].is_ok());
+        assert!(res[1].is_ok());
+        assert!(matches!(
+            res[2],
+            Err(crypto::Error::InvalidPedersenCommitment)
+        ));
+
+        // broken proof of knowledge
+        let mut invalid = batch;
+        invalid[0].0.pok_0.1 = invalid[1].0.pok_0.1;
+        let res = DLEQProof::verify_batch(&invalid);
+        assert!(matches!(
+            res[0],
+            Err(crypto::Error::InvalidProofOfKnowledge)
+        ));
+        assert!(res[1].is_ok());
+        assert!(res[2].is_ok());
+    }
+
+    #[test]
+    #[allow(non_snake_case)]
+    fn batch_verification_rejects_torsioned_keys() {
+        let (dleq, xG_p, xH_p) = random_proof();
+        let torsioned = xG_p + curve25519_dalek::constants::EIGHT_TORSION[1];
+        assert!(!torsioned.is_torsion_free());
+
+        let batch = [(dleq.clone(), torsioned, xH_p)];
+        // whatever the random weights, the batch and the single verification agree
+        let mut rng = rand::thread_rng();
+        for _ in 0..32 {
+            assert!(!DLEQProof::batch_is_valid(&batch, &mut rng));
+        }
+        assert!(dleq.verify(torsioned, xH_p).is_err());
+        assert!(DLEQProof::verify_batch(&batch)[0].is_err());
+        assert!(DLEQProof::verify_batch(&[(dleq, xG_p, xH_p)])[0].is_ok());
     }
 
     #[test]
@@ -936,7 +1832,7 @@ mod tests {
         use rand::Rng;
         let x: [u8; 32] = rand::thread_rng().gen();
         let x_shaved = _zeroize_highest_bits(x, 252);
-        let dleq = DLEQProof::generate(x_shaved);
+        let dleq = DLEQProof::generate(x_shaved, &mut rand::thread_rng()).unwrap();
 
         assert_eq!(
             DLEQProof::from_canonical_bytes(dleq.as_canonical_bytes().as_slice()).unwrap(),
@@ -12,6 +12,28 @@
 //!
 //! assert!(derived_key.to_secp256k1().is_some());
 //! ```
+//!
+//! Secp256k1 extended public keys can be shared with a watch-only party, as `xpub` or `tpub`
+//! strings, to derive the non-hardened children public keys.
+//!
+//! ```rust
+//! use bitcoin::secp256k1::Secp256k1;
+//! use bitcoin::Network;
+//! use farcaster_core::crypto::slip10::{DerivationPath, Secp256k1ExtPublicKey, Secp256k1ExtSecretKey};
+//! use std::str::FromStr;
+//!
+//! let secp = Secp256k1::new();
+//! let seed = hex::decode("deadbeefdeadbeefdeadbeefdeadbeef").unwrap();
+//! let account = Secp256k1ExtSecretKey::new_master(&seed)
+//!     .derive_priv(&secp, &DerivationPath::from_str("m/44'/0'/0'").unwrap())
+//!     .unwrap();
+//! let xpub = Secp256k1ExtPublicKey::from_secret_key(&secp, &account).to_base58(Network::Bitcoin);
+//!
+//! let (_, watch_only) = Secp256k1ExtPublicKey::from_base58(&xpub).unwrap();
+//! let path = DerivationPath::from_str("m/0/1").unwrap();
+//! let child = watch_only.derive_pub(&secp, &path).unwrap();
+//! assert_eq!(child.public_key, account.derive_priv(&secp, &path).unwrap().public_key(&secp));
+//! ```
 
 use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
 use curve25519_dalek::edwards::CompressedEdwardsY;
@@ -19,8 +41,15 @@ use curve25519_dalek::scalar::Scalar;
 
 use bitcoin::hashes::{sha512, Hash, HashEngine, Hmac, HmacEngine};
 use bitcoin::secp256k1::{self, Secp256k1};
+use bitcoin::util::base58;
+use bitcoin::Network;
 
 use thiserror::Error;
+use zeroize::{Zeroize, Zeroizing};
+
+use std::fmt;
+
+use crate::crypto::REDACTED;
 
 pub use bitcoin::hash_types::XpubIdentifier;
 /// The 32-bytes entropy extention called chain code.
@@ -29,6 +58,11 @@ pub use bitcoin::util::bip32::ChainCode;
 pub use bitcoin::util::bip32::Fingerprint;
 pub use bitcoin::util::bip32::{ChildNumber, DerivationPath};
 
+/// Version bytes of mainnet extended public keys, serialized as `xpub...`.
+pub const XPUB_VERSION: [u8; 4] = [0x04, 0x88, 0xb2, 0x1e];
+/// Version bytes of testnet extended public keys, serialized as `tpub...`.
+pub const TPUB_VERSION: [u8; 4] = [0x04, 0x35, 0x87, 0xcf];
+
 /// Possible errors when deriving keys as described in SLIP-10.
 #[derive(Error, Debug)]
 pub enum Error {
@@ -38,11 +72,31 @@ pub enum Error {
     /// Hardened not supported in ed25519.
     #[error("Hardened not supported in ed25519")]
     HardenedNotSupportedForEd25519,
+    /// Hardened child keys cannot be derived from an extended public key.
+    #[error("Hardened child keys cannot be derived from an extended public key")]
+    HardenedDerivationFromPublicKey,
+    /// Base58 decoding error.
+    #[error("Base58 decoding error: {0}")]
+    Base58(#[from] base58::Error),
+    /// Unknown extended public key version bytes.
+    #[error("Unknown extended public key version: {0:02x?}")]
+    UnknownVersion([u8; 4]),
+    /// The extended public key is not 78 bytes long.
+    #[error("Wrong extended public key length: {0}")]
+    WrongExtendedKeyLength(usize),
+    /// The derived key would exceed the maximum depth of 255.
+    #[error("Maximum extended key depth exceeded")]
+    MaximumDepthExceeded,
+    /// A master extended public key, i.e. of depth 0, has a non-zero parent fingerprint or child
+    /// number.
+    #[error("Master extended public key with a non-zero parent fingerprint or child number")]
+    InvalidMasterKey,
 }
 
 /// Ed25519 extended secret key. The extended secret key contains its depth, parent figerprint,
-/// child number, the derived secret key, and the chain code.
-#[derive(Debug, Clone, Copy)]
+/// child number, the derived secret key, and the chain code. The secret key and the chain code are
+/// zeroized on drop and redacted from the `Debug` output.
+#[derive(Clone)]
 pub struct Ed25519ExtSecretKey {
     /// The depth of this extended key, start with 0 for the master.
     pub depth: u8,
@@ -53,8 +107,8 @@ pub struct Ed25519ExtSecretKey {
     /// The secret key, a 32-bytes value. In Ed25519 any 32-bytes long value is considered as valid
     /// secret key, computation is done on-top before using that value.
     pub secret_key: [u8; 32],
-    /// The 32-bytes entropy extention called chain code.
-    pub chain_code: ChainCode,
+    // The 32-bytes entropy extention called chain code.
+    chain_code: Zeroizing<[u8; 32]>,
 }
 
 impl Ed25519ExtSecretKey {
@@ -73,13 +127,13 @@ impl Ed25519ExtSecretKey {
             parent_fingerprint: Default::default(),
             child_number: ChildNumber::Normal { index: 0 },
             secret_key,
-            chain_code: ChainCode::from(&hmac_result[32..]),
+            chain_code: secret_bytes(&hmac_result[32..]),
         }
     }
 
     /// Derive the extended secret key from `&self` up to the given `path`.
     pub fn derive_priv(&self, path: &impl AsRef<[ChildNumber]>) -> Result<Self, Error> {
-        let mut sk = *self;
+        let mut sk = self.clone();
         for cnum in path.as_ref() {
             sk = sk.ckd_priv(*cnum)?;
         }
@@ -109,14 +163,22 @@ impl Ed25519ExtSecretKey {
         secret_key.clone_from_slice(&hmac_result[..32]);
 
         Ok(Ed25519ExtSecretKey {
-            depth: self.depth + 1,
+            depth: self
+                .depth
+                .checked_add(1)
+                .ok_or(Error::MaximumDepthExceeded)?,
             parent_fingerprint: self.fingerprint(),
             child_number: i,
             secret_key,
-            chain_code: ChainCode::from(&hmac_result[32..]),
+            chain_code: secret_bytes(&hmac_result[32..]),
         })
     }
 
+    /// Returns the chain code of the extended secret key.
+    pub fn chain_code(&self) -> ChainCode {
+        ChainCode::from(&self.chain_code[..])
+    }
+
     /// Get the associated public key to the extended secret key as defined in SLIP10 and ed25519
     /// scheme. This might be used in schemes like EdDSA or X25519.
     pub fn public_key(&self) -> CompressedEdwardsY {
@@ -157,8 +219,9 @@ impl Ed25519ExtSecretKey {
 }
 
 /// Secp256k1 extended secret key. The extended secret key contains its depth, parent figerprint,
-/// child number, the derived secret key, and the chain code.
-#[derive(Debug, Clone, Copy)]
+/// child number, the derived secret key, and the chain code. The secret key and the chain code are
+/// erased on drop and redacted from the `Debug` output.
+#[derive(Clone)]
 pub struct Secp256k1ExtSecretKey {
     /// The depth of this extended key, start with 0 for the master.
     pub depth: u8,
@@ -166,10 +229,10 @@ pub struct Secp256k1ExtSecretKey {
     pub parent_fingerprint: Fingerprint,
     /// The child number, with a hardened or non-hardened value.
     pub child_number: ChildNumber,
-    /// The secret key value, a valid secp256k1 secret key.
-    pub secret_key: secp256k1::SecretKey,
-    /// The 32-bytes entropy extention called chain code.
-    pub chain_code: ChainCode,
+    // The bytes of the secret key, a valid secp256k1 secret key.
+    secret_key: Zeroizing<[u8; 32]>,
+    // The 32-bytes entropy extention called chain code.
+    chain_code: Zeroizing<[u8; 32]>,
 }
 
 impl Secp256k1ExtSecretKey {
@@ -180,23 +243,18 @@ impl Secp256k1ExtSecretKey {
         hmac_engine.input(seed.as_ref());
         let mut hmac_result: Hmac<sha512::Hash> = Hmac::from_engine(hmac_engine);
 
-        let (secret_key, chain_code) = loop {
-            match secp256k1::SecretKey::from_slice(&hmac_result[..32]) {
-                Ok(key) => break (key, ChainCode::from(&hmac_result[32..])),
-                Err(_) => {
-                    hmac_engine = HmacEngine::new(b"Bitcoin seed");
-                    hmac_engine.input(&hmac_result[..32]);
-                    hmac_result = Hmac::from_engine(hmac_engine);
-                }
-            }
-        };
+        while secp256k1::SecretKey::from_slice(&hmac_result[..32]).is_err() {
+            hmac_engine = HmacEngine::new(b"Bitcoin seed");
+            hmac_engine.input(&hmac_result[..32]);
+            hmac_result = Hmac::from_engine(hmac_engine);
+        }
 
         Secp256k1ExtSecretKey {
             depth: 0,
             parent_fingerprint: Default::default(),
             child_number: ChildNumber::Normal { index: 0 },
-            secret_key,
-            chain_code,
+            secret_key: secret_bytes(&hmac_result[..32]),
+            chain_code: secret_bytes(&hmac_result[32..]),
         }
     }
 
@@ -206,7 +264,7 @@ impl Secp256k1ExtSecretKey {
         secp: &Secp256k1<C>,
         path: &impl AsRef<[ChildNumber]>,
     ) -> Result<Self, Error> {
-        let mut sk = *self;
+        let mut sk = self.clone();
         for cnum in path.as_ref() {
             sk = sk.ckd_priv(secp, *cnum)?;
         }
@@ -232,9 +290,7 @@ impl Secp256k1ExtSecretKey {
         match i {
             ChildNumber::Normal { .. } => {
                 // Non-hardened key: compute public data and use that
-                hmac_engine.input(
-                    &secp256k1::PublicKey::from_secret_key(secp, &self.secret_key).serialize()[..],
-                );
+                hmac_engine.input(&self.public_key(secp).serialize()[..]);
             }
             ChildNumber::Hardened { .. } => {
                 // Hardened key: use only secret data to prevent public derivation
@@ -246,9 +302,9 @@ impl Secp256k1ExtSecretKey {
         hmac_engine.input(u32::from(i).to_be_bytes().as_ref());
         let mut hmac_result: Hmac<sha512::Hash> = Hmac::from_engine(hmac_engine);
 
-        let (mut secret_key, chain_code) = loop {
+        let mut secret_key = loop {
             match secp256k1::SecretKey::from_slice(&hmac_result[..32]) {
-                Ok(key) => break (key, ChainCode::from(&hmac_result[32..])),
+                Ok(key) => break key,
                 Err(_) => {
                     // let I = HMAC-SHA512(Key = cpar, Data = 0x01 || IR || ser32(i) and restart at step 2.
                     hmac_engine = HmacEngine::new(&self.chain_code[..]);
@@ -263,17 +319,30 @@ impl Secp256k1ExtSecretKey {
         secret_key.add_assign(&self.secret_key[..])?;
 
         Ok(Secp256k1ExtSecretKey {
-            depth: self.depth + 1,
+            depth: self
+                .depth
+                .checked_add(1)
+                .ok_or(Error::MaximumDepthExceeded)?,
             parent_fingerprint: self.fingerprint(secp),
             child_number: i,
-            secret_key,
-            chain_code,
+            secret_key: secret_bytes(&secret_key[..]),
+            chain_code: secret_bytes(&hmac_result[32..]),
         })
     }
 
+    /// Returns the secret key, built from the erased on drop bytes each time it is used.
+    pub fn secret_key(&self) -> secp256k1::SecretKey {
+        secp256k1::SecretKey::from_slice(&self.secret_key[..]).expect("Valid secret key bytes")
+    }
+
+    /// Returns the chain code of the extended secret key.
+    pub fn chain_code(&self) -> ChainCode {
+        ChainCode::from(&self.chain_code[..])
+    }
+
     /// Returns the public key computed from the secret key.
     pub fn public_key<C: secp256k1::Signing>(&self, secp: &Secp256k1<C>) -> secp256k1::PublicKey {
-        secp256k1::PublicKey::from_secret_key(secp, &self.secret_key)
+        secp256k1::PublicKey::from_secret_key(secp, &self.secret_key())
     }
 
     /// Returns the HASH160 of the serialized public key belonging to the xpriv.
@@ -289,9 +358,234 @@ impl Secp256k1ExtSecretKey {
     }
 }
 
+/// Secp256k1 extended public key. The extended public key contains its depth, parent fingerprint,
+/// child number, the public key, and the chain code. Non-hardened children are derived as defined
+/// in BIP32, which allows a watch-only party to compute the public keys without the secret keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Secp256k1ExtPublicKey {
+    /// The depth of this extended key, start with 0 for the master.
+    pub depth: u8,
+    /// The parent fingerprint, 0 for the master.
+    pub parent_fingerprint: Fingerprint,
+    /// The child number, with a hardened or non-hardened value.
+    pub child_number: ChildNumber,
+    /// The public key value.
+    pub public_key: secp256k1::PublicKey,
+    /// The 32-bytes entropy extention called chain code.
+    pub chain_code: ChainCode,
+}
+
+impl Secp256k1ExtPublicKey {
+    /// Compute the extended public key of an extended secret key, the linkage to the parent is
+    /// preserved.
+    pub fn from_secret_key<C: secp256k1::Signing>(
+        secp: &Secp256k1<C>,
+        sk: &Secp256k1ExtSecretKey,
+    ) -> Secp256k1ExtPublicKey {
+        Secp256k1ExtPublicKey {
+            depth: sk.depth,
+            parent_fingerprint: sk.parent_fingerprint,
+            child_number: sk.child_number,
+            public_key: sk.public_key(secp),
+            chain_code: sk.chain_code(),
+        }
+    }
+
+    /// Derive the extended public key from `&self` up to the given `path`, returns an error if the
+    /// path contains hardened child numbers.
+    pub fn derive_pub<C: secp256k1::Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        path: &impl AsRef<[ChildNumber]>,
+    ) -> Result<Self, Error> {
+        let mut pk = *self;
+        for cnum in path.as_ref() {
+            pk = pk.ckd_pub(secp, *cnum)?;
+        }
+        Ok(pk)
+    }
+
+    /// Derive the next extended public key given a non-hardened child number as defined in BIP32.
+    ///
+    /// ## SLIP10
+    /// As in [`Secp256k1ExtSecretKey::ckd_priv`] the computation is executed multiple times until
+    /// a valid tweak is found, the derived public key always matches the public key of the
+    /// non-hardened derived secret key.
+    pub fn ckd_pub<C: secp256k1::Verification>(
+        &self,
+        secp: &Secp256k1<C>,
+        i: ChildNumber,
+    ) -> Result<Secp256k1ExtPublicKey, Error> {
+        if i.is_hardened() {
+            return Err(Error::HardenedDerivationFromPublicKey);
+        }
+
+        let mut hmac_engine: HmacEngine<sha512::Hash> = HmacEngine::new(&self.chain_code[..]);
+        hmac_engine.input(&self.public_key.serialize()[..]);
+        hmac_engine.input(u32::from(i).to_be_bytes().as_ref());
+        let mut hmac_result: Hmac<sha512::Hash> = Hmac::from_engine(hmac_engine);
+
+        let (tweak, chain_code) = loop {
+            match secp256k1::SecretKey::from_slice(&hmac_result[..32]) {
+                Ok(key) => break (key, ChainCode::from(&hmac_result[32..])),
+                Err(_) => {
+                    // let I = HMAC-SHA512(Key = cpar, Data = 0x01 || IR || ser32(i) and restart at step 2.
+                    hmac_engine = HmacEngine::new(&self.chain_code[..]);
+                    hmac_engine.input(&[1u8]);
+                    hmac_engine.input(&hmac_result[32..]);
+                    hmac_engine.input(u32::from(i).to_be_bytes().as_ref());
+                    hmac_result = Hmac::from_engine(hmac_engine);
+                }
+            }
+        };
+
+        let mut public_key = self.public_key;
+        public_key.add_exp_assign(secp, &tweak[..])?;
+
+        Ok(Secp256k1ExtPublicKey {
+            depth: self
+                .depth
+                .checked_add(1)
+                .ok_or(Error::MaximumDepthExceeded)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: i,
+            public_key,
+            chain_code,
+        })
+    }
+
+    /// Returns the HASH160 of the serialized public key.
+    pub fn identifier(&self) -> XpubIdentifier {
+        let mut engine = XpubIdentifier::engine();
+        engine.input(self.public_key.serialize().as_ref());
+        XpubIdentifier::from_engine(engine)
+    }
+
+    /// Returns the first four bytes of the identifier.
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::from(&self.identifier()[0..4])
+    }
+
+    /// Serialize the extended public key in the 78 bytes BIP32 format, with the `xpub` version
+    /// for [`Network::Bitcoin`] and the `tpub` version otherwise.
+    pub fn encode(&self, network: Network) -> [u8; 78] {
+        let mut ret = [0u8; 78];
+        ret[0..4].copy_from_slice(match network {
+            Network::Bitcoin => &XPUB_VERSION,
+            _ => &TPUB_VERSION,
+        });
+        ret[4] = self.depth;
+        ret[5..9].copy_from_slice(&self.parent_fingerprint[..]);
+        ret[9..13].copy_from_slice(&u32::from(self.child_number).to_be_bytes());
+        ret[13..45].copy_from_slice(&self.chain_code[..]);
+        ret[45..78].copy_from_slice(&self.public_key.serialize()[..]);
+        ret
+    }
+
+    /// Deserialize an extended public key from the 78 bytes BIP32 format, returns the network,
+    /// [`Network::Testnet`] for `tpub` versions, and the key. Master keys, i.e. of depth 0, must
+    /// have a zero parent fingerprint and child number.
+    pub fn decode(data: &[u8]) -> Result<(Network, Secp256k1ExtPublicKey), Error> {
+        if data.len() != 78 {
+            return Err(Error::WrongExtendedKeyLength(data.len()));
+        }
+        let mut version = [0u8; 4];
+        version.copy_from_slice(&data[0..4]);
+        let network = match version {
+            XPUB_VERSION => Network::Bitcoin,
+            TPUB_VERSION => Network::Testnet,
+            _ => return Err(Error::UnknownVersion(version)),
+        };
+        let mut child_number = [0u8; 4];
+        child_number.copy_from_slice(&data[9..13]);
+        if data[4] == 0 && (data[5..9] != [0u8; 4] || child_number != [0u8; 4]) {
+            return Err(Error::InvalidMasterKey);
+        }
+
+        Ok((
+            network,
+            Secp256k1ExtPublicKey {
+                depth: data[4],
+                parent_fingerprint: Fingerprint::from(&data[5..9]),
+                child_number: ChildNumber::from(u32::from_be_bytes(child_number)),
+                public_key: secp256k1::PublicKey::from_slice(&data[45..78])?,
+                chain_code: ChainCode::from(&data[13..45]),
+            },
+        ))
+    }
+
+    /// Serialize the extended public key in the base58check `xpub`/`tpub` string format.
+    pub fn to_base58(&self, network: Network) -> String {
+        base58::check_encode_slice(&self.encode(network)[..])
+    }
+
+    /// Parse an extended public key from a base58check `xpub`/`tpub` string.
+    pub fn from_base58(s: &str) -> Result<(Network, Secp256k1ExtPublicKey), Error> {
+        Self::decode(&base58::from_check(s)?)
+    }
+}
+
+// Copy secret bytes, e.g. a secret key or a chain code, in a storage zeroized on drop. The
+// secp256k1 secret keys and chain codes do not give mutable access to their bytes.
+fn secret_bytes(bytes: &[u8]) -> Zeroizing<[u8; 32]> {
+    let mut secret = Zeroizing::new([0u8; 32]);
+    secret.copy_from_slice(bytes);
+    secret
+}
+
+impl Zeroize for Ed25519ExtSecretKey {
+    fn zeroize(&mut self) {
+        self.secret_key.zeroize();
+        self.chain_code.zeroize();
+    }
+}
+
+impl Drop for Ed25519ExtSecretKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl fmt::Debug for Ed25519ExtSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Ed25519ExtSecretKey")
+            .field("depth", &self.depth)
+            .field("parent_fingerprint", &self.parent_fingerprint)
+            .field("child_number", &self.child_number)
+            .field("secret_key", &REDACTED)
+            .field("chain_code", &REDACTED)
+            .finish()
+    }
+}
+
+impl Zeroize for Secp256k1ExtSecretKey {
+    fn zeroize(&mut self) {
+        self.secret_key.zeroize();
+        self.chain_code.zeroize();
+    }
+}
+
+impl Drop for Secp256k1ExtSecretKey {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl fmt::Debug for Secp256k1ExtSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Secp256k1ExtSecretKey")
+            .field("depth", &self.depth)
+            .field("parent_fingerprint", &self.parent_fingerprint)
+            .field("child_number", &self.child_number)
+            .field("secret_key", &REDACTED)
+            .field("chain_code", &REDACTED)
+            .finish()
+    }
+}
+
 /// An extended secret key. Generic interface for creating either a secp256k1 extended secret key
 /// or an ed25519 extended secret key and deriving sub-keys.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum ExtSecretKey {
     /// An extended secret key of type secp256k1.
     Secp256k1(Secp256k1ExtSecretKey),
@@ -313,7 +607,7 @@ impl ExtSecretKey {
     /// Derive the extended secret key given the path. When operating on Bitcoin curve a new
     /// `secp256k1` context is created.
     pub fn derive_priv(&self, path: &impl AsRef<[ChildNumber]>) -> Result<Self, Error> {
-        let mut sk = *self;
+        let mut sk = self.clone();
         for cnum in path.as_ref() {
             sk = sk.ckd_priv(*cnum)?;
         }
@@ -373,8 +667,8 @@ impl ExtSecretKey {
     /// Returns the chain code of the extended secret key.
     pub fn chain_code(&self) -> ChainCode {
         match self {
-            Self::Secp256k1(Secp256k1ExtSecretKey { chain_code, .. }) => *chain_code,
-            Self::Ed25519(Ed25519ExtSecretKey { chain_code, .. }) => *chain_code,
+            Self::Secp256k1(extended_key) => extended_key.chain_code(),
+            Self::Ed25519(extended_key) => extended_key.chain_code(),
         }
     }
 }
@@ -396,12 +690,55 @@ mod tests {
         }
     }
 
+    #[test]
+    fn debug_redacts_secrets() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let secp256k1 = ExtSecretKey::new_master_secp256k1(&seed);
+        let ed25519 = ExtSecretKey::new_master_ed25519(&seed);
+        let secp256k1_key = secp256k1.clone().to_secp256k1().unwrap();
+        let ed25519_key = ed25519.clone().to_ed25519().unwrap();
+
+        let secrets = [
+            secp256k1_key.secret_key().to_string(),
+            secp256k1_key.chain_code().to_string(),
+            format!("{:?}", ed25519_key.secret_key),
+            hex::encode(ed25519_key.secret_key),
+            ed25519_key.chain_code().to_string(),
+        ];
+        for debug in [format!("{:?}", secp256k1), format!("{:?}", ed25519)] {
+            assert!(debug.contains(REDACTED));
+            for secret in secrets.iter() {
+                assert!(
+                    !debug.contains(secret.as_str()),
+                    "{} leaks {}",
+                    debug,
+                    secret
+                );
+            }
+        }
+        assert!(format!("{:?}", ed25519).contains("child_number"));
+    }
+
+    #[test]
+    fn zeroize_erases_secrets() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let mut secp256k1_key = Secp256k1ExtSecretKey::new_master(&seed);
+        secp256k1_key.zeroize();
+        assert_eq!(*secp256k1_key.secret_key, [0u8; 32]);
+        assert_eq!(*secp256k1_key.chain_code, [0u8; 32]);
+
+        let mut ed25519_key = Ed25519ExtSecretKey::new_master(&seed);
+        ed25519_key.zeroize();
+        assert_eq!(ed25519_key.secret_key, [0u8; 32]);
+        assert_eq!(*ed25519_key.chain_code, [0u8; 32]);
+    }
+
     fn assert_eq_secp256k1_elem(res: &Secp256k1ExtSecretKey, asserts: Vec<&str>) {
         let ctx = Secp256k1::new();
 
         assert_eq!(asserts[0], res.parent_fingerprint.to_string());
-        assert_eq!(asserts[1], res.chain_code.to_string());
-        assert_eq!(asserts[2], res.secret_key.to_string());
+        assert_eq!(asserts[1], res.chain_code().to_string());
+        assert_eq!(asserts[2], res.secret_key().to_string());
         assert_eq!(asserts[3], res.public_key(&ctx).to_string());
     }
 
@@ -513,6 +850,197 @@ mod tests {
         );
     }
 
+    fn assert_secp256k1_xpubs(master: &Secp256k1ExtSecretKey, asserts: Vec<Vec<&str>>) {
+        let secp = Secp256k1::new();
+        for assert in asserts {
+            let path = DerivationPath::from_str(assert[0]).unwrap();
+            let sk = master.derive_priv(&secp, &path).unwrap();
+            let xpub = Secp256k1ExtPublicKey::from_secret_key(&secp, &sk);
+            assert_eq!(assert[1], xpub.to_base58(Network::Bitcoin));
+            assert_eq!(
+                Secp256k1ExtPublicKey::from_base58(assert[1]).unwrap(),
+                (Network::Bitcoin, xpub)
+            );
+            assert_eq!(xpub.fingerprint(), sk.fingerprint(&secp));
+
+            // derive the non-hardened tail of the path publicly
+            let path = path.as_ref();
+            let split = path
+                .iter()
+                .rposition(|cnum| cnum.is_hardened())
+                .map_or(0, |pos| pos + 1);
+            let parent = master.derive_priv(&secp, &path[..split].to_vec()).unwrap();
+            let derived = Secp256k1ExtPublicKey::from_secret_key(&secp, &parent)
+                .derive_pub(&secp, &path[split..].to_vec())
+                .unwrap();
+            assert_eq!(derived, xpub);
+        }
+    }
+
+    #[test]
+    fn secp256k1_xpub_vector_1() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = Secp256k1ExtSecretKey::new_master(&seed);
+
+        assert_secp256k1_xpubs(
+            &master,
+            vec![
+                vec![
+                    "m",
+                    "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+                ],
+                vec![
+                    "m/0'",
+                    "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+                ],
+                vec![
+                    "m/0'/1",
+                    "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+                ],
+                vec![
+                    "m/0'/1/2'",
+                    "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+                ],
+                vec![
+                    "m/0'/1/2'/2",
+                    "xpub6FHa3pjLCk84BayeJxFW2SP4XRrFd1JYnxeLeU8EqN3vDfZmbqBqaGJAyiLjTAwm6ZLRQUMv1ZACTj37sR62cfN7fe5JnJ7dh8zL4fiyLHV",
+                ],
+                vec![
+                    "m/0'/1/2'/2/1000000000",
+                    "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+                ],
+            ],
+        );
+    }
+
+    #[test]
+    fn secp256k1_xpub_vector_2() {
+        let seed = hex::decode("fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542").unwrap();
+        let master = Secp256k1ExtSecretKey::new_master(&seed);
+
+        assert_secp256k1_xpubs(
+            &master,
+            vec![
+                vec![
+                    "m",
+                    "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+                ],
+                vec![
+                    "m/0",
+                    "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH",
+                ],
+                vec![
+                    "m/0/2147483647'",
+                    "xpub6ASAVgeehLbnwdqV6UKMHVzgqAG8Gr6riv3Fxxpj8ksbH9ebxaEyBLZ85ySDhKiLDBrQSARLq1uNRts8RuJiHjaDMBU4Zn9h8LZNnBC5y4a",
+                ],
+                vec![
+                    "m/0/2147483647'/1",
+                    "xpub6DF8uhdarytz3FWdA8TvFSvvAh8dP3283MY7p2V4SeE2wyWmG5mg5EwVvmdMVCQcoNJxGoWaU9DCWh89LojfZ537wTfunKau47EL2dhHKon",
+                ],
+                vec![
+                    "m/0/2147483647'/1/2147483646'",
+                    "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL",
+                ],
+                vec![
+                    "m/0/2147483647'/1/2147483646'/2",
+                    "xpub6FnCn6nSzZAw5Tw7cgR9bi15UV96gLZhjDstkXXxvCLsUXBGXPdSnLFbdpq8p9HmGsApME5hQTZ3emM2rnY5agb9rXpVGyy3bdW6EEgAtqt",
+                ],
+            ],
+        );
+    }
+
+    #[test]
+    fn secp256k1_xpub_vector_3() {
+        // retention of leading zeros
+        let seed = hex::decode("4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be").unwrap();
+        let master = Secp256k1ExtSecretKey::new_master(&seed);
+
+        assert_secp256k1_xpubs(
+            &master,
+            vec![
+                vec![
+                    "m",
+                    "xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13",
+                ],
+                vec![
+                    "m/0'",
+                    "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y",
+                ],
+            ],
+        );
+    }
+
+    #[test]
+    fn secp256k1_xpub_serialization() {
+        let secp = Secp256k1::new();
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let xpub = Secp256k1ExtPublicKey::from_secret_key(
+            &secp,
+            &Secp256k1ExtSecretKey::new_master(&seed),
+        );
+
+        let tpub = xpub.to_base58(Network::Testnet);
+        assert!(tpub.starts_with("tpub"));
+        assert_eq!(xpub.to_base58(Network::Regtest), tpub);
+        assert_eq!(
+            Secp256k1ExtPublicKey::from_base58(&tpub).unwrap(),
+            (Network::Testnet, xpub)
+        );
+
+        assert!(matches!(
+            xpub.ckd_pub(&secp, ChildNumber::from_hardened_idx(0).unwrap()),
+            Err(Error::HardenedDerivationFromPublicKey)
+        ));
+
+        let mut data = xpub.encode(Network::Bitcoin);
+        assert!(matches!(
+            Secp256k1ExtPublicKey::decode(&data[..77]),
+            Err(Error::WrongExtendedKeyLength(77))
+        ));
+        data[0] = 0xff;
+        assert!(matches!(
+            Secp256k1ExtPublicKey::decode(&data),
+            Err(Error::UnknownVersion([0xff, 0x88, 0xb2, 0x1e]))
+        ));
+        let mut data = xpub.encode(Network::Bitcoin);
+        data[45] = 0x05;
+        assert!(matches!(
+            Secp256k1ExtPublicKey::decode(&data),
+            Err(Error::Secp256k1(_))
+        ));
+        // a master key must have a zero parent fingerprint and child number
+        let mut data = xpub.encode(Network::Bitcoin);
+        data[5] = 0x01;
+        assert!(matches!(
+            Secp256k1ExtPublicKey::decode(&data),
+            Err(Error::InvalidMasterKey)
+        ));
+        let mut data = xpub.encode(Network::Bitcoin);
+        data[12] = 0x01;
+        assert!(matches!(
+            Secp256k1ExtPublicKey::decode(&data),
+            Err(Error::InvalidMasterKey)
+        ));
+        data[4] = 0x01;
+        assert!(Secp256k1ExtPublicKey::decode(&data).is_ok());
+
+        let max_depth = Secp256k1ExtPublicKey {
+            depth: u8::MAX,
+            ..xpub
+        };
+        assert!(matches!(
+            max_depth.ckd_pub(&secp, ChildNumber::from_normal_idx(0).unwrap()),
+            Err(Error::MaximumDepthExceeded)
+        ));
+
+        let mut xpub_str = xpub.to_base58(Network::Bitcoin);
+        xpub_str.pop();
+        assert!(matches!(
+            Secp256k1ExtPublicKey::from_base58(&xpub_str),
+            Err(Error::Base58(_))
+        ));
+    }
+
     fn assert_ed25519_curve(master: &ExtSecretKey, asserts: Vec<Vec<&str>>) {
         for mut assert in asserts {
             let chain = master
@@ -526,7 +1054,7 @@ mod tests {
 
     fn assert_eq_ed25519_elem(res: &Ed25519ExtSecretKey, asserts: Vec<&str>) {
         assert_eq!(asserts[0], res.parent_fingerprint.to_string());
-        assert_eq!(asserts[1], res.chain_code.to_string());
+        assert_eq!(asserts[1], res.chain_code().to_string());
         assert_eq!(asserts[2], hex::encode(res.secret_key));
         assert_eq!(asserts[3], hex::encode(res.serialized_public_key()));
     }
@@ -51,10 +51,10 @@ impl<'de> Visitor<'de> for OfferString {
     where
         E: de::Error,
     {
-        if s.len() == 195 {
+        if s.starts_with(crate::negotiation::PUB_OFFER_PREFIX) {
             Ok(s.to_string())
         } else {
-            Err(de::Error::invalid_length(s.len(), &self))
+            Err(de::Error::invalid_value(Unexpected::Str(s), &self))
         }
     }
 }
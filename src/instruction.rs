@@ -1,18 +1,40 @@
 //! Farcaster instructions sent between client and daemon to instruct what to do next in the swap
 //! process.
 
+use crate::protocol_message::AbortCode;
+use crate::Error;
+
 pub trait Instruction {}
 
 /// Provides deamon the instruction to abort the swap, it is the daemon responsability to abort
 /// accordingly to the current state swap. By transmitting latter feedback via datum messages, the
 /// client must be able to provide any missing signatures.
 pub struct Abort {
-    /// OPTIONAL: A code conveying the reason of the abort
-    pub abort_code: Option<u16>,
+    /// A code conveying the reason of the abort
+    pub abort_code: AbortCode,
+    /// OPTIONAL: Details on the reason of the abort
+    pub error_body: Option<String>,
 }
 
 impl Instruction for Abort {}
 
+impl From<Error> for Abort {
+    /// Abort the swap because of the error. The error is described in the body, unless it is a
+    /// local failure not caused by the counterparty, as in the protocol message [`Abort`].
+    ///
+    /// [`Abort`]: crate::protocol_message::Abort
+    fn from(error: Error) -> Self {
+        let abort_code = AbortCode::from(&error);
+        Self {
+            abort_code,
+            error_body: match abort_code {
+                AbortCode::InternalError => None,
+                _ => Some(error.to_string()),
+            },
+        }
+    }
+}
+
 /// Provides deamon the instruction to follow the protocol swap, daemon can create locking steps
 /// during the protocol execution and require client to acknoledge the execution progression.
 pub struct Next {
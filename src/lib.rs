@@ -55,6 +55,9 @@
 #![deny(unused_mut)]
 //#![deny(missing_docs)]
 
+// Allow the consensus derive macros to refer to `::farcaster_core` inside this crate
+extern crate self as farcaster_core;
+
 #[macro_use]
 extern crate amplify;
 
@@ -103,6 +106,9 @@ pub enum Error {
     /// A negotiation error.
     #[error("Negotiation error: {0}")]
     Negotiation(#[from] negotiation::Error),
+    /// An error when validating the parameters of a participant.
+    #[error("Bundle error: {0}")]
+    Bundle(#[from] bundle::Error),
     /// A syncer task or event error.
     #[error("Syncer error: {0}")]
     Syncer(#[from] syncer::Error),
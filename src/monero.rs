@@ -1,7 +1,7 @@
 //! Implementation of the Monero blockchain as an accordant blockchain in a swap. This
 //! implementation should work in pair with any other arbitrating implementation, like Bitcoin.
 
-use crate::blockchain::{self, Asset, Network};
+use crate::blockchain::{self, Asset, BaseUnit, Network, Rounding};
 use crate::consensus::{self, CanonicalBytes};
 use crate::crypto::{self, AccordantKeys, Keys, SharedKeyId, SharedSecretKeys, SwapAccordantKeys};
 use crate::role::Accordant;
@@ -102,6 +102,18 @@ impl Asset for Monero {
     }
 }
 
+impl BaseUnit for Amount {
+    const ROUNDING: Rounding = Rounding::Down;
+
+    fn as_base_units(&self) -> u64 {
+        self.as_pico()
+    }
+
+    fn from_base_units(units: u64) -> Self {
+        Amount::from_pico(units)
+    }
+}
+
 impl CanonicalBytes for Amount {
     fn as_canonical_bytes(&self) -> Vec<u8> {
         monero::consensus::encode::serialize(&self.as_pico())
@@ -119,6 +131,10 @@ impl CanonicalBytes for Amount {
 
 impl blockchain::Address for Monero {
     type Address = Address;
+
+    fn is_valid_address(address: &Address, network: Network) -> bool {
+        address.network == network.into()
+    }
 }
 
 impl CanonicalBytes for Address {
@@ -142,6 +158,13 @@ impl Keys for Monero {
         // No extra key
         vec![]
     }
+
+    fn is_valid_public_key(key: &PublicKey) -> bool {
+        // Reject non-canonical points and points of small order, including the identity
+        key.point
+            .decompress()
+            .map_or(false, |point| !point.is_small_order())
+    }
 }
 
 impl CanonicalBytes for PrivateKey {
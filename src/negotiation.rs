@@ -18,6 +18,19 @@
 //! - The offer, containing the asset types, amounts, timings, etc.
 //! - A node identifier, used to secure the communication with the other peer
 //! - A peer address, used to connect to the other peer
+//! - Since version 2, an optional range of arbitrating amounts the taker can choose from
+//!
+//! ## Amount Ranges
+//!
+//! A public offer of [`Version`] 2 can carry an [`AmountRange`] allowing the taker to choose the
+//! arbitrating amount of the swap. The offer amounts then only define the fixed price of the
+//! trade, see [`Offer::price`]. The taker commits to an amount during the handshake and both
+//! participants derive the swap parameters from [`PublicOffer::fill`].
+//!
+//! ## Offer URI
+//!
+//! A public offer can be shared as a `farcaster:` URI, see [`OfferUri`], carrying an optional
+//! label and expiration time for wallets and web pages linking to it.
 
 use bitcoin::secp256k1::PublicKey;
 use inet2_addr::InetSocketAddr;
@@ -28,10 +41,12 @@ use std::str::FromStr;
 use thiserror::Error;
 use tiny_keccak::{Hasher, Keccak};
 
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
 use std::io;
 
-use crate::blockchain::{Asset, Fee, FeeStrategy, Network, Timelock};
+use crate::blockchain::{Asset, BaseUnit, Fee, FeeStrategy, Network, Timelock};
 use crate::consensus::{self, serialize, serialize_hex, CanonicalBytes, Decodable, Encodable};
 #[cfg(feature = "serde")]
 use crate::hash::{HashString, OfferString};
@@ -60,6 +75,11 @@ impl Version {
         Self::new(1)
     }
 
+    /// Create a new version 2 public offer, supporting arbitrating amount ranges.
+    pub fn new_v2() -> Self {
+        Self::new(2)
+    }
+
     /// Create a public offer from a raw version and feature `u16`.
     pub fn new(version: u16) -> Self {
         Version(version)
@@ -92,6 +112,302 @@ pub enum Error {
     /// The public offer signature does not pass the validation tests.
     #[error("Invalid signature")]
     InvalidSignature,
+    /// The amount range is empty or its minimum is zero.
+    #[error("Invalid amount range")]
+    InvalidAmountRange,
+    /// The chosen amount is not accepted by the public offer.
+    #[error("Amount out of the offer range")]
+    AmountOutOfRange,
+    /// The public offer amount range must be filled with a chosen amount before being used.
+    #[error("Public offer amount range is not filled")]
+    UnfilledAmountRange,
+    /// The amount cannot be computed from the offer price.
+    #[error("Amount cannot be computed from the offer price")]
+    InvalidPrice,
+    /// The public offer does not match the one referenced.
+    #[error("Public offer mismatch")]
+    PublicOfferMismatch,
+    /// Not all the parameters have been set to create the offer.
+    #[error("Incomplete offer, missing parameters")]
+    IncompleteOffer,
+    /// The amount of assets is zero.
+    #[error("Amount is zero")]
+    ZeroAmount,
+    /// The amount of assets is below the blockchain dust limit.
+    #[error("Amount is below the dust limit")]
+    DustAmount,
+    /// The amount of assets is below the minimum required by the validation policy.
+    #[error("Amount is below the policy minimum")]
+    AmountBelowMinimum,
+    /// The timelock cannot be enforced on-chain, e.g. exceeds BIP68 limits for Bitcoin.
+    #[error("Timelock exceeds the blockchain consensus limits")]
+    InvalidTimelock,
+    /// The punish timelock is shorter than the cancel timelock.
+    #[error("Punish timelock is shorter than the cancel timelock")]
+    PunishTimelockBeforeCancel,
+    /// The timelock is below the minimum required by the validation policy.
+    #[error("Timelock is below the policy minimum")]
+    TimelockBelowMinimum,
+    /// The fee strategy range minimum is greater than its maximum.
+    #[error("Fee strategy range minimum is greater than its maximum")]
+    InvalidFeeRange,
+    /// The handshakes are not related to the same swap.
+    #[error("Handshake swap identifier mismatch")]
+    SwapIdMismatch,
+    /// The participants do not support any common protocol version.
+    #[error("No common protocol version")]
+    NoCommonProtocolVersion,
+    /// A feature required by one participant is not supported by the other, identified by its
+    /// required feature bit.
+    #[error("Unsupported required feature bit {0}")]
+    UnsupportedFeature(u8),
+}
+
+/// Configurable minimums applied on top of the blockchain limits when validating an [`Offer`] or
+/// a [`PublicOffer`], see [`Offer::validate_with`]. Unset values are not checked.
+#[derive(Debug, Clone)]
+pub struct ValidationPolicy<Ctx: Swap> {
+    /// The minimum amount of arbitrating assets.
+    pub min_arbitrating_amount: Option<<Ctx::Ar as Asset>::AssetUnit>,
+    /// The minimum amount of accordant assets.
+    pub min_accordant_amount: Option<<Ctx::Ac as Asset>::AssetUnit>,
+    /// The minimum cancel timelock.
+    pub min_cancel_timelock: Option<<Ctx::Ar as Timelock>::Timelock>,
+    /// The minimum punish timelock.
+    pub min_punish_timelock: Option<<Ctx::Ar as Timelock>::Timelock>,
+}
+
+impl<Ctx> Default for ValidationPolicy<Ctx>
+where
+    Ctx: Swap,
+{
+    fn default() -> Self {
+        ValidationPolicy {
+            min_arbitrating_amount: None,
+            min_accordant_amount: None,
+            min_cancel_timelock: None,
+            min_punish_timelock: None,
+        }
+    }
+}
+
+// Validate an amount against zero, the blockchain dust limit, and the optional policy minimum
+fn validate_amount<A: Asset>(
+    amount: A::AssetUnit,
+    minimum: Option<A::AssetUnit>,
+) -> Result<(), Error> {
+    let units = amount.as_base_units();
+    if units == 0 {
+        return Err(Error::ZeroAmount);
+    }
+    if units < A::dust_limit().as_base_units() {
+        return Err(Error::DustAmount);
+    }
+    match minimum {
+        Some(min) if units < min.as_base_units() => Err(Error::AmountBelowMinimum),
+        _ => Ok(()),
+    }
+}
+
+/// An exact price expressed as the ratio between base units of two assets, e.g. piconeros per
+/// satoshi. The ratio is always stored in its reduced form, no floating point arithmetic is
+/// involved when comparing or applying prices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[display("{numerator}/{denominator}")]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(crate = "serde_crate"))]
+pub struct Price {
+    numerator: u64,
+    denominator: u64,
+}
+
+impl Price {
+    /// Create a new price of `numerator` quote base units for `denominator` base units, returns
+    /// `None` if the denominator is zero.
+    pub fn new(numerator: u64, denominator: u64) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+        let gcd = gcd(numerator, denominator);
+        Some(Price {
+            numerator: numerator / gcd,
+            denominator: denominator / gcd,
+        })
+    }
+
+    /// The number of quote base units in the reduced ratio.
+    pub fn numerator(&self) -> u64 {
+        self.numerator
+    }
+
+    /// The number of base units in the reduced ratio.
+    pub fn denominator(&self) -> u64 {
+        self.denominator
+    }
+
+    /// Return the price in the other direction, `None` if the price is zero.
+    pub fn inverse(&self) -> Option<Self> {
+        Self::new(self.denominator, self.numerator)
+    }
+
+    /// Apply the price on an amount of base units and return the resulting amount in the quote
+    /// unit. The result is rounded following the [`BaseUnit::ROUNDING`] rule of the quote unit,
+    /// returns `None` if the result overflows.
+    pub fn apply<T: BaseUnit>(&self, base_units: u64) -> Option<T> {
+        T::ROUNDING
+            .apply(base_units, self.numerator, self.denominator)
+            .map(T::from_base_units)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Price {
+    fn deserialize<D>(deserializer: D) -> Result<Price, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // go through the constructor to reject zero denominators and reduce the ratio
+        #[derive(Deserialize)]
+        #[serde(crate = "serde_crate", rename = "Price")]
+        struct RawPrice {
+            numerator: u64,
+            denominator: u64,
+        }
+
+        let raw = RawPrice::deserialize(deserializer)?;
+        Price::new(raw.numerator, raw.denominator)
+            .ok_or_else(|| de::Error::custom(Error::InvalidPrice))
+    }
+}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // a/b <=> c/d is equivalent to a*d <=> c*b with non-zero denominators, cannot overflow
+        // in 128 bits
+        let lhs = self.numerator as u128 * other.denominator as u128;
+        let rhs = other.numerator as u128 * self.denominator as u128;
+        lhs.cmp(&rhs)
+    }
+}
+
+// An optional price ordered after all the prices when missing, the order of offers by price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PriceKey(Option<Price>);
+
+impl PartialOrd for PriceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriceKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let t = b;
+        b = a % b;
+        a = t;
+    }
+    a
+}
+
+/// A range of amounts, minimum and maximum inclusive, used in public offers to let the taker
+/// choose the arbitrating amount of the swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[display("from {min_inc} to {max_inc} (inclusive)")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct AmountRange<T>
+where
+    T: fmt::Display,
+{
+    /// The minimum amount, inclusive.
+    pub min_inc: T,
+    /// The maximum amount, inclusive.
+    pub max_inc: T,
+}
+
+impl<T> AmountRange<T>
+where
+    T: fmt::Display + BaseUnit,
+{
+    /// Create a new amount range, returns an error if the minimum is zero or greater than the
+    /// maximum.
+    pub fn new(min_inc: T, max_inc: T) -> Result<Self, Error> {
+        let range = Self { min_inc, max_inc };
+        range.validate()?;
+        Ok(range)
+    }
+
+    /// Validate that the minimum is not zero and not greater than the maximum.
+    pub fn validate(&self) -> Result<(), Error> {
+        let min = self.min_inc.as_base_units();
+        if min == 0 || min > self.max_inc.as_base_units() {
+            return Err(Error::InvalidAmountRange);
+        }
+        Ok(())
+    }
+
+    /// Return `true` if the amount is within the range, bounds included.
+    pub fn contains(&self, amount: &T) -> bool {
+        let amount = amount.as_base_units();
+        amount >= self.min_inc.as_base_units() && amount <= self.max_inc.as_base_units()
+    }
+}
+
+impl<T> Encodable for AmountRange<T>
+where
+    T: fmt::Display + CanonicalBytes,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.min_inc.as_canonical_bytes().consensus_encode(s)?;
+        Ok(len + self.max_inc.as_canonical_bytes().consensus_encode(s)?)
+    }
+}
+
+impl<T> Decodable for AmountRange<T>
+where
+    T: fmt::Display + CanonicalBytes,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self {
+            min_inc: T::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+            max_inc: T::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
+        })
+    }
+}
+
+impl<T> CanonicalBytes for AmountRange<T>
+where
+    T: fmt::Debug + fmt::Display + CanonicalBytes,
+{
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        serialize(self)
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        consensus::deserialize(bytes)
+    }
 }
 
 fixed_hash::construct_fixed_hash!(
@@ -193,7 +509,32 @@ impl<Ctx: Swap> Offer<Ctx> {
             offer: self,
             node_id,
             peer_address,
+            amount_range: None,
+        }
+    }
+
+    /// Transform the offer in a public offer of [`Version`] 2 where the taker chooses the
+    /// arbitrating amount within the given range. The offer amounts define the fixed price of the
+    /// trade.
+    ///
+    /// Returns an error if the range is invalid or if the offer does not define a price.
+    pub fn to_public_v2(
+        self,
+        node_id: PublicKey,
+        peer_address: InetSocketAddr,
+        amount_range: AmountRange<<Ctx::Ar as Asset>::AssetUnit>,
+    ) -> Result<PublicOffer<Ctx>, Error> {
+        amount_range.validate()?;
+        if self.price().is_none() {
+            return Err(Error::InvalidPrice);
         }
+        Ok(PublicOffer {
+            version: Version::new_v2(),
+            offer: self,
+            node_id,
+            peer_address,
+            amount_range: Some(amount_range),
+        })
     }
 
     /// Return the future swap role for the given trade role.
@@ -212,6 +553,83 @@ impl<Ctx: Swap> Offer<Ctx> {
         keccak.finalize(&mut out);
         OfferId(out)
     }
+
+    /// Validate the offer with the default [`ValidationPolicy`], see [`Offer::validate_with`].
+    pub fn validate(&self) -> Result<(), Error> {
+        self.validate_with(&ValidationPolicy::default())
+    }
+
+    /// Validate the offer parameters and return a typed error for the first failing check:
+    ///
+    /// - amounts must be non-zero, above the blockchain dust limit and above the policy minimums
+    /// - timelocks must be enforceable on-chain and above the policy minimums
+    /// - the punish timelock must not be shorter than the cancel timelock
+    /// - a fee strategy range minimum must not be greater than its maximum
+    pub fn validate_with(&self, policy: &ValidationPolicy<Ctx>) -> Result<(), Error> {
+        validate_amount::<Ctx::Ar>(self.arbitrating_amount, policy.min_arbitrating_amount)?;
+        validate_amount::<Ctx::Ac>(self.accordant_amount, policy.min_accordant_amount)?;
+        if !<Ctx::Ar as Timelock>::is_valid_timelock(&self.cancel_timelock)
+            || !<Ctx::Ar as Timelock>::is_valid_timelock(&self.punish_timelock)
+        {
+            return Err(Error::InvalidTimelock);
+        }
+        if self.punish_timelock < self.cancel_timelock {
+            return Err(Error::PunishTimelockBeforeCancel);
+        }
+        if matches!(policy.min_cancel_timelock, Some(min) if self.cancel_timelock < min)
+            || matches!(policy.min_punish_timelock, Some(min) if self.punish_timelock < min)
+        {
+            return Err(Error::TimelockBelowMinimum);
+        }
+        match &self.fee_strategy {
+            FeeStrategy::Range { min_inc, max_inc } if min_inc > max_inc => {
+                Err(Error::InvalidFeeRange)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Return the price of one arbitrating base unit expressed in accordant base units, e.g.
+    /// piconeros per satoshi. Returns `None` if the arbitrating amount is zero.
+    pub fn price(&self) -> Option<Price> {
+        Price::new(
+            self.accordant_amount.as_base_units(),
+            self.arbitrating_amount.as_base_units(),
+        )
+    }
+
+    /// Return the price of one accordant base unit expressed in arbitrating base units, e.g.
+    /// satoshis per piconero. Returns `None` if the accordant amount is zero.
+    pub fn inverse_price(&self) -> Option<Price> {
+        Price::new(
+            self.arbitrating_amount.as_base_units(),
+            self.accordant_amount.as_base_units(),
+        )
+    }
+
+    /// Compare two offers by their [`Offer::price`], i.e. offers asking less accordant assets per
+    /// arbitrating asset are lower. Offers without a price are ordered after all others.
+    pub fn cmp_price(&self, other: &Self) -> Ordering {
+        PriceKey(self.price()).cmp(&PriceKey(other.price()))
+    }
+
+    /// Return the amount of accordant assets exchanged for the given arbitrating amount at the
+    /// offer's price, rounded following the accordant [`BaseUnit::ROUNDING`] rule.
+    pub fn accordant_amount_for(
+        &self,
+        amount: <Ctx::Ar as Asset>::AssetUnit,
+    ) -> Option<<Ctx::Ac as Asset>::AssetUnit> {
+        self.price()?.apply(amount.as_base_units())
+    }
+
+    /// Return the amount of arbitrating assets exchanged for the given accordant amount at the
+    /// offer's price, rounded following the arbitrating [`BaseUnit::ROUNDING`] rule.
+    pub fn arbitrating_amount_for(
+        &self,
+        amount: <Ctx::Ac as Asset>::AssetUnit,
+    ) -> Option<<Ctx::Ar as Asset>::AssetUnit> {
+        self.inverse_price()?.apply(amount.as_base_units())
+    }
 }
 
 impl<Ctx> Encodable for Offer<Ctx>
@@ -305,6 +723,14 @@ where
         self
     }
 
+    /// Defines the asset the maker will send to get the assets defined in the `some` method, the
+    /// amount is computed from the given price in accordant base units per arbitrating base unit.
+    /// If the amount cannot be computed the offer will not be created.
+    pub fn at_price(mut self, asset: Ctx::Ac, price: Price) -> Self {
+        self.0.set_price(asset, price);
+        self
+    }
+
     /// Sets the timelocks for the proposed offer.
     pub fn with_timelocks(
         mut self,
@@ -347,6 +773,14 @@ where
             maker_role: self.0.maker_role?,
         })
     }
+
+    /// Transform the internal state into an offer and validate it against the policy, see
+    /// [`Offer::validate_with`]. Returns [`Error::IncompleteOffer`] if some parameters are missing.
+    pub fn to_valid_offer(self, policy: &ValidationPolicy<Ctx>) -> Result<Offer<Ctx>, Error> {
+        let offer = self.to_offer().ok_or(Error::IncompleteOffer)?;
+        offer.validate_with(policy)?;
+        Ok(offer)
+    }
 }
 
 /// Helper to create an offer from an arbitrating asset seller perspective. Only works only for
@@ -380,6 +814,14 @@ where
         self
     }
 
+    /// Defines the asset the maker will receive in exchange of the assets defined in the `some`
+    /// method, the amount is computed from the given price in accordant base units per
+    /// arbitrating base unit. If the amount cannot be computed the offer will not be created.
+    pub fn at_price(mut self, asset: Ctx::Ac, price: Price) -> Self {
+        self.0.set_price(asset, price);
+        self
+    }
+
     /// Sets the timelocks for the proposed offer.
     pub fn with_timelocks(
         mut self,
@@ -422,6 +864,14 @@ where
             maker_role: self.0.maker_role?,
         })
     }
+
+    /// Transform the internal state into an offer and validate it against the policy, see
+    /// [`Offer::validate_with`]. Returns [`Error::IncompleteOffer`] if some parameters are missing.
+    pub fn to_valid_offer(self, policy: &ValidationPolicy<Ctx>) -> Result<Offer<Ctx>, Error> {
+        let offer = self.to_offer().ok_or(Error::IncompleteOffer)?;
+        offer.validate_with(policy)?;
+        Ok(offer)
+    }
 }
 
 // Internal state of an offer builder
@@ -437,6 +887,18 @@ struct BuilderState<Ctx: Swap> {
     maker_role: Option<SwapRole>,
 }
 
+impl<Ctx> BuilderState<Ctx>
+where
+    Ctx: Swap,
+{
+    fn set_price(&mut self, asset: Ctx::Ac, price: Price) {
+        self.accordant_blockchain = Some(asset);
+        self.accordant_amount = self
+            .arbitrating_amount
+            .and_then(|amount| price.apply(amount.as_base_units()));
+    }
+}
+
 impl<Ctx> Default for BuilderState<Ctx>
 where
     Ctx: Swap,
@@ -503,10 +965,14 @@ impl_strict_encoding!(PublicOfferId);
 /// A public offer is shared across [`TradeRole::Maker`]'s prefered network to signal is willing of
 /// trading some assets at some conditions. The assets and condition are defined in the [`Offer`],
 /// maker peer connection information are contained in the public offer.
+///
+/// Public offers are created with [`Offer::to_public_v1`] and [`Offer::to_public_v2`], the
+/// version and the amount range are read-only so that a version 1 public offer never carries a
+/// range.
 #[derive(Debug, Clone, Eq)]
 pub struct PublicOffer<Ctx: Swap> {
-    /// The public offer version.
-    pub version: Version,
+    // The public offer version.
+    version: Version,
     /// The content of the offer.
     pub offer: Offer<Ctx>,
     /// Node public key, used both as an ID and encryption key for per-session ECDH.
@@ -514,9 +980,23 @@ pub struct PublicOffer<Ctx: Swap> {
     /// Address of the listening daemon's peer. An internet socket address, which consists of an IP
     /// or Tor address and a port number.
     pub peer_address: InetSocketAddr,
+    // The range of arbitrating amounts the taker can choose from, only available since version
+    // 2.
+    amount_range: Option<AmountRange<<Ctx::Ar as Asset>::AssetUnit>>,
 }
 
 impl<Ctx: Swap> PublicOffer<Ctx> {
+    /// The public offer version.
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// The range of arbitrating amounts the taker can choose from, only available since
+    /// [`Version`] 2. When set, the offer amounts only define the price of the trade.
+    pub fn amount_range(&self) -> Option<&AmountRange<<Ctx::Ar as Asset>::AssetUnit>> {
+        self.amount_range.as_ref()
+    }
+
     /// Generate the [`PublicOfferId`] from the offer. Serialized the public offer with consensus
     /// encoding and return the keccak hash result with [`PublicOfferId`].
     pub fn id(&self) -> PublicOfferId {
@@ -531,6 +1011,76 @@ impl<Ctx: Swap> PublicOffer<Ctx> {
     pub fn to_hex(&self) -> String {
         serialize_hex(&self.clone())
     }
+
+    /// Validate the public offer with the default [`ValidationPolicy`], see
+    /// [`PublicOffer::validate_with`].
+    pub fn validate(&self) -> Result<(), Error> {
+        self.validate_with(&ValidationPolicy::default())
+    }
+
+    /// Validate the offer with [`Offer::validate_with`] and, if any, the amount range. The range
+    /// minimum, and its accordant counterpart at the offer price, must pass the same amount checks
+    /// as the offer amounts.
+    pub fn validate_with(&self, policy: &ValidationPolicy<Ctx>) -> Result<(), Error> {
+        self.offer.validate_with(policy)?;
+        if let Some(range) = &self.amount_range {
+            range.validate()?;
+            validate_amount::<Ctx::Ar>(range.min_inc, policy.min_arbitrating_amount)?;
+            let accordant_amount = self
+                .offer
+                .accordant_amount_for(range.min_inc)
+                .ok_or(Error::InvalidPrice)?;
+            validate_amount::<Ctx::Ac>(accordant_amount, policy.min_accordant_amount)?;
+        }
+        Ok(())
+    }
+
+    /// Return `true` if the taker must choose the arbitrating amount within a range.
+    pub fn is_range(&self) -> bool {
+        self.amount_range.is_some()
+    }
+
+    /// Return `true` if the arbitrating amount can be chosen by the taker for this public offer.
+    pub fn accepts_amount(&self, amount: &<Ctx::Ar as Asset>::AssetUnit) -> bool {
+        match &self.amount_range {
+            Some(range) => range.contains(amount),
+            None => self.offer.arbitrating_amount == *amount,
+        }
+    }
+
+    /// Fix the arbitrating amount chosen by the taker and return the public offer with the exact
+    /// amounts used to derive the swap parameters. The accordant amount is computed from the
+    /// offer price and rounded following the accordant [`BaseUnit::ROUNDING`] rule. The filled
+    /// public offer has no range and is a [`Version`] 1 public offer.
+    ///
+    /// Returns an error if the amount is not accepted by the public offer or if the accordant
+    /// amount is rounded down to zero.
+    pub fn fill(&self, amount: <Ctx::Ar as Asset>::AssetUnit) -> Result<PublicOffer<Ctx>, Error> {
+        if !self.accepts_amount(&amount) {
+            return Err(Error::AmountOutOfRange);
+        }
+        if !self.is_range() {
+            return Ok(self.clone());
+        }
+        let accordant_amount = self
+            .offer
+            .accordant_amount_for(amount)
+            .ok_or(Error::InvalidPrice)?;
+        if accordant_amount.as_base_units() == 0 {
+            return Err(Error::ZeroAmount);
+        }
+        Ok(PublicOffer {
+            version: Version::new_v1(),
+            offer: Offer {
+                arbitrating_amount: amount,
+                accordant_amount,
+                ..self.offer.clone()
+            },
+            node_id: self.node_id,
+            peer_address: self.peer_address,
+            amount_range: None,
+        })
+    }
 }
 
 // https://doc.rust-lang.org/std/hash/trait.Hash.html#hash-and-eq
@@ -573,13 +1123,17 @@ where
 {
     type Err = consensus::Error;
 
+    /// Parse a public offer and validate it with the default [`ValidationPolicy`].
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if &s[..6] != PUB_OFFER_PREFIX {
+        if !s.starts_with(PUB_OFFER_PREFIX) {
             return Err(consensus::Error::IncorrectMagicBytes);
         }
-        let decoded = base58_monero::decode_check(&s[6..]).map_err(consensus::Error::new)?;
+        let decoded = base58_monero::decode_check(&s[PUB_OFFER_PREFIX.len()..])
+            .map_err(consensus::Error::new)?;
         let mut res = std::io::Cursor::new(decoded);
-        Decodable::consensus_decode(&mut res)
+        let public_offer: PublicOffer<Ctx> = Decodable::consensus_decode(&mut res)?;
+        public_offer.validate().map_err(consensus::Error::new)?;
+        Ok(public_offer)
     }
 }
 
@@ -622,6 +1176,16 @@ where
         let mut len = OFFER_MAGIC_BYTES.consensus_encode(s)?;
         len += self.version.consensus_encode(s)?;
         len += self.offer.consensus_encode(s)?;
+        match (self.version.to_u16(), &self.amount_range) {
+            (1, None) => (),
+            (2, Some(_)) => len += self.amount_range.consensus_encode(s)?,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Public offer version does not match its amount range",
+                ))
+            }
+        }
         len += self.node_id.as_canonical_bytes().consensus_encode(s)?;
         len +=
             strict_encoding::StrictEncode::strict_encode(&self.peer_address, s).map_err(|_| {
@@ -643,18 +1207,587 @@ where
         if magic_bytes != *OFFER_MAGIC_BYTES {
             return Err(consensus::Error::IncorrectMagicBytes);
         }
+        let version: Version = Decodable::consensus_decode(d)?;
+        let offer = Decodable::consensus_decode(d)?;
+        let amount_range: Option<AmountRange<<Ctx::Ar as Asset>::AssetUnit>> =
+            match version.to_u16() {
+                1 => None,
+                // version 2 public offers always carry a range, filled offers are version 1
+                2 => match Decodable::consensus_decode(d)? {
+                    Some(range) => Some(range),
+                    None => {
+                        return Err(consensus::Error::ParseFailed(
+                            "Version 2 public offer without amount range",
+                        ))
+                    }
+                },
+                _ => return Err(consensus::Error::new(Error::UnsupportedVersion)),
+            };
+        if let Some(range) = &amount_range {
+            range.validate().map_err(consensus::Error::new)?;
+        }
         Ok(PublicOffer {
-            version: Decodable::consensus_decode(d)?,
-            offer: Decodable::consensus_decode(d)?,
+            version,
+            offer,
             node_id: PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
             peer_address: strict_encoding::StrictDecode::strict_decode(d)
                 .map_err(consensus::Error::new)?,
+            amount_range,
         })
     }
 }
 
 impl_strict_encoding!(PublicOffer<Ctx>, Ctx: Swap);
 
+/// Scheme of a public offer URI, see [`OfferUri`].
+pub const OFFER_URI_SCHEME: &str = "farcaster";
+
+/// A public offer wrapped in a URI that wallets and web pages can link to. The URI carries an
+/// optional human-readable label and an optional expiration time (Unix timestamp in seconds) as
+/// query parameters:
+///
+/// ```text
+/// "farcaster:" | base58(serialize(public_offer)) [ "?label=" | label ] [ "&expiry=" | expiry ]
+/// ```
+///
+/// The label is percent-encoded. The scheme is case-insensitive while the base58 part is not.
+/// Unknown query parameters are ignored unless they are prefixed with `req-`, in which case
+/// parsing fails as in BIP21.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfferUri<Ctx: Swap> {
+    /// The public offer.
+    pub public_offer: PublicOffer<Ctx>,
+    /// An optional human-readable label describing the offer.
+    pub label: Option<String>,
+    /// An optional expiration time of the offer, as a Unix timestamp in seconds.
+    pub expiry: Option<u64>,
+}
+
+impl<Ctx: Swap> OfferUri<Ctx> {
+    /// Create a new URI for the public offer without label nor expiry.
+    pub fn new(public_offer: PublicOffer<Ctx>) -> Self {
+        Self {
+            public_offer,
+            label: None,
+            expiry: None,
+        }
+    }
+
+    /// Set the human-readable label of the URI.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Set the expiration time of the URI, as a Unix timestamp in seconds.
+    pub fn with_expiry(mut self, expiry: u64) -> Self {
+        self.expiry = Some(expiry);
+        self
+    }
+
+    /// Return `true` if the URI carries an expiration time reached at `now`.
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiry.map(|expiry| expiry <= now).unwrap_or(false)
+    }
+}
+
+impl<Ctx: Swap> From<PublicOffer<Ctx>> for OfferUri<Ctx> {
+    fn from(public_offer: PublicOffer<Ctx>) -> Self {
+        Self::new(public_offer)
+    }
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                res.push(b as char)
+            }
+            _ => res.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    res
+}
+
+fn percent_decode(s: &str) -> Result<String, consensus::Error> {
+    let bytes = s.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .ok_or(consensus::Error::ParseFailed("Truncated percent-encoding"))?;
+            // from_str_radix accepts a leading sign, only two hex digits are valid
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return Err(consensus::Error::ParseFailed("Invalid percent-encoding"));
+            }
+            let byte = u8::from_str_radix(std::str::from_utf8(hex).unwrap(), 16)
+                .map_err(|_| consensus::Error::ParseFailed("Invalid percent-encoding"))?;
+            res.push(byte);
+            i += 3;
+        } else {
+            res.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(res).map_err(|_| consensus::Error::ParseFailed("Label is not valid UTF-8"))
+}
+
+impl<Ctx> std::fmt::Display for OfferUri<Ctx>
+where
+    Ctx: Swap,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let public_offer = self.public_offer.to_string();
+        write!(
+            f,
+            "{}:{}",
+            OFFER_URI_SCHEME,
+            &public_offer[PUB_OFFER_PREFIX.len()..]
+        )?;
+        let mut separator = '?';
+        if let Some(label) = &self.label {
+            write!(f, "{}label={}", separator, percent_encode(label))?;
+            separator = '&';
+        }
+        if let Some(expiry) = self.expiry {
+            write!(f, "{}expiry={}", separator, expiry)?;
+        }
+        Ok(())
+    }
+}
+
+impl<Ctx> std::str::FromStr for OfferUri<Ctx>
+where
+    Ctx: Swap,
+{
+    type Err = consensus::Error;
+
+    /// Parse an offer URI and validate its public offer with the default [`ValidationPolicy`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = match s.find(':') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => return Err(consensus::Error::ParseFailed("Missing offer URI scheme")),
+        };
+        if !scheme.eq_ignore_ascii_case(OFFER_URI_SCHEME) {
+            return Err(consensus::Error::ParseFailed("Invalid offer URI scheme"));
+        }
+        let (encoded, query) = match rest.find('?') {
+            Some(i) => (&rest[..i], Some(&rest[i + 1..])),
+            None => (rest, None),
+        };
+        let public_offer: PublicOffer<Ctx> = format!("{}{}", PUB_OFFER_PREFIX, encoded).parse()?;
+
+        let mut uri = Self::new(public_offer);
+        for param in query.into_iter().flat_map(|q| q.split('&')) {
+            if param.is_empty() {
+                continue;
+            }
+            let (key, value) = match param.find('=') {
+                Some(i) => (&param[..i], &param[i + 1..]),
+                None => (param, ""),
+            };
+            match key {
+                "label" if uri.label.is_some() => {
+                    return Err(consensus::Error::ParseFailed("Duplicated label parameter"))
+                }
+                "label" => uri.label = Some(percent_decode(value)?),
+                "expiry" if uri.expiry.is_some() => {
+                    return Err(consensus::Error::ParseFailed("Duplicated expiry parameter"))
+                }
+                "expiry" => {
+                    uri.expiry =
+                        Some(value.parse().map_err(|_| {
+                            consensus::Error::ParseFailed("Invalid expiry parameter")
+                        })?)
+                }
+                key if key.starts_with("req-") => {
+                    return Err(consensus::Error::ParseFailed(
+                        "Unknown required offer URI parameter",
+                    ))
+                }
+                _ => (),
+            }
+        }
+        Ok(uri)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Ctx> Serialize for OfferUri<Ctx>
+where
+    Ctx: Swap,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_ref())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Ctx> Deserialize<'de> for OfferUri<Ctx>
+where
+    Ctx: Swap,
+{
+    fn deserialize<D>(deserializer: D) -> Result<OfferUri<Ctx>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// An entry of an [`OfferBook`], a public offer with an optional expiration time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+pub struct OfferBookEntry<Ctx: Swap> {
+    /// The public offer.
+    pub public_offer: PublicOffer<Ctx>,
+    /// Unix timestamp, in seconds, at which the offer expires. `None` if the offer never expires.
+    pub expires_at: Option<u64>,
+}
+
+impl<Ctx: Swap> OfferBookEntry<Ctx> {
+    /// Return `true` if the entry is expired at the given unix timestamp.
+    pub fn is_expired(&self, now: u64) -> bool {
+        matches!(self.expires_at, Some(expires_at) if expires_at <= now)
+    }
+}
+
+impl<Ctx> Encodable for OfferBookEntry<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.public_offer.consensus_encode(s)?;
+        match self.expires_at {
+            Some(expires_at) => {
+                len += 0x01u8.consensus_encode(s)?;
+                Ok(len + expires_at.consensus_encode(s)?)
+            }
+            None => Ok(len + 0x00u8.consensus_encode(s)?),
+        }
+    }
+}
+
+impl<Ctx> Decodable for OfferBookEntry<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let public_offer = Decodable::consensus_decode(d)?;
+        let expires_at = match Decodable::consensus_decode(d)? {
+            0x01u8 => Some(Decodable::consensus_decode(d)?),
+            0x00u8 => None,
+            _ => return Err(consensus::Error::UnknownType),
+        };
+        Ok(OfferBookEntry {
+            public_offer,
+            expires_at,
+        })
+    }
+}
+
+impl_strict_encoding!(OfferBookEntry<Ctx>, Ctx: Swap);
+
+/// A collection of public offers indexed by [`PublicOfferId`], [`OfferId`], network, maker swap
+/// role, and price. The offer book is used by takers and aggregators to organize public offers
+/// received from the network and to select the best offer for a given amount.
+///
+/// Expiration times are provided by the caller as unix timestamps when inserting offers, the
+/// offer book never reads the system clock. The book is serialized as the list of its entries
+/// ordered by [`PublicOfferId`] and can hold at most `u16::MAX` entries when encoded.
+#[derive(Debug, Clone)]
+pub struct OfferBook<Ctx: Swap> {
+    offers: HashMap<PublicOfferId, OfferBookEntry<Ctx>>,
+    by_offer_id: HashMap<OfferId, BTreeSet<PublicOfferId>>,
+    by_market: HashMap<(Network, SwapRole), MarketOffers>,
+}
+
+// Public offers of a market ordered by price.
+type MarketOffers = BTreeSet<(PriceKey, PublicOfferId)>;
+
+impl<Ctx> Default for OfferBook<Ctx>
+where
+    Ctx: Swap,
+{
+    fn default() -> Self {
+        OfferBook {
+            offers: HashMap::new(),
+            by_offer_id: HashMap::new(),
+            by_market: HashMap::new(),
+        }
+    }
+}
+
+impl<Ctx> OfferBook<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create a new empty offer book.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the number of public offers in the book.
+    pub fn len(&self) -> usize {
+        self.offers.len()
+    }
+
+    /// Return `true` if the book contains no public offer.
+    pub fn is_empty(&self) -> bool {
+        self.offers.is_empty()
+    }
+
+    /// Insert a public offer with its optional expiration time. Returns `true` if the public offer
+    /// was not present in the book, otherwise only the expiration time is updated and `false` is
+    /// returned.
+    pub fn insert(&mut self, public_offer: PublicOffer<Ctx>, expires_at: Option<u64>) -> bool {
+        let id = public_offer.id();
+        if let Some(entry) = self.offers.get_mut(&id) {
+            entry.expires_at = expires_at;
+            return false;
+        }
+        self.by_offer_id
+            .entry(public_offer.offer.id())
+            .or_default()
+            .insert(id);
+        self.by_market
+            .entry(Self::market_of(&public_offer))
+            .or_default()
+            .insert((PriceKey(public_offer.offer.price()), id));
+        self.offers.insert(
+            id,
+            OfferBookEntry {
+                public_offer,
+                expires_at,
+            },
+        );
+        true
+    }
+
+    /// Remove the public offer from the book and return its entry if it was present.
+    pub fn remove(&mut self, id: &PublicOfferId) -> Option<OfferBookEntry<Ctx>> {
+        let entry = self.offers.remove(id)?;
+        let offer_id = entry.public_offer.offer.id();
+        if let Some(ids) = self.by_offer_id.get_mut(&offer_id) {
+            ids.remove(id);
+            if ids.is_empty() {
+                self.by_offer_id.remove(&offer_id);
+            }
+        }
+        let market = Self::market_of(&entry.public_offer);
+        if let Some(offers) = self.by_market.get_mut(&market) {
+            offers.remove(&(PriceKey(entry.public_offer.offer.price()), *id));
+            if offers.is_empty() {
+                self.by_market.remove(&market);
+            }
+        }
+        Some(entry)
+    }
+
+    /// Return the entry for the given public offer identifier, if any.
+    pub fn get(&self, id: &PublicOfferId) -> Option<&OfferBookEntry<Ctx>> {
+        self.offers.get(id)
+    }
+
+    /// Return `true` if the book contains the public offer identifier.
+    pub fn contains(&self, id: &PublicOfferId) -> bool {
+        self.offers.contains_key(id)
+    }
+
+    /// Return all the public offers publishing the offer identified by [`OfferId`].
+    pub fn get_by_offer_id(&self, id: &OfferId) -> Vec<&PublicOffer<Ctx>> {
+        self.by_offer_id
+            .get(id)
+            .map(|ids| ids.iter().map(|id| &self.offers[id].public_offer).collect())
+            .unwrap_or_default()
+    }
+
+    /// Iterate over all the entries in the book, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &OfferBookEntry<Ctx>> {
+        self.offers.values()
+    }
+
+    /// Return all public offers on the network where the maker takes the given swap role, sorted
+    /// by ascending price as with [`Offer::cmp_price`]. Offers without a price are returned last.
+    pub fn offers(&self, network: Network, maker_role: SwapRole) -> Vec<&PublicOffer<Ctx>> {
+        self.by_market
+            .get(&(network, maker_role))
+            .map(|offers| {
+                offers
+                    .iter()
+                    .map(|(_, id)| &self.offers[id].public_offer)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Return the best public offer on the network where the maker takes the given swap role for
+    /// exchanging the given arbitrating amount, i.e. public offers with exactly that amount or with
+    /// an amount range containing it.
+    ///
+    /// When the maker is [`SwapRole::Bob`] he is selling arbitrating assets and the best offer is
+    /// the one with the lowest price, when the maker is [`SwapRole::Alice`] he is buying
+    /// arbitrating assets and the best offer is the one with the highest price.
+    pub fn best_offer(
+        &self,
+        network: Network,
+        maker_role: SwapRole,
+        amount: <Ctx::Ar as Asset>::AssetUnit,
+    ) -> Option<&PublicOffer<Ctx>> {
+        let offers = self.by_market.get(&(network, maker_role))?;
+        let mut candidates = offers
+            .iter()
+            .filter(|(price, _)| price.0.is_some())
+            .map(|(_, id)| &self.offers[id].public_offer)
+            .filter(|public_offer| public_offer.accepts_amount(&amount));
+        match maker_role {
+            SwapRole::Bob => candidates.next(),
+            SwapRole::Alice => candidates.last(),
+        }
+    }
+
+    /// Remove all entries expired at the given unix timestamp and return the number of removed
+    /// entries.
+    pub fn prune_expired(&mut self, now: u64) -> usize {
+        let expired: Vec<PublicOfferId> = self
+            .offers
+            .iter()
+            .filter(|(_, entry)| entry.is_expired(now))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired.iter() {
+            self.remove(id);
+        }
+        expired.len()
+    }
+
+    /// Remove duplicated public offers, i.e. the same offer published multiple times by the same
+    /// node, and return the number of removed entries. For each duplicated offer, the entry
+    /// expiring last is kept.
+    pub fn prune_duplicates(&mut self) -> usize {
+        let mut duplicates = vec![];
+        for ids in self.by_offer_id.values() {
+            let mut kept: HashMap<[u8; 33], PublicOfferId> = HashMap::new();
+            for id in ids.iter() {
+                let entry = &self.offers[id];
+                let node_id = entry.public_offer.node_id.serialize();
+                match kept.get_mut(&node_id) {
+                    Some(kept_id) => {
+                        // `None` expires last
+                        let kept_expiry = self.offers[kept_id].expires_at.unwrap_or(u64::MAX);
+                        if entry.expires_at.unwrap_or(u64::MAX) > kept_expiry {
+                            duplicates.push(*kept_id);
+                            *kept_id = *id;
+                        } else {
+                            duplicates.push(*id);
+                        }
+                    }
+                    None => {
+                        kept.insert(node_id, *id);
+                    }
+                }
+            }
+        }
+        for id in duplicates.iter() {
+            self.remove(id);
+        }
+        duplicates.len()
+    }
+
+    /// Remove all expired and duplicated entries and return the number of removed entries.
+    pub fn prune(&mut self, now: u64) -> usize {
+        self.prune_expired(now) + self.prune_duplicates()
+    }
+
+    fn market_of(public_offer: &PublicOffer<Ctx>) -> (Network, SwapRole) {
+        (public_offer.offer.network, public_offer.offer.maker_role)
+    }
+}
+
+impl<Ctx> Encodable for OfferBook<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        if self.offers.len() > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::Other, "Value is too long"));
+        }
+        let mut ids: Vec<&PublicOfferId> = self.offers.keys().collect();
+        ids.sort();
+        let mut len = (ids.len() as u16).consensus_encode(s)?;
+        for id in ids {
+            len += self.offers[id].consensus_encode(s)?;
+        }
+        Ok(len)
+    }
+}
+
+impl<Ctx> Decodable for OfferBook<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let entries: Vec<OfferBookEntry<Ctx>> = Decodable::consensus_decode(d)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+impl_strict_encoding!(OfferBook<Ctx>, Ctx: Swap);
+
+impl<Ctx> std::iter::FromIterator<OfferBookEntry<Ctx>> for OfferBook<Ctx>
+where
+    Ctx: Swap,
+{
+    fn from_iter<I: IntoIterator<Item = OfferBookEntry<Ctx>>>(iter: I) -> Self {
+        let mut book = OfferBook::new();
+        for entry in iter {
+            book.insert(entry.public_offer, entry.expires_at);
+        }
+        book
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Ctx> Serialize for OfferBook<Ctx>
+where
+    Ctx: Swap,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut entries: Vec<&OfferBookEntry<Ctx>> = self.offers.values().collect();
+        entries.sort_by_key(|entry| entry.public_offer.id());
+        serializer.collect_seq(entries)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Ctx> Deserialize<'de> for OfferBook<Ctx>
+where
+    Ctx: Swap,
+{
+    fn deserialize<D>(deserializer: D) -> Result<OfferBook<Ctx>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let entries = Vec::<OfferBookEntry<Ctx>>::deserialize(deserializer)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -709,7 +1842,7 @@ mod tests {
         assert!(pub_offer.is_ok());
 
         let pub_offer = pub_offer.unwrap();
-        assert_eq!(pub_offer.version, Version::new_v1());
+        assert_eq!(pub_offer.version(), &Version::new_v1());
         assert_eq!(pub_offer.offer, OFFER.clone());
         assert_eq!(pub_offer.node_id, *NODE_ID);
         assert_eq!(pub_offer.peer_address, *PEER_ADDRESS);
@@ -735,6 +1868,374 @@ mod tests {
         assert_eq!(&format!("{}", pub_offer), S);
     }
 
+    const URI: &str = "farcaster:Cke4ftrP5A71LQM2fvVdFMNR4gmBqNCsR11111uMM4pF11111112Lvo11111TBALTh113GTvtvqfD1111114A4TUWxWeBc1WxwGBKaUssrb6pnijjhnb6RAs1HBr1CaX7o1a1111111111111111111111111111111111111111115T1WG8uDoExnA3T?label=BTC%2FXMR%20swap%20%E2%9C%93&expiry=1650000000";
+
+    #[test]
+    fn display_offer_uri() {
+        let public_offer = OFFER.clone().to_public_v1(*NODE_ID, *PEER_ADDRESS);
+        let uri = OfferUri::new(public_offer.clone());
+        assert_eq!(&format!("{}", uri), &format!("farcaster:{}", &S[6..]));
+
+        let uri = uri
+            .with_label("BTC/XMR swap \u{2713}")
+            .with_expiry(1650000000);
+        assert_eq!(&format!("{}", uri), URI);
+
+        let uri = OfferUri::new(public_offer).with_expiry(1650000000);
+        assert_eq!(
+            &format!("{}", uri),
+            &format!("farcaster:{}?expiry=1650000000", &S[6..])
+        );
+    }
+
+    #[test]
+    fn parse_offer_uri() {
+        let uri = OfferUri::<BtcXmr>::from_str(URI).expect("Valid offer URI");
+        assert_eq!(uri.public_offer, PublicOffer::from_str(S).unwrap());
+        assert_eq!(uri.label.as_deref(), Some("BTC/XMR swap \u{2713}"));
+        assert_eq!(uri.expiry, Some(1650000000));
+        assert!(!uri.is_expired(1649999999));
+        assert!(uri.is_expired(1650000000));
+        assert_eq!(&uri.to_string(), URI);
+
+        // scheme is case-insensitive, output is always lowercase
+        let upper = URI.replacen("farcaster", "FARCASTER", 1);
+        let uri = OfferUri::<BtcXmr>::from_str(&upper).expect("Valid offer URI");
+        assert_eq!(&uri.to_string(), URI);
+        assert!(OfferUri::<BtcXmr>::from_str(&URI.replacen("farcaster", "FarCaster", 1)).is_ok());
+
+        // no query parameters
+        let uri = OfferUri::<BtcXmr>::from_str(&format!("farcaster:{}", &S[6..])).unwrap();
+        assert_eq!(uri.label, None);
+        assert_eq!(uri.expiry, None);
+        assert!(!uri.is_expired(u64::MAX));
+
+        // unknown optional parameters are ignored
+        let uri = OfferUri::<BtcXmr>::from_str(&format!("farcaster:{}?foo=bar&expiry=42", &S[6..]))
+            .unwrap();
+        assert_eq!(uri.expiry, Some(42));
+        assert_eq!(
+            &uri.to_string(),
+            &format!("farcaster:{}?expiry=42", &S[6..])
+        );
+    }
+
+    #[test]
+    fn parse_offer_uri_fail() {
+        let failing = vec![
+            // wrong or missing scheme
+            format!("bitcoin:{}", &S[6..]),
+            S[6..].to_string(),
+            S.to_string(),
+            // base58 part is case-sensitive
+            format!("farcaster:{}", S[6..].to_lowercase()),
+            // required unknown parameter
+            format!("farcaster:{}?req-foo=bar", &S[6..]),
+            // invalid parameters
+            format!("farcaster:{}?expiry=soon", &S[6..]),
+            format!("farcaster:{}?expiry=1&expiry=2", &S[6..]),
+            format!("farcaster:{}?label=a&label=b", &S[6..]),
+            format!("farcaster:{}?label=%E2%9", &S[6..]),
+            format!("farcaster:{}?label=%ZZ", &S[6..]),
+            format!("farcaster:{}?label=%+1", &S[6..]),
+            format!("farcaster:{}?label=%FF", &S[6..]),
+        ];
+        for uri in failing {
+            assert!(
+                OfferUri::<BtcXmr>::from_str(&uri).is_err(),
+                "{} should fail",
+                uri
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serialize_offer_uri_in_yaml() {
+        let uri = OfferUri::<BtcXmr>::from_str(URI).expect("Valid offer URI");
+        let s = serde_yaml::to_string(&uri).expect("Encode offer URI in yaml");
+        assert_eq!(format!("---\n\"{}\"\n", URI), s);
+        let res: OfferUri<BtcXmr> = serde_yaml::from_str(&s).expect("Decode offer URI");
+        assert_eq!(uri, res);
+    }
+
+    #[test]
+    fn offer_price() {
+        // 10000 piconeros for 1350 satoshis
+        let price = OFFER.price().expect("non-zero arbitrating amount");
+        assert_eq!(price, Price::new(200, 27).unwrap());
+        assert_eq!(price.numerator(), 200);
+        assert_eq!(price.denominator(), 27);
+        assert_eq!(OFFER.inverse_price(), price.inverse());
+        assert_eq!(&format!("{}", price), "200/27");
+
+        // 100 * 200 / 27 = 740.74... rounded down
+        assert_eq!(
+            OFFER.accordant_amount_for(bitcoin::Amount::from_sat(100)),
+            Some(monero::Amount::from_pico(740))
+        );
+        // 1000 * 27 / 200 = 135
+        assert_eq!(
+            OFFER.arbitrating_amount_for(monero::Amount::from_pico(1000)),
+            Some(bitcoin::Amount::from_sat(135))
+        );
+    }
+
+    #[test]
+    fn compare_offers_by_price() {
+        let mut cheaper = OFFER.clone();
+        cheaper.accordant_amount = monero::Amount::from_pico(9999);
+        let mut no_price = OFFER.clone();
+        no_price.arbitrating_amount = bitcoin::Amount::from_sat(0);
+
+        assert_eq!(cheaper.cmp_price(&OFFER), Ordering::Less);
+        assert_eq!(OFFER.cmp_price(&cheaper), Ordering::Greater);
+        assert_eq!(OFFER.cmp_price(&OFFER), Ordering::Equal);
+        assert_eq!(OFFER.cmp_price(&no_price), Ordering::Less);
+        assert!(Price::new(1, 3).unwrap() < Price::new(1, 2).unwrap());
+        assert_eq!(Price::new(2, 4), Price::new(1, 2));
+        assert_eq!(Price::new(1, 0), None);
+        assert_eq!(Price::new(0, 3).unwrap().inverse(), None);
+    }
+
+    #[test]
+    fn validate_offer() {
+        assert!(OFFER.validate().is_ok());
+
+        let mut offer = OFFER.clone();
+        offer.arbitrating_amount = bitcoin::Amount::from_sat(0);
+        assert!(matches!(offer.validate(), Err(Error::ZeroAmount)));
+
+        let mut offer = OFFER.clone();
+        offer.accordant_amount = monero::Amount::from_pico(0);
+        assert!(matches!(offer.validate(), Err(Error::ZeroAmount)));
+
+        let mut offer = OFFER.clone();
+        offer.arbitrating_amount = bitcoin::Amount::from_sat(329);
+        assert!(matches!(offer.validate(), Err(Error::DustAmount)));
+
+        let mut offer = OFFER.clone();
+        offer.punish_timelock = CSVTimelock::new(3);
+        assert!(matches!(
+            offer.validate(),
+            Err(Error::PunishTimelockBeforeCancel)
+        ));
+
+        let mut offer = OFFER.clone();
+        offer.punish_timelock = CSVTimelock::new(0x10000);
+        assert!(matches!(offer.validate(), Err(Error::InvalidTimelock)));
+
+        let mut offer = OFFER.clone();
+        offer.cancel_timelock = CSVTimelock::new(CSVTimelock::disable() | 4);
+        assert!(matches!(offer.validate(), Err(Error::InvalidTimelock)));
+
+        let mut offer = OFFER.clone();
+        offer.fee_strategy = FeeStrategy::Range {
+            min_inc: SatPerVByte::from_sat(2),
+            max_inc: SatPerVByte::from_sat(1),
+        };
+        assert!(matches!(offer.validate(), Err(Error::InvalidFeeRange)));
+    }
+
+    #[test]
+    fn validate_offer_with_policy() {
+        let policy = ValidationPolicy::<BtcXmr> {
+            min_arbitrating_amount: Some(bitcoin::Amount::from_sat(1351)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            OFFER.validate_with(&policy),
+            Err(Error::AmountBelowMinimum)
+        ));
+
+        let policy = ValidationPolicy::<BtcXmr> {
+            min_accordant_amount: Some(monero::Amount::from_pico(10000)),
+            ..Default::default()
+        };
+        assert!(OFFER.validate_with(&policy).is_ok());
+
+        let policy = ValidationPolicy::<BtcXmr> {
+            min_cancel_timelock: Some(CSVTimelock::new(5)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            OFFER.validate_with(&policy),
+            Err(Error::TimelockBelowMinimum)
+        ));
+
+        let policy = ValidationPolicy::<BtcXmr> {
+            min_punish_timelock: Some(CSVTimelock::new(7)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            OFFER.validate_with(&policy),
+            Err(Error::TimelockBelowMinimum)
+        ));
+    }
+
+    #[test]
+    fn parse_public_offer_fail_validation() {
+        let mut offer = OFFER.clone();
+        offer.punish_timelock = CSVTimelock::new(3);
+        let s = offer.to_public_v1(*NODE_ID, *PEER_ADDRESS).to_string();
+        let pub_offer = PublicOffer::<BtcXmr>::from_str(&s);
+        assert!(pub_offer.is_err());
+    }
+
+    fn offer_with(
+        accordant_amount: u64,
+        maker_role: SwapRole,
+        peer_port: &str,
+    ) -> PublicOffer<BtcXmr> {
+        let mut offer = OFFER.clone();
+        offer.accordant_amount = monero::Amount::from_pico(accordant_amount);
+        offer.maker_role = maker_role;
+        let peer_address = InetSocketAddr::new(
+            FromStr::from_str("1.2.3.4").unwrap(),
+            FromStr::from_str(peer_port).unwrap(),
+        );
+        offer.to_public_v1(*NODE_ID, peer_address)
+    }
+
+    #[test]
+    fn offer_book_indexes_offers() {
+        let mut book = OfferBook::<BtcXmr>::new();
+        let cheap = offer_with(9000, SwapRole::Bob, "9735");
+        let expensive = offer_with(11000, SwapRole::Bob, "9735");
+        let buying = offer_with(10000, SwapRole::Alice, "9735");
+
+        assert!(book.insert(expensive.clone(), None));
+        assert!(book.insert(cheap.clone(), Some(10)));
+        assert!(book.insert(buying.clone(), None));
+        assert!(!book.insert(cheap.clone(), Some(20)));
+        assert_eq!(book.len(), 3);
+        assert_eq!(book.get(&cheap.id()).unwrap().expires_at, Some(20));
+        assert_eq!(book.get_by_offer_id(&cheap.offer.id()), vec![&cheap]);
+
+        assert_eq!(
+            book.offers(Network::Testnet, SwapRole::Bob),
+            vec![&cheap, &expensive]
+        );
+        assert_eq!(
+            book.offers(Network::Testnet, SwapRole::Alice),
+            vec![&buying]
+        );
+        assert!(book.offers(Network::Mainnet, SwapRole::Bob).is_empty());
+
+        // offers without a price are ordered last, as with cmp_price
+        let mut free = OFFER.clone();
+        free.arbitrating_amount = bitcoin::Amount::ZERO;
+        let free = free.to_public_v1(*NODE_ID, *PEER_ADDRESS);
+        assert!(book.insert(free.clone(), None));
+        assert_eq!(
+            book.offers(Network::Testnet, SwapRole::Bob),
+            vec![&cheap, &expensive, &free]
+        );
+        assert_eq!(expensive.offer.cmp_price(&free.offer), Ordering::Less);
+        assert!(book.remove(&free.id()).is_some());
+
+        assert!(book.remove(&cheap.id()).is_some());
+        assert!(!book.contains(&cheap.id()));
+        assert!(book.get_by_offer_id(&cheap.offer.id()).is_empty());
+        assert_eq!(
+            book.offers(Network::Testnet, SwapRole::Bob),
+            vec![&expensive]
+        );
+    }
+
+    #[test]
+    fn offer_book_best_offer() {
+        let mut book = OfferBook::<BtcXmr>::new();
+        let amount = OFFER.arbitrating_amount;
+        let sell_cheap = offer_with(9000, SwapRole::Bob, "9735");
+        let sell_expensive = offer_with(11000, SwapRole::Bob, "9735");
+        let buy_cheap = offer_with(9000, SwapRole::Alice, "9735");
+        let buy_expensive = offer_with(11000, SwapRole::Alice, "9735");
+        for public_offer in [&sell_cheap, &sell_expensive, &buy_cheap, &buy_expensive].iter() {
+            book.insert((*public_offer).clone(), None);
+        }
+
+        assert_eq!(
+            book.best_offer(Network::Testnet, SwapRole::Bob, amount),
+            Some(&sell_cheap)
+        );
+        assert_eq!(
+            book.best_offer(Network::Testnet, SwapRole::Alice, amount),
+            Some(&buy_expensive)
+        );
+        assert_eq!(
+            book.best_offer(
+                Network::Testnet,
+                SwapRole::Bob,
+                bitcoin::Amount::from_sat(1)
+            ),
+            None
+        );
+        assert_eq!(
+            book.best_offer(Network::Mainnet, SwapRole::Bob, amount),
+            None
+        );
+    }
+
+    #[test]
+    fn offer_book_prune() {
+        let mut book = OfferBook::<BtcXmr>::new();
+        let expiring = offer_with(9000, SwapRole::Bob, "9735");
+        let duplicate_short = offer_with(10000, SwapRole::Bob, "9736");
+        let duplicate_long = offer_with(10000, SwapRole::Bob, "9737");
+        book.insert(expiring.clone(), Some(100));
+        book.insert(duplicate_short.clone(), Some(150));
+        book.insert(duplicate_long.clone(), None);
+
+        assert_eq!(book.prune_expired(99), 0);
+        assert_eq!(book.prune(100), 2);
+        assert_eq!(book.len(), 1);
+        assert!(book.contains(&duplicate_long.id()));
+    }
+
+    #[test]
+    fn offer_book_encoding() {
+        let mut book = OfferBook::<BtcXmr>::new();
+        book.insert(offer_with(9000, SwapRole::Bob, "9735"), Some(100));
+        book.insert(offer_with(10000, SwapRole::Alice, "9736"), None);
+
+        let bytes = consensus::serialize(&book);
+        let res: OfferBook<BtcXmr> = consensus::deserialize(&bytes).unwrap();
+        assert_eq!(res.len(), 2);
+        for entry in book.iter() {
+            assert_eq!(res.get(&entry.public_offer.id()), Some(entry));
+        }
+        assert_eq!(bytes, consensus::serialize(&res));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn deserialize_price_through_constructor() {
+        let price = Price::new(200, 27).unwrap();
+        let s = serde_yaml::to_string(&price).expect("Encode price in yaml");
+        let res: Price = serde_yaml::from_str(&s).expect("Decode price from yaml");
+        assert_eq!(res, price);
+
+        // unreduced ratios are reduced
+        let res: Price = serde_yaml::from_str("---\nnumerator: 2\ndenominator: 4\n").unwrap();
+        assert_eq!(res, Price::new(1, 2).unwrap());
+        assert_eq!(res.numerator(), 1);
+        // zero denominators are rejected
+        assert!(serde_yaml::from_str::<Price>("---\nnumerator: 1\ndenominator: 0\n").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn offer_book_yaml_round_trip() {
+        let mut book = OfferBook::<BtcXmr>::new();
+        book.insert(offer_with(9000, SwapRole::Bob, "9735"), Some(100));
+        book.insert(offer_with(10000, SwapRole::Alice, "9736"), None);
+
+        let s = serde_yaml::to_string(&book).expect("Encode offer book in yaml");
+        let res: OfferBook<BtcXmr> = serde_yaml::from_str(&s).expect("Decode offer book");
+        assert_eq!(consensus::serialize(&book), consensus::serialize(&res));
+    }
+
     #[test]
     #[cfg(feature = "serde")]
     fn serialize_public_offer_in_yaml() {
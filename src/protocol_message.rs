@@ -1,18 +1,24 @@
 //! Protocol messages exchanged between swap daemons at each step of the swap protocol. These
 //! messages are untrusted and must be validated uppon reception by each swap participant.
 
-use std::io;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
 
-use crate::blockchain::{Address, Onchain};
+use crate::blockchain::{Address, Asset, Onchain};
 use crate::bundle;
-use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::consensus::{self, CanonicalBytes, Decodable, DecodingLimits, Encodable};
 use crate::crypto::{
-    self, Commit, Keys, SharedKeyId, SharedSecretKeys, Signatures, TaggedElement, TaggedElements,
+    self, Commit, CommitmentDomain, CommitmentField, CommitmentNonce, CommitmentNonces, Keys,
+    SaltedCommit, SharedKeyId, SharedSecretKeys, Signatures, TaggedElement, TaggedElements,
 };
+use crate::negotiation::{self, PublicOffer, PublicOfferId};
+use crate::role::SwapRole;
 use crate::swap::{Swap, SwapId};
 use crate::Error;
 
 use lightning_encoding::{strategies::AsStrict, Strategy};
+#[cfg(feature = "serde")]
+use serde_crate::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 fn commit_to_vec<T: Clone + Eq, K: CanonicalBytes, C: Clone + Eq>(
     wallet: &impl Commit<C>,
@@ -52,34 +58,400 @@ fn verify_vec_of_commitments<T: Eq, K: CanonicalBytes, C: Clone + Eq>(
         .map(|_| ())
 }
 
+// Strict mode: each commitment must be revealed exactly once and each revealed element must be
+// committed exactly once.
+fn verify_bijection<T: Eq, A, B>(
+    field: impl Fn(&T) -> CommitmentField,
+    commitments: &[TaggedElement<T, A>],
+    reveals: &[TaggedElement<T, B>],
+) -> Result<(), Error> {
+    fn check<T: Eq, A, B>(
+        field: &impl Fn(&T) -> CommitmentField,
+        elements: &[TaggedElement<T, A>],
+        others: &[TaggedElement<T, B>],
+    ) -> Result<(), crypto::Error> {
+        for (i, element) in elements.iter().enumerate() {
+            if elements[..i]
+                .iter()
+                .any(|other| other.tag() == element.tag())
+            {
+                return Err(crypto::Error::DuplicateCommitmentTag(field(element.tag())));
+            }
+            if !others.iter().any(|other| other.tag() == element.tag()) {
+                return Err(crypto::Error::MissingCommitmentTag(field(element.tag())));
+            }
+        }
+        Ok(())
+    }
+    check(&field, commitments, reveals)?;
+    check(&field, reveals, commitments).map_err(Error::Crypto)
+}
+
+fn salted_commit_to<C: Eq>(
+    wallet: &impl SaltedCommit<C>,
+    seed: &[u8; 32],
+    domain: CommitmentDomain,
+    value: &impl CanonicalBytes,
+) -> C {
+    let nonce = CommitmentNonce::derive(seed, &domain);
+    wallet.commit_to_salted(&domain, &nonce, value.as_canonical_bytes())
+}
+
+fn salted_commit_to_vec<T: Clone + Eq, K: CanonicalBytes, C: Clone + Eq>(
+    wallet: &impl SaltedCommit<C>,
+    seed: &[u8; 32],
+    (swap_id, role): (SwapId, SwapRole),
+    field: impl Fn(&T) -> CommitmentField,
+    keys: &[TaggedElement<T, K>],
+) -> TaggedElements<T, C> {
+    keys.iter()
+        .map(|tagged_key| {
+            let domain = CommitmentDomain::new(swap_id, role, field(tagged_key.tag()));
+            TaggedElement::new(
+                tagged_key.tag().clone(),
+                salted_commit_to(wallet, seed, domain, tagged_key.elem()),
+            )
+        })
+        .collect()
+}
+
+fn derive_nonces(
+    seed: &[u8; 32],
+    (swap_id, role): (SwapId, SwapRole),
+    fields: Vec<CommitmentField>,
+) -> CommitmentNonces {
+    fields
+        .into_iter()
+        .map(|field| {
+            let domain = CommitmentDomain::new(swap_id, role, field);
+            TaggedElement::new(field, CommitmentNonce::derive(seed, &domain))
+        })
+        .collect()
+}
+
+fn salted_validate<C: Eq>(
+    wallet: &impl SaltedCommit<C>,
+    nonces: &[TaggedElement<CommitmentField, CommitmentNonce>],
+    domain: CommitmentDomain,
+    value: &impl CanonicalBytes,
+    commitment: C,
+) -> Result<(), Error> {
+    let nonce = nonces
+        .iter()
+        .find(|tagged_nonce| *tagged_nonce.tag() == domain.field)
+        .ok_or(crypto::Error::InvalidCommitment)?;
+    wallet
+        .validate_salted(
+            &domain,
+            nonce.elem(),
+            value.as_canonical_bytes(),
+            commitment,
+        )
+        .map_err(Error::Crypto)
+}
+
+fn verify_vec_of_salted_commitments<T: Eq, K: CanonicalBytes, C: Clone + Eq>(
+    wallet: &impl SaltedCommit<C>,
+    nonces: &[TaggedElement<CommitmentField, CommitmentNonce>],
+    (swap_id, role): (SwapId, SwapRole),
+    field: impl Fn(&T) -> CommitmentField,
+    keys: Vec<TaggedElement<T, K>>,
+    commitments: &[TaggedElement<T, C>],
+) -> Result<(), Error> {
+    keys.into_iter()
+        .map(|tagged_key| {
+            let commitment = commitments
+                .iter()
+                .find(|tagged_commitment| tagged_commitment.tag() == tagged_key.tag())
+                .ok_or(Error::Crypto(crypto::Error::InvalidCommitment))?;
+            let domain = CommitmentDomain::new(swap_id, role, field(tagged_key.tag()));
+            salted_validate(
+                wallet,
+                nonces,
+                domain,
+                tagged_key.elem(),
+                commitment.elem().clone(),
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|_| ())
+}
+
+// Salted commit/reveal scheme of the Alice and Bob parameters. The single keys are listed with
+// their commitment field, the tagged keys are common to both roles.
+macro_rules! impl_salted_commitments {
+    ($commit:ident, $reveal:ident, $bundle:ident, $role:expr, [$($field:ident: $variant:ident),*]) => {
+        impl<Ctx> $commit<Ctx>
+        where
+            Ctx: Swap,
+        {
+            /// Commit to the bundle with the salted commitment scheme, each element is blinded
+            /// with a nonce derived from `seed` and bound to the swap, the role and the field. The
+            /// seed must be random and used for this swap only, the same seed is used to create the
+            /// reveal message with `from_bundle_salted`. The swap context must use salted
+            /// commitments, see [`Swap::SALTED_COMMITMENTS`].
+            pub fn commit_to_bundle_salted(
+                swap_id: SwapId,
+                wallet: &impl SaltedCommit<Ctx::Commitment>,
+                bundle: &bundle::$bundle<Ctx>,
+                seed: &[u8; 32],
+            ) -> Self {
+                let ctx = (swap_id, $role);
+                Self {
+                    swap_id,
+                    $($field: salted_commit_to(
+                        wallet,
+                        seed,
+                        CommitmentDomain::new(swap_id, $role, CommitmentField::$variant),
+                        &bundle.$field,
+                    ),)*
+                    extra_arbitrating_keys: salted_commit_to_vec(
+                        wallet,
+                        seed,
+                        ctx,
+                        |tag| CommitmentField::ExtraArbitratingKey(*tag),
+                        &bundle.extra_arbitrating_keys,
+                    ),
+                    arbitrating_shared_keys: salted_commit_to_vec(
+                        wallet,
+                        seed,
+                        ctx,
+                        |id| CommitmentField::ArbitratingSharedKey(*id),
+                        &bundle.arbitrating_shared_keys,
+                    ),
+                    extra_accordant_keys: salted_commit_to_vec(
+                        wallet,
+                        seed,
+                        ctx,
+                        |tag| CommitmentField::ExtraAccordantKey(*tag),
+                        &bundle.extra_accordant_keys,
+                    ),
+                    accordant_shared_keys: salted_commit_to_vec(
+                        wallet,
+                        seed,
+                        ctx,
+                        |id| CommitmentField::AccordantSharedKey(*id),
+                        &bundle.accordant_shared_keys,
+                    ),
+                }
+            }
+
+            /// Verify the salted commitments with the revealed values and nonces, the commitments
+            /// must be bound to this swap, to the role and to the field of each value. The
+            /// verification is strict as in [`Self::verify_with_reveal_strict`].
+            pub fn verify_with_salted_reveal(
+                &self,
+                wallet: &impl SaltedCommit<Ctx::Commitment>,
+                reveal: $reveal<Ctx>,
+            ) -> Result<(), Error> {
+                self.verify_bijection_with(&reveal)?;
+                let ctx = (self.swap_id, $role);
+                let nonces = &reveal.nonces;
+                $(salted_validate(
+                    wallet,
+                    nonces,
+                    CommitmentDomain::new(self.swap_id, $role, CommitmentField::$variant),
+                    &reveal.$field,
+                    self.$field.clone(),
+                )?;)*
+                verify_vec_of_salted_commitments(
+                    wallet,
+                    nonces,
+                    ctx,
+                    |tag| CommitmentField::ExtraArbitratingKey(*tag),
+                    reveal.extra_arbitrating_keys,
+                    &self.extra_arbitrating_keys,
+                )?;
+                verify_vec_of_salted_commitments(
+                    wallet,
+                    nonces,
+                    ctx,
+                    |id| CommitmentField::ArbitratingSharedKey(*id),
+                    reveal.arbitrating_shared_keys,
+                    &self.arbitrating_shared_keys,
+                )?;
+                verify_vec_of_salted_commitments(
+                    wallet,
+                    nonces,
+                    ctx,
+                    |tag| CommitmentField::ExtraAccordantKey(*tag),
+                    reveal.extra_accordant_keys,
+                    &self.extra_accordant_keys,
+                )?;
+                verify_vec_of_salted_commitments(
+                    wallet,
+                    nonces,
+                    ctx,
+                    |id| CommitmentField::AccordantSharedKey(*id),
+                    reveal.accordant_shared_keys,
+                    &self.accordant_shared_keys,
+                )
+            }
+        }
+
+        impl<Ctx> $reveal<Ctx>
+        where
+            Ctx: Swap,
+        {
+            /// Create the reveal message opening the commitments created with
+            /// `commit_to_bundle_salted` and the same `seed`. The swap context must use salted
+            /// commitments, otherwise the nonces are not part of the encoded message.
+            pub fn from_bundle_salted(
+                swap_id: SwapId,
+                bundle: bundle::$bundle<Ctx>,
+                seed: &[u8; 32],
+            ) -> Self {
+                let mut fields = vec![$(CommitmentField::$variant),*];
+                fields.extend(
+                    bundle
+                        .extra_arbitrating_keys
+                        .iter()
+                        .map(|key| CommitmentField::ExtraArbitratingKey(*key.tag())),
+                );
+                fields.extend(
+                    bundle
+                        .arbitrating_shared_keys
+                        .iter()
+                        .map(|key| CommitmentField::ArbitratingSharedKey(*key.tag())),
+                );
+                fields.extend(
+                    bundle
+                        .extra_accordant_keys
+                        .iter()
+                        .map(|key| CommitmentField::ExtraAccordantKey(*key.tag())),
+                );
+                fields.extend(
+                    bundle
+                        .accordant_shared_keys
+                        .iter()
+                        .map(|key| CommitmentField::AccordantSharedKey(*key.tag())),
+                );
+                let nonces = derive_nonces(seed, (swap_id, $role), fields);
+                Self {
+                    nonces,
+                    ..Self::from((swap_id, bundle))
+                }
+            }
+        }
+    };
+}
+
+// TakerAmount
+
+/// Sent by the [`TradeRole::Taker`] during the handshake to commit to the arbitrating amount
+/// chosen within the amount range of the [`PublicOffer`]. Both participants then derive the swap
+/// parameters from the public offer filled with this amount.
+///
+/// [`TradeRole::Taker`]: crate::role::TradeRole::Taker
+#[derive(Clone, Debug, Display, Encodable, Decodable)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
+pub struct TakerAmount<Ctx: Swap> {
+    /// The swap identifier related to this message.
+    pub swap_id: SwapId,
+    /// The identifier of the public offer taken.
+    pub public_offer_id: PublicOfferId,
+    /// The arbitrating amount chosen by the taker.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
+    pub arbitrating_amount: <Ctx::Ar as Asset>::AssetUnit,
+}
+
+impl<Ctx> TakerAmount<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Create the message for the public offer and the chosen amount, returns an error if the
+    /// amount is not accepted by the public offer.
+    pub fn new(
+        swap_id: SwapId,
+        public_offer: &PublicOffer<Ctx>,
+        arbitrating_amount: <Ctx::Ar as Asset>::AssetUnit,
+    ) -> Result<Self, Error> {
+        if !public_offer.accepts_amount(&arbitrating_amount) {
+            return Err(negotiation::Error::AmountOutOfRange.into());
+        }
+        Ok(Self {
+            swap_id,
+            public_offer_id: public_offer.id(),
+            arbitrating_amount,
+        })
+    }
+
+    /// Validate the message against the public offer and return the public offer filled with the
+    /// chosen amount, used to derive the swap parameters.
+    pub fn fill(&self, public_offer: &PublicOffer<Ctx>) -> Result<PublicOffer<Ctx>, Error> {
+        if public_offer.id() != self.public_offer_id {
+            return Err(negotiation::Error::PublicOfferMismatch.into());
+        }
+        public_offer
+            .fill(self.arbitrating_amount)
+            .map_err(Error::Negotiation)
+    }
+}
+
+impl<Ctx> Strategy for TakerAmount<Ctx>
+where
+    Ctx: Swap,
+{
+    type Strategy = AsStrict;
+}
+
 // CommitAliceParameters
 
 /// Forces Alice to commit to the result of her cryptographic setup before receiving Bob's setup.
 /// This is done to remove adaptive behavior in the cryptographic parameters.
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct CommitAliceParameters<Ctx: Swap> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Commitment to the buy public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy: Ctx::Commitment,
     /// Commitment to the cancel public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub cancel: Ctx::Commitment,
     /// Commitment to the refund public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund: Ctx::Commitment,
     /// Commitment to the punish public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub punish: Ctx::Commitment,
     /// Commitment to the adaptor public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub adaptor: Ctx::Commitment,
     /// Commitments to the extra arbitrating public keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_arbitrating_keys: Vec<TaggedElement<u16, Ctx::Commitment>>,
     /// Commitments to the arbitrating shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub arbitrating_shared_keys: Vec<TaggedElement<SharedKeyId, Ctx::Commitment>>,
     /// Commitment to the spend public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub spend: Ctx::Commitment,
     /// Commitments to the extra accordant public keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_accordant_keys: Vec<TaggedElement<u16, Ctx::Commitment>>,
     /// Commitments to the accordant shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub accordant_shared_keys: Vec<TaggedElement<SharedKeyId, Ctx::Commitment>>,
 }
 
@@ -139,50 +511,43 @@ where
             &self.accordant_shared_keys,
         )
     }
-}
 
-impl<Ctx> Encodable for CommitAliceParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.swap_id.consensus_encode(s)?;
-        len += self.buy.as_canonical_bytes().consensus_encode(s)?;
-        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
-        len += self.refund.as_canonical_bytes().consensus_encode(s)?;
-        len += self.punish.as_canonical_bytes().consensus_encode(s)?;
-        len += self.adaptor.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_arbitrating_keys.consensus_encode(s)?;
-        len += self.arbitrating_shared_keys.consensus_encode(s)?;
-        len += self.spend.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_accordant_keys.consensus_encode(s)?;
-        Ok(len + self.accordant_shared_keys.consensus_encode(s)?)
+    /// Verify the commitments with the revealed values in strict mode, the tagged commitments and
+    /// the tagged revealed values must be in bijection: a committed value not revealed, a revealed
+    /// value not committed or a duplicated tag is an error naming the tag.
+    pub fn verify_with_reveal_strict(
+        &self,
+        wallet: &impl Commit<Ctx::Commitment>,
+        reveal: RevealAliceParameters<Ctx>,
+    ) -> Result<(), Error> {
+        self.verify_bijection_with(&reveal)?;
+        self.verify_with_reveal(wallet, reveal)
     }
-}
 
-impl<Ctx> Decodable for CommitAliceParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            swap_id: Decodable::consensus_decode(d)?,
-            buy: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            cancel: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            refund: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            punish: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            adaptor: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            extra_arbitrating_keys: Decodable::consensus_decode(d)?,
-            arbitrating_shared_keys: Decodable::consensus_decode(d)?,
-            spend: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            extra_accordant_keys: Decodable::consensus_decode(d)?,
-            accordant_shared_keys: Decodable::consensus_decode(d)?,
-        })
+    fn verify_bijection_with(&self, reveal: &RevealAliceParameters<Ctx>) -> Result<(), Error> {
+        verify_bijection(
+            |tag| CommitmentField::ExtraArbitratingKey(*tag),
+            &self.extra_arbitrating_keys,
+            &reveal.extra_arbitrating_keys,
+        )?;
+        verify_bijection(
+            |id| CommitmentField::ArbitratingSharedKey(*id),
+            &self.arbitrating_shared_keys,
+            &reveal.arbitrating_shared_keys,
+        )?;
+        verify_bijection(
+            |tag| CommitmentField::ExtraAccordantKey(*tag),
+            &self.extra_accordant_keys,
+            &reveal.extra_accordant_keys,
+        )?;
+        verify_bijection(
+            |id| CommitmentField::AccordantSharedKey(*id),
+            &self.accordant_shared_keys,
+            &reveal.accordant_shared_keys,
+        )
     }
 }
 
-impl_strict_encoding!(CommitAliceParameters<Ctx>, Ctx: Swap);
-
 impl<Ctx> Strategy for CommitAliceParameters<Ctx>
 where
     Ctx: Swap,
@@ -194,28 +559,48 @@ where
 
 /// Forces Bob to commit to the result of his cryptographic setup before receiving Alice's setup.
 /// This is done to remove adaptive behavior in the cryptographic parameters.
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct CommitBobParameters<Ctx: Swap> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Commitment to the buy public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy: Ctx::Commitment,
     /// Commitment to the cancel public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub cancel: Ctx::Commitment,
     /// Commitment to the refund public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund: Ctx::Commitment,
     /// Commitment to the adaptor public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub adaptor: Ctx::Commitment,
     /// Commitments to the extra arbitrating public keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_arbitrating_keys: Vec<TaggedElement<u16, Ctx::Commitment>>,
     /// Commitments to the arbitrating shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub arbitrating_shared_keys: Vec<TaggedElement<SharedKeyId, Ctx::Commitment>>,
     /// Commitment to the spend public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub spend: Ctx::Commitment,
     /// Commitments to the extra accordant public keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_accordant_keys: Vec<TaggedElement<u16, Ctx::Commitment>>,
     /// Commitments to the accordant shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub accordant_shared_keys: Vec<TaggedElement<SharedKeyId, Ctx::Commitment>>,
 }
 
@@ -273,48 +658,43 @@ where
             &self.accordant_shared_keys,
         )
     }
-}
 
-impl<Ctx> Encodable for CommitBobParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.swap_id.consensus_encode(s)?;
-        len += self.buy.as_canonical_bytes().consensus_encode(s)?;
-        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
-        len += self.refund.as_canonical_bytes().consensus_encode(s)?;
-        len += self.adaptor.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_arbitrating_keys.consensus_encode(s)?;
-        len += self.arbitrating_shared_keys.consensus_encode(s)?;
-        len += self.spend.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_accordant_keys.consensus_encode(s)?;
-        Ok(len + self.accordant_shared_keys.consensus_encode(s)?)
+    /// Verify the commitments with the revealed values in strict mode, the tagged commitments and
+    /// the tagged revealed values must be in bijection: a committed value not revealed, a revealed
+    /// value not committed or a duplicated tag is an error naming the tag.
+    pub fn verify_with_reveal_strict(
+        &self,
+        wallet: &impl Commit<Ctx::Commitment>,
+        reveal: RevealBobParameters<Ctx>,
+    ) -> Result<(), Error> {
+        self.verify_bijection_with(&reveal)?;
+        self.verify_with_reveal(wallet, reveal)
     }
-}
 
-impl<Ctx> Decodable for CommitBobParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            swap_id: Decodable::consensus_decode(d)?,
-            buy: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            cancel: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            refund: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            adaptor: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            extra_arbitrating_keys: Decodable::consensus_decode(d)?,
-            arbitrating_shared_keys: Decodable::consensus_decode(d)?,
-            spend: Ctx::Commitment::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            extra_accordant_keys: Decodable::consensus_decode(d)?,
-            accordant_shared_keys: Decodable::consensus_decode(d)?,
-        })
+    fn verify_bijection_with(&self, reveal: &RevealBobParameters<Ctx>) -> Result<(), Error> {
+        verify_bijection(
+            |tag| CommitmentField::ExtraArbitratingKey(*tag),
+            &self.extra_arbitrating_keys,
+            &reveal.extra_arbitrating_keys,
+        )?;
+        verify_bijection(
+            |id| CommitmentField::ArbitratingSharedKey(*id),
+            &self.arbitrating_shared_keys,
+            &reveal.arbitrating_shared_keys,
+        )?;
+        verify_bijection(
+            |tag| CommitmentField::ExtraAccordantKey(*tag),
+            &self.extra_accordant_keys,
+            &reveal.extra_accordant_keys,
+        )?;
+        verify_bijection(
+            |id| CommitmentField::AccordantSharedKey(*id),
+            &self.accordant_shared_keys,
+            &reveal.accordant_shared_keys,
+        )
     }
 }
 
-impl_strict_encoding!(CommitBobParameters<Ctx>, Ctx: Swap);
-
 impl<Ctx> Strategy for CommitBobParameters<Ctx>
 where
     Ctx: Swap,
@@ -325,39 +705,23 @@ where
 // RevealProof
 
 /// Reveals the proof.
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct RevealProof<Ctx: Swap> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Reveal the cross-group discrete logarithm zero-knowledge proof.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub proof: Ctx::Proof,
 }
 
-impl<Ctx> Encodable for RevealProof<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let len = self.swap_id.consensus_encode(s)?;
-        Ok(len + self.proof.as_canonical_bytes().consensus_encode(s)?)
-    }
-}
-
-impl<Ctx> Decodable for RevealProof<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            swap_id: Decodable::consensus_decode(d)?,
-            proof: Ctx::Proof::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-        })
-    }
-}
-
-impl_strict_encoding!(RevealProof<Ctx>, Ctx: Swap);
-
 impl<Ctx> Strategy for RevealProof<Ctx>
 where
     Ctx: Swap,
@@ -380,92 +744,73 @@ where
 // RevealAliceParameters
 
 /// Reveals the parameters commited by the [`CommitAliceParameters`] protocol message.
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct RevealAliceParameters<Ctx: Swap> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Reveal the buy public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy: <Ctx::Ar as Keys>::PublicKey,
     /// Reveal the cancel public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub cancel: <Ctx::Ar as Keys>::PublicKey,
     /// Reveal the refund public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund: <Ctx::Ar as Keys>::PublicKey,
     /// Reveal the punish public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub punish: <Ctx::Ar as Keys>::PublicKey,
     /// Reveal the adaptor public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub adaptor: <Ctx::Ar as Keys>::PublicKey,
     /// Reveal the vector of extra arbitrating public keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_arbitrating_keys: Vec<TaggedElement<u16, <Ctx::Ar as Keys>::PublicKey>>,
     /// Reveal the vector of extra arbitrating shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub arbitrating_shared_keys:
         Vec<TaggedElement<SharedKeyId, <Ctx::Ar as SharedSecretKeys>::SharedSecretKey>>,
     /// Reveal the spend public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub spend: <Ctx::Ac as Keys>::PublicKey,
     /// Reveal the vector of extra accordant public keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_accordant_keys: Vec<TaggedElement<u16, <Ctx::Ac as Keys>::PublicKey>>,
     /// Reveal the vector of extra accordant shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub accordant_shared_keys:
         Vec<TaggedElement<SharedKeyId, <Ctx::Ac as SharedSecretKeys>::SharedSecretKey>>,
     /// Reveal the destination address.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub address: <Ctx::Ar as Address>::Address,
+    /// The nonces opening the salted commitments, only encoded when the swap context uses
+    /// salted commitments, see [`Swap::SALTED_COMMITMENTS`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Vec::is_empty",
+            with = "crate::consensus::serde_hex::tagged"
+        )
+    )]
+    #[consensus(when = "Ctx::SALTED_COMMITMENTS")]
+    pub nonces: CommitmentNonces,
 }
 
-impl<Ctx> Encodable for RevealAliceParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.swap_id.consensus_encode(s)?;
-        len += self.buy.as_canonical_bytes().consensus_encode(s)?;
-        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
-        len += self.refund.as_canonical_bytes().consensus_encode(s)?;
-        len += self.punish.as_canonical_bytes().consensus_encode(s)?;
-        len += self.adaptor.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_arbitrating_keys.consensus_encode(s)?;
-        len += self.arbitrating_shared_keys.consensus_encode(s)?;
-        // this can go?
-        len += self.spend.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_accordant_keys.consensus_encode(s)?;
-        len += self.accordant_shared_keys.consensus_encode(s)?;
-        Ok(len + self.address.as_canonical_bytes().consensus_encode(s)?)
-    }
-}
-
-impl<Ctx> Decodable for RevealAliceParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            swap_id: Decodable::consensus_decode(d)?,
-            buy: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            cancel: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            refund: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            punish: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            adaptor: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            extra_arbitrating_keys: Decodable::consensus_decode(d)?,
-            arbitrating_shared_keys: Decodable::consensus_decode(d)?,
-            spend: <Ctx::Ac as Keys>::PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            extra_accordant_keys: Decodable::consensus_decode(d)?,
-            accordant_shared_keys: Decodable::consensus_decode(d)?,
-            address: <Ctx::Ar as Address>::Address::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-        })
-    }
-}
-
-impl_strict_encoding!(RevealAliceParameters<Ctx>, Ctx: Swap);
-
 impl<Ctx> Strategy for RevealAliceParameters<Ctx>
 where
     Ctx: Swap,
@@ -491,90 +836,94 @@ where
             extra_accordant_keys: bundle.1.extra_accordant_keys,
             accordant_shared_keys: bundle.1.accordant_shared_keys,
             address: bundle.1.destination_address,
+            nonces: vec![],
         }
     }
 }
 
+impl<Ctx> RevealAliceParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Validate the revealed parameters against the agreed public offer, see
+    /// [`bundle::AliceParameters::validate_against`].
+    pub fn validate_against(&self, public_offer: &PublicOffer<Ctx>) -> Result<(), bundle::Error> {
+        bundle::AliceParameters::from(self.clone()).validate_against(public_offer)
+    }
+}
+
+impl_salted_commitments!(
+    CommitAliceParameters,
+    RevealAliceParameters,
+    AliceParameters,
+    SwapRole::Alice,
+    [buy: Buy, cancel: Cancel, refund: Refund, punish: Punish, adaptor: Adaptor, spend: Spend]
+);
+
 /// Reveals the parameters commited by the [`CommitBobParameters`] protocol message.
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct RevealBobParameters<Ctx: Swap> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// Reveal the buy public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy: <Ctx::Ar as Keys>::PublicKey,
     /// Reveal the cancel public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub cancel: <Ctx::Ar as Keys>::PublicKey,
     /// Reveal the refund public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund: <Ctx::Ar as Keys>::PublicKey,
     /// Reveal the adaptor public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub adaptor: <Ctx::Ar as Keys>::PublicKey,
     /// Reveal the vector of extra arbitrating public keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_arbitrating_keys: Vec<TaggedElement<u16, <Ctx::Ar as Keys>::PublicKey>>,
     /// Reveal the vector of extra arbitrating shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub arbitrating_shared_keys:
         Vec<TaggedElement<SharedKeyId, <Ctx::Ar as SharedSecretKeys>::SharedSecretKey>>,
     /// Reveal the spend public key.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub spend: <Ctx::Ac as Keys>::PublicKey,
     /// Reveal the vector of extra accordant public keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub extra_accordant_keys: Vec<TaggedElement<u16, <Ctx::Ac as Keys>::PublicKey>>,
     /// Reveal the vector of extra accordant shared keys.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex::tagged"))]
     pub accordant_shared_keys:
         Vec<TaggedElement<SharedKeyId, <Ctx::Ac as SharedSecretKeys>::SharedSecretKey>>,
     /// The refund Bitcoin address.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub address: <Ctx::Ar as Address>::Address,
+    /// The nonces opening the salted commitments, only encoded when the swap context uses
+    /// salted commitments, see [`Swap::SALTED_COMMITMENTS`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            default,
+            skip_serializing_if = "Vec::is_empty",
+            with = "crate::consensus::serde_hex::tagged"
+        )
+    )]
+    #[consensus(when = "Ctx::SALTED_COMMITMENTS")]
+    pub nonces: CommitmentNonces,
 }
 
-impl<Ctx> Encodable for RevealBobParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.swap_id.consensus_encode(s)?;
-        len += self.buy.as_canonical_bytes().consensus_encode(s)?;
-        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
-        len += self.refund.as_canonical_bytes().consensus_encode(s)?;
-        len += self.adaptor.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_arbitrating_keys.consensus_encode(s)?;
-        len += self.arbitrating_shared_keys.consensus_encode(s)?;
-        len += self.spend.as_canonical_bytes().consensus_encode(s)?;
-        len += self.extra_accordant_keys.consensus_encode(s)?;
-        len += self.accordant_shared_keys.consensus_encode(s)?;
-        Ok(len + self.address.as_canonical_bytes().consensus_encode(s)?)
-    }
-}
-
-impl<Ctx> Decodable for RevealBobParameters<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            swap_id: Decodable::consensus_decode(d)?,
-            buy: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            cancel: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            refund: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            adaptor: <Ctx::Ar as Keys>::PublicKey::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            extra_arbitrating_keys: Decodable::consensus_decode(d)?,
-            arbitrating_shared_keys: Decodable::consensus_decode(d)?,
-            spend: <Ctx::Ac as Keys>::PublicKey::from_canonical_bytes(unwrap_vec_ref!(d).as_ref())?,
-            extra_accordant_keys: Decodable::consensus_decode(d)?,
-            accordant_shared_keys: Decodable::consensus_decode(d)?,
-            address: <Ctx::Ar as Address>::Address::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-        })
-    }
-}
-
-impl_strict_encoding!(RevealBobParameters<Ctx>, Ctx: Swap);
-
 impl<Ctx> Strategy for RevealBobParameters<Ctx>
 where
     Ctx: Swap,
@@ -599,10 +948,30 @@ where
             extra_accordant_keys: bundle.1.extra_accordant_keys,
             accordant_shared_keys: bundle.1.accordant_shared_keys,
             address: bundle.1.refund_address,
+            nonces: vec![],
         }
     }
 }
 
+impl<Ctx> RevealBobParameters<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Validate the revealed parameters against the agreed public offer, see
+    /// [`bundle::BobParameters::validate_against`].
+    pub fn validate_against(&self, public_offer: &PublicOffer<Ctx>) -> Result<(), bundle::Error> {
+        bundle::BobParameters::from(self.clone()).validate_against(public_offer)
+    }
+}
+
+impl_salted_commitments!(
+    CommitBobParameters,
+    RevealBobParameters,
+    BobParameters,
+    SwapRole::Bob,
+    [buy: Buy, cancel: Cancel, refund: Refund, adaptor: Adaptor, spend: Spend]
+);
+
 /// Sends the [`Lockable`], [`Cancelable`] and [`Refundable`] arbritrating transactions from
 /// [`SwapRole::Bob`] to [`SwapRole::Alice`], as well as Bob's signature for the [`Cancelable`]
 /// transaction.
@@ -612,59 +981,35 @@ where
 /// [`Lockable`]: crate::transaction::Lockable
 /// [`Cancelable`]: crate::transaction::Cancelable
 /// [`Refundable`]: crate::transaction::Refundable
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct CoreArbitratingSetup<Ctx: Swap> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// The arbitrating `lock (b)` transaction.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_base64"))]
+    #[canonical]
     pub lock: <Ctx::Ar as Onchain>::PartialTransaction,
     /// The arbitrating `cancel (d)` transaction.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_base64"))]
+    #[canonical]
     pub cancel: <Ctx::Ar as Onchain>::PartialTransaction,
     /// The arbitrating `refund (e)` transaction.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_base64"))]
+    #[canonical]
     pub refund: <Ctx::Ar as Onchain>::PartialTransaction,
     /// The `Bc` `cancel (d)` signature.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub cancel_sig: <Ctx::Ar as Signatures>::Signature,
 }
 
-impl<Ctx> Encodable for CoreArbitratingSetup<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.swap_id.consensus_encode(s)?;
-        len += self.lock.as_canonical_bytes().consensus_encode(s)?;
-        len += self.cancel.as_canonical_bytes().consensus_encode(s)?;
-        len += self.refund.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len + self.cancel_sig.as_canonical_bytes().consensus_encode(s)?)
-    }
-}
-
-impl<Ctx> Decodable for CoreArbitratingSetup<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            swap_id: Decodable::consensus_decode(d)?,
-            lock: <Ctx::Ar as Onchain>::PartialTransaction::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            cancel: <Ctx::Ar as Onchain>::PartialTransaction::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            refund: <Ctx::Ar as Onchain>::PartialTransaction::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            cancel_sig: <Ctx::Ar as Signatures>::Signature::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-        })
-    }
-}
-
-impl_strict_encoding!(CoreArbitratingSetup<Ctx>, Ctx: Swap);
-
 impl<Ctx> Strategy for CoreArbitratingSetup<Ctx>
 where
     Ctx: Swap,
@@ -706,51 +1051,27 @@ where
 /// [`SwapRole::Bob`]: crate::role::SwapRole::Bob
 /// [`Cancelable`]: crate::transaction::Cancelable
 /// [`Refundable`]: crate::transaction::Refundable
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct RefundProcedureSignatures<Ctx: Swap> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// The `Ac` `cancel (d)` signature.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub cancel_sig: <Ctx::Ar as Signatures>::Signature,
     /// The `Ar(Tb)` `refund (e)` adaptor signature.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub refund_adaptor_sig: <Ctx::Ar as Signatures>::EncryptedSignature,
 }
 
-impl<Ctx> Encodable for RefundProcedureSignatures<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.swap_id.consensus_encode(s)?;
-        len += self.cancel_sig.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len
-            + self
-                .refund_adaptor_sig
-                .as_canonical_bytes()
-                .consensus_encode(s)?)
-    }
-}
-
-impl<Ctx> Decodable for RefundProcedureSignatures<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            swap_id: Decodable::consensus_decode(d)?,
-            cancel_sig: <Ctx::Ar as Signatures>::Signature::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            refund_adaptor_sig: <Ctx::Ar as Signatures>::EncryptedSignature::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-        })
-    }
-}
-
-impl_strict_encoding!(RefundProcedureSignatures<Ctx>, Ctx: Swap);
-
 impl<Ctx> Strategy for RefundProcedureSignatures<Ctx>
 where
     Ctx: Swap,
@@ -788,51 +1109,27 @@ where
 ///
 /// [`SwapRole::Bob`]: crate::role::SwapRole::Bob
 /// [`Buyable`]: crate::transaction::Buyable
-#[derive(Clone, Debug, Display)]
+#[derive(Clone, Debug, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+#[consensus(strict_encoding)]
 pub struct BuyProcedureSignature<Ctx: Swap> {
     /// The swap identifier related to this message.
     pub swap_id: SwapId,
     /// The arbitrating `buy (c)` transaction.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_base64"))]
+    #[canonical]
     pub buy: <Ctx::Ar as Onchain>::PartialTransaction,
     /// The `Bb(Ta)` `buy (c)` adaptor signature.
+    #[cfg_attr(feature = "serde", serde(with = "crate::consensus::serde_hex"))]
+    #[canonical]
     pub buy_adaptor_sig: <Ctx::Ar as Signatures>::EncryptedSignature,
 }
 
-impl<Ctx> Encodable for BuyProcedureSignature<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.swap_id.consensus_encode(s)?;
-        len += self.buy.as_canonical_bytes().consensus_encode(s)?;
-        Ok(len
-            + self
-                .buy_adaptor_sig
-                .as_canonical_bytes()
-                .consensus_encode(s)?)
-    }
-}
-
-impl<Ctx> Decodable for BuyProcedureSignature<Ctx>
-where
-    Ctx: Swap,
-{
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            swap_id: Decodable::consensus_decode(d)?,
-            buy: <Ctx::Ar as Onchain>::PartialTransaction::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-            buy_adaptor_sig: <Ctx::Ar as Signatures>::EncryptedSignature::from_canonical_bytes(
-                unwrap_vec_ref!(d).as_ref(),
-            )?,
-        })
-    }
-}
-
-impl_strict_encoding!(BuyProcedureSignature<Ctx>, Ctx: Swap);
-
 impl<Ctx> Strategy for BuyProcedureSignature<Ctx>
 where
     Ctx: Swap,
@@ -853,31 +1150,256 @@ where
     }
 }
 
-/// Optional courtesy message from either [`SwapRole`] to inform the counterparty
-/// that they have aborted the swap with an `OPTIONAL` message body to provide the reason.
+/// Registry of the reasons of an abort with their numeric codes, shared by [`Abort`] and
+/// [`instruction::Abort`]. Codes are grouped by hundreds: general reasons, negotiation, swap
+/// parameters, signatures and transactions, and local failures. Codes not registered in this
+/// version are kept as [`AbortCode::Unknown`].
 ///
-/// [`SwapRole`]: crate::role::SwapRole
-#[derive(Clone, Debug, Display)]
+/// [`instruction::Abort`]: crate::instruction::Abort
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
 #[display(Debug)]
-pub struct Abort {
-    /// The swap identifier related to this message.
-    pub swap_id: SwapId,
-    /// OPTIONAL `body`: error string.
-    pub error_body: Option<String>,
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub enum AbortCode {
+    /// No reason given, with code `0`.
+    Unspecified,
+    /// The user cancelled the swap, with code `1`.
+    UserCancelled,
+    /// The counterparty did not respond in time, with code `2`.
+    Timeout,
+    /// The counterparty sent an unexpected message or broke the protocol, with code `3`.
+    ProtocolViolation,
+    /// A message cannot be decoded, with code `100`.
+    InvalidMessage,
+    /// No protocol or public offer version is supported by both participants, with code `101`.
+    UnsupportedVersion,
+    /// A required feature is not supported, with code `102`.
+    UnsupportedFeature,
+    /// The public offer is invalid or does not match the one agreed upon, with code `103`.
+    InvalidOffer,
+    /// An amount is invalid or out of the offer range, with code `104`.
+    InvalidAmount,
+    /// A timelock is invalid or does not match the public offer, with code `105`.
+    InvalidTimelock,
+    /// A fee is out of the range allowed by the fee strategy, with code `106`.
+    FeeOutOfRange,
+    /// The parameters of the counterparty are invalid, with code `200`.
+    InvalidParameters,
+    /// A commitment does not match its revealed value, with code `201`.
+    InvalidCommitment,
+    /// The cross-group discrete logarithm proof is invalid, with code `202`.
+    InvalidDleqProof,
+    /// A signature is invalid, with code `300`.
+    InvalidSignature,
+    /// An adaptor signature is invalid, with code `301`.
+    InvalidAdaptorSignature,
+    /// A transaction is invalid or does not match the expected template, with code `302`.
+    InvalidTransaction,
+    /// A local failure not caused by the counterparty, with code `900`.
+    InternalError,
+    /// A code not registered in this version.
+    Unknown(u16),
 }
 
-impl Encodable for Abort {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let len = self.swap_id.consensus_encode(s)?;
+impl AbortCode {
+    /// Return the numeric code.
+    pub fn code(&self) -> u16 {
+        match self {
+            Self::Unspecified => 0,
+            Self::UserCancelled => 1,
+            Self::Timeout => 2,
+            Self::ProtocolViolation => 3,
+            Self::InvalidMessage => 100,
+            Self::UnsupportedVersion => 101,
+            Self::UnsupportedFeature => 102,
+            Self::InvalidOffer => 103,
+            Self::InvalidAmount => 104,
+            Self::InvalidTimelock => 105,
+            Self::FeeOutOfRange => 106,
+            Self::InvalidParameters => 200,
+            Self::InvalidCommitment => 201,
+            Self::InvalidDleqProof => 202,
+            Self::InvalidSignature => 300,
+            Self::InvalidAdaptorSignature => 301,
+            Self::InvalidTransaction => 302,
+            Self::InternalError => 900,
+            Self::Unknown(code) => *code,
+        }
+    }
+
+    /// Return the abort code registered for the numeric code, [`AbortCode::Unknown`] otherwise.
+    pub fn from_code(code: u16) -> Self {
+        match code {
+            0 => Self::Unspecified,
+            1 => Self::UserCancelled,
+            2 => Self::Timeout,
+            3 => Self::ProtocolViolation,
+            100 => Self::InvalidMessage,
+            101 => Self::UnsupportedVersion,
+            102 => Self::UnsupportedFeature,
+            103 => Self::InvalidOffer,
+            104 => Self::InvalidAmount,
+            105 => Self::InvalidTimelock,
+            106 => Self::FeeOutOfRange,
+            200 => Self::InvalidParameters,
+            201 => Self::InvalidCommitment,
+            202 => Self::InvalidDleqProof,
+            300 => Self::InvalidSignature,
+            301 => Self::InvalidAdaptorSignature,
+            302 => Self::InvalidTransaction,
+            900 => Self::InternalError,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl From<&Error> for AbortCode {
+    fn from(error: &Error) -> Self {
+        use crate::blockchain::FeeStrategyError;
+
+        match error {
+            Error::Consensus(_) => Self::InvalidMessage,
+            Error::Crypto(e) => match e {
+                crypto::Error::InvalidSignature => Self::InvalidSignature,
+                crypto::Error::InvalidEncryptedSignature => Self::InvalidAdaptorSignature,
+                crypto::Error::InvalidCommitment
+                | crypto::Error::MissingCommitmentTag(_)
+                | crypto::Error::DuplicateCommitmentTag(_) => Self::InvalidCommitment,
+                crypto::Error::InvalidProof
+                | crypto::Error::InvalidPedersenCommitment
+                | crypto::Error::InvalidRingSignature
+                | crypto::Error::InvalidProofOfKnowledge
+                | crypto::Error::InvalidProofSize { .. } => Self::InvalidDleqProof,
+                crypto::Error::InvalidAdaptorKey | crypto::Error::InvalidPoint => {
+                    Self::InvalidParameters
+                }
+                _ => Self::InternalError,
+            },
+            Error::FeeStrategy(e) => match e {
+                FeeStrategyError::AmountOfFeeTooLow | FeeStrategyError::AmountOfFeeTooHigh => {
+                    Self::FeeOutOfRange
+                }
+                _ => Self::InvalidTransaction,
+            },
+            Error::Transaction(_) => Self::InvalidTransaction,
+            Error::Negotiation(e) => match e {
+                negotiation::Error::UnsupportedVersion
+                | negotiation::Error::NoCommonProtocolVersion => Self::UnsupportedVersion,
+                negotiation::Error::UnsupportedFeature(_) => Self::UnsupportedFeature,
+                negotiation::Error::InvalidAmountRange
+                | negotiation::Error::AmountOutOfRange
+                | negotiation::Error::UnfilledAmountRange
+                | negotiation::Error::InvalidPrice
+                | negotiation::Error::ZeroAmount
+                | negotiation::Error::DustAmount
+                | negotiation::Error::AmountBelowMinimum => Self::InvalidAmount,
+                negotiation::Error::InvalidTimelock
+                | negotiation::Error::PunishTimelockBeforeCancel
+                | negotiation::Error::TimelockBelowMinimum => Self::InvalidTimelock,
+                negotiation::Error::InvalidFeeRange => Self::FeeOutOfRange,
+                negotiation::Error::SwapIdMismatch => Self::ProtocolViolation,
+                negotiation::Error::InvalidSignature
+                | negotiation::Error::PublicOfferMismatch
+                | negotiation::Error::IncompleteOffer => Self::InvalidOffer,
+            },
+            Error::Bundle(e) => match e {
+                bundle::Error::CancelTimelockMismatch | bundle::Error::PunishTimelockMismatch => {
+                    Self::InvalidTimelock
+                }
+                bundle::Error::FeeStrategyMismatch => Self::FeeOutOfRange,
+                bundle::Error::InvalidAddressNetwork(_)
+                | bundle::Error::InvalidPublicKey(_)
+                | bundle::Error::DuplicatePublicKey(..)
+                | bundle::Error::CounterpartyPublicKey(..) => Self::InvalidParameters,
+            },
+            Error::Syncer(_) => Self::InternalError,
+        }
+    }
+}
+
+impl From<Error> for AbortCode {
+    fn from(error: Error) -> Self {
+        Self::from(&error)
+    }
+}
+
+impl Encodable for AbortCode {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        self.code().consensus_encode(s)
+    }
+}
+
+impl Decodable for AbortCode {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        Ok(Self::from_code(Decodable::consensus_decode(d)?))
+    }
+}
+
+impl_strict_encoding!(AbortCode);
+
+/// Optional courtesy message from either [`SwapRole`] to inform the counterparty that they have
+/// aborted the swap, with the [`AbortCode`] of the reason and an `OPTIONAL` message body to
+/// provide details.
+///
+/// The abort is encoded as the swap identifier and the body, as by previous versions, followed
+/// by the code unless unspecified. The trailing code is optional, an abort without it has the
+/// [`AbortCode::Unspecified`] code, so an abort must be decoded from its own buffer.
+///
+/// In a [`MessageFrame`] the payload stops after the body and the code is carried by the odd
+/// [`ABORT_CODE_EXTENSION_TYPE`] extension, peers not knowing the codes ignore it.
+///
+/// [`SwapRole`]: crate::role::SwapRole
+#[derive(Clone, Debug, Display)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct Abort {
+    /// The swap identifier related to this message.
+    pub swap_id: SwapId,
+    /// The reason of the abort.
+    pub abort_code: AbortCode,
+    /// OPTIONAL `body`: error string.
+    pub error_body: Option<String>,
+}
+
+impl Abort {
+    // Encode the swap identifier and the body, the abort payload of previous versions.
+    fn encode_without_code<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.swap_id.consensus_encode(s)?;
         Ok(len + self.error_body.consensus_encode(s)?)
     }
 }
 
+impl Encodable for Abort {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.encode_without_code(s)?;
+        match self.abort_code {
+            AbortCode::Unspecified => Ok(len),
+            code => Ok(len + code.consensus_encode(s)?),
+        }
+    }
+}
+
 impl Decodable for Abort {
     fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let swap_id = Decodable::consensus_decode(d)?;
+        let error_body = Decodable::consensus_decode(d)?;
+        // the code is absent from the aborts of previous versions
+        let mut first = [0u8; 1];
+        let abort_code = match d.read(&mut first)? {
+            0 => AbortCode::Unspecified,
+            _ => AbortCode::consensus_decode(&mut (&first[..]).chain(d))?,
+        };
         Ok(Self {
-            swap_id: Decodable::consensus_decode(d)?,
-            error_body: Option::<String>::consensus_decode(d)?,
+            swap_id,
+            abort_code,
+            error_body,
         })
     }
 }
@@ -887,3 +1409,884 @@ impl_strict_encoding!(Abort);
 impl Strategy for Abort {
     type Strategy = AsStrict;
 }
+
+impl From<(SwapId, Error)> for Abort {
+    /// Abort the swap because of the error. The error is described in the message body, unless
+    /// it is a local failure not caused by the counterparty.
+    fn from(abort: (SwapId, Error)) -> Self {
+        let abort_code = AbortCode::from(&abort.1);
+        Self {
+            swap_id: abort.0,
+            abort_code,
+            error_body: match abort_code {
+                AbortCode::InternalError => None,
+                _ => Some(abort.1.to_string()),
+            },
+        }
+    }
+}
+
+// Protocol versions and capabilities
+
+/// Protocol versions supported by this implementation, in increasing order.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[u16] = &[1];
+
+/// Optional protocol features negotiated with a [`Handshake`]. Each feature is identified by a
+/// pair of bits in [`Features`]: the even bit when the feature is required, the odd bit when the
+/// feature is optional.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[display(Debug)]
+pub enum Feature {
+    /// Compact encoding of the cross-group discrete logarithm proof, see
+    /// [`DLEQProofFormat::Compact`], with feature bits `0` and `1`.
+    ///
+    /// [`DLEQProofFormat::Compact`]: crate::crypto::dleq::DLEQProofFormat::Compact
+    CompactDleqProof,
+}
+
+impl Feature {
+    /// All the features known by this implementation.
+    pub const ALL: [Feature; 1] = [Feature::CompactDleqProof];
+
+    /// The even bit set when the feature is required.
+    pub fn required_bit(&self) -> u8 {
+        match self {
+            Self::CompactDleqProof => 0,
+        }
+    }
+
+    /// The odd bit set when the feature is optional.
+    pub fn optional_bit(&self) -> u8 {
+        self.required_bit() + 1
+    }
+
+    /// Return the feature identified by the bit, required or optional, if any.
+    pub fn from_bit(bit: u8) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|feature| feature.required_bit() == bit & !1)
+    }
+}
+
+/// A bit field of [`Feature`]s. As for message and extension types it's ok to be odd: a peer
+/// ignores the unknown optional (odd) bits but rejects the unknown required (even) bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Display, Encodable, Decodable)]
+#[display("{0:#x}")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[consensus(strict_encoding)]
+pub struct Features(u64);
+
+impl Features {
+    /// Create an empty bit field.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create the bit field from its raw value.
+    pub fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// The features supported by this implementation, all optional.
+    pub fn supported() -> Self {
+        Self::new().with_optional(Feature::CompactDleqProof)
+    }
+
+    /// The raw value of the bit field.
+    pub fn bits(&self) -> u64 {
+        self.0
+    }
+
+    /// Set the feature as optional.
+    pub fn with_optional(self, feature: Feature) -> Self {
+        Self(self.0 | (1 << feature.optional_bit()))
+    }
+
+    /// Set the feature as required.
+    pub fn with_required(self, feature: Feature) -> Self {
+        Self(self.0 | (1 << feature.required_bit()))
+    }
+
+    /// Return `true` if the feature is set, required or optional.
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.requires(feature) || self.0 & (1 << feature.optional_bit()) != 0
+    }
+
+    /// Return `true` if the feature is set as required.
+    pub fn requires(&self, feature: Feature) -> bool {
+        self.0 & (1 << feature.required_bit()) != 0
+    }
+
+    // Iterate over the even bits set.
+    fn required_bits(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..64u8)
+            .step_by(2)
+            .filter(move |bit| self.0 & (1 << bit) != 0)
+    }
+}
+
+impl Strategy for Features {
+    type Strategy = AsStrict;
+}
+
+/// The protocol version and the features agreed upon with the counterparty, see
+/// [`Handshake::negotiate`]. The roles record the capabilities to adapt the messages sent to the
+/// counterparty, e.g. with [`ProtocolMessage::serialize_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, Encodable, Decodable)]
+#[display("v{version} features {features}")]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[consensus(strict_encoding)]
+pub struct Capabilities {
+    /// The negotiated protocol version.
+    pub version: u16,
+    /// The features supported by both participants.
+    pub features: Features,
+}
+
+impl Capabilities {
+    /// Return `true` if the feature has been negotiated.
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.supports(feature)
+    }
+}
+
+impl Default for Capabilities {
+    /// The capabilities of a counterparty that did not send a [`Handshake`]: the first protocol
+    /// version without any feature.
+    fn default() -> Self {
+        Self {
+            version: SUPPORTED_PROTOCOL_VERSIONS[0],
+            features: Features::new(),
+        }
+    }
+}
+
+/// Message exchanged by both participants before the commit/reveal round to agree on the protocol
+/// version and the optional features used in the swap, see [`Handshake::negotiate`].
+#[derive(Clone, Debug, Display, PartialEq, Eq, Encodable, Decodable)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[consensus(strict_encoding)]
+pub struct Handshake {
+    /// The swap identifier related to this message.
+    pub swap_id: SwapId,
+    /// The protocol versions supported by the sender.
+    pub versions: Vec<u16>,
+    /// The features supported or required by the sender.
+    pub features: Features,
+}
+
+impl Handshake {
+    /// Create the handshake with the protocol versions supported by this implementation and the
+    /// features the sender supports or requires.
+    pub fn new(swap_id: SwapId, features: Features) -> Self {
+        Self {
+            swap_id,
+            versions: SUPPORTED_PROTOCOL_VERSIONS.to_vec(),
+            features,
+        }
+    }
+
+    /// Negotiate the capabilities with the counterparty's handshake: the highest protocol version
+    /// supported by both participants and the features supported by both. Returns an error if no
+    /// version is supported by both, or if a feature required by one participant is not supported
+    /// by the other.
+    pub fn negotiate(&self, remote: &Handshake) -> Result<Capabilities, negotiation::Error> {
+        if self.swap_id != remote.swap_id {
+            return Err(negotiation::Error::SwapIdMismatch);
+        }
+        let version = self
+            .versions
+            .iter()
+            .filter(|version| remote.versions.contains(version))
+            .max()
+            .copied()
+            .ok_or(negotiation::Error::NoCommonProtocolVersion)?;
+        // an unknown required bit on either side is a feature the other does not support
+        for (required, other) in [
+            (&self.features, &remote.features),
+            (&remote.features, &self.features),
+        ] {
+            for bit in required.required_bits() {
+                match Feature::from_bit(bit) {
+                    Some(feature) if other.supports(feature) => (),
+                    _ => return Err(negotiation::Error::UnsupportedFeature(bit)),
+                }
+            }
+        }
+        let features = Feature::ALL
+            .iter()
+            .filter(|feature| {
+                self.features.supports(**feature) && remote.features.supports(**feature)
+            })
+            .fold(Features::new(), |features, feature| {
+                features.with_optional(*feature)
+            });
+        Ok(Capabilities { version, features })
+    }
+}
+
+impl Strategy for Handshake {
+    type Strategy = AsStrict;
+}
+
+// Protocol message framing
+
+/// Registry of the protocol message types with their numeric type identifiers. Identifiers
+/// follow the RFCs numbering, messages not defined in the RFCs use the identifiers following
+/// the commit/reveal round.
+///
+/// The parity of an identifier only matters for the types missing from this registry: it's ok
+/// to be odd, a peer skips an unknown odd message type but rejects an unknown even one. Every
+/// registered type is required and understood whatever its parity, e.g. the RFC messages
+/// `33701`, `33703` and `33705` are odd but never skipped. A message type added to the protocol
+/// after this version uses an odd identifier if peers can safely ignore it, as [`Handshake`]
+/// does.
+///
+/// [`Handshake`]: MessageType::Handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+#[display(Debug)]
+pub enum MessageType {
+    /// [`CommitAliceParameters`] with type identifier `33701`.
+    CommitAliceParameters,
+    /// [`CommitBobParameters`] with type identifier `33702`.
+    CommitBobParameters,
+    /// [`RevealAliceParameters`] with type identifier `33703`.
+    RevealAliceParameters,
+    /// [`RevealBobParameters`] with type identifier `33704`.
+    RevealBobParameters,
+    /// [`RevealProof`] with type identifier `33705`.
+    RevealProof,
+    /// [`TakerAmount`] with type identifier `33706`.
+    TakerAmount,
+    /// [`Handshake`] with type identifier `33707`. The identifier is odd so peers not supporting
+    /// the handshake skip it, and use the first protocol version without any feature.
+    Handshake,
+    /// [`CoreArbitratingSetup`] with type identifier `33710`.
+    CoreArbitratingSetup,
+    /// [`RefundProcedureSignatures`] with type identifier `33720`.
+    RefundProcedureSignatures,
+    /// [`BuyProcedureSignature`] with type identifier `33730`.
+    BuyProcedureSignature,
+    /// [`Abort`] with type identifier `33790`.
+    Abort,
+}
+
+impl MessageType {
+    /// Return the numeric type identifier of the message type.
+    pub fn type_id(&self) -> u16 {
+        match self {
+            Self::CommitAliceParameters => 33701,
+            Self::CommitBobParameters => 33702,
+            Self::RevealAliceParameters => 33703,
+            Self::RevealBobParameters => 33704,
+            Self::RevealProof => 33705,
+            Self::TakerAmount => 33706,
+            Self::Handshake => 33707,
+            Self::CoreArbitratingSetup => 33710,
+            Self::RefundProcedureSignatures => 33720,
+            Self::BuyProcedureSignature => 33730,
+            Self::Abort => 33790,
+        }
+    }
+
+    /// Return the message type registered for the type identifier, if any.
+    pub fn from_type_id(type_id: u16) -> Option<Self> {
+        match type_id {
+            33701 => Some(Self::CommitAliceParameters),
+            33702 => Some(Self::CommitBobParameters),
+            33703 => Some(Self::RevealAliceParameters),
+            33704 => Some(Self::RevealBobParameters),
+            33705 => Some(Self::RevealProof),
+            33706 => Some(Self::TakerAmount),
+            33707 => Some(Self::Handshake),
+            33710 => Some(Self::CoreArbitratingSetup),
+            33720 => Some(Self::RefundProcedureSignatures),
+            33730 => Some(Self::BuyProcedureSignature),
+            33790 => Some(Self::Abort),
+            _ => None,
+        }
+    }
+
+    /// Return the maximum size in bytes of the message payload, larger payloads are rejected
+    /// before being read. The same value bounds the number of vector elements decoded in the
+    /// payload.
+    pub fn max_size(&self) -> usize {
+        match self {
+            Self::CommitAliceParameters | Self::CommitBobParameters => 4_096,
+            Self::RevealAliceParameters | Self::RevealBobParameters => 8_192,
+            // Proofs are vectors of bytes of at most `u16::MAX` bytes
+            Self::RevealProof => MAX_MESSAGE_SIZE,
+            Self::TakerAmount => 256,
+            Self::Handshake => 1_024,
+            Self::CoreArbitratingSetup => 65_536,
+            Self::RefundProcedureSignatures => 1_024,
+            Self::BuyProcedureSignature => 16_384,
+            Self::Abort => MAX_MESSAGE_SIZE,
+        }
+    }
+}
+
+/// Maximum size in bytes of any message payload, payloads of unknown odd message types larger
+/// than this are rejected instead of skipped.
+pub const MAX_MESSAGE_SIZE: usize = 66_560;
+
+/// Maximum size in bytes of all the extension values of a [`MessageFrame`].
+pub const MAX_EXTENSIONS_SIZE: usize = 4_096;
+
+/// Extension type of the [`AbortCode`] of an [`Abort`] message, encoded as a little endian
+/// `u16`. The type is odd so peers not knowing the codes ignore it.
+pub const ABORT_CODE_EXTENSION_TYPE: u16 = 1;
+
+/// Extension types understood by this version of the protocol. Unknown even extensions are
+/// rejected when decoding a [`MessageFrame`], unknown odd extensions are ignored.
+pub const KNOWN_EXTENSION_TYPES: &[u16] = &[ABORT_CODE_EXTENSION_TYPE];
+
+/// TLV-style optional extensions appended to a framed protocol message for forward
+/// compatibility. Each record is identified by a type, records are serialized in strictly
+/// increasing type order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Extensions(BTreeMap<u16, Vec<u8>>);
+
+impl Extensions {
+    /// Create an empty set of extensions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert an extension record, returns the previous value for this type if any.
+    pub fn insert(&mut self, extension_type: u16, value: Vec<u8>) -> Option<Vec<u8>> {
+        self.0.insert(extension_type, value)
+    }
+
+    /// Return the value of the extension record for the type, if any.
+    pub fn get(&self, extension_type: u16) -> Option<&[u8]> {
+        self.0.get(&extension_type).map(|value| value.as_ref())
+    }
+
+    /// Remove and return the extension record for the type, if any.
+    pub fn remove(&mut self, extension_type: u16) -> Option<Vec<u8>> {
+        self.0.remove(&extension_type)
+    }
+
+    /// Iterate over the extension records in increasing type order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &[u8])> {
+        self.0
+            .iter()
+            .map(|(extension_type, value)| (*extension_type, value.as_ref()))
+    }
+
+    /// Return the number of extension records.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Return `true` if there is no extension record.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Encodable for Extensions {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        if self.0.len() > u16::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::Other, "Too many extensions"));
+        }
+        let mut len = (self.0.len() as u16).consensus_encode(s)?;
+        for (extension_type, value) in self.0.iter() {
+            len += extension_type.consensus_encode(s)?;
+            len += value.consensus_encode(s)?;
+        }
+        Ok(len)
+    }
+}
+
+impl Decodable for Extensions {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let count: u16 = Decodable::consensus_decode(d)?;
+        let mut extensions = BTreeMap::new();
+        let mut previous: Option<u16> = None;
+        let mut size = 0;
+        for _ in 0..count {
+            let extension_type: u16 = Decodable::consensus_decode(d)?;
+            let value: Vec<u8> = Decodable::consensus_decode(d)?;
+            size += value.len();
+            if size > MAX_EXTENSIONS_SIZE {
+                return Err(consensus::Error::LimitExceeded("extensions are too large"));
+            }
+            if matches!(previous, Some(previous) if previous >= extension_type) {
+                return Err(consensus::Error::ParseFailed(
+                    "Extension types are not strictly increasing",
+                ));
+            }
+            previous = Some(extension_type);
+            // It's ok to be odd: unknown odd extensions are skipped, unknown even extensions must
+            // be understood
+            if KNOWN_EXTENSION_TYPES.contains(&extension_type) {
+                extensions.insert(extension_type, value);
+            } else if extension_type % 2 == 0 {
+                return Err(consensus::Error::UnknownExtensionType(extension_type));
+            }
+        }
+        Ok(Self(extensions))
+    }
+}
+
+impl_strict_encoding!(Extensions);
+
+#[cfg(feature = "serde")]
+impl Serialize for Extensions {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(
+            self.0
+                .iter()
+                .map(|(extension_type, value)| (extension_type, hex::encode(value))),
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Extensions {
+    fn deserialize<D>(deserializer: D) -> Result<Extensions, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        BTreeMap::<u16, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(extension_type, value)| {
+                hex::decode(value)
+                    .map(|value| (extension_type, value))
+                    .map_err(de::Error::custom)
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Envelope over all the protocol messages, identified on the wire by their [`MessageType`]
+/// identifier.
+#[derive(Clone, Debug, Display)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+pub enum ProtocolMessage<Ctx: Swap> {
+    /// The [`CommitAliceParameters`] message.
+    CommitAliceParameters(CommitAliceParameters<Ctx>),
+    /// The [`CommitBobParameters`] message.
+    CommitBobParameters(CommitBobParameters<Ctx>),
+    /// The [`RevealAliceParameters`] message.
+    RevealAliceParameters(RevealAliceParameters<Ctx>),
+    /// The [`RevealBobParameters`] message.
+    RevealBobParameters(RevealBobParameters<Ctx>),
+    /// The [`RevealProof`] message.
+    RevealProof(RevealProof<Ctx>),
+    /// The [`TakerAmount`] message.
+    TakerAmount(TakerAmount<Ctx>),
+    /// The [`Handshake`] message.
+    Handshake(Handshake),
+    /// The [`CoreArbitratingSetup`] message.
+    CoreArbitratingSetup(CoreArbitratingSetup<Ctx>),
+    /// The [`RefundProcedureSignatures`] message.
+    RefundProcedureSignatures(RefundProcedureSignatures<Ctx>),
+    /// The [`BuyProcedureSignature`] message.
+    BuyProcedureSignature(BuyProcedureSignature<Ctx>),
+    /// The [`Abort`] message.
+    Abort(Abort),
+}
+
+impl<Ctx> ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Return the registered type of the message.
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            Self::CommitAliceParameters(_) => MessageType::CommitAliceParameters,
+            Self::CommitBobParameters(_) => MessageType::CommitBobParameters,
+            Self::RevealAliceParameters(_) => MessageType::RevealAliceParameters,
+            Self::RevealBobParameters(_) => MessageType::RevealBobParameters,
+            Self::RevealProof(_) => MessageType::RevealProof,
+            Self::TakerAmount(_) => MessageType::TakerAmount,
+            Self::Handshake(_) => MessageType::Handshake,
+            Self::CoreArbitratingSetup(_) => MessageType::CoreArbitratingSetup,
+            Self::RefundProcedureSignatures(_) => MessageType::RefundProcedureSignatures,
+            Self::BuyProcedureSignature(_) => MessageType::BuyProcedureSignature,
+            Self::Abort(_) => MessageType::Abort,
+        }
+    }
+
+    /// Return the numeric type identifier of the message.
+    pub fn type_id(&self) -> u16 {
+        self.message_type().type_id()
+    }
+
+    /// Return the swap identifier related to the message.
+    pub fn swap_id(&self) -> SwapId {
+        match self {
+            Self::CommitAliceParameters(msg) => msg.swap_id,
+            Self::CommitBobParameters(msg) => msg.swap_id,
+            Self::RevealAliceParameters(msg) => msg.swap_id,
+            Self::RevealBobParameters(msg) => msg.swap_id,
+            Self::RevealProof(msg) => msg.swap_id,
+            Self::TakerAmount(msg) => msg.swap_id,
+            Self::Handshake(msg) => msg.swap_id,
+            Self::CoreArbitratingSetup(msg) => msg.swap_id,
+            Self::RefundProcedureSignatures(msg) => msg.swap_id,
+            Self::BuyProcedureSignature(msg) => msg.swap_id,
+            Self::Abort(msg) => msg.swap_id,
+        }
+    }
+
+    // Serialize the inner message without its type identifier.
+    fn payload(&self) -> Vec<u8> {
+        match self {
+            Self::CommitAliceParameters(msg) => consensus::serialize(msg),
+            Self::CommitBobParameters(msg) => consensus::serialize(msg),
+            Self::RevealAliceParameters(msg) => consensus::serialize(msg),
+            Self::RevealBobParameters(msg) => consensus::serialize(msg),
+            Self::RevealProof(msg) => consensus::serialize(msg),
+            Self::TakerAmount(msg) => consensus::serialize(msg),
+            Self::Handshake(msg) => consensus::serialize(msg),
+            Self::CoreArbitratingSetup(msg) => consensus::serialize(msg),
+            Self::RefundProcedureSignatures(msg) => consensus::serialize(msg),
+            Self::BuyProcedureSignature(msg) => consensus::serialize(msg),
+            Self::Abort(msg) => consensus::serialize(msg),
+        }
+    }
+
+    // Serialize the inner message for a counterparty with the capabilities, only the encoding of
+    // the proof depends on the capabilities.
+    fn payload_with(&self, capabilities: &Capabilities) -> Vec<u8> {
+        match self {
+            Self::RevealProof(msg) => {
+                let mut payload = consensus::serialize(&msg.swap_id);
+                Ctx::proof_bytes(&msg.proof, capabilities)
+                    .consensus_encode(&mut payload)
+                    .unwrap();
+                payload
+            }
+            _ => self.payload(),
+        }
+    }
+
+    /// Serialize the message for a counterparty with the negotiated capabilities. The encoding
+    /// differs from [`consensus::serialize`] only when the capabilities change the format of a
+    /// field, e.g. the proof of a [`RevealProof`] is sent in its legacy format to a counterparty
+    /// not supporting [`Feature::CompactDleqProof`].
+    pub fn serialize_with(&self, capabilities: &Capabilities) -> Vec<u8> {
+        let mut bytes = consensus::serialize(&self.type_id());
+        write_payload(&self.payload_with(capabilities), &mut bytes).unwrap();
+        bytes
+    }
+
+    // Deserialize the inner message of the given type under the message limits, the payload must
+    // be consumed entirely.
+    fn from_payload(message_type: MessageType, payload: &[u8]) -> Result<Self, consensus::Error> {
+        let max_size = message_type.max_size();
+        let limits = DecodingLimits::new(max_size, max_size);
+        Ok(match message_type {
+            MessageType::CommitAliceParameters => {
+                Self::CommitAliceParameters(consensus::deserialize_with_limits(payload, limits)?)
+            }
+            MessageType::CommitBobParameters => {
+                Self::CommitBobParameters(consensus::deserialize_with_limits(payload, limits)?)
+            }
+            MessageType::RevealAliceParameters => {
+                Self::RevealAliceParameters(consensus::deserialize_with_limits(payload, limits)?)
+            }
+            MessageType::RevealBobParameters => {
+                Self::RevealBobParameters(consensus::deserialize_with_limits(payload, limits)?)
+            }
+            MessageType::RevealProof => {
+                Self::RevealProof(consensus::deserialize_with_limits(payload, limits)?)
+            }
+            MessageType::TakerAmount => {
+                Self::TakerAmount(consensus::deserialize_with_limits(payload, limits)?)
+            }
+            MessageType::Handshake => {
+                Self::Handshake(consensus::deserialize_with_limits(payload, limits)?)
+            }
+            MessageType::CoreArbitratingSetup => {
+                Self::CoreArbitratingSetup(consensus::deserialize_with_limits(payload, limits)?)
+            }
+            MessageType::RefundProcedureSignatures => Self::RefundProcedureSignatures(
+                consensus::deserialize_with_limits(payload, limits)?,
+            ),
+            MessageType::BuyProcedureSignature => {
+                Self::BuyProcedureSignature(consensus::deserialize_with_limits(payload, limits)?)
+            }
+            MessageType::Abort => Self::Abort(consensus::deserialize_with_limits(payload, limits)?),
+        })
+    }
+}
+
+// Read a payload prefixed by its length on four bytes, without allocating the announced length
+// upfront. Payloads announced larger than `max_size` are rejected before being read.
+fn read_payload<D: io::Read>(d: &mut D, max_size: usize) -> Result<Vec<u8>, consensus::Error> {
+    let len: u32 = Decodable::consensus_decode(d)?;
+    if len as usize > max_size {
+        return Err(consensus::Error::LimitExceeded(
+            "message payload is too large",
+        ));
+    }
+    let mut payload = Vec::new();
+    d.take(len as u64).read_to_end(&mut payload)?;
+    if payload.len() != len as usize {
+        return Err(consensus::Error::ParseFailed("Truncated message payload"));
+    }
+    Ok(payload)
+}
+
+fn write_payload<W: io::Write>(payload: &[u8], s: &mut W) -> Result<usize, io::Error> {
+    if payload.len() > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::Other, "Payload is too long"));
+    }
+    let len = (payload.len() as u32).consensus_encode(s)?;
+    s.write_all(payload)?;
+    Ok(len + payload.len())
+}
+
+impl<Ctx> Encodable for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.type_id().consensus_encode(s)?;
+        Ok(len + write_payload(&self.payload(), s)?)
+    }
+}
+
+impl<Ctx> Decodable for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let type_id: u16 = Decodable::consensus_decode(d)?;
+        let message_type = MessageType::from_type_id(type_id)
+            .ok_or(consensus::Error::UnknownMessageType(type_id))?;
+        Self::from_payload(message_type, &read_payload(d, message_type.max_size())?)
+    }
+}
+
+impl_strict_encoding!(ProtocolMessage<Ctx>, Ctx: Swap);
+
+impl<Ctx> Strategy for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    type Strategy = AsStrict;
+}
+
+macro_rules! impl_from_message {
+    ($variant:ident) => {
+        impl<Ctx> From<$variant<Ctx>> for ProtocolMessage<Ctx>
+        where
+            Ctx: Swap,
+        {
+            fn from(msg: $variant<Ctx>) -> Self {
+                Self::$variant(msg)
+            }
+        }
+    };
+}
+
+impl_from_message!(CommitAliceParameters);
+impl_from_message!(CommitBobParameters);
+impl_from_message!(RevealAliceParameters);
+impl_from_message!(RevealBobParameters);
+impl_from_message!(RevealProof);
+impl_from_message!(TakerAmount);
+impl_from_message!(CoreArbitratingSetup);
+impl_from_message!(RefundProcedureSignatures);
+impl_from_message!(BuyProcedureSignature);
+
+impl<Ctx> From<Handshake> for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn from(msg: Handshake) -> Self {
+        Self::Handshake(msg)
+    }
+}
+
+impl<Ctx> From<Abort> for ProtocolMessage<Ctx>
+where
+    Ctx: Swap,
+{
+    fn from(msg: Abort) -> Self {
+        Self::Abort(msg)
+    }
+}
+
+/// A framed protocol message as sent over the transport: the message type identifier, the
+/// message payload prefixed by its length, and the optional [`Extensions`].
+///
+/// The payload length allows a transport to skip messages it does not understand. When decoding
+/// a frame unknown even message types are rejected and unknown odd message types are skipped,
+/// see [`MessageType`] for the parity rule. [`MessageFrame::decode_frame`] returns `None` for
+/// a skipped frame while the [`Decodable`] implementation, which must return a frame, reports
+/// it with [`consensus::Error::IgnoredMessageType`]. In both cases the skipped frame is consumed
+/// and the next frame can be decoded from the reader.
+#[derive(Clone, Debug, Display)]
+#[display(Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(crate = "serde_crate", bound = "")
+)]
+pub struct MessageFrame<Ctx: Swap> {
+    /// The framed protocol message.
+    pub message: ProtocolMessage<Ctx>,
+    /// The optional extensions attached to the message. The records carried by the message, e.g.
+    /// the [`ABORT_CODE_EXTENSION_TYPE`] of an [`Abort`], are added when encoding and moved back
+    /// into the message when decoding.
+    pub extensions: Extensions,
+}
+
+impl<Ctx> MessageFrame<Ctx>
+where
+    Ctx: Swap,
+{
+    /// Frame a protocol message with no extension.
+    pub fn new(message: impl Into<ProtocolMessage<Ctx>>) -> Self {
+        Self {
+            message: message.into(),
+            extensions: Extensions::new(),
+        }
+    }
+
+    /// Attach the extensions to the framed message.
+    pub fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Serialize the frame for a counterparty with the negotiated capabilities, see
+    /// [`ProtocolMessage::serialize_with`].
+    pub fn serialize_with(&self, capabilities: &Capabilities) -> Vec<u8> {
+        let payload = match &self.message {
+            ProtocolMessage::Abort(abort) => abort_payload(abort),
+            message => message.payload_with(capabilities),
+        };
+        let mut bytes = consensus::serialize(&self.message.type_id());
+        write_payload(&payload, &mut bytes).unwrap();
+        bytes.extend(consensus::serialize(&self.wire_extensions()));
+        bytes
+    }
+
+    // The extensions sent with the frame, including the records carried by the message: the code
+    // of an abort is sent unless unspecified, in place of the code trailing the abort payload.
+    fn wire_extensions(&self) -> Extensions {
+        let mut extensions = self.extensions.clone();
+        if let ProtocolMessage::Abort(abort) = &self.message {
+            if abort.abort_code != AbortCode::Unspecified {
+                extensions.insert(
+                    ABORT_CODE_EXTENSION_TYPE,
+                    consensus::serialize(&abort.abort_code),
+                );
+            }
+        }
+        extensions
+    }
+
+    /// Decode the next frame from the reader. Returns `None` if the message type is unknown and
+    /// odd, the frame is then consumed entirely and can be safely skipped. Unknown even message
+    /// types are rejected with [`consensus::Error::UnknownMessageType`].
+    pub fn decode_frame<D: io::Read>(d: &mut D) -> Result<Option<Self>, consensus::Error> {
+        Self::decode_frame_with_type(d).map(|(_, frame)| frame)
+    }
+
+    // Decode the next frame and return its type identifier along with the frame, if known.
+    fn decode_frame_with_type<D: io::Read>(
+        d: &mut D,
+    ) -> Result<(u16, Option<Self>), consensus::Error> {
+        let type_id: u16 = Decodable::consensus_decode(d)?;
+        let max_size = MessageType::from_type_id(type_id)
+            .map_or(MAX_MESSAGE_SIZE, |message_type| message_type.max_size());
+        let payload = read_payload(d, max_size)?;
+        let mut extensions = Extensions::consensus_decode(d)?;
+        match MessageType::from_type_id(type_id) {
+            Some(message_type) => {
+                let mut message = ProtocolMessage::from_payload(message_type, &payload)?;
+                if let ProtocolMessage::Abort(abort) = &mut message {
+                    if let Some(code) = extensions.remove(ABORT_CODE_EXTENSION_TYPE) {
+                        abort.abort_code = consensus::deserialize(&code)?;
+                    }
+                }
+                Ok((
+                    type_id,
+                    Some(Self {
+                        message,
+                        extensions,
+                    }),
+                ))
+            }
+            None if type_id % 2 == 1 => Ok((type_id, None)),
+            None => Err(consensus::Error::UnknownMessageType(type_id)),
+        }
+    }
+}
+
+// The payload of a framed abort, without the code sent in the frame extensions so that peers not
+// knowing the codes decode it.
+fn abort_payload(abort: &Abort) -> Vec<u8> {
+    let mut payload = vec![];
+    abort.encode_without_code(&mut payload).unwrap();
+    payload
+}
+
+impl<Ctx> Encodable for MessageFrame<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = match &self.message {
+            ProtocolMessage::Abort(abort) => {
+                let len = self.message.type_id().consensus_encode(s)?;
+                len + write_payload(&abort_payload(abort), s)?
+            }
+            message => message.consensus_encode(s)?,
+        };
+        Ok(len + self.wire_extensions().consensus_encode(s)?)
+    }
+}
+
+impl<Ctx> Decodable for MessageFrame<Ctx>
+where
+    Ctx: Swap,
+{
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        match Self::decode_frame_with_type(d)? {
+            (_, Some(frame)) => Ok(frame),
+            (type_id, None) => Err(consensus::Error::IgnoredMessageType(type_id)),
+        }
+    }
+}
+
+impl_strict_encoding!(MessageFrame<Ctx>, Ctx: Swap);
+
+impl<Ctx> Strategy for MessageFrame<Ctx>
+where
+    Ctx: Swap,
+{
+    type Strategy = AsStrict;
+}
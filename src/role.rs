@@ -10,16 +10,17 @@ use crate::blockchain::{
     Address, Asset, Fee, FeePriority, Network, Onchain, Timelock, Transactions,
 };
 use crate::bundle::{
-    AliceParameters, BobParameters, CoreArbitratingTransactions, CosignedArbitratingCancel,
-    FullySignedBuy, FullySignedPunish, FullySignedRefund, Proof, SignedAdaptorBuy,
-    SignedAdaptorRefund, SignedArbitratingLock,
+    validate_participants, AliceParameters, BobParameters, CoreArbitratingTransactions,
+    CosignedArbitratingCancel, FullySignedBuy, FullySignedPunish, FullySignedRefund, Proof,
+    SignedAdaptorBuy, SignedAdaptorRefund, SignedArbitratingLock,
 };
 use crate::consensus::{self, Decodable, Encodable};
 use crate::crypto::{
     self, AccordantKeyId, ArbitratingKeyId, KeyGenerator, Keys, SharedSecretKeys, Sign, Signatures,
     SwapAccordantKeys, TaggedElement, TaggedExtraKeys, TaggedSharedKeys,
 };
-use crate::negotiation::PublicOffer;
+use crate::negotiation::{self, PublicOffer};
+use crate::protocol_message::{Capabilities, MessageFrame, ProtocolMessage};
 use crate::script::{DataLock, DataPunishableLock, DoubleKeys, ScriptPath};
 use crate::swap::Swap;
 use crate::transaction::{
@@ -91,7 +92,7 @@ impl FromStr for TradeRole {
 
 /// Possible roles during the swap phase. When negotitation phase is completed [`TradeRole`] will
 /// transition into swap role according to the [`PublicOffer`].
-#[derive(Display, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[display(Debug)]
 #[cfg_attr(
     feature = "serde",
@@ -158,6 +159,8 @@ pub struct Alice<Ctx: Swap> {
     pub destination_address: <Ctx::Ar as Address>::Address,
     /// The fee politic to apply during the swap fee calculation
     pub fee_politic: FeePriority,
+    // The capabilities negotiated with Bob, the defaults if Bob did not send a handshake
+    capabilities: Capabilities,
 }
 
 struct ValidatedCoreTransactions<'a, Ctx: Swap> {
@@ -179,9 +182,28 @@ where
         Self {
             destination_address,
             fee_politic,
+            capabilities: Capabilities::default(),
         }
     }
 
+    /// Record the capabilities negotiated with Bob, see [`Handshake::negotiate`].
+    ///
+    /// [`Handshake::negotiate`]: crate::protocol_message::Handshake::negotiate
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// The capabilities negotiated with Bob, the defaults if Bob did not send a handshake.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Frame and serialize a protocol message for Bob with the negotiated capabilities.
+    pub fn serialize_message(&self, message: impl Into<ProtocolMessage<Ctx>>) -> Vec<u8> {
+        MessageFrame::new(message).serialize_with(&self.capabilities)
+    }
+
     /// Generate Alice's parameters for the protocol execution based on the arbitrating and
     /// accordant seeds and the public offer agreed upon during the negotiation phase.
     ///
@@ -650,6 +672,11 @@ where
         core: &CoreArbitratingTransactions<Ctx::Ar>,
         public_offer: &PublicOffer<Ctx>,
     ) -> Res<ValidatedCoreTransactions<'a, Ctx>> {
+        // Validate Bob's parameters against the agreed public offer and our own parameters
+        // before using them.
+        bob_parameters.validate_against(public_offer)?;
+        validate_participants(alice_parameters, bob_parameters)?;
+
         // Extract the partial transaction from the core arbitrating bundle, this operation should
         // not error if the bundle is well formed.
         let partial_lock = core.lock.clone();
@@ -675,7 +702,11 @@ where
 
         // Verify the lock transaction template.
         lock.verify_template(data_lock.clone())?;
-        // The target amount is dictated from the public offer.
+        // The target amount is dictated from the public offer, an amount range must have been
+        // filled with the amount chosen by the taker.
+        if public_offer.is_range() {
+            return Err(negotiation::Error::UnfilledAmountRange.into());
+        }
         let target_amount = public_offer.offer.arbitrating_amount;
         // Verify the target amount
         lock.verify_target_amount(target_amount)?;
@@ -741,6 +772,8 @@ pub struct Bob<Ctx: Swap> {
     pub refund_address: <Ctx::Ar as Address>::Address,
     /// The fee politic to apply during the swap fee calculation
     pub fee_politic: FeePriority,
+    // The capabilities negotiated with Alice, the defaults if Alice did not send a handshake
+    capabilities: Capabilities,
 }
 
 impl<Ctx: Swap> Bob<Ctx> {
@@ -749,9 +782,28 @@ impl<Ctx: Swap> Bob<Ctx> {
         Self {
             refund_address,
             fee_politic,
+            capabilities: Capabilities::default(),
         }
     }
 
+    /// Record the capabilities negotiated with Alice, see [`Handshake::negotiate`].
+    ///
+    /// [`Handshake::negotiate`]: crate::protocol_message::Handshake::negotiate
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// The capabilities negotiated with Alice, the defaults if Alice did not send a handshake.
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// Frame and serialize a protocol message for Alice with the negotiated capabilities.
+    pub fn serialize_message(&self, message: impl Into<ProtocolMessage<Ctx>>) -> Vec<u8> {
+        MessageFrame::new(message).serialize_with(&self.capabilities)
+    }
+
     /// Generate Bob's parameters for the protocol execution based on the arbitrating and accordant
     /// seeds and the public offer agreed upon during the negotiation phase.
     ///
@@ -883,6 +935,11 @@ impl<Ctx: Swap> Bob<Ctx> {
         funding: impl Fundable<Ctx::Ar, <Ctx::Ar as Transactions>::Metadata>,
         public_offer: &PublicOffer<Ctx>,
     ) -> Res<CoreArbitratingTransactions<Ctx::Ar>> {
+        // Validate Alice's parameters against the agreed public offer and our own parameters
+        // before using them.
+        alice_parameters.validate_against(public_offer)?;
+        validate_participants(alice_parameters, bob_parameters)?;
+
         // Initialize the fundable transaction to build the lockable transaction on top of it.
         //
         // The fundable transaction `funding` contains all the logic to build on top of a
@@ -905,7 +962,11 @@ impl<Ctx: Swap> Bob<Ctx> {
             failure: DoubleKeys::new(alice_cancel, bob_cancel),
         };
 
-        // The target amount is dictated from the public offer.
+        // The target amount is dictated from the public offer, an amount range must have been
+        // filled with the amount chosen by the taker.
+        if public_offer.is_range() {
+            return Err(negotiation::Error::UnfilledAmountRange.into());
+        }
         let target_amount = public_offer.offer.arbitrating_amount;
 
         // Initialize the lockable transaction based on the fundable structure. The lockable
@@ -1122,6 +1183,11 @@ impl<Ctx: Swap> Bob<Ctx> {
         core: &CoreArbitratingTransactions<Ctx::Ar>,
         public_offer: &PublicOffer<Ctx>,
     ) -> Res<SignedAdaptorBuy<Ctx::Ar>> {
+        // Validate Alice's parameters against the agreed public offer and our own parameters
+        // before using them.
+        alice_parameters.validate_against(public_offer)?;
+        validate_participants(alice_parameters, bob_parameters)?;
+
         // Extract the partial transaction from the core arbitrating bundle, this operation should
         // not error if the bundle is well formed.
         let partial_lock = core.lock.clone();
@@ -9,6 +9,7 @@ use std::str::FromStr;
 use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
 #[cfg(feature = "serde")]
 use crate::hash::HashString;
+use crate::protocol_message::Capabilities;
 use crate::role::{Accordant, Arbitrating};
 
 use lightning_encoding::strategies::AsStrict;
@@ -79,6 +80,17 @@ pub trait Swap: Debug + Clone {
 
     /// Commitment type used in the commit/reveal scheme during swap setup.
     type Commitment: Clone + PartialEq + Eq + Debug + fmt::Display + CanonicalBytes;
+
+    /// Whether the commitments are salted with per-swap nonces. Reveal messages carry the nonces
+    /// only when set, the encoding of the other swap contexts is unchanged.
+    const SALTED_COMMITMENTS: bool = false;
+
+    /// Serialize the proof for a counterparty with the negotiated capabilities. Defaults to the
+    /// canonical bytes of the proof, implementations override it when the proof encoding depends
+    /// on a negotiated feature.
+    fn proof_bytes(proof: &Self::Proof, _capabilities: &Capabilities) -> Vec<u8> {
+        proof.as_canonical_bytes()
+    }
 }
 
 #[cfg(test)]
@@ -1,14 +1,30 @@
 //! Concrete implementation of a swap between Bitcoin as the arbitrating blockchain and Monero as the
 //! accordant blockchain.
 
+#[cfg(feature = "experimental")]
+#[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
+pub mod keystore;
+#[cfg(feature = "experimental")]
+#[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
+pub mod mnemonic;
+#[cfg(feature = "experimental")]
+#[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
+pub mod registry;
+
 use crate::crypto::{
     self,
     slip10::{ChildNumber, DerivationPath, Ed25519ExtSecretKey, Secp256k1ExtSecretKey},
     AccordantKeyId, ArbitratingKeyId, GenerateKey, GenerateSharedKey, KeccakCommitment,
-    ProveCrossGroupDleq, SharedKeyId,
+    ProveCrossGroupDleq, SaltedKeccakCommitment, SharedKeyId, REDACTED,
 };
 #[cfg(feature = "experimental")]
-use crate::{bitcoin::BitcoinSegwitV0, crypto::Sign, monero::Monero, swap::Swap};
+use crate::{
+    bitcoin::BitcoinSegwitV0,
+    crypto::{dleq::DLEQProofFormat, Sign},
+    monero::Monero,
+    protocol_message::{Capabilities, Feature},
+    swap::Swap,
+};
 use crate::{blockchain::Blockchain, crypto::dleq::DLEQProof};
 
 use monero::cryptonote::hash::Hash;
@@ -22,9 +38,11 @@ use ecdsa_fun::{
 #[cfg(feature = "experimental")]
 use rand::rngs::ThreadRng;
 #[cfg(feature = "experimental")]
+use rand::{CryptoRng, RngCore, SeedableRng};
+#[cfg(feature = "experimental")]
 use rand_chacha::ChaCha20Rng;
 #[cfg(feature = "experimental")]
-use sha2::Sha256;
+use sha2::{Digest, Sha256};
 
 #[cfg(feature = "experimental")]
 use bitcoin::{hashes::sha256d::Hash as Sha256dHash, secp256k1::Message, secp256k1::Signature};
@@ -35,8 +53,11 @@ use bitcoin::secp256k1::{
 };
 
 use std::collections::HashMap;
+use std::fmt;
 use std::str::FromStr;
 
+use zeroize::Zeroize;
+
 #[cfg(feature = "experimental")]
 type Transcript = HashTranscript<Sha256, ChaCha20Rng>;
 
@@ -56,6 +77,10 @@ pub const SHARED_KEY_DERIVE_INDEX: u32 = 3;
 /// discrete logarithm proof.
 pub const CLAMPING_TO_252_BITS_MASK: u8 = 0b0000_1111;
 
+/// Domain separation tag used to derive the seed of the random number generator producing the
+/// cross group discrete logarithm proof, see [`KeyManager::dleq_proof_rng`].
+pub const DLEQ_PROOF_RNG_TAG: &[u8] = b"farcaster:dleq-proof-rng";
+
 /// The context for a Bitcoin and Monero [`Swap`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BtcXmr;
@@ -67,6 +92,35 @@ impl Swap for BtcXmr {
     type Ac = Monero;
     type Proof = DLEQProof;
     type Commitment = KeccakCommitment;
+
+    /// Counterparties not supporting [`Feature::CompactDleqProof`] receive the legacy encoding of
+    /// the proof.
+    fn proof_bytes(proof: &DLEQProof, capabilities: &Capabilities) -> Vec<u8> {
+        match capabilities.supports(Feature::CompactDleqProof) {
+            true => proof.to_bytes_with_format(DLEQProofFormat::Compact),
+            false => proof.to_bytes_with_format(DLEQProofFormat::Legacy),
+        }
+    }
+}
+
+/// The context for a Bitcoin and Monero [`Swap`] using commitments salted with per-swap nonces,
+/// see [`SaltedKeccakCommitment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaltedBtcXmr;
+
+#[cfg(feature = "experimental")]
+#[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
+impl Swap for SaltedBtcXmr {
+    type Ar = BitcoinSegwitV0;
+    type Ac = Monero;
+    type Proof = DLEQProof;
+    type Commitment = SaltedKeccakCommitment;
+
+    const SALTED_COMMITMENTS: bool = true;
+
+    fn proof_bytes(proof: &DLEQProof, capabilities: &Capabilities) -> Vec<u8> {
+        BtcXmr::proof_bytes(proof, capabilities)
+    }
 }
 
 /// Retrieve the derivation path of something. Might be a blockchain, a type of key, anything that
@@ -131,18 +185,20 @@ impl Derivation for SharedKeyId {
 
 /// Manager responsible for handling key operations (secret and public). Implements traits for
 /// handling [`GenerateKey`], [`GenerateSharedKey`] and [`Sign`].
-#[derive(Debug)]
+///
+/// The master seed and all the derived secret keys are wiped when the manager is dropped, its
+/// `Debug` output only lists the derivation paths of the derived keys.
 pub struct KeyManager {
     /// The master 32-bytes seed used to derive all the keys for all the swaps.
-    _master_seed: [u8; 32],
+    master_seed: [u8; 32],
     /// The swap identifier used in the derivation.
     swap_index: ChildNumber,
     /// The master secp256k1 seed.
     bitcoin_master_key: Secp256k1ExtSecretKey,
     /// The master ed25519 seed.
     monero_master_key: Ed25519ExtSecretKey,
-    /// A list of already derived keys for secp256k1 by derivation path.
-    bitcoin_derivations: HashMap<DerivationPath, SecretKey>,
+    /// A list of already derived extended keys for secp256k1 by derivation path.
+    bitcoin_derivations: HashMap<DerivationPath, Secp256k1ExtSecretKey>,
     /// A list of already derived monero keys for ed25519 by derivation path.
     monero_derivations: HashMap<DerivationPath, monero::PrivateKey>,
 }
@@ -170,15 +226,16 @@ impl KeyManager {
         self.bitcoin_derivations
             .get(&path)
             // Option<Result<SecretKey, _>>
-            .map(|key| Ok(*key))
+            .map(|key| Ok(key.secret_key()))
             // Some(Ok(_)) => Ok(_)
             // None => || { ... } => Result<SecretKey, crypto::Error>
             .unwrap_or_else(|| {
                 let secp = Secp256k1::new();
                 match self.bitcoin_master_key.derive_priv(&secp, &path) {
                     Ok(key) => {
-                        self.bitcoin_derivations.insert(path, key.secret_key);
-                        Ok(key.secret_key)
+                        let secret_key = key.secret_key();
+                        self.bitcoin_derivations.insert(path, key);
+                        Ok(secret_key)
                     }
                     Err(e) => Err(e.into()),
                 }
@@ -199,12 +256,13 @@ impl KeyManager {
             // Some(Ok(_)) => Ok(_)
             // None => || { ... } => Result<PrivateKey, crypto::Error>
             .unwrap_or_else(|| {
-                let key_seed = self
+                let mut key_seed = self
                     .monero_master_key
                     .derive_priv(&path)
                     .expect("Path does not contain non-hardened derivation")
                     .secret_key;
                 let secret_key = Hash::from_slice(&key_seed).as_scalar();
+                key_seed.zeroize();
 
                 self.monero_derivations.insert(path, secret_key);
                 Ok(secret_key)
@@ -218,15 +276,17 @@ impl KeyManager {
             .get_or_derive_monero_key(AccordantKeyId::Spend)?
             .to_bytes();
         little_endian_bytes[31] &= CLAMPING_TO_252_BITS_MASK;
-        Ok(monero::PrivateKey::from_slice(little_endian_bytes.as_ref())
-            .expect("Valid canonical bytes"))
+        let spend_key = monero::PrivateKey::from_slice(little_endian_bytes.as_ref())
+            .expect("Valid canonical bytes");
+        little_endian_bytes.zeroize();
+        Ok(spend_key)
     }
 
     /// Create a new key manager with the provided master seed, returns an error if the swap index is
     /// not within `[0, 2^31 - 1]`.
     pub fn new(seed: [u8; 32], swap_index: u32) -> Result<Self, crypto::Error> {
         Ok(Self {
-            _master_seed: seed,
+            master_seed: seed,
             swap_index: ChildNumber::from_hardened_idx(swap_index).map_err(crypto::Error::new)?,
             bitcoin_master_key: Secp256k1ExtSecretKey::new_master(seed.as_ref()),
             monero_master_key: Ed25519ExtSecretKey::new_master(seed.as_ref()),
@@ -236,6 +296,34 @@ impl KeyManager {
     }
 }
 
+impl Drop for KeyManager {
+    fn drop(&mut self) {
+        self.master_seed.zeroize();
+        for secret_key in self.monero_derivations.values_mut() {
+            secret_key.scalar.zeroize();
+        }
+    }
+}
+
+impl fmt::Debug for KeyManager {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KeyManager")
+            .field("master_seed", &REDACTED)
+            .field("swap_index", &self.swap_index)
+            .field("bitcoin_master_key", &self.bitcoin_master_key)
+            .field("monero_master_key", &self.monero_master_key)
+            .field(
+                "bitcoin_derivations",
+                &self.bitcoin_derivations.keys().collect::<Vec<_>>(),
+            )
+            .field(
+                "monero_derivations",
+                &self.monero_derivations.keys().collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
 impl GenerateKey<monero::PublicKey, AccordantKeyId> for KeyManager {
     fn get_pubkey(&mut self, key_id: AccordantKeyId) -> Result<monero::PublicKey, crypto::Error> {
         let secret_key = match key_id {
@@ -385,18 +473,46 @@ impl Sign<PublicKey, SecretKey, Sha256dHash, Signature, EncryptedSignature> for
 
 #[cfg(feature = "experimental")]
 #[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
-impl ProveCrossGroupDleq<PublicKey, monero::PublicKey, DLEQProof> for KeyManager {
-    fn generate_proof(
+impl KeyManager {
+    /// Return the random number generator used to produce the cross group discrete logarithm
+    /// proof of the swap. The generator is seeded with `SHA256(tag | master_seed | swap_index)`,
+    /// where the swap index is serialized as a big endian hardened index, so re-generating the
+    /// proof for the same swap, e.g. after a crash, yields the exact same proof.
+    pub fn dleq_proof_rng(&self) -> ChaCha20Rng {
+        let mut engine = Sha256::new();
+        engine.update(DLEQ_PROOF_RNG_TAG);
+        engine.update(self.master_seed);
+        engine.update(u32::from(self.swap_index).to_be_bytes());
+        ChaCha20Rng::from_seed(engine.finalize().into())
+    }
+
+    /// Generate the cross group discrete logarithm proof with the provided random number
+    /// generator instead of the one derived from the seed and the swap index.
+    pub fn generate_proof_with_rng<R: RngCore + CryptoRng>(
         &mut self,
+        rng: &mut R,
     ) -> Result<(monero::PublicKey, PublicKey, DLEQProof), crypto::Error> {
         let spend = self.get_pubkey(AccordantKeyId::Spend)?;
         let encryption_key = self.get_encryption_key()?;
 
         let x = self.get_or_derive_monero_spend_key()?.to_bytes();
-        let proof = crypto::dleq::DLEQProof::generate(x);
+        let proof = crypto::dleq::DLEQProof::generate(x, rng)?;
 
         Ok((spend, encryption_key, proof))
     }
+}
+
+#[cfg(feature = "experimental")]
+#[cfg_attr(docsrs, doc(cfg(feature = "experimental")))]
+impl ProveCrossGroupDleq<PublicKey, monero::PublicKey, DLEQProof> for KeyManager {
+    /// Generate the proof deterministically from the master seed and the swap index, see
+    /// [`KeyManager::dleq_proof_rng`].
+    fn generate_proof(
+        &mut self,
+    ) -> Result<(monero::PublicKey, PublicKey, DLEQProof), crypto::Error> {
+        let mut rng = self.dleq_proof_rng();
+        self.generate_proof_with_rng(&mut rng)
+    }
 
     fn get_encryption_key(&mut self) -> Result<PublicKey, crypto::Error> {
         let secp = Secp256k1::new();
@@ -418,7 +534,7 @@ impl ProveCrossGroupDleq<PublicKey, monero::PublicKey, DLEQProof> for KeyManager
             public_spend
                 .point
                 .decompress()
-                .expect("Valid point to decompress"),
+                .ok_or(crypto::Error::InvalidPoint)?,
             ecdsa_fun::fun::Point::from(*encryption_key),
         )
     }
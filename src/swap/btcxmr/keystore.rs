@@ -0,0 +1,524 @@
+//! Encrypted and persistable storage of the [`KeyManager`] master seed.
+//!
+//! The master seed is encrypted with XChaCha20-Poly1305 under a key derived from a password with
+//! scrypt. The keystore also records the swap indexes already used to create key managers; these
+//! metadata are not secret but are authenticated with the ciphertext, altering them makes the
+//! keystore impossible to decrypt.
+//!
+//! ```
+//! use farcaster_core::swap::btcxmr::keystore::{Keystore, KeystoreParams};
+//!
+//! // weak parameters to keep the example fast, use `KeystoreParams::default()` instead
+//! let params = KeystoreParams::new(4, 8, 1).unwrap();
+//! let mut keystore = Keystore::new([1u8; 32], "password", params).unwrap();
+//! let _key_manager = keystore.key_manager("password", 0).unwrap();
+//! assert!(keystore.swap_indexes().contains(&0));
+//!
+//! let mut file = vec![];
+//! keystore.save(&mut file).unwrap();
+//! assert_eq!(Keystore::load(&mut file.as_slice()).unwrap(), keystore);
+//! ```
+
+use std::collections::BTreeSet;
+use std::io;
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::crypto;
+use crate::swap::btcxmr::KeyManager;
+
+/// Magic bytes at the start of a serialized [`Keystore`].
+pub const KEYSTORE_MAGIC_BYTES: &[u8; 6] = b"FCKEYS";
+
+/// Version of the [`Keystore`] serialization format.
+pub const KEYSTORE_VERSION: u16 = 1;
+
+// Size of the encrypted master seed with its authentication tag.
+const CIPHERTEXT_SIZE: usize = 32 + 16;
+
+/// Maximum memory used by the scrypt key derivation, i.e. `128 * r * 2^log_n` bytes, accepted in
+/// [`KeystoreParams`]: 1 GiB.
+pub const KEYSTORE_MAX_MEMORY: u64 = 1 << 30;
+
+/// Maximum amount of work of the scrypt key derivation, i.e. `2^log_n * r * p`, accepted in
+/// [`KeystoreParams`]: 64 times the work of the default parameters.
+pub const KEYSTORE_MAX_WORK: u64 = 1 << 24;
+
+/// Parameters of the scrypt key derivation, `2^log_n` is the CPU/memory cost, `r` the block size
+/// and `p` the parallelization. The parameters are bounded by [`KEYSTORE_MAX_MEMORY`] and
+/// [`KEYSTORE_MAX_WORK`] so that a crafted keystore file cannot exhaust the memory or the CPU.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeystoreParams {
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+impl KeystoreParams {
+    /// Create new key derivation parameters, returns an error if they are not valid scrypt
+    /// parameters or if they exceed the memory or work bounds.
+    pub fn new(log_n: u8, r: u32, p: u32) -> Result<Self, crypto::Error> {
+        scrypt::Params::new(log_n, r, p).map_err(|_| crypto::Error::InvalidKeystoreParameters)?;
+        let n = 1u64
+            .checked_shl(log_n as u32)
+            .ok_or(crypto::Error::InvalidKeystoreParameters)?;
+        let memory = n.checked_mul(128 * r as u64);
+        let work = n
+            .checked_mul(r as u64)
+            .and_then(|work| work.checked_mul(p as u64));
+        match (memory, work) {
+            (Some(memory), Some(work))
+                if memory <= KEYSTORE_MAX_MEMORY && work <= KEYSTORE_MAX_WORK =>
+            {
+                Ok(Self { log_n, r, p })
+            }
+            _ => Err(crypto::Error::InvalidKeystoreParameters),
+        }
+    }
+
+    /// The base 2 logarithm of the CPU/memory cost.
+    pub fn log_n(&self) -> u8 {
+        self.log_n
+    }
+
+    /// The block size.
+    pub fn r(&self) -> u32 {
+        self.r
+    }
+
+    /// The parallelization.
+    pub fn p(&self) -> u32 {
+        self.p
+    }
+}
+
+impl Default for KeystoreParams {
+    /// The recommended scrypt parameters for interactive logins, `log_n = 15, r = 8, p = 1`.
+    fn default() -> Self {
+        Self {
+            log_n: 15,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+impl Encodable for KeystoreParams {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = self.log_n.consensus_encode(s)?;
+        len += self.r.consensus_encode(s)?;
+        Ok(len + self.p.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for KeystoreParams {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let log_n = Decodable::consensus_decode(d)?;
+        let r = Decodable::consensus_decode(d)?;
+        let p = Decodable::consensus_decode(d)?;
+        Self::new(log_n, r, p).map_err(consensus::Error::new)
+    }
+}
+
+/// An encrypted master seed with the swap indexes already used to create key managers from it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Keystore {
+    params: KeystoreParams,
+    salt: [u8; 32],
+    nonce: [u8; 24],
+    swap_indexes: BTreeSet<u32>,
+    ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// Encrypt the master seed with a key derived from the password.
+    pub fn new(
+        seed: [u8; 32],
+        password: impl AsRef<[u8]>,
+        params: KeystoreParams,
+    ) -> Result<Self, crypto::Error> {
+        let seed = Zeroizing::new(seed);
+        let mut keystore = Keystore {
+            params,
+            salt: [0u8; 32],
+            nonce: [0u8; 24],
+            swap_indexes: BTreeSet::new(),
+            ciphertext: vec![],
+        };
+        rand::thread_rng().fill_bytes(&mut keystore.salt);
+        let key = keystore.derive_key(password.as_ref())?;
+        keystore.seal(&key, &seed)?;
+        Ok(keystore)
+    }
+
+    /// Decrypt the master seed, returns [`crypto::Error::KeystoreDecryption`] if the password is
+    /// wrong or the keystore has been altered. The returned seed is zeroized on drop.
+    pub fn seed(&self, password: impl AsRef<[u8]>) -> Result<Zeroizing<[u8; 32]>, crypto::Error> {
+        let key = self.derive_key(password.as_ref())?;
+        self.open(&key)
+    }
+
+    /// Create the key manager of a new swap and record its swap index in the keystore metadata,
+    /// returns [`crypto::Error::SwapIndexAlreadyUsed`] if the index is already used as two swaps
+    /// must never share their keys. The keystore must be saved again to persist the new index.
+    pub fn key_manager(
+        &mut self,
+        password: impl AsRef<[u8]>,
+        swap_index: u32,
+    ) -> Result<KeyManager, crypto::Error> {
+        if self.swap_indexes.contains(&swap_index) {
+            return Err(crypto::Error::SwapIndexAlreadyUsed(swap_index));
+        }
+        let key = self.derive_key(password.as_ref())?;
+        let seed = self.open(&key)?;
+        let key_manager = KeyManager::new(*seed, swap_index)?;
+        // the metadata are authenticated, re-encrypt the seed with the new index into a copy
+        let mut keystore = self.clone();
+        keystore.swap_indexes.insert(swap_index);
+        keystore.seal(&key, &seed)?;
+        *self = keystore;
+        Ok(key_manager)
+    }
+
+    /// Return the key manager of an already used swap index, e.g. to resume a swap, returns
+    /// [`crypto::Error::UnallocatedSwapIndex`] if the index is not used.
+    pub fn resume_key_manager(
+        &self,
+        password: impl AsRef<[u8]>,
+        swap_index: u32,
+    ) -> Result<KeyManager, crypto::Error> {
+        if !self.swap_indexes.contains(&swap_index) {
+            return Err(crypto::Error::UnallocatedSwapIndex(swap_index));
+        }
+        KeyManager::new(*self.seed(password)?, swap_index)
+    }
+
+    /// Re-encrypt the master seed under a new password, with a fresh salt and nonce. The keystore
+    /// is left unchanged if an error is returned.
+    pub fn change_password(
+        &mut self,
+        password: impl AsRef<[u8]>,
+        new_password: impl AsRef<[u8]>,
+    ) -> Result<(), crypto::Error> {
+        let seed = self.seed(password)?;
+        // derive and seal into a copy, the salt must not change unless the seed is sealed again
+        let mut keystore = self.clone();
+        rand::thread_rng().fill_bytes(&mut keystore.salt);
+        let key = keystore.derive_key(new_password.as_ref())?;
+        keystore.seal(&key, &seed)?;
+        *self = keystore;
+        Ok(())
+    }
+
+    /// The swap indexes already used to create key managers from this keystore.
+    pub fn swap_indexes(&self) -> &BTreeSet<u32> {
+        &self.swap_indexes
+    }
+
+    /// The key derivation parameters.
+    pub fn params(&self) -> KeystoreParams {
+        self.params
+    }
+
+    /// Write the keystore to `writer`, returns the number of bytes written.
+    pub fn save<W: io::Write>(&self, writer: &mut W) -> Result<usize, io::Error> {
+        self.consensus_encode(writer)
+    }
+
+    /// Read a keystore previously written with [`Keystore::save`].
+    pub fn load<R: io::Read>(reader: &mut R) -> Result<Self, consensus::Error> {
+        Self::consensus_decode(reader)
+    }
+
+    fn derive_key(&self, password: &[u8]) -> Result<Zeroizing<[u8; 32]>, crypto::Error> {
+        let params = scrypt::Params::new(self.params.log_n, self.params.r, self.params.p)
+            .map_err(|_| crypto::Error::InvalidKeystoreParameters)?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        scrypt::scrypt(password, &self.salt, &params, &mut key[..])
+            .map_err(|_| crypto::Error::InvalidKeystoreParameters)?;
+        Ok(key)
+    }
+
+    // The associated data of the ciphertext, i.e. everything serialized before it.
+    fn associated_data(&self) -> Vec<u8> {
+        let mut header = vec![];
+        self.encode_header(&mut header)
+            .expect("in-memory writers don't error");
+        header
+    }
+
+    fn seal(&mut self, key: &[u8; 32], seed: &[u8; 32]) -> Result<(), crypto::Error> {
+        rand::thread_rng().fill_bytes(&mut self.nonce);
+        let aad = self.associated_data();
+        self.ciphertext = XChaCha20Poly1305::new(&Key::from(*key))
+            .encrypt(
+                &XNonce::from(self.nonce),
+                Payload {
+                    msg: seed,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| crypto::Error::new("Keystore encryption failed"))?;
+        Ok(())
+    }
+
+    fn open(&self, key: &[u8; 32]) -> Result<Zeroizing<[u8; 32]>, crypto::Error> {
+        let plaintext = Zeroizing::new(
+            XChaCha20Poly1305::new(&Key::from(*key))
+                .decrypt(
+                    &XNonce::from(self.nonce),
+                    Payload {
+                        msg: &self.ciphertext,
+                        aad: &self.associated_data(),
+                    },
+                )
+                .map_err(|_| crypto::Error::KeystoreDecryption)?,
+        );
+        let mut seed = Zeroizing::new([0u8; 32]);
+        if plaintext.len() != seed.len() {
+            return Err(crypto::Error::KeystoreDecryption);
+        }
+        seed.copy_from_slice(&plaintext);
+        Ok(seed)
+    }
+
+    fn encode_header<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = KEYSTORE_MAGIC_BYTES.consensus_encode(s)?;
+        len += KEYSTORE_VERSION.consensus_encode(s)?;
+        len += self.params.consensus_encode(s)?;
+        len += self.salt.consensus_encode(s)?;
+        len += self.nonce.consensus_encode(s)?;
+        let swap_indexes: Vec<u32> = self.swap_indexes.iter().copied().collect();
+        Ok(len + swap_indexes.consensus_encode(s)?)
+    }
+}
+
+impl Encodable for Keystore {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let len = self.encode_header(s)?;
+        Ok(len + self.ciphertext.consensus_encode(s)?)
+    }
+}
+
+impl Decodable for Keystore {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let magic_bytes: [u8; 6] = Decodable::consensus_decode(d)?;
+        if magic_bytes != *KEYSTORE_MAGIC_BYTES {
+            return Err(consensus::Error::IncorrectMagicBytes);
+        }
+        if u16::consensus_decode(d)? != KEYSTORE_VERSION {
+            return Err(consensus::Error::ParseFailed(
+                "Unsupported keystore version",
+            ));
+        }
+        let params = Decodable::consensus_decode(d)?;
+        let salt = Decodable::consensus_decode(d)?;
+        let nonce = Decodable::consensus_decode(d)?;
+        let indexes: Vec<u32> = Decodable::consensus_decode(d)?;
+        // indexes are strictly increasing for the encoding to be unique
+        if indexes.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(consensus::Error::ParseFailed(
+                "Keystore swap indexes are not sorted",
+            ));
+        }
+        let ciphertext: Vec<u8> = Decodable::consensus_decode(d)?;
+        if ciphertext.len() != CIPHERTEXT_SIZE {
+            return Err(consensus::Error::ParseFailed(
+                "Invalid keystore ciphertext size",
+            ));
+        }
+        Ok(Keystore {
+            params,
+            salt,
+            nonce,
+            swap_indexes: indexes.into_iter().collect(),
+            ciphertext,
+        })
+    }
+}
+
+impl CanonicalBytes for Keystore {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        consensus::serialize(self)
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        consensus::deserialize(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{ArbitratingKeyId, GenerateKey};
+
+    const SEED: [u8; 32] = [0x42; 32];
+
+    fn params() -> KeystoreParams {
+        KeystoreParams::new(4, 8, 1).unwrap()
+    }
+
+    #[test]
+    fn keystore_decrypts_seed() {
+        let keystore = Keystore::new(SEED, "password", params()).unwrap();
+        assert_eq!(*keystore.seed("password").unwrap(), SEED);
+        assert!(matches!(
+            keystore.seed("wrong password"),
+            Err(crypto::Error::KeystoreDecryption)
+        ));
+        // salt and nonce are random
+        let other = Keystore::new(SEED, "password", params()).unwrap();
+        assert_ne!(keystore.ciphertext, other.ciphertext);
+    }
+
+    #[test]
+    fn keystore_creates_key_managers() {
+        let mut keystore = Keystore::new(SEED, "password", params()).unwrap();
+        assert!(keystore.swap_indexes().is_empty());
+
+        let mut key_manager = keystore.key_manager("password", 3).unwrap();
+        let mut expected = KeyManager::new(SEED, 3).unwrap();
+        assert_eq!(
+            key_manager.get_pubkey(ArbitratingKeyId::Lock).unwrap(),
+            expected.get_pubkey(ArbitratingKeyId::Lock).unwrap()
+        );
+        keystore.key_manager("password", 1).unwrap();
+        // a swap index cannot be used twice, its key manager is resumed instead
+        let before = keystore.clone();
+        assert!(matches!(
+            keystore.key_manager("password", 3),
+            Err(crypto::Error::SwapIndexAlreadyUsed(3))
+        ));
+        assert_eq!(keystore, before);
+        let mut resumed = keystore.resume_key_manager("password", 3).unwrap();
+        assert_eq!(
+            resumed.get_pubkey(ArbitratingKeyId::Lock).unwrap(),
+            expected.get_pubkey(ArbitratingKeyId::Lock).unwrap()
+        );
+        assert!(matches!(
+            keystore.resume_key_manager("password", 2),
+            Err(crypto::Error::UnallocatedSwapIndex(2))
+        ));
+        assert_eq!(
+            keystore.swap_indexes().iter().copied().collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(*keystore.seed("password").unwrap(), SEED);
+
+        assert!(matches!(
+            keystore.key_manager("wrong password", 2),
+            Err(crypto::Error::KeystoreDecryption)
+        ));
+        assert!(keystore.key_manager("password", u32::MAX).is_err());
+        assert!(!keystore.swap_indexes().contains(&2));
+        assert!(!keystore.swap_indexes().contains(&u32::MAX));
+    }
+
+    #[test]
+    fn keystore_changes_password() {
+        let mut keystore = Keystore::new(SEED, "password", params()).unwrap();
+        keystore.key_manager("password", 0).unwrap();
+        let before = keystore.clone();
+        assert!(keystore.change_password("wrong password", "new").is_err());
+        assert_eq!(keystore, before);
+
+        keystore.change_password("password", "new").unwrap();
+        assert!(matches!(
+            keystore.seed("password"),
+            Err(crypto::Error::KeystoreDecryption)
+        ));
+        assert_eq!(*keystore.seed("new").unwrap(), SEED);
+        assert!(keystore.swap_indexes().contains(&0));
+    }
+
+    #[test]
+    fn keystore_params_are_bounded() {
+        assert!(KeystoreParams::new(15, 8, 1).is_ok());
+        // 1 GiB of memory
+        assert!(KeystoreParams::new(20, 8, 1).is_ok());
+        assert!(KeystoreParams::new(21, 8, 1).is_err());
+        assert!(KeystoreParams::new(19, 32, 1).is_err());
+        // 64 times the default work
+        assert!(KeystoreParams::new(15, 8, 64).is_ok());
+        assert!(KeystoreParams::new(15, 8, 65).is_err());
+        assert!(KeystoreParams::new(4, 8, u32::MAX).is_err());
+    }
+
+    #[test]
+    fn keystore_save_and_load() {
+        let mut keystore = Keystore::new(SEED, "password", params()).unwrap();
+        keystore.key_manager("password", 7).unwrap();
+
+        let mut file = vec![];
+        let len = keystore.save(&mut file).unwrap();
+        assert_eq!(len, file.len());
+        assert_eq!(&file[..6], KEYSTORE_MAGIC_BYTES);
+        let loaded = Keystore::load(&mut file.as_slice()).unwrap();
+        assert_eq!(loaded, keystore);
+        assert_eq!(*loaded.seed("password").unwrap(), SEED);
+        assert_eq!(
+            Keystore::from_canonical_bytes(&keystore.as_canonical_bytes()).unwrap(),
+            keystore
+        );
+
+        // wrong magic bytes
+        let mut invalid = file.clone();
+        invalid[0] = b'X';
+        assert!(matches!(
+            Keystore::load(&mut invalid.as_slice()),
+            Err(consensus::Error::IncorrectMagicBytes)
+        ));
+        // unsupported version
+        let mut invalid = file.clone();
+        invalid[6] = 0x02;
+        assert!(Keystore::load(&mut invalid.as_slice()).is_err());
+        // invalid key derivation parameters, the block size is zero
+        let mut invalid = file.clone();
+        invalid[9] = 0;
+        assert!(Keystore::load(&mut invalid.as_slice()).is_err());
+        // valid scrypt parameters exceeding the bounds
+        let mut invalid = file.clone();
+        invalid[8] = 40;
+        assert!(Keystore::load(&mut invalid.as_slice()).is_err());
+        // truncated
+        assert!(Keystore::load(&mut &file[..file.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn altered_keystore_does_not_decrypt() {
+        let mut keystore = Keystore::new(SEED, "password", params()).unwrap();
+        keystore.key_manager("password", 7).unwrap();
+        let file = keystore.as_canonical_bytes();
+
+        // swap index metadata is authenticated
+        let mut altered = Keystore::from_canonical_bytes(&file).unwrap();
+        altered.swap_indexes.insert(8);
+        assert!(matches!(
+            altered.seed("password"),
+            Err(crypto::Error::KeystoreDecryption)
+        ));
+        let mut altered = Keystore::from_canonical_bytes(&file).unwrap();
+        altered.swap_indexes.clear();
+        assert!(matches!(
+            altered.seed("password"),
+            Err(crypto::Error::KeystoreDecryption)
+        ));
+
+        // ciphertext
+        let mut altered = file;
+        let last = altered.len() - 1;
+        altered[last] ^= 0x01;
+        let altered = Keystore::from_canonical_bytes(&altered).unwrap();
+        assert!(matches!(
+            altered.seed("password"),
+            Err(crypto::Error::KeystoreDecryption)
+        ));
+    }
+}
@@ -0,0 +1,513 @@
+//! Allocation of swap indexes to protect against key reuse across swaps.
+//!
+//! All the keys of a swap are derived from the master seed and the swap index, two swaps running
+//! with the same index share their adaptor and spend keys. The [`SwapIndexRegistry`] is bound to a
+//! master seed, hands out fresh indexes, persists which ones are used, detects reuse and maps the
+//! used indexes to their [`SwapId`].
+//!
+//! When the master seed is stored in a [`Keystore`], which also refuses to reuse a swap index, the
+//! keystore is the source of truth: allocate the indexes with
+//! [`SwapIndexRegistry::allocate_with_keystore`] and [`SwapIndexRegistry::reserve_with_keystore`]
+//! which record them in the keystore and pick up the indexes the keystore used on its own.
+//!
+//! ```
+//! use farcaster_core::swap::btcxmr::registry::SwapIndexRegistry;
+//! use farcaster_core::swap::SwapId;
+//!
+//! let seed = [1u8; 32];
+//! let mut registry = SwapIndexRegistry::new(&seed);
+//! let (index, _key_manager) = registry.allocate(&seed).unwrap();
+//! registry.bind(index, SwapId::repeat_byte(0x01)).unwrap();
+//!
+//! // the index cannot be allocated again
+//! assert!(registry.reserve(&seed, index).is_err());
+//! ```
+
+use std::collections::BTreeMap;
+use std::io;
+
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::Address;
+
+use crate::bitcoin::segwitv0::funding::Funding;
+use crate::blockchain::Network;
+use crate::consensus::{self, CanonicalBytes, Decodable, Encodable};
+use crate::crypto::slip10::Secp256k1ExtSecretKey;
+use crate::crypto::{self, ArbitratingKeyId, GenerateKey};
+use crate::swap::btcxmr::keystore::Keystore;
+use crate::swap::btcxmr::KeyManager;
+use crate::swap::SwapId;
+use crate::transaction::Fundable;
+
+/// Magic bytes at the start of a serialized [`SwapIndexRegistry`].
+pub const SWAP_INDEX_REGISTRY_MAGIC_BYTES: &[u8; 6] = b"FCSIDX";
+
+/// The largest swap index, swap indexes are used as hardened derivation indexes.
+pub const MAX_SWAP_INDEX: u32 = (1 << 31) - 1;
+
+/// Registry of the swap indexes used with a master seed and the swaps they are bound to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwapIndexRegistry {
+    seed_identifier: [u8; 20],
+    swaps: BTreeMap<u32, Option<SwapId>>,
+}
+
+impl SwapIndexRegistry {
+    /// Create an empty registry bound to the master seed. The registry only stores the identifier
+    /// of the Bitcoin master key derived from the seed.
+    pub fn new(seed: &[u8; 32]) -> Self {
+        Self {
+            seed_identifier: seed_identifier(seed),
+            swaps: BTreeMap::new(),
+        }
+    }
+
+    /// Create a registry bound to the master seed of the keystore with the swap indexes already
+    /// used by the keystore, returns an error if the password is wrong.
+    pub fn from_keystore(
+        keystore: &Keystore,
+        password: impl AsRef<[u8]>,
+    ) -> Result<Self, crypto::Error> {
+        let seed = keystore.seed(password)?;
+        Ok(Self {
+            seed_identifier: seed_identifier(&seed),
+            swaps: keystore
+                .swap_indexes()
+                .iter()
+                .map(|index| (*index, None))
+                .collect(),
+        })
+    }
+
+    /// Allocate the lowest unused swap index and return it with its key manager. Use
+    /// [`SwapIndexRegistry::allocate_with_keystore`] instead if the seed is stored in a
+    /// [`Keystore`].
+    pub fn allocate(&mut self, seed: &[u8; 32]) -> Result<(u32, KeyManager), crypto::Error> {
+        self.check_seed(seed)?;
+        let index = self.lowest_unused_index()?;
+        let key_manager = KeyManager::new(*seed, index)?;
+        self.swaps.insert(index, None);
+        Ok((index, key_manager))
+    }
+
+    /// Allocate the lowest swap index unused by the registry and the keystore, record it in both
+    /// and return it with its key manager. The keystore must be saved again to persist the new
+    /// index.
+    pub fn allocate_with_keystore(
+        &mut self,
+        keystore: &mut Keystore,
+        password: impl AsRef<[u8]>,
+    ) -> Result<(u32, KeyManager), crypto::Error> {
+        self.sync_keystore(keystore, password.as_ref())?;
+        let index = self.lowest_unused_index()?;
+        let key_manager = keystore.key_manager(password, index)?;
+        self.swaps.insert(index, None);
+        Ok((index, key_manager))
+    }
+
+    /// Mark a specific swap index as used and return its key manager, returns an error if the
+    /// index is already used. Use [`SwapIndexRegistry::reserve_with_keystore`] instead if the seed
+    /// is stored in a [`Keystore`].
+    pub fn reserve(&mut self, seed: &[u8; 32], index: u32) -> Result<KeyManager, crypto::Error> {
+        self.check_seed(seed)?;
+        if self.swaps.contains_key(&index) {
+            return Err(crypto::Error::SwapIndexAlreadyUsed(index));
+        }
+        let key_manager = KeyManager::new(*seed, index)?;
+        self.swaps.insert(index, None);
+        Ok(key_manager)
+    }
+
+    /// Mark a specific swap index as used in the registry and the keystore and return its key
+    /// manager, returns an error if the index is already used by either of them. The keystore must
+    /// be saved again to persist the new index.
+    pub fn reserve_with_keystore(
+        &mut self,
+        keystore: &mut Keystore,
+        password: impl AsRef<[u8]>,
+        index: u32,
+    ) -> Result<KeyManager, crypto::Error> {
+        self.sync_keystore(keystore, password.as_ref())?;
+        if self.swaps.contains_key(&index) {
+            return Err(crypto::Error::SwapIndexAlreadyUsed(index));
+        }
+        let key_manager = keystore.key_manager(password, index)?;
+        self.swaps.insert(index, None);
+        Ok(key_manager)
+    }
+
+    /// Return the key manager of an already used swap index, e.g. to resume a swap.
+    pub fn key_manager(&self, seed: &[u8; 32], index: u32) -> Result<KeyManager, crypto::Error> {
+        self.check_seed(seed)?;
+        if !self.swaps.contains_key(&index) {
+            return Err(crypto::Error::UnallocatedSwapIndex(index));
+        }
+        KeyManager::new(*seed, index)
+    }
+
+    /// Bind a used swap index to a swap. Binding the same swap again is a no-op, returns an error
+    /// if the index is bound to another swap or the swap to another index.
+    pub fn bind(&mut self, index: u32, swap_id: SwapId) -> Result<(), crypto::Error> {
+        if let Some(other) = self.swap_index(&swap_id) {
+            if other != index {
+                return Err(crypto::Error::SwapIdAlreadyBound(other));
+            }
+        }
+        match self.swaps.get_mut(&index) {
+            None => Err(crypto::Error::UnallocatedSwapIndex(index)),
+            Some(Some(bound)) if *bound != swap_id => {
+                Err(crypto::Error::SwapIndexAlreadyUsed(index))
+            }
+            Some(entry) => {
+                *entry = Some(swap_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Return `true` if the swap index is already used.
+    pub fn is_used(&self, index: u32) -> bool {
+        self.swaps.contains_key(&index)
+    }
+
+    /// The used swap indexes in increasing order.
+    pub fn used_indexes(&self) -> impl Iterator<Item = u32> + '_ {
+        self.swaps.keys().copied()
+    }
+
+    /// Return the swap bound to the swap index, if any.
+    pub fn swap_id(&self, index: u32) -> Option<SwapId> {
+        self.swaps.get(&index).copied().flatten()
+    }
+
+    /// Return the swap index bound to the swap, if any.
+    pub fn swap_index(&self, swap_id: &SwapId) -> Option<u32> {
+        self.swaps
+            .iter()
+            .find(|(_, bound)| bound.as_ref() == Some(swap_id))
+            .map(|(index, _)| *index)
+    }
+
+    /// Re-derive the funding addresses of the first `count` swap indexes, used to find the swaps
+    /// funded with the master seed when the registry is lost. Found indexes should then be
+    /// [`reserve`]d in a new registry.
+    ///
+    /// [`reserve`]: SwapIndexRegistry::reserve
+    pub fn recovery_scan(
+        seed: &[u8; 32],
+        count: u32,
+        network: Network,
+    ) -> Result<Vec<(u32, Address)>, crypto::Error> {
+        (0..count.min(MAX_SWAP_INDEX + 1))
+            .map(|index| {
+                let mut key_manager = KeyManager::new(*seed, index)?;
+                let pubkey = key_manager.get_pubkey(ArbitratingKeyId::Lock)?;
+                let address = Funding::initialize(pubkey, network)
+                    .and_then(|funding| funding.get_address())
+                    .map_err(crypto::Error::new)?;
+                Ok((index, address))
+            })
+            .collect()
+    }
+
+    fn lowest_unused_index(&self) -> Result<u32, crypto::Error> {
+        let index = (0..=MAX_SWAP_INDEX)
+            .zip(self.swaps.keys())
+            .find(|(expected, used)| expected != *used)
+            .map_or(self.swaps.len() as u32, |(expected, _)| expected);
+        if index > MAX_SWAP_INDEX {
+            return Err(crypto::Error::SwapIndexesExhausted);
+        }
+        Ok(index)
+    }
+
+    // Mark as used the swap indexes used by the keystore, e.g. with `Keystore::key_manager`, since
+    // the registry was created.
+    fn sync_keystore(&mut self, keystore: &Keystore, password: &[u8]) -> Result<(), crypto::Error> {
+        self.check_seed(&*keystore.seed(password)?)?;
+        for index in keystore.swap_indexes() {
+            self.swaps.entry(*index).or_insert(None);
+        }
+        Ok(())
+    }
+
+    fn check_seed(&self, seed: &[u8; 32]) -> Result<(), crypto::Error> {
+        if seed_identifier(seed) != self.seed_identifier {
+            return Err(crypto::Error::SeedMismatch);
+        }
+        Ok(())
+    }
+}
+
+// The HASH160 of the Bitcoin master public key derived from the seed.
+fn seed_identifier(seed: &[u8; 32]) -> [u8; 20] {
+    let secp = Secp256k1::new();
+    let mut identifier = [0u8; 20];
+    identifier
+        .copy_from_slice(&Secp256k1ExtSecretKey::new_master(seed.as_ref()).identifier(&secp)[..]);
+    identifier
+}
+
+impl Encodable for SwapIndexRegistry {
+    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
+        let mut len = SWAP_INDEX_REGISTRY_MAGIC_BYTES.consensus_encode(s)?;
+        len += self.seed_identifier.consensus_encode(s)?;
+        // the number of indexes is not limited to u16::MAX as vectors are
+        len += (self.swaps.len() as u32).consensus_encode(s)?;
+        for (index, swap_id) in self.swaps.iter() {
+            len += index.consensus_encode(s)?;
+            len += match swap_id {
+                Some(swap_id) => 0x01u8.consensus_encode(s)? + swap_id.consensus_encode(s)?,
+                None => 0x00u8.consensus_encode(s)?,
+            };
+        }
+        Ok(len)
+    }
+}
+
+impl Decodable for SwapIndexRegistry {
+    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
+        let magic_bytes: [u8; 6] = Decodable::consensus_decode(d)?;
+        if magic_bytes != *SWAP_INDEX_REGISTRY_MAGIC_BYTES {
+            return Err(consensus::Error::IncorrectMagicBytes);
+        }
+        let seed_identifier = Decodable::consensus_decode(d)?;
+        let len = u32::consensus_decode(d)?;
+        let mut registry = SwapIndexRegistry {
+            seed_identifier,
+            swaps: BTreeMap::new(),
+        };
+        let mut previous = None;
+        for _ in 0..len {
+            let index = u32::consensus_decode(d)?;
+            // indexes are strictly increasing for the encoding to be unique
+            if index > MAX_SWAP_INDEX || previous.map_or(false, |previous| index <= previous) {
+                return Err(consensus::Error::ParseFailed("Invalid swap index"));
+            }
+            previous = Some(index);
+            let swap_id = match u8::consensus_decode(d)? {
+                0x00 => None,
+                0x01 => Some(SwapId::consensus_decode(d)?),
+                _ => return Err(consensus::Error::UnknownType),
+            };
+            if let Some(swap_id) = swap_id {
+                if registry.swap_index(&swap_id).is_some() {
+                    return Err(consensus::Error::ParseFailed(
+                        "Swap id bound to multiple swap indexes",
+                    ));
+                }
+            }
+            registry.swaps.insert(index, swap_id);
+        }
+        Ok(registry)
+    }
+}
+
+impl CanonicalBytes for SwapIndexRegistry {
+    fn as_canonical_bytes(&self) -> Vec<u8> {
+        consensus::serialize(self)
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, consensus::Error>
+    where
+        Self: Sized,
+    {
+        consensus::deserialize(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: [u8; 32] = [0x42; 32];
+
+    #[test]
+    fn allocate_lowest_unused_indexes() {
+        let mut registry = SwapIndexRegistry::new(&SEED);
+        assert_eq!(registry.allocate(&SEED).unwrap().0, 0);
+        assert_eq!(registry.allocate(&SEED).unwrap().0, 1);
+        registry.reserve(&SEED, 3).unwrap();
+        assert_eq!(registry.allocate(&SEED).unwrap().0, 2);
+        assert_eq!(registry.allocate(&SEED).unwrap().0, 4);
+        assert_eq!(
+            registry.used_indexes().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4]
+        );
+
+        let (index, mut key_manager) = registry.allocate(&SEED).unwrap();
+        let mut expected = KeyManager::new(SEED, index).unwrap();
+        assert_eq!(
+            key_manager.get_pubkey(ArbitratingKeyId::Lock).unwrap(),
+            expected.get_pubkey(ArbitratingKeyId::Lock).unwrap()
+        );
+        let mut resumed = registry.key_manager(&SEED, index).unwrap();
+        assert_eq!(
+            resumed.get_pubkey(ArbitratingKeyId::Lock).unwrap(),
+            expected.get_pubkey(ArbitratingKeyId::Lock).unwrap()
+        );
+    }
+
+    #[test]
+    fn detect_swap_index_reuse() {
+        let mut registry = SwapIndexRegistry::new(&SEED);
+        let (index, _) = registry.allocate(&SEED).unwrap();
+        assert!(matches!(
+            registry.reserve(&SEED, index),
+            Err(crypto::Error::SwapIndexAlreadyUsed(0))
+        ));
+        assert!(matches!(
+            registry.key_manager(&SEED, 1),
+            Err(crypto::Error::UnallocatedSwapIndex(1))
+        ));
+        assert!(registry.reserve(&SEED, MAX_SWAP_INDEX + 1).is_err());
+        assert!(!registry.is_used(MAX_SWAP_INDEX + 1));
+
+        // the registry is bound to its seed
+        let other_seed = [0x43; 32];
+        assert!(matches!(
+            registry.allocate(&other_seed),
+            Err(crypto::Error::SeedMismatch)
+        ));
+        assert!(matches!(
+            registry.reserve(&other_seed, 1),
+            Err(crypto::Error::SeedMismatch)
+        ));
+        assert!(matches!(
+            registry.key_manager(&other_seed, index),
+            Err(crypto::Error::SeedMismatch)
+        ));
+    }
+
+    #[test]
+    fn registry_from_keystore() {
+        use crate::swap::btcxmr::keystore::KeystoreParams;
+
+        let mut keystore =
+            Keystore::new(SEED, "password", KeystoreParams::new(4, 8, 1).unwrap()).unwrap();
+        keystore.key_manager("password", 0).unwrap();
+        keystore.key_manager("password", 2).unwrap();
+        assert!(SwapIndexRegistry::from_keystore(&keystore, "wrong password").is_err());
+
+        let mut registry = SwapIndexRegistry::from_keystore(&keystore, "password").unwrap();
+        assert_eq!(registry.used_indexes().collect::<Vec<_>>(), vec![0, 2]);
+        assert!(registry.reserve(&SEED, 2).is_err());
+
+        // indexes allocated by the registry are recorded in the keystore
+        let (index, _) = registry
+            .allocate_with_keystore(&mut keystore, "password")
+            .unwrap();
+        assert_eq!(index, 1);
+        assert!(keystore.swap_indexes().contains(&1));
+        assert!(matches!(
+            keystore.key_manager("password", 1),
+            Err(crypto::Error::SwapIndexAlreadyUsed(1))
+        ));
+        registry
+            .reserve_with_keystore(&mut keystore, "password", 5)
+            .unwrap();
+        assert!(keystore.swap_indexes().contains(&5));
+
+        // indexes used by the keystore on its own are never allocated by the registry
+        keystore.key_manager("password", 3).unwrap();
+        assert!(matches!(
+            registry.reserve_with_keystore(&mut keystore, "password", 3),
+            Err(crypto::Error::SwapIndexAlreadyUsed(3))
+        ));
+        let (index, _) = registry
+            .allocate_with_keystore(&mut keystore, "password")
+            .unwrap();
+        assert_eq!(index, 4);
+        assert_eq!(
+            registry.used_indexes().collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            keystore.swap_indexes().iter().copied().collect::<Vec<_>>(),
+            registry.used_indexes().collect::<Vec<_>>()
+        );
+
+        // the registry is bound to the keystore seed
+        let mut other = Keystore::new(
+            [0x43; 32],
+            "password",
+            KeystoreParams::new(4, 8, 1).unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(
+            registry.allocate_with_keystore(&mut other, "password"),
+            Err(crypto::Error::SeedMismatch)
+        ));
+    }
+
+    #[test]
+    fn bind_swap_ids() {
+        let mut registry = SwapIndexRegistry::new(&SEED);
+        let (first, _) = registry.allocate(&SEED).unwrap();
+        let (second, _) = registry.allocate(&SEED).unwrap();
+        let swap_id = SwapId::repeat_byte(0x01);
+
+        assert!(matches!(
+            registry.bind(2, swap_id),
+            Err(crypto::Error::UnallocatedSwapIndex(2))
+        ));
+        registry.bind(first, swap_id).unwrap();
+        registry.bind(first, swap_id).unwrap();
+        assert_eq!(registry.swap_id(first), Some(swap_id));
+        assert_eq!(registry.swap_index(&swap_id), Some(first));
+        assert_eq!(registry.swap_id(second), None);
+
+        assert!(matches!(
+            registry.bind(first, SwapId::repeat_byte(0x02)),
+            Err(crypto::Error::SwapIndexAlreadyUsed(0))
+        ));
+        assert!(matches!(
+            registry.bind(second, swap_id),
+            Err(crypto::Error::SwapIdAlreadyBound(0))
+        ));
+    }
+
+    #[test]
+    fn registry_serialization() {
+        let mut registry = SwapIndexRegistry::new(&SEED);
+        let (index, _) = registry.allocate(&SEED).unwrap();
+        registry.bind(index, SwapId::repeat_byte(0x01)).unwrap();
+        registry.reserve(&SEED, 7).unwrap();
+
+        let bytes = registry.as_canonical_bytes();
+        assert_eq!(&bytes[..6], SWAP_INDEX_REGISTRY_MAGIC_BYTES);
+        let mut decoded = SwapIndexRegistry::from_canonical_bytes(&bytes).unwrap();
+        assert_eq!(decoded, registry);
+        assert!(decoded.reserve(&SEED, 7).is_err());
+
+        // unsorted indexes, the index 7 is encoded at offset 6 + 20 + 4 + 4 + 1 + 32
+        let mut invalid = bytes.clone();
+        invalid[67..71].copy_from_slice(&0u32.to_le_bytes());
+        assert!(SwapIndexRegistry::from_canonical_bytes(&invalid).is_err());
+        // out of range index
+        invalid[67..71].copy_from_slice(&(MAX_SWAP_INDEX + 1).to_le_bytes());
+        assert!(SwapIndexRegistry::from_canonical_bytes(&invalid).is_err());
+        // truncated
+        assert!(SwapIndexRegistry::from_canonical_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn recovery_scan_rederives_funding_addresses() {
+        let addresses = SwapIndexRegistry::recovery_scan(&SEED, 3, Network::Testnet).unwrap();
+        assert_eq!(addresses.len(), 3);
+        for (expected_index, (index, address)) in addresses.iter().enumerate() {
+            assert_eq!(*index, expected_index as u32);
+            let mut key_manager = KeyManager::new(SEED, *index).unwrap();
+            let pubkey = key_manager.get_pubkey(ArbitratingKeyId::Lock).unwrap();
+            assert_eq!(
+                *address,
+                Address::p2wpkh(
+                    &bitcoin::util::ecdsa::PublicKey::new(pubkey),
+                    bitcoin::Network::Testnet
+                )
+                .unwrap()
+            );
+        }
+        assert_ne!(addresses[0].1, addresses[1].1);
+    }
+}
@@ -3,11 +3,10 @@
 
 use std::error;
 use std::fmt;
-use std::io;
 
 use thiserror::Error;
 
-use crate::consensus::{self, Decodable, Encodable};
+use crate::consensus::{Decodable, Encodable};
 
 /// Errors encountered when manipulating tasks in syncers. [`Self::Other`] can carry out errors
 /// from external sources.
@@ -62,34 +61,20 @@ pub trait Syncer {
     fn broadcast_transaction(&mut self, task: BroadcastTransaction) -> Result<(), Error>;
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct Abort {
     pub id: i32,
 }
 
-impl Encodable for Abort {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        self.id.consensus_encode(s)
-    }
-}
-
-impl Decodable for Abort {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(Abort);
-
 impl fmt::Display for Abort {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "abort id {}", self.id)
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct WatchHeight {
     pub id: i32,
     pub lifetime: u64,
@@ -100,33 +85,14 @@ pub struct WatchHeight {
     pub addendum: Vec<u8>,
 }
 
-impl Encodable for WatchHeight {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.id.consensus_encode(s)?;
-        len += self.lifetime.consensus_encode(s)?;
-        Ok(len + self.addendum.consensus_encode(s)?)
-    }
-}
-
-impl Decodable for WatchHeight {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-            lifetime: u64::consensus_decode(d)?,
-            addendum: Vec::<u8>::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(WatchHeight);
-
 impl fmt::Display for WatchHeight {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "watchheight")
     }
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct WatchAddress {
     pub id: i32,
     pub lifetime: u64,
@@ -134,61 +100,21 @@ pub struct WatchAddress {
     pub include_tx: Boolean,
 }
 
-impl Encodable for WatchAddress {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.id.consensus_encode(s)?;
-        len += self.lifetime.consensus_encode(s)?;
-        len += self.addendum.consensus_encode(s)?;
-        Ok(len + self.include_tx.consensus_encode(s)?)
-    }
-}
-
-impl Decodable for WatchAddress {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-            lifetime: u64::consensus_decode(d)?,
-            addendum: Vec::<u8>::consensus_decode(d)?,
-            include_tx: Boolean::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(WatchAddress);
-
 impl fmt::Display for WatchAddress {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "watchaddress")
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub enum Boolean {
+    #[consensus(tag = 0x01)]
     True,
+    #[consensus(tag = 0x00)]
     False,
 }
 
-impl Encodable for Boolean {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        match self {
-            Boolean::True => Ok(0x01u8.consensus_encode(s)?),
-            Boolean::False => Ok(0x00u8.consensus_encode(s)?),
-        }
-    }
-}
-
-impl Decodable for Boolean {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        match Decodable::consensus_decode(d)? {
-            0x01u8 => Ok(Boolean::True),
-            0x00u8 => Ok(Boolean::False),
-            _ => Err(consensus::Error::UnknownType),
-        }
-    }
-}
-
-impl_strict_encoding!(Boolean);
-
 impl From<Boolean> for bool {
     fn from(w: Boolean) -> bool {
         match w {
@@ -198,7 +124,8 @@ impl From<Boolean> for bool {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct WatchTransaction {
     pub id: i32,
     pub lifetime: u64,
@@ -206,58 +133,19 @@ pub struct WatchTransaction {
     pub confirmation_bound: u16,
 }
 
-impl Encodable for WatchTransaction {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.id.consensus_encode(s)?;
-        len += self.lifetime.consensus_encode(s)?;
-        len += self.hash.consensus_encode(s)?;
-        Ok(len + self.confirmation_bound.consensus_encode(s)?)
-    }
-}
-
-impl Decodable for WatchTransaction {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-            lifetime: u64::consensus_decode(d)?,
-            hash: Vec::<u8>::consensus_decode(d)?,
-            confirmation_bound: u16::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(WatchTransaction);
-
 impl fmt::Display for WatchTransaction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "watchtransaction")
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct BroadcastTransaction {
     pub id: i32,
     pub tx: Vec<u8>,
 }
 
-impl Encodable for BroadcastTransaction {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let len = self.id.consensus_encode(s)?;
-        Ok(len + self.tx.consensus_encode(s)?)
-    }
-}
-
-impl Decodable for BroadcastTransaction {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-            tx: Vec::<u8>::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(BroadcastTransaction);
-
 impl fmt::Display for BroadcastTransaction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "broadcasttransaction")
@@ -266,122 +154,51 @@ impl fmt::Display for BroadcastTransaction {
 
 /// Tasks created by the daemon and handle by syncers to process a blockchain and generate
 /// [`Event`] back to the syncer.
-#[derive(Debug, Clone, Display)]
+#[derive(Debug, Clone, Display, Encodable, Decodable)]
 #[display(Debug)]
+#[consensus(strict_encoding)]
 pub enum Task {
+    #[consensus(tag = 0x01)]
     Abort(Abort),
+    #[consensus(tag = 0x02)]
     WatchHeight(WatchHeight),
+    #[consensus(tag = 0x03)]
     WatchAddress(WatchAddress),
+    #[consensus(tag = 0x04)]
     WatchTransaction(WatchTransaction),
+    #[consensus(tag = 0x05)]
     BroadcastTransaction(BroadcastTransaction),
 }
 
-impl Encodable for Task {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        match self {
-            Task::Abort(t) => {
-                let len = 0x01u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-            Task::WatchHeight(t) => {
-                let len = 0x02u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-            Task::WatchAddress(t) => {
-                let len = 0x03u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-            Task::WatchTransaction(t) => {
-                let len = 0x04u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-            Task::BroadcastTransaction(t) => {
-                let len = 0x05u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-        }
-    }
-}
-
-impl Decodable for Task {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        match Decodable::consensus_decode(d)? {
-            0x01u8 => Ok(Task::Abort(Decodable::consensus_decode(d)?)),
-            0x02u8 => Ok(Task::WatchHeight(Decodable::consensus_decode(d)?)),
-            0x03u8 => Ok(Task::WatchAddress(Decodable::consensus_decode(d)?)),
-            0x04u8 => Ok(Task::WatchTransaction(Decodable::consensus_decode(d)?)),
-            0x05u8 => Ok(Task::BroadcastTransaction(Decodable::consensus_decode(d)?)),
-            _ => Err(consensus::Error::UnknownType),
-        }
-    }
-}
-
-impl_strict_encoding!(Task);
-
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct TaskAborted {
     pub id: i32,
     pub success_abort: i32,
 }
 
-impl Encodable for TaskAborted {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let len = self.id.consensus_encode(s)?;
-        Ok(len + self.success_abort.consensus_encode(s)?)
-    }
-}
-
-impl Decodable for TaskAborted {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-            success_abort: i32::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(TaskAborted);
-
 impl fmt::Display for TaskAborted {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "taskaborted id {}", 32)
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct HeightChanged {
     pub id: i32,
     pub block: Vec<u8>,
     pub height: u64,
 }
 
-impl Encodable for HeightChanged {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.id.consensus_encode(s)?;
-        len += self.block.consensus_encode(s)?;
-        Ok(len + self.height.consensus_encode(s)?)
-    }
-}
-
-impl Decodable for HeightChanged {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-            block: Vec::<u8>::consensus_decode(d)?,
-            height: u64::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(HeightChanged);
-
 impl fmt::Display for HeightChanged {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "heightchanged")
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct AddressTransaction {
     pub id: i32,
     pub hash: Vec<u8>,
@@ -390,70 +207,28 @@ pub struct AddressTransaction {
     pub tx: Vec<u8>,
 }
 
-impl Encodable for AddressTransaction {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.id.consensus_encode(s)?;
-        len += self.hash.consensus_encode(s)?;
-        len += self.amount.consensus_encode(s)?;
-        len += self.block.consensus_encode(s)?;
-        Ok(len + self.tx.consensus_encode(s)?)
-    }
-}
-
-impl Decodable for AddressTransaction {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-            hash: Vec::<u8>::consensus_decode(d)?,
-            amount: u64::consensus_decode(d)?,
-            block: Vec::<u8>::consensus_decode(d)?,
-            tx: Vec::<u8>::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(AddressTransaction);
-
 impl fmt::Display for AddressTransaction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "addresstransaction")
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct TransactionConfirmations {
     pub id: i32,
     pub block: Vec<u8>,
     pub confirmations: i32,
 }
 
-impl Encodable for TransactionConfirmations {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.id.consensus_encode(s)?;
-        len += self.block.consensus_encode(s)?;
-        Ok(len + self.confirmations.consensus_encode(s)?)
-    }
-}
-
-impl Decodable for TransactionConfirmations {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-            block: Vec::<u8>::consensus_decode(d)?,
-            confirmations: i32::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(TransactionConfirmations);
-
 impl fmt::Display for TransactionConfirmations {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "transactionconfirmations")
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Encodable, Decodable)]
+#[consensus(strict_encoding)]
 pub struct TransactionBroadcasted {
     pub id: i32,
     pub tx_len: i16,
@@ -461,28 +236,6 @@ pub struct TransactionBroadcasted {
     pub success_broadcast: i32,
 }
 
-impl Encodable for TransactionBroadcasted {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        let mut len = self.id.consensus_encode(s)?;
-        len += self.tx_len.consensus_encode(s)?;
-        len += self.tx.consensus_encode(s)?;
-        Ok(len + self.success_broadcast.consensus_encode(s)?)
-    }
-}
-
-impl Decodable for TransactionBroadcasted {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        Ok(Self {
-            id: i32::consensus_decode(d)?,
-            tx_len: i16::consensus_decode(d)?,
-            tx: Vec::<u8>::consensus_decode(d)?,
-            success_broadcast: i32::consensus_decode(d)?,
-        })
-    }
-}
-
-impl_strict_encoding!(TransactionBroadcasted);
-
 impl fmt::Display for TransactionBroadcasted {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "transactionbroadcasted")
@@ -491,65 +244,25 @@ impl fmt::Display for TransactionBroadcasted {
 
 /// Events returned by syncers to the daemon to update the blockchain states.  Events are
 /// identified with a unique 32-bits integer that match the [`Task`] id.
-#[derive(Debug, Clone, Display, PartialEq)]
+#[derive(Debug, Clone, Display, PartialEq, Encodable, Decodable)]
 #[display(Debug)]
+#[consensus(strict_encoding)]
 pub enum Event {
     /// Notify the daemon the blockchain height changed.
+    #[consensus(tag = 0x01)]
     HeightChanged(HeightChanged),
+    #[consensus(tag = 0x02)]
     AddressTransaction(AddressTransaction),
+    #[consensus(tag = 0x03)]
     TransactionConfirmations(TransactionConfirmations),
+    #[consensus(tag = 0x04)]
     TransactionBroadcasted(TransactionBroadcasted),
     /// Notify the daemon the task has been aborted with success or failure. Carries the status for
     /// the task abortion.
+    #[consensus(tag = 0x05)]
     TaskAborted(TaskAborted),
 }
 
-impl Encodable for Event {
-    fn consensus_encode<W: io::Write>(&self, s: &mut W) -> Result<usize, io::Error> {
-        match self {
-            Event::HeightChanged(t) => {
-                let len = 0x01u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-            Event::AddressTransaction(t) => {
-                let len = 0x02u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-            Event::TransactionConfirmations(t) => {
-                let len = 0x03u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-            Event::TransactionBroadcasted(t) => {
-                let len = 0x04u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-            Event::TaskAborted(t) => {
-                let len = 0x05u8.consensus_encode(s)?;
-                Ok(len + t.consensus_encode(s)?)
-            }
-        }
-    }
-}
-
-impl Decodable for Event {
-    fn consensus_decode<D: io::Read>(d: &mut D) -> Result<Self, consensus::Error> {
-        match Decodable::consensus_decode(d)? {
-            0x01u8 => Ok(Event::HeightChanged(Decodable::consensus_decode(d)?)),
-            0x02u8 => Ok(Event::AddressTransaction(Decodable::consensus_decode(d)?)),
-            0x03u8 => Ok(Event::TransactionConfirmations(
-                Decodable::consensus_decode(d)?,
-            )),
-            0x04u8 => Ok(Event::TransactionBroadcasted(Decodable::consensus_decode(
-                d,
-            )?)),
-            0x05u8 => Ok(Event::TaskAborted(Decodable::consensus_decode(d)?)),
-            _ => Err(consensus::Error::UnknownType),
-        }
-    }
-}
-
-impl_strict_encoding!(Event);
-
 #[test]
 fn test_event_encoding() {
     let height_changed = HeightChanged {
@@ -591,3 +304,27 @@ fn test_event_encoding() {
         }
     }
 }
+
+#[test]
+fn test_task_encoding() {
+    let task = Task::WatchAddress(WatchAddress {
+        id: 1,
+        lifetime: 2,
+        addendum: vec![0xaa],
+        include_tx: Boolean::True,
+    });
+    let mut encoder = Vec::new();
+    assert_eq!(task.consensus_encode(&mut encoder).unwrap(), 17);
+    assert_eq!(
+        encoder,
+        vec![
+            0x03, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0xaa, 0x01
+        ]
+    );
+    let mut res = std::io::Cursor::new(encoder);
+    match Task::consensus_decode(&mut res).unwrap() {
+        Task::WatchAddress(task) => assert_eq!(task.include_tx, Boolean::True),
+        _ => panic!("expected watch address task"),
+    }
+}
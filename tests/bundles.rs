@@ -1,17 +1,42 @@
-use farcaster_core::swap::btcxmr::{BtcXmr, KeyManager};
+use farcaster_core::bitcoin::BitcoinSegwitV0;
+use farcaster_core::crypto::dleq::DLEQ_PROOF_LEGACY_VERSION;
+use farcaster_core::swap::btcxmr::{BtcXmr, KeyManager, SaltedBtcXmr};
+use farcaster_core::swap::Swap;
 
-use farcaster_core::blockchain::FeePriority;
-use farcaster_core::bundle::{AliceParameters, BobParameters, Proof};
-use farcaster_core::consensus::deserialize;
-use farcaster_core::crypto::CommitmentEngine;
-use farcaster_core::negotiation::PublicOffer;
+use farcaster_core::bitcoin::fee::SatPerVByte;
+use farcaster_core::bitcoin::timelock::CSVTimelock;
+use farcaster_core::blockchain::{FeePriority, FeeStrategy, Network};
+use farcaster_core::bundle::{
+    validate_participants, AliceParameters, BobParameters, CoreArbitratingTransactions,
+    CosignedArbitratingCancel, Error as BundleError, FullySignedBuy, FullySignedPunish,
+    FullySignedRefund, FundingTransaction, Proof, SignedAdaptorBuy, SignedAdaptorRefund,
+    SignedArbitratingLock,
+};
+use farcaster_core::consensus::{
+    self, deserialize, serialize, CanonicalBytes, Decodable, Encodable,
+};
+use farcaster_core::crypto::{
+    self, CommitmentEngine, CommitmentField, CommitmentNonce, KeccakCommitment,
+    SaltedCommitmentEngine, SharedKeyId, TaggedElement,
+};
+use farcaster_core::negotiation::{Error as NegotiationError, PublicOffer};
 use farcaster_core::protocol_message::{
-    CommitAliceParameters, CommitBobParameters, RevealAliceParameters, RevealBobParameters,
+    Abort, AbortCode, BuyProcedureSignature, Capabilities, CommitAliceParameters,
+    CommitBobParameters, CoreArbitratingSetup, Extensions, Feature, Features, Handshake,
+    MessageFrame, MessageType, ProtocolMessage, RefundProcedureSignatures, RevealAliceParameters,
+    RevealBobParameters, RevealProof, ABORT_CODE_EXTENSION_TYPE, MAX_EXTENSIONS_SIZE,
 };
 use farcaster_core::role::{Alice, Bob};
 use farcaster_core::swap::SwapId;
 
+use bitcoin::secp256k1::{PublicKey, SecretKey, Signature};
+use bitcoin::util::psbt::PartiallySignedTransaction;
 use bitcoin::Address;
+use ecdsa_fun::adaptor::{Adaptor, EncryptedSignature, HashTranscript};
+use ecdsa_fun::fun::{Point, Scalar};
+use ecdsa_fun::nonce;
+use rand_chacha::ChaCha20Rng;
+use sha2::Sha256;
 
 use std::str::FromStr;
 
@@ -23,21 +48,21 @@ macro_rules! test_strict_ser {
     };
 }
 
-fn init_alice() -> (Alice<BtcXmr>, Bob<BtcXmr>, PublicOffer<BtcXmr>, SwapId) {
+fn init<Ctx: Swap<Ar = BitcoinSegwitV0>>() -> (Alice<Ctx>, Bob<Ctx>, PublicOffer<Ctx>, SwapId) {
     let hex = "46435357415001000200000080800000800800a0860100000000000800c80000000000000004000\
                a00000004000a000000010800140000000000000002210003b31a0a70343bb46f3db3768296ac50\
                27f9873921b37f852860c690063ff9e4c9000000000000000000000000000000000000000000000\
                00000000000000000000000260700";
 
     let destination_address =
-        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").expect("Parsable address");
+        Address::from_str("tb1qesgvtyx9y6lax0x34napc2m7t5zdq6s7vq4jh9").expect("Parsable address");
     let fee_politic = FeePriority::Low;
-    let alice: Alice<BtcXmr> = Alice::new(destination_address, fee_politic);
+    let alice: Alice<Ctx> = Alice::new(destination_address, fee_politic);
     let refund_address =
-        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").expect("Parsable address");
-    let bob: Bob<BtcXmr> = Bob::new(refund_address, fee_politic);
+        Address::from_str("tb1qesgvtyx9y6lax0x34napc2m7t5zdq6s7vq4jh9").expect("Parsable address");
+    let bob: Bob<Ctx> = Bob::new(refund_address, fee_politic);
 
-    let pub_offer: PublicOffer<BtcXmr> =
+    let pub_offer: PublicOffer<Ctx> =
         deserialize(&hex::decode(hex).unwrap()[..]).expect("Parsable public offer");
 
     let swap_id = SwapId::random();
@@ -45,6 +70,19 @@ fn init_alice() -> (Alice<BtcXmr>, Bob<BtcXmr>, PublicOffer<BtcXmr>, SwapId) {
     (alice, bob, pub_offer, swap_id)
 }
 
+fn init_alice() -> (Alice<BtcXmr>, Bob<BtcXmr>, PublicOffer<BtcXmr>, SwapId) {
+    init()
+}
+
+fn init_salted() -> (
+    Alice<SaltedBtcXmr>,
+    Bob<SaltedBtcXmr>,
+    PublicOffer<SaltedBtcXmr>,
+    SwapId,
+) {
+    init()
+}
+
 #[test]
 fn create_alice_parameters() {
     let (alice, _, pub_offer, swap_id) = init_alice();
@@ -195,3 +233,1358 @@ fn missing_commitment_in_vec() {
 }
 
 // What if you commit in vec but you don't reveal?
+
+#[test]
+fn strict_reveal_alice_parameters() {
+    let (alice, _, pub_offer, swap_id) = init_alice();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+    let commitment_engine = CommitmentEngine;
+
+    let (alice_params, _) = alice
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    assert!(!alice_params.accordant_shared_keys.is_empty());
+    let shared_key_id = *alice_params.accordant_shared_keys[0].tag();
+    let commit_alice_params =
+        CommitAliceParameters::commit_to_bundle(swap_id, &commitment_engine, alice_params.clone());
+
+    let reveal_alice_params: RevealAliceParameters<BtcXmr> = (swap_id, alice_params.clone()).into();
+    assert!(commit_alice_params
+        .verify_with_reveal_strict(&commitment_engine, reveal_alice_params.clone())
+        .is_ok());
+
+    // committed shared key not revealed, accepted only in lenient mode
+    let mut partial_reveal = reveal_alice_params.clone();
+    partial_reveal.accordant_shared_keys = vec![];
+    assert!(commit_alice_params
+        .verify_with_reveal(&commitment_engine, partial_reveal.clone())
+        .is_ok());
+    assert!(matches!(
+        commit_alice_params.verify_with_reveal_strict(&commitment_engine, partial_reveal),
+        Err(farcaster_core::Error::Crypto(
+            crypto::Error::MissingCommitmentTag(CommitmentField::AccordantSharedKey(id))
+        )) if id == shared_key_id
+    ));
+
+    // shared key revealed twice, accepted only in lenient mode
+    let mut duplicated_reveal = reveal_alice_params;
+    duplicated_reveal
+        .accordant_shared_keys
+        .push(alice_params.accordant_shared_keys[0].clone());
+    assert!(commit_alice_params
+        .verify_with_reveal(&commitment_engine, duplicated_reveal.clone())
+        .is_ok());
+    assert!(matches!(
+        commit_alice_params.verify_with_reveal_strict(&commitment_engine, duplicated_reveal),
+        Err(farcaster_core::Error::Crypto(
+            crypto::Error::DuplicateCommitmentTag(CommitmentField::AccordantSharedKey(id))
+        )) if id == shared_key_id
+    ));
+}
+
+#[test]
+fn strict_reveal_bob_parameters() {
+    let (_, bob, pub_offer, swap_id) = init_alice();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+    let commitment_engine = CommitmentEngine;
+
+    let (bob_params, _) = bob
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    let shared_key_id = *bob_params.accordant_shared_keys[0].tag();
+    let commit_bob_params =
+        CommitBobParameters::commit_to_bundle(swap_id, &commitment_engine, bob_params.clone());
+    let reveal_bob_params: RevealBobParameters<BtcXmr> = (swap_id, bob_params.clone()).into();
+    assert!(commit_bob_params
+        .verify_with_reveal_strict(&commitment_engine, reveal_bob_params.clone())
+        .is_ok());
+
+    // shared key committed twice
+    let mut duplicated_commit = commit_bob_params.clone();
+    duplicated_commit
+        .accordant_shared_keys
+        .push(commit_bob_params.accordant_shared_keys[0].clone());
+    assert!(commit_bob_params
+        .verify_with_reveal(&commitment_engine, reveal_bob_params.clone())
+        .is_ok());
+    assert!(matches!(
+        duplicated_commit.verify_with_reveal_strict(&commitment_engine, reveal_bob_params.clone()),
+        Err(farcaster_core::Error::Crypto(
+            crypto::Error::DuplicateCommitmentTag(CommitmentField::AccordantSharedKey(id))
+        )) if id == shared_key_id
+    ));
+
+    // shared key revealed but not committed
+    let mut partial_commit = commit_bob_params;
+    partial_commit.accordant_shared_keys = vec![];
+    assert!(matches!(
+        partial_commit.verify_with_reveal_strict(&commitment_engine, reveal_bob_params.clone()),
+        Err(farcaster_core::Error::Crypto(
+            crypto::Error::MissingCommitmentTag(CommitmentField::AccordantSharedKey(id))
+        )) if id == shared_key_id
+    ));
+
+    // the salted verification is strict
+    let salted_engine = SaltedCommitmentEngine;
+    let seed = [0x2a; 32];
+    let (_, bob, pub_offer, _) = init_salted();
+    let (bob_params, _) = bob
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    let commit_bob_params =
+        CommitBobParameters::commit_to_bundle_salted(swap_id, &salted_engine, &bob_params, &seed);
+    let mut reveal_bob_params = RevealBobParameters::from_bundle_salted(swap_id, bob_params, &seed);
+    reveal_bob_params.accordant_shared_keys = vec![];
+    assert!(matches!(
+        commit_bob_params.verify_with_salted_reveal(&salted_engine, reveal_bob_params),
+        Err(farcaster_core::Error::Crypto(
+            crypto::Error::MissingCommitmentTag(CommitmentField::AccordantSharedKey(_))
+        ))
+    ));
+}
+
+#[test]
+fn validate_alice_parameters_against_offer() {
+    let (alice, _, pub_offer, swap_id) = init_alice();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+
+    let (alice_params, _) = alice
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    assert!(alice_params.validate_against(&pub_offer).is_ok());
+    let reveal_alice_params: RevealAliceParameters<BtcXmr> = (swap_id, alice_params.clone()).into();
+    assert!(reveal_alice_params.validate_against(&pub_offer).is_ok());
+
+    let mut params = alice_params.clone();
+    params.cancel_timelock = Some(CSVTimelock::new(4242));
+    assert!(matches!(
+        params.validate_against(&pub_offer),
+        Err(BundleError::CancelTimelockMismatch)
+    ));
+
+    let mut params = alice_params.clone();
+    params.punish_timelock = Some(CSVTimelock::new(4242));
+    assert!(matches!(
+        params.validate_against(&pub_offer),
+        Err(BundleError::PunishTimelockMismatch)
+    ));
+
+    // the offer is on testnet
+    let mut reveal = reveal_alice_params.clone();
+    reveal.address =
+        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").expect("Parsable address");
+    assert!(matches!(
+        reveal.validate_against(&pub_offer),
+        Err(BundleError::InvalidAddressNetwork(Network::Testnet))
+    ));
+
+    let mut reveal = reveal_alice_params;
+    reveal.punish = reveal.buy;
+    assert!(matches!(
+        reveal.validate_against(&pub_offer),
+        Err(BundleError::DuplicatePublicKey(
+            CommitmentField::Buy,
+            CommitmentField::Punish
+        ))
+    ));
+}
+
+#[test]
+fn validate_bob_parameters_against_offer() {
+    let (_, bob, pub_offer, swap_id) = init_alice();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+
+    let (bob_params, _) = bob
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    assert!(bob_params.validate_against(&pub_offer).is_ok());
+    let reveal_bob_params: RevealBobParameters<BtcXmr> = (swap_id, bob_params.clone()).into();
+    assert!(reveal_bob_params.validate_against(&pub_offer).is_ok());
+
+    let mut params = bob_params;
+    params.fee_strategy = Some(FeeStrategy::Fixed(SatPerVByte::from_sat(9999)));
+    assert!(matches!(
+        params.validate_against(&pub_offer),
+        Err(BundleError::FeeStrategyMismatch)
+    ));
+
+    let mut reveal = reveal_bob_params.clone();
+    reveal.adaptor = reveal.refund;
+    assert!(matches!(
+        reveal.validate_against(&pub_offer),
+        Err(BundleError::DuplicatePublicKey(
+            CommitmentField::Refund,
+            CommitmentField::Adaptor
+        ))
+    ));
+
+    // the identity point is rejected as accordant spend key
+    let mut identity = [0u8; 32];
+    identity[0] = 1;
+    let mut reveal = reveal_bob_params;
+    reveal.spend = monero::util::key::PublicKey::from_slice(&identity).unwrap();
+    assert!(matches!(
+        reveal.validate_against(&pub_offer),
+        Err(BundleError::InvalidPublicKey(CommitmentField::Spend))
+    ));
+}
+
+#[test]
+fn validate_participants_keys() {
+    let (alice, bob, pub_offer, _) = init_alice();
+    let mut alice_key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+    let mut bob_key_manager = KeyManager::new([0x43; 32], 1).unwrap();
+
+    let (alice_params, _) = alice
+        .generate_parameters(&mut alice_key_manager, &pub_offer)
+        .unwrap();
+    let (bob_params, _) = bob
+        .generate_parameters(&mut bob_key_manager, &pub_offer)
+        .unwrap();
+    assert!(validate_participants(&alice_params, &bob_params).is_ok());
+
+    // a counterparty replaying our keys passes its own validation
+    let mut params = bob_params.clone();
+    params.buy = alice_params.adaptor;
+    assert!(params.validate_against(&pub_offer).is_ok());
+    assert!(matches!(
+        validate_participants(&alice_params, &params),
+        Err(BundleError::CounterpartyPublicKey(
+            CommitmentField::Adaptor,
+            CommitmentField::Buy
+        ))
+    ));
+
+    let mut params = bob_params;
+    params.spend = alice_params.spend;
+    assert!(matches!(
+        validate_participants(&alice_params, &params),
+        Err(BundleError::CounterpartyPublicKey(
+            CommitmentField::Spend,
+            CommitmentField::Spend
+        ))
+    ));
+}
+
+#[test]
+fn frame_protocol_messages() {
+    let (alice, _, pub_offer, swap_id) = init_alice();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+    let commitment_engine = CommitmentEngine;
+
+    let (alice_params, _) = alice
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    let commit_alice_params =
+        CommitAliceParameters::commit_to_bundle(swap_id, &commitment_engine, alice_params);
+    let msg: ProtocolMessage<BtcXmr> = commit_alice_params.into();
+    assert_eq!(msg.message_type(), MessageType::CommitAliceParameters);
+    assert_eq!(msg.type_id(), 33701);
+    assert_eq!(msg.swap_id(), swap_id);
+    test_strict_ser!(msg, ProtocolMessage<BtcXmr>);
+
+    let abort = Abort {
+        swap_id,
+        abort_code: AbortCode::UserCancelled,
+        error_body: Some("error".to_string()),
+    };
+    let frame = MessageFrame::<BtcXmr>::new(abort);
+    let bytes = serialize(&frame);
+    // type identifier and payload length
+    assert_eq!(bytes[..2], 33790u16.to_le_bytes());
+    // the abort code is sent in the extensions and moved back into the message
+    assert_eq!(
+        bytes[bytes.len() - 8..],
+        [0x01, 0x00, 0x01, 0x00, 0x02, 0x00, 0x01, 0x00]
+    );
+    let decoded: MessageFrame<BtcXmr> = deserialize(&bytes).unwrap();
+    assert_eq!(decoded.message.type_id(), 33790);
+    assert!(decoded.extensions.is_empty());
+    assert!(matches!(
+        decoded.message,
+        ProtocolMessage::Abort(Abort {
+            abort_code: AbortCode::UserCancelled,
+            ..
+        })
+    ));
+    assert_eq!(serialize(&decoded), bytes);
+
+    for message_type in [
+        MessageType::CommitAliceParameters,
+        MessageType::RevealProof,
+        MessageType::Abort,
+    ] {
+        assert_eq!(
+            MessageType::from_type_id(message_type.type_id()),
+            Some(message_type)
+        );
+    }
+    assert_eq!(MessageType::from_type_id(1), None);
+}
+
+#[test]
+fn frame_unknown_types_and_extensions() {
+    let abort = Abort {
+        swap_id: SwapId::random(),
+        abort_code: AbortCode::Unspecified,
+        error_body: None,
+    };
+    let known = serialize(&MessageFrame::<BtcXmr>::new(abort));
+
+    // frame with an unknown type identifier, a payload and no extension
+    let unknown_frame = |type_id: u16| {
+        let mut bytes = type_id.to_le_bytes().to_vec();
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend([0xaa, 0xbb, 0xcc]);
+        bytes.extend(0u16.to_le_bytes());
+        bytes
+    };
+
+    // unknown odd message types are skipped and the next frame is decoded
+    let mut stream = unknown_frame(40001);
+    stream.extend(&known);
+    let mut reader = std::io::Cursor::new(stream);
+    assert!(MessageFrame::<BtcXmr>::decode_frame(&mut reader)
+        .unwrap()
+        .is_none());
+    let frame = MessageFrame::<BtcXmr>::decode_frame(&mut reader)
+        .unwrap()
+        .expect("known frame");
+    assert_eq!(frame.message.message_type(), MessageType::Abort);
+
+    // unknown even message types are rejected
+    let mut reader = std::io::Cursor::new(unknown_frame(40002));
+    assert!(matches!(
+        MessageFrame::<BtcXmr>::decode_frame(&mut reader),
+        Err(consensus::Error::UnknownMessageType(40002))
+    ));
+    assert!(matches!(
+        deserialize::<MessageFrame<BtcXmr>>(&unknown_frame(40002)),
+        Err(consensus::Error::UnknownMessageType(40002))
+    ));
+
+    // the frame decoder skips unknown odd message types the same way
+    assert!(matches!(
+        deserialize::<MessageFrame<BtcXmr>>(&unknown_frame(40001)),
+        Err(consensus::Error::IgnoredMessageType(40001))
+    ));
+    let mut stream = unknown_frame(40001);
+    stream.extend(&known);
+    let mut reader = std::io::Cursor::new(stream);
+    assert!(matches!(
+        MessageFrame::<BtcXmr>::consensus_decode(&mut reader),
+        Err(consensus::Error::IgnoredMessageType(40001))
+    ));
+    let frame = MessageFrame::<BtcXmr>::consensus_decode(&mut reader).unwrap();
+    assert_eq!(frame.message.message_type(), MessageType::Abort);
+
+    // registered message types are decoded whatever their parity
+    let handshake = Handshake::new(SwapId::random(), Features::new());
+    let bytes = serialize(&MessageFrame::<BtcXmr>::new(handshake));
+    let mut reader = std::io::Cursor::new(bytes);
+    let frame = MessageFrame::<BtcXmr>::decode_frame(&mut reader)
+        .unwrap()
+        .expect("registered odd frame");
+    assert_eq!(frame.message.type_id() % 2, 1);
+    assert_eq!(frame.message.message_type(), MessageType::Handshake);
+
+    // unknown odd extensions are ignored
+    let mut extensions = Extensions::new();
+    extensions.insert(7, vec![0x01, 0x02]);
+    assert_eq!(extensions.get(7), Some(&[0x01, 0x02][..]));
+    let frame = MessageFrame::<BtcXmr>::new(Abort {
+        swap_id: SwapId::random(),
+        abort_code: AbortCode::Unspecified,
+        error_body: None,
+    })
+    .with_extensions(extensions);
+    let decoded: MessageFrame<BtcXmr> = deserialize(&serialize(&frame)).unwrap();
+    assert!(decoded.extensions.is_empty());
+
+    // unknown even extensions are rejected
+    let mut extensions = Extensions::new();
+    extensions.insert(8, vec![0x01]);
+    let frame = frame.with_extensions(extensions);
+    assert!(matches!(
+        deserialize::<MessageFrame<BtcXmr>>(&serialize(&frame)),
+        Err(consensus::Error::UnknownExtensionType(8))
+    ));
+
+    // extension types must be strictly increasing
+    let mut bytes = known[..known.len() - 2].to_vec();
+    bytes.extend(2u16.to_le_bytes());
+    for _ in 0..2 {
+        bytes.extend(7u16.to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+    }
+    assert!(matches!(
+        deserialize::<MessageFrame<BtcXmr>>(&bytes),
+        Err(consensus::Error::ParseFailed(_))
+    ));
+}
+
+#[test]
+fn frame_size_limits() {
+    // frame announcing a payload larger than allowed for its type
+    let oversized_frame = |type_id: u16, len: u32| {
+        let mut bytes = type_id.to_le_bytes().to_vec();
+        bytes.extend(len.to_le_bytes());
+        bytes
+    };
+
+    let max_size = MessageType::TakerAmount.max_size() as u32;
+    let mut reader = std::io::Cursor::new(oversized_frame(33706, max_size + 1));
+    assert!(matches!(
+        MessageFrame::<BtcXmr>::decode_frame(&mut reader),
+        Err(consensus::Error::LimitExceeded(_))
+    ));
+    // unknown odd message types are not skipped if too large
+    let mut reader = std::io::Cursor::new(oversized_frame(40001, u32::MAX));
+    assert!(matches!(
+        MessageFrame::<BtcXmr>::decode_frame(&mut reader),
+        Err(consensus::Error::LimitExceeded(_))
+    ));
+
+    // extensions are bounded in size
+    let mut extensions = Extensions::new();
+    extensions.insert(7, vec![0x01; MAX_EXTENSIONS_SIZE + 1]);
+    let frame = MessageFrame::<BtcXmr>::new(Abort {
+        swap_id: SwapId::random(),
+        abort_code: AbortCode::Unspecified,
+        error_body: None,
+    })
+    .with_extensions(extensions);
+    assert!(matches!(
+        deserialize::<MessageFrame<BtcXmr>>(&serialize(&frame)),
+        Err(consensus::Error::LimitExceeded(_))
+    ));
+}
+
+#[test]
+fn negotiate_capabilities() {
+    let swap_id = SwapId::random();
+    let local = Handshake::new(swap_id, Features::supported());
+
+    // a counterparty without features gets the default capabilities
+    let remote = Handshake::new(swap_id, Features::new());
+    let capabilities = local.negotiate(&remote).unwrap();
+    assert_eq!(capabilities, Capabilities::default());
+    assert!(!capabilities.supports(Feature::CompactDleqProof));
+    let capabilities = local.negotiate(&local).unwrap();
+    assert_eq!(capabilities.version, 1);
+    assert!(capabilities.supports(Feature::CompactDleqProof));
+
+    // the highest common version is selected
+    let mut remote = Handshake::new(swap_id, Features::supported());
+    remote.versions = vec![1, 2];
+    assert_eq!(local.negotiate(&remote).unwrap().version, 1);
+    remote.versions = vec![2];
+    assert!(matches!(
+        local.negotiate(&remote),
+        Err(NegotiationError::NoCommonProtocolVersion)
+    ));
+
+    // required features must be supported by both, unknown optional bits are ignored
+    let remote = Handshake::new(
+        swap_id,
+        Features::new().with_required(Feature::CompactDleqProof),
+    );
+    assert!(local.negotiate(&remote).is_ok());
+    assert!(matches!(
+        Handshake::new(swap_id, Features::new()).negotiate(&remote),
+        Err(NegotiationError::UnsupportedFeature(0))
+    ));
+    let remote = Handshake::new(swap_id, Features::from_bits(1 << 41));
+    assert!(local.negotiate(&remote).is_ok());
+    let remote = Handshake::new(swap_id, Features::from_bits(1 << 40));
+    assert!(matches!(
+        local.negotiate(&remote),
+        Err(NegotiationError::UnsupportedFeature(40))
+    ));
+    let remote = Handshake::new(SwapId::random(), Features::supported());
+    assert!(matches!(
+        local.negotiate(&remote),
+        Err(NegotiationError::SwapIdMismatch)
+    ));
+
+    let frame = MessageFrame::<BtcXmr>::new(local.clone());
+    let decoded: MessageFrame<BtcXmr> = deserialize(&serialize(&frame)).unwrap();
+    assert_eq!(decoded.message.type_id(), 33707);
+    assert!(matches!(decoded.message, ProtocolMessage::Handshake(msg) if msg == local));
+}
+
+#[test]
+fn serialize_messages_with_capabilities() {
+    let (alice, _, pub_offer, swap_id) = init_alice();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+    let (_, proof) = alice
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    let reveal_proof = RevealProof::<BtcXmr>::from((swap_id, proof));
+    let frame = MessageFrame::<BtcXmr>::new(reveal_proof.clone());
+
+    // the compact proof is only sent when negotiated
+    let compact = Capabilities {
+        version: 1,
+        features: Features::supported(),
+    };
+    let alice = alice.with_capabilities(compact);
+    assert_eq!(alice.capabilities(), &compact);
+    assert_eq!(
+        alice.serialize_message(reveal_proof.clone()),
+        serialize(&frame)
+    );
+
+    let alice = alice.with_capabilities(Capabilities::default());
+    let legacy = alice.serialize_message(reveal_proof.clone());
+    assert_ne!(legacy, serialize(&frame));
+    // type identifier, payload length, swap identifier and proof length
+    assert_eq!(legacy[2 + 4 + 32 + 2], DLEQ_PROOF_LEGACY_VERSION);
+    // both formats decode to the same message
+    let decoded: MessageFrame<BtcXmr> = deserialize(&legacy).unwrap();
+    assert_eq!(serialize(&decoded), serialize(&frame));
+
+    // other messages do not depend on the capabilities
+    let abort = Abort {
+        swap_id,
+        abort_code: AbortCode::Unspecified,
+        error_body: None,
+    };
+    assert_eq!(
+        alice.serialize_message(abort.clone()),
+        serialize(&MessageFrame::<BtcXmr>::new(abort))
+    );
+}
+
+#[test]
+fn abort_codes_from_errors() {
+    use farcaster_core::instruction;
+    use farcaster_core::Error;
+
+    for code in [
+        0, 1, 2, 3, 100, 101, 102, 103, 104, 105, 106, 200, 201, 202, 300, 301, 302, 900,
+    ] {
+        let abort_code = AbortCode::from_code(code);
+        assert!(!matches!(abort_code, AbortCode::Unknown(_)));
+        assert_eq!(abort_code.code(), code);
+        assert_eq!(
+            deserialize::<AbortCode>(&serialize(&abort_code)).unwrap(),
+            abort_code
+        );
+    }
+    // unregistered codes are kept as is
+    assert_eq!(AbortCode::from_code(42), AbortCode::Unknown(42));
+    assert_eq!(serialize(&AbortCode::Unknown(42)), 42u16.to_le_bytes());
+
+    let cases = [
+        (
+            Error::Consensus(consensus::Error::UnknownType),
+            AbortCode::InvalidMessage,
+        ),
+        (
+            Error::Negotiation(NegotiationError::NoCommonProtocolVersion),
+            AbortCode::UnsupportedVersion,
+        ),
+        (
+            Error::Negotiation(NegotiationError::UnsupportedFeature(0)),
+            AbortCode::UnsupportedFeature,
+        ),
+        (
+            Error::Negotiation(NegotiationError::SwapIdMismatch),
+            AbortCode::ProtocolViolation,
+        ),
+        (
+            Error::Bundle(BundleError::CancelTimelockMismatch),
+            AbortCode::InvalidTimelock,
+        ),
+        (
+            Error::Bundle(BundleError::FeeStrategyMismatch),
+            AbortCode::FeeOutOfRange,
+        ),
+        (
+            Error::Crypto(crypto::Error::MissingCommitmentTag(CommitmentField::Buy)),
+            AbortCode::InvalidCommitment,
+        ),
+        (
+            Error::Crypto(crypto::Error::InvalidProof),
+            AbortCode::InvalidDleqProof,
+        ),
+        (
+            Error::Crypto(crypto::Error::InvalidEncryptedSignature),
+            AbortCode::InvalidAdaptorSignature,
+        ),
+        (
+            Error::Crypto(crypto::Error::KeystoreDecryption),
+            AbortCode::InternalError,
+        ),
+    ];
+    for (error, abort_code) in cases {
+        assert_eq!(AbortCode::from(&error), abort_code, "{}", error);
+    }
+
+    // the error is described in the message, unless it is a local failure
+    let swap_id = SwapId::random();
+    let error = || Error::Crypto(crypto::Error::InvalidSignature);
+    let abort = Abort::from((swap_id, error()));
+    assert_eq!(abort.swap_id, swap_id);
+    assert_eq!(abort.abort_code, AbortCode::InvalidSignature);
+    assert_eq!(abort.error_body, Some(error().to_string()));
+    // the code trails the body of a bare abort, but only goes in the extensions of its frame
+    let bytes = serialize(&abort);
+    assert_eq!(bytes.len(), 32 + 3 + error().to_string().len() + 2);
+    assert_eq!(bytes[bytes.len() - 2..], [0x2c, 0x01]);
+    let decoded: Abort = deserialize(&bytes).unwrap();
+    assert_eq!(decoded.abort_code, AbortCode::InvalidSignature);
+    assert_eq!(decoded.error_body, abort.error_body);
+    let frame = MessageFrame::<BtcXmr>::new(abort.clone());
+    let mut extensions = Extensions::new();
+    extensions.insert(ABORT_CODE_EXTENSION_TYPE, 300u16.to_le_bytes().to_vec());
+    // the frame payload is the abort of previous versions, without the trailing code
+    let mut expected = serialize(&ProtocolMessage::<BtcXmr>::from(Abort {
+        abort_code: AbortCode::Unspecified,
+        ..abort.clone()
+    }));
+    expected.extend(serialize(&extensions));
+    assert_eq!(serialize(&frame), expected);
+    match deserialize::<MessageFrame<BtcXmr>>(&expected)
+        .unwrap()
+        .message
+    {
+        ProtocolMessage::Abort(decoded) => {
+            assert_eq!(decoded.abort_code, AbortCode::InvalidSignature)
+        }
+        _ => panic!("expected an abort"),
+    }
+    // peers not sending the code abort with an unspecified code
+    let legacy = serialize(&MessageFrame::<BtcXmr>::new(Abort {
+        abort_code: AbortCode::Unspecified,
+        ..abort
+    }));
+    assert_eq!(legacy[legacy.len() - 2..], [0x00, 0x00]);
+    let abort = Abort::from((swap_id, Error::Crypto(crypto::Error::KeystoreDecryption)));
+    assert_eq!(abort.abort_code, AbortCode::InternalError);
+    assert_eq!(abort.error_body, None);
+
+    let instruction = instruction::Abort::from(Error::Bundle(BundleError::PunishTimelockMismatch));
+    assert_eq!(instruction.abort_code, AbortCode::InvalidTimelock);
+    assert!(instruction.error_body.is_some());
+}
+
+#[test]
+fn abort_round_trip() {
+    let swap_id = SwapId::random();
+    for (abort_code, error_body) in [
+        (AbortCode::Unspecified, None),
+        (
+            AbortCode::InvalidSignature,
+            Some("invalid signature".to_string()),
+        ),
+        (AbortCode::InternalError, None),
+    ] {
+        let abort = Abort {
+            swap_id,
+            abort_code,
+            error_body,
+        };
+        let decoded: Abort = deserialize(&serialize(&abort)).unwrap();
+        assert_eq!(decoded.swap_id, abort.swap_id);
+        assert_eq!(decoded.abort_code, abort.abort_code);
+        assert_eq!(decoded.error_body, abort.error_body);
+        test_strict_ser!(abort, Abort);
+    }
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn serde_yaml_protocol_messages() {
+    let (alice, _, pub_offer, swap_id) = init_alice();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+    let salted_engine = SaltedCommitmentEngine;
+    let seed = [0x2a; 32];
+
+    let (alice_params, _) = alice
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    let reveal_alice_params: RevealAliceParameters<BtcXmr> = (swap_id, alice_params.clone()).into();
+    let s = serde_yaml::to_string(&reveal_alice_params).expect("Encode reveal in yaml");
+    // keys are serialized in their canonical hex form
+    assert!(s.contains(&hex::encode(alice_params.buy.serialize())));
+    let res: RevealAliceParameters<BtcXmr> =
+        serde_yaml::from_str(&s).expect("Decode reveal from yaml");
+    assert_eq!(serialize(&res), serialize(&reveal_alice_params));
+
+    let (_, _, salted_offer, _) = init_salted();
+    let (salted_params, _) = init_salted()
+        .0
+        .generate_parameters(&mut key_manager, &salted_offer)
+        .unwrap();
+    let commit_alice_params = CommitAliceParameters::commit_to_bundle_salted(
+        swap_id,
+        &salted_engine,
+        &salted_params,
+        &seed,
+    );
+    let s = serde_yaml::to_string(&commit_alice_params).expect("Encode commit in yaml");
+    let res: CommitAliceParameters<SaltedBtcXmr> =
+        serde_yaml::from_str(&s).expect("Decode commit from yaml");
+    assert_eq!(serialize(&res), serialize(&commit_alice_params));
+
+    let mut extensions = Extensions::new();
+    extensions.insert(7, vec![0x01, 0x02]);
+    let frame = MessageFrame::<BtcXmr>::new(Abort {
+        swap_id,
+        abort_code: AbortCode::UserCancelled,
+        error_body: Some("error".to_string()),
+    })
+    .with_extensions(extensions);
+    let s = serde_yaml::to_string(&frame).expect("Encode frame in yaml");
+    let res: MessageFrame<BtcXmr> = serde_yaml::from_str(&s).expect("Decode frame from yaml");
+    assert_eq!(serialize(&res), serialize(&frame));
+    assert_eq!(res.extensions.get(7), Some(&[0x01, 0x02][..]));
+
+    let msg: ProtocolMessage<BtcXmr> = reveal_alice_params.into();
+    let s = serde_yaml::to_string(&msg).expect("Encode message in yaml");
+    let res: ProtocolMessage<BtcXmr> = serde_yaml::from_str(&s).expect("Decode message");
+    assert_eq!(res.message_type(), MessageType::RevealAliceParameters);
+}
+
+#[test]
+fn salted_commit_reveal_alice_parameters() {
+    let (alice, _, pub_offer, swap_id) = init_salted();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+    let commitment_engine = SaltedCommitmentEngine;
+    let seed = [0x2a; 32];
+
+    let (alice_params, _) = alice
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    let commit_alice_params = CommitAliceParameters::commit_to_bundle_salted(
+        swap_id,
+        &commitment_engine,
+        &alice_params,
+        &seed,
+    );
+    test_strict_ser!(commit_alice_params, CommitAliceParameters<SaltedBtcXmr>);
+
+    let reveal_alice_params =
+        RevealAliceParameters::from_bundle_salted(swap_id, alice_params.clone(), &seed);
+    assert!(!reveal_alice_params.nonces.is_empty());
+    test_strict_ser!(reveal_alice_params, RevealAliceParameters<SaltedBtcXmr>);
+    // the nonces are encoded with salted commitments
+    let decoded: RevealAliceParameters<SaltedBtcXmr> =
+        deserialize(&serialize(&reveal_alice_params)).unwrap();
+    assert_eq!(
+        serialize(&decoded.nonces),
+        serialize(&reveal_alice_params.nonces)
+    );
+    assert!(commit_alice_params
+        .verify_with_salted_reveal(&commitment_engine, reveal_alice_params)
+        .is_ok());
+
+    // the same keys are committed to different values with another seed
+    let other_commit = CommitAliceParameters::commit_to_bundle_salted(
+        swap_id,
+        &commitment_engine,
+        &alice_params,
+        &[0x2b; 32],
+    );
+    assert_ne!(other_commit.buy, commit_alice_params.buy);
+    let reveal_alice_params =
+        RevealAliceParameters::from_bundle_salted(swap_id, alice_params.clone(), &[0x2b; 32]);
+    assert!(commit_alice_params
+        .verify_with_salted_reveal(&commitment_engine, reveal_alice_params)
+        .is_err());
+
+    // reveal without nonces
+    let reveal_alice_params: RevealAliceParameters<SaltedBtcXmr> = (swap_id, alice_params).into();
+    assert!(commit_alice_params
+        .verify_with_salted_reveal(&commitment_engine, reveal_alice_params)
+        .is_err());
+}
+
+#[test]
+fn salted_commitments_are_bound_to_swap_and_field() {
+    let (_, bob, pub_offer, swap_id) = init_salted();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+    let commitment_engine = SaltedCommitmentEngine;
+    let seed = [0x2a; 32];
+
+    let (bob_params, _) = bob
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    let commit_bob_params = CommitBobParameters::commit_to_bundle_salted(
+        swap_id,
+        &commitment_engine,
+        &bob_params,
+        &seed,
+    );
+    let reveal_bob_params = RevealBobParameters::from_bundle_salted(swap_id, bob_params, &seed);
+    assert!(commit_bob_params
+        .verify_with_salted_reveal(&commitment_engine, reveal_bob_params.clone())
+        .is_ok());
+
+    // commitments replayed in another swap
+    let mut replayed = commit_bob_params.clone();
+    replayed.swap_id = SwapId::random();
+    assert!(replayed
+        .verify_with_salted_reveal(&commitment_engine, reveal_bob_params.clone())
+        .is_err());
+
+    // commitments swapped between fields
+    let mut swapped = commit_bob_params;
+    std::mem::swap(&mut swapped.buy, &mut swapped.cancel);
+    let mut reveal_swapped = reveal_bob_params;
+    std::mem::swap(&mut reveal_swapped.buy, &mut reveal_swapped.cancel);
+    assert!(swapped
+        .verify_with_salted_reveal(&commitment_engine, reveal_swapped)
+        .is_err());
+}
+
+// Reference encoding of Alice parameters as hand-written before deriving `Encodable`.
+fn encode_alice_parameters(params: &AliceParameters<BtcXmr>) -> Vec<u8> {
+    let mut s = Vec::new();
+    params
+        .buy
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .cancel
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .refund
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .punish
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .adaptor
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .extra_arbitrating_keys
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .arbitrating_shared_keys
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .spend
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .extra_accordant_keys
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .accordant_shared_keys
+        .consensus_encode(&mut s)
+        .unwrap();
+    params
+        .destination_address
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    params.cancel_timelock.consensus_encode(&mut s).unwrap();
+    params.punish_timelock.consensus_encode(&mut s).unwrap();
+    params.fee_strategy.consensus_encode(&mut s).unwrap();
+    s
+}
+
+// Reference encoding of Alice commitments as hand-written before deriving `Encodable`.
+fn encode_commit_alice_parameters(msg: &CommitAliceParameters<BtcXmr>) -> Vec<u8> {
+    let mut s = Vec::new();
+    msg.swap_id.consensus_encode(&mut s).unwrap();
+    msg.buy
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.cancel
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.refund
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.punish
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.adaptor
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.extra_arbitrating_keys.consensus_encode(&mut s).unwrap();
+    msg.arbitrating_shared_keys
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.spend
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.extra_accordant_keys.consensus_encode(&mut s).unwrap();
+    msg.accordant_shared_keys.consensus_encode(&mut s).unwrap();
+    s
+}
+
+// Reference encoding of Alice reveal as hand-written before deriving `Encodable`.
+fn encode_reveal_alice_parameters(msg: &RevealAliceParameters<BtcXmr>) -> Vec<u8> {
+    let mut s = Vec::new();
+    msg.swap_id.consensus_encode(&mut s).unwrap();
+    msg.buy
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.cancel
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.refund
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.punish
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.adaptor
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.extra_arbitrating_keys.consensus_encode(&mut s).unwrap();
+    msg.arbitrating_shared_keys
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.spend
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    msg.extra_accordant_keys.consensus_encode(&mut s).unwrap();
+    msg.accordant_shared_keys.consensus_encode(&mut s).unwrap();
+    msg.address
+        .as_canonical_bytes()
+        .consensus_encode(&mut s)
+        .unwrap();
+    s
+}
+
+#[test]
+fn derived_encoding_matches_hand_written() {
+    let (alice, _, pub_offer, swap_id) = init_alice();
+    let mut key_manager = KeyManager::new([0x42; 32], 1).unwrap();
+    let commitment_engine = CommitmentEngine;
+
+    let (alice_params, _) = alice
+        .generate_parameters(&mut key_manager, &pub_offer)
+        .unwrap();
+    let bytes = serialize(&alice_params);
+    assert_eq!(bytes, encode_alice_parameters(&alice_params));
+    let decoded: AliceParameters<BtcXmr> = deserialize(&bytes).unwrap();
+    assert_eq!(serialize(&decoded), bytes);
+
+    let commit_alice_params =
+        CommitAliceParameters::commit_to_bundle(swap_id, &commitment_engine, alice_params.clone());
+    let bytes = serialize(&commit_alice_params);
+    assert_eq!(bytes, encode_commit_alice_parameters(&commit_alice_params));
+    let decoded: CommitAliceParameters<BtcXmr> = deserialize(&bytes).unwrap();
+    assert_eq!(serialize(&decoded), bytes);
+
+    let reveal_alice_params: RevealAliceParameters<BtcXmr> = (swap_id, alice_params).into();
+    let bytes = serialize(&reveal_alice_params);
+    assert_eq!(bytes, encode_reveal_alice_parameters(&reveal_alice_params));
+    let decoded: RevealAliceParameters<BtcXmr> = deserialize(&bytes).unwrap();
+    assert_eq!(serialize(&decoded), bytes);
+
+    let abort = Abort {
+        swap_id,
+        abort_code: AbortCode::UserCancelled,
+        error_body: Some("error".into()),
+    };
+    let mut expected = serialize(&swap_id);
+    expected.extend([0x01, 0x05, 0x00]);
+    expected.extend(b"error");
+    expected.extend([0x01, 0x00]);
+    assert_eq!(serialize(&abort), expected);
+}
+
+// Encodings captured with the hand-written implementations replaced by the derived ones, for the
+// values built in `derived_encoding_matches_captured_encoding`. The nonces of the reveal are only
+// encoded with salted commitments.
+const CAPTURED_ALICE_PARAMETERS: &str =
+    "21000279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798210002c6047f9441ed7d6d30\
+     45406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5210002f9308a019258c31049344f85f89d5229b531c84583\
+     6f99b08601f113bce036f9210002e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd1321\
+     00022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe401000700210003fff97bd5755e\
+     eea420453a14355235d382f6472f8568a18b2f057a14602975560100010020002a2a2a2a2a2a2a2a2a2a2a2a2a2a2a\
+     2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a200058666666666666666666666666666666666666666666666666666666\
+     6666666601000700200058666666666666666666666666666666666666666666666666666666666666660100010020\
+     002a000000000000000000000000000000000000000000000000000000000000002a00626331717735303864367165\
+     6a7874646734793572337a6172766172793063357877376b7638663374340104000a00000001040014000000010b00\
+     0108001400000000000000";
+const CAPTURED_BOB_PARAMETERS: &str =
+    "21000279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798210002c6047f9441ed7d6d30\
+     45406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5210002f9308a019258c31049344f85f89d5229b531c84583\
+     6f99b08601f113bce036f92100022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe400\
+     00000020005866666666666666666666666666666666666666666666666666666666666666000000002a0062633171\
+     77353038643671656a7874646734793572337a6172766172793063357877376b76386633743400000115000208000a\
+     0000000000000008001e00000000000000";
+const CAPTURED_COSIGNED_ARBITRATING_CANCEL: &str =
+    "4000111111111111111111111111111111111111111111111111111111111111111112121212121212121212121212\
+     12121212121212121212121212121212121212";
+const CAPTURED_FUNDING_TRANSACTION: &str =
+    "52000200000001eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee0000000000ffffff\
+     ff01a0860100000000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f00000000";
+const CAPTURED_CORE_ARBITRATING_TRANSACTIONS: &str =
+    "5d0070736274ff0100520200000001aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\
+     0000000000ffffffff01a0860100000000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f000000000000\
+     005d0070736274ff0100520200000001bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\
+     bb0000000000ffffffff01a0860100000000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0000000000\
+     00005d0070736274ff0100520200000001cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc\
+     cccc0000000000ffffffff01a0860100000000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f00000000\
+     000000";
+const CAPTURED_SIGNED_ADAPTOR_BUY: &str =
+    "5d0070736274ff0100520200000001dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd\
+     0000000000ffffffff01a0860100000000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f000000000000\
+     00a20002a01d2fa7be644bbd6b9a4d0d2baac181828b92013ea8e46dd1fb257ab507c50102d300e80e7cac20843cbe\
+     98befd78977e77df5bfdeced681f72b99e256b8a8393142128b1c10494ac9de042f91718db46395c07a13ec5b12d2f\
+     1e8143006f2b281f902d44ecc5916ae094af5d0892186813ec8bb1de8c1059bd6a4e56fa3328c48be32a8f9513cca2\
+     a369b1062067cf155cd2ef3bc4de4124a87bd11040d65e0c";
+const CAPTURED_FULLY_SIGNED_BUY: &str =
+    "4000111111111111111111111111111111111111111111111111111111111111111112121212121212121212121212\
+     1212121212121212121212121212121212121240003333333333333333333333333333333333333333333333333333\
+     3333333333333434343434343434343434343434343434343434343434343434343434343434";
+const CAPTURED_SIGNED_ADAPTOR_REFUND: &str =
+    "a20002a01d2fa7be644bbd6b9a4d0d2baac181828b92013ea8e46dd1fb257ab507c50102d300e80e7cac20843cbe98\
+     befd78977e77df5bfdeced681f72b99e256b8a8393142128b1c10494ac9de042f91718db46395c07a13ec5b12d2f1e\
+     8143006f2b281f902d44ecc5916ae094af5d0892186813ec8bb1de8c1059bd6a4e56fa3328c48be32a8f9513cca2a3\
+     69b1062067cf155cd2ef3bc4de4124a87bd11040d65e0c";
+const CAPTURED_FULLY_SIGNED_REFUND: &str =
+    "4000111111111111111111111111111111111111111111111111111111111111111112121212121212121212121212\
+     1212121212121212121212121212121212121240003333333333333333333333333333333333333333333333333333\
+     3333333333333434343434343434343434343434343434343434343434343434343434343434";
+const CAPTURED_SIGNED_ARBITRATING_LOCK: &str =
+    "4000555555555555555555555555555555555555555555555555555555555555555556565656565656565656565656\
+     56565656565656565656565656565656565656";
+const CAPTURED_FULLY_SIGNED_PUNISH: &str =
+    "5d0070736274ff0100520200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff\
+     0000000000ffffffff01a0860100000000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f000000000000\
+     0040005555555555555555555555555555555555555555555555555555555555555555565656565656565656565656\
+     5656565656565656565656565656565656565656";
+const CAPTURED_COMMIT_BOB_PARAMETERS: &str =
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f200001010101010101010101010101\
+     0101010101010101010101010101010101010120000202020202020202020202020202020202020202020202020202\
+     0202020202022000030303030303030303030303030303030303030303030303030303030303030320000505050505\
+     0505050505050505050505050505050505050505050505050505050100070020000606060606060606060606060606\
+     0606060606060606060606060606060606060100010020000707070707070707070707070707070707070707070707\
+     0707070707070707072000080808080808080808080808080808080808080808080808080808080808080801000700\
+     200009090909090909090909090909090909090909090909090909090909090909090100010020000a0a0a0a0a0a0a\
+     0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a0a";
+const CAPTURED_REVEAL_BOB_PARAMETERS: &str =
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f21000279be667ef9dcbbac55a06295\
+     ce870b07029bfcdb2dce28d959f2815b16f81798210002c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7\
+     abac09b95c709ee5210002f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f92100022f\
+     8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe401000700210003fff97bd5755eeea420\
+     453a14355235d382f6472f8568a18b2f057a1460297556000020005866666666666666666666666666666666666666\
+     666666666666666666666666000000002a006263317177353038643671656a7874646734793572337a617276617279\
+     3063357877376b763866337434";
+const CAPTURED_SALTED_REVEAL_BOB_PARAMETERS: &str =
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f21000279be667ef9dcbbac55a06295\
+     ce870b07029bfcdb2dce28d959f2815b16f81798210002c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7\
+     abac09b95c709ee5210002f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f92100022f\
+     8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe401000700210003fff97bd5755eeea420\
+     453a14355235d382f6472f8568a18b2f057a1460297556000020005866666666666666666666666666666666666666\
+     666666666666666666666666000000002a006263317177353038643671656a7874646734793572337a617276617279\
+     3063357877376b76386633743401000120001111111111111111111111111111111111111111111111111111111111\
+     111111";
+const CAPTURED_CORE_ARBITRATING_SETUP: &str =
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f5d0070736274ff0100520200000001\
+     aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa0000000000ffffffff01a086010000\
+     0000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f000000000000005d0070736274ff01005202000000\
+     01bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb0000000000ffffffff01a0860100\
+     000000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f000000000000005d0070736274ff010052020000\
+     0001cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc0000000000ffffffff01a08601\
+     00000000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f00000000000000400011111111111111111111\
+     1111111111111111111111111111111111111111111112121212121212121212121212121212121212121212121212\
+     12121212121212";
+const CAPTURED_REFUND_PROCEDURE_SIGNATURES: &str =
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f400011111111111111111111111111\
+     1111111111111111111111111111111111111112121212121212121212121212121212121212121212121212121212\
+     12121212a20002a01d2fa7be644bbd6b9a4d0d2baac181828b92013ea8e46dd1fb257ab507c50102d300e80e7cac20\
+     843cbe98befd78977e77df5bfdeced681f72b99e256b8a8393142128b1c10494ac9de042f91718db46395c07a13ec5\
+     b12d2f1e8143006f2b281f902d44ecc5916ae094af5d0892186813ec8bb1de8c1059bd6a4e56fa3328c48be32a8f95\
+     13cca2a369b1062067cf155cd2ef3bc4de4124a87bd11040d65e0c";
+const CAPTURED_BUY_PROCEDURE_SIGNATURE: &str =
+    "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f5d0070736274ff0100520200000001\
+     dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd0000000000ffffffff01a086010000\
+     0000001600140f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f0f00000000000000a20002a01d2fa7be644bbd6b9a4d\
+     0d2baac181828b92013ea8e46dd1fb257ab507c50102d300e80e7cac20843cbe98befd78977e77df5bfdeced681f72\
+     b99e256b8a8393142128b1c10494ac9de042f91718db46395c07a13ec5b12d2f1e8143006f2b281f902d44ecc5916a\
+     e094af5d0892186813ec8bb1de8c1059bd6a4e56fa3328c48be32a8f9513cca2a369b1062067cf155cd2ef3bc4de41\
+     24a87bd11040d65e0c";
+
+const BUY_KEY: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+const CANCEL_KEY: &str = "02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5";
+const REFUND_KEY: &str = "02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9";
+const PUNISH_KEY: &str = "02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13";
+const ADAPTOR_KEY: &str = "022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4";
+const EXTRA_KEY: &str = "03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556";
+
+fn captured_swap_id() -> SwapId {
+    SwapId::from_str("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").unwrap()
+}
+
+fn public_key(hex: &str) -> PublicKey {
+    PublicKey::from_str(hex).unwrap()
+}
+
+// The ed25519 basepoint.
+fn spend_key() -> monero::PublicKey {
+    let mut bytes = [0x66; 32];
+    bytes[0] = 0x58;
+    monero::PublicKey::from_slice(&bytes).unwrap()
+}
+
+fn monero_shared_key() -> monero::PrivateKey {
+    let mut bytes = [0x00; 32];
+    bytes[0] = 0x2a;
+    monero::PrivateKey::from_slice(&bytes).unwrap()
+}
+
+fn mainnet_address() -> Address {
+    Address::from_str("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4").unwrap()
+}
+
+// A transaction spending the outpoint `byte * 32:0`.
+fn psbt(byte: u8) -> PartiallySignedTransaction {
+    let hex = format!(
+        "70736274ff0100520200000001{}0000000000ffffffff01a0860100000000001600140f0f0f0f0f0f0f0f0f0f\
+         0f0f0f0f0f0f0f0f0f0f00000000000000",
+        hex::encode([byte; 32])
+    );
+    bitcoin::consensus::deserialize(&hex::decode(hex).unwrap()).unwrap()
+}
+
+fn signature(byte: u8) -> Signature {
+    let mut bytes = [byte; 64];
+    bytes[32..].copy_from_slice(&[byte + 1; 32]);
+    Signature::from_compact(&bytes).unwrap()
+}
+
+// An adaptor signature produced with a deterministic nonce.
+fn encrypted_signature() -> EncryptedSignature {
+    let adaptor =
+        Adaptor::<HashTranscript<Sha256, ChaCha20Rng>, nonce::Deterministic<Sha256>>::default();
+    let signing_key = Scalar::from(SecretKey::from_slice(&[0x2a; 32]).unwrap());
+    let encryption_key = Point::from(public_key(CANCEL_KEY));
+    adaptor.encrypted_sign(&signing_key, &encryption_key, &[0x42; 32])
+}
+
+fn commitment(byte: u8) -> KeccakCommitment {
+    KeccakCommitment::new([byte; 32])
+}
+
+fn assert_captured<T>(value: &T, captured: &str)
+where
+    T: Encodable + Decodable + std::fmt::Debug,
+{
+    let bytes = serialize(value);
+    assert_eq!(hex::encode(&bytes), captured);
+    let decoded: T = deserialize(&bytes).unwrap();
+    assert_eq!(serialize(&decoded), bytes);
+}
+
+#[test]
+fn derived_encoding_matches_captured_encoding() {
+    let alice_params = AliceParameters::<BtcXmr> {
+        buy: public_key(BUY_KEY),
+        cancel: public_key(CANCEL_KEY),
+        refund: public_key(REFUND_KEY),
+        punish: public_key(PUNISH_KEY),
+        adaptor: public_key(ADAPTOR_KEY),
+        extra_arbitrating_keys: vec![TaggedElement::new(7, public_key(EXTRA_KEY))],
+        arbitrating_shared_keys: vec![TaggedElement::new(
+            SharedKeyId::new(1),
+            SecretKey::from_slice(&[0x2a; 32]).unwrap(),
+        )],
+        spend: spend_key(),
+        extra_accordant_keys: vec![TaggedElement::new(7, spend_key())],
+        accordant_shared_keys: vec![TaggedElement::new(SharedKeyId::new(1), monero_shared_key())],
+        destination_address: mainnet_address(),
+        cancel_timelock: Some(CSVTimelock::new(10)),
+        punish_timelock: Some(CSVTimelock::new(20)),
+        fee_strategy: Some(FeeStrategy::Fixed(SatPerVByte::from_sat(20))),
+    };
+    assert_captured(&alice_params, CAPTURED_ALICE_PARAMETERS);
+    let bob_params = BobParameters::<BtcXmr> {
+        buy: public_key(BUY_KEY),
+        cancel: public_key(CANCEL_KEY),
+        refund: public_key(REFUND_KEY),
+        adaptor: public_key(ADAPTOR_KEY),
+        extra_arbitrating_keys: vec![],
+        arbitrating_shared_keys: vec![],
+        spend: spend_key(),
+        extra_accordant_keys: vec![],
+        accordant_shared_keys: vec![],
+        refund_address: mainnet_address(),
+        cancel_timelock: None,
+        punish_timelock: None,
+        fee_strategy: Some(FeeStrategy::Range {
+            min_inc: SatPerVByte::from_sat(10),
+            max_inc: SatPerVByte::from_sat(30),
+        }),
+    };
+    assert_captured(&bob_params, CAPTURED_BOB_PARAMETERS);
+
+    assert_captured(
+        &CosignedArbitratingCancel::<BitcoinSegwitV0> {
+            cancel_sig: signature(0x11),
+        },
+        CAPTURED_COSIGNED_ARBITRATING_CANCEL,
+    );
+    assert_captured(
+        &FundingTransaction::<BitcoinSegwitV0> {
+            funding: psbt(0xee).extract_tx(),
+        },
+        CAPTURED_FUNDING_TRANSACTION,
+    );
+    assert_captured(
+        &CoreArbitratingTransactions::<BitcoinSegwitV0> {
+            lock: psbt(0xaa),
+            cancel: psbt(0xbb),
+            refund: psbt(0xcc),
+        },
+        CAPTURED_CORE_ARBITRATING_TRANSACTIONS,
+    );
+    assert_captured(
+        &SignedAdaptorBuy::<BitcoinSegwitV0> {
+            buy: psbt(0xdd),
+            buy_adaptor_sig: encrypted_signature(),
+        },
+        CAPTURED_SIGNED_ADAPTOR_BUY,
+    );
+    assert_captured(
+        &FullySignedBuy::<BitcoinSegwitV0> {
+            buy_sig: signature(0x11),
+            buy_adapted_sig: signature(0x33),
+        },
+        CAPTURED_FULLY_SIGNED_BUY,
+    );
+    assert_captured(
+        &SignedAdaptorRefund::<BitcoinSegwitV0> {
+            refund_adaptor_sig: encrypted_signature(),
+        },
+        CAPTURED_SIGNED_ADAPTOR_REFUND,
+    );
+    assert_captured(
+        &FullySignedRefund::<BitcoinSegwitV0> {
+            refund_sig: signature(0x11),
+            refund_adapted_sig: signature(0x33),
+        },
+        CAPTURED_FULLY_SIGNED_REFUND,
+    );
+    assert_captured(
+        &SignedArbitratingLock::<BitcoinSegwitV0> {
+            lock_sig: signature(0x55),
+        },
+        CAPTURED_SIGNED_ARBITRATING_LOCK,
+    );
+    assert_captured(
+        &FullySignedPunish::<BitcoinSegwitV0> {
+            punish: psbt(0xff),
+            punish_sig: signature(0x55),
+        },
+        CAPTURED_FULLY_SIGNED_PUNISH,
+    );
+
+    assert_captured(
+        &CommitBobParameters::<BtcXmr> {
+            swap_id: captured_swap_id(),
+            buy: commitment(0x01),
+            cancel: commitment(0x02),
+            refund: commitment(0x03),
+            adaptor: commitment(0x05),
+            extra_arbitrating_keys: vec![TaggedElement::new(7, commitment(0x06))],
+            arbitrating_shared_keys: vec![TaggedElement::new(
+                SharedKeyId::new(1),
+                commitment(0x07),
+            )],
+            spend: commitment(0x08),
+            extra_accordant_keys: vec![TaggedElement::new(7, commitment(0x09))],
+            accordant_shared_keys: vec![TaggedElement::new(SharedKeyId::new(1), commitment(0x0a))],
+        },
+        CAPTURED_COMMIT_BOB_PARAMETERS,
+    );
+    let reveal_bob_params = RevealBobParameters::<BtcXmr> {
+        swap_id: captured_swap_id(),
+        buy: public_key(BUY_KEY),
+        cancel: public_key(CANCEL_KEY),
+        refund: public_key(REFUND_KEY),
+        adaptor: public_key(ADAPTOR_KEY),
+        extra_arbitrating_keys: vec![TaggedElement::new(7, public_key(EXTRA_KEY))],
+        arbitrating_shared_keys: vec![],
+        spend: spend_key(),
+        extra_accordant_keys: vec![],
+        accordant_shared_keys: vec![],
+        address: mainnet_address(),
+        nonces: vec![],
+    };
+    assert_captured(&reveal_bob_params, CAPTURED_REVEAL_BOB_PARAMETERS);
+    let salted_reveal_bob_params = RevealBobParameters::<SaltedBtcXmr> {
+        swap_id: reveal_bob_params.swap_id,
+        buy: reveal_bob_params.buy,
+        cancel: reveal_bob_params.cancel,
+        refund: reveal_bob_params.refund,
+        adaptor: reveal_bob_params.adaptor,
+        extra_arbitrating_keys: reveal_bob_params.extra_arbitrating_keys,
+        arbitrating_shared_keys: vec![],
+        spend: reveal_bob_params.spend,
+        extra_accordant_keys: vec![],
+        accordant_shared_keys: vec![],
+        address: reveal_bob_params.address,
+        nonces: vec![TaggedElement::new(
+            CommitmentField::Buy,
+            CommitmentNonce::repeat_byte(0x11),
+        )],
+    };
+    assert_captured(
+        &salted_reveal_bob_params,
+        CAPTURED_SALTED_REVEAL_BOB_PARAMETERS,
+    );
+
+    assert_captured(
+        &CoreArbitratingSetup::<BtcXmr> {
+            swap_id: captured_swap_id(),
+            lock: psbt(0xaa),
+            cancel: psbt(0xbb),
+            refund: psbt(0xcc),
+            cancel_sig: signature(0x11),
+        },
+        CAPTURED_CORE_ARBITRATING_SETUP,
+    );
+    assert_captured(
+        &RefundProcedureSignatures::<BtcXmr> {
+            swap_id: captured_swap_id(),
+            cancel_sig: signature(0x11),
+            refund_adaptor_sig: encrypted_signature(),
+        },
+        CAPTURED_REFUND_PROCEDURE_SIGNATURES,
+    );
+    assert_captured(
+        &BuyProcedureSignature::<BtcXmr> {
+            swap_id: captured_swap_id(),
+            buy: psbt(0xdd),
+            buy_adaptor_sig: encrypted_signature(),
+        },
+        CAPTURED_BUY_PROCEDURE_SIGNATURE,
+    );
+}
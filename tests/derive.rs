@@ -0,0 +1,246 @@
+use farcaster_core::consensus::{
+    self, deserialize, serialize, CanonicalBytes, Decodable, Encodable,
+};
+use farcaster_core::syncer::{
+    self, AddressTransaction, Boolean, BroadcastTransaction, Event, HeightChanged, Task,
+    TaskAborted, TransactionBroadcasted, TransactionConfirmations, WatchAddress, WatchHeight,
+    WatchTransaction,
+};
+
+use bitcoin::secp256k1::PublicKey;
+
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Encodable, Decodable, CanonicalBytes)]
+#[consensus(strict_encoding)]
+struct Named {
+    id: u16,
+    #[canonical]
+    key: PublicKey,
+    data: Vec<u8>,
+    note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Encodable, Decodable)]
+struct Unnamed(u32, #[canonical] String);
+
+#[derive(Debug, Clone, PartialEq, Encodable, Decodable)]
+struct Unit;
+
+#[derive(Debug, Clone, PartialEq, Encodable, Decodable)]
+#[consensus(strict_encoding)]
+enum Tagged {
+    #[consensus(tag = 0x01)]
+    Named(Named),
+    #[consensus(tag = 0x02)]
+    Unnamed(u8, #[canonical] String),
+    #[consensus(tag = 0x03)]
+    Struct { id: u16, data: Vec<u8> },
+    #[consensus(tag = 0xff)]
+    Unit,
+}
+
+fn key() -> PublicKey {
+    PublicKey::from_str("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+        .expect("Valid public key")
+}
+
+#[test]
+fn derive_struct_encoding() {
+    let named = Named {
+        id: 0x0102,
+        key: key(),
+        data: vec![0xaa, 0xbb],
+        note: Some("note".into()),
+    };
+    let mut expected = vec![0x02, 0x01, 0x21, 0x00];
+    expected.extend(key().serialize());
+    expected.extend([0x02, 0x00, 0xaa, 0xbb]);
+    expected.extend([0x01, 0x04, 0x00]);
+    expected.extend(b"note");
+    assert_eq!(serialize(&named), expected);
+    assert_eq!(deserialize::<Named>(&expected).unwrap(), named);
+
+    // canonical bytes are the consensus encoding
+    assert_eq!(named.as_canonical_bytes(), expected);
+    assert_eq!(Named::from_canonical_bytes(&expected).unwrap(), named);
+    assert!(Named::from_canonical_bytes(&expected[..expected.len() - 1]).is_err());
+
+    // strict encoding is the consensus encoding
+    assert_eq!(strict_encoding::strict_serialize(&named).unwrap(), expected);
+    let res: Named = strict_encoding::strict_deserialize(&expected).unwrap();
+    assert_eq!(res, named);
+
+    let unnamed = Unnamed(7, "abc".into());
+    let expected = [0x07, 0x00, 0x00, 0x00, 0x03, 0x00, b'a', b'b', b'c'];
+    assert_eq!(serialize(&unnamed), expected);
+    assert_eq!(deserialize::<Unnamed>(&expected).unwrap(), unnamed);
+
+    assert!(serialize(&Unit).is_empty());
+    assert_eq!(deserialize::<Unit>(&[]).unwrap(), Unit);
+}
+
+#[test]
+fn derive_enum_encoding() {
+    let tagged = Tagged::Unnamed(0x2a, "a".into());
+    let expected = [0x02, 0x2a, 0x01, 0x00, b'a'];
+    assert_eq!(serialize(&tagged), expected);
+    assert_eq!(deserialize::<Tagged>(&expected).unwrap(), tagged);
+
+    let tagged = Tagged::Struct {
+        id: 3,
+        data: vec![0x01],
+    };
+    let expected = [0x03, 0x03, 0x00, 0x01, 0x00, 0x01];
+    assert_eq!(serialize(&tagged), expected);
+    assert_eq!(deserialize::<Tagged>(&expected).unwrap(), tagged);
+
+    assert_eq!(serialize(&Tagged::Unit), [0xff]);
+    assert_eq!(deserialize::<Tagged>(&[0xff]).unwrap(), Tagged::Unit);
+
+    let named = Named {
+        id: 1,
+        key: key(),
+        data: vec![],
+        note: None,
+    };
+    let mut expected = vec![0x01];
+    expected.extend(serialize(&named));
+    let tagged = Tagged::Named(named);
+    assert_eq!(serialize(&tagged), expected);
+    assert_eq!(deserialize::<Tagged>(&expected).unwrap(), tagged);
+    assert_eq!(
+        strict_encoding::strict_serialize(&tagged).unwrap(),
+        expected
+    );
+
+    assert!(matches!(
+        deserialize::<Tagged>(&[0x04]),
+        Err(consensus::Error::UnknownType)
+    ));
+}
+
+#[test]
+fn derive_decoding_consumes_fields_in_order() {
+    let mut reader = std::io::Cursor::new([0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff]);
+    assert_eq!(
+        Unnamed::consensus_decode(&mut reader).unwrap(),
+        Unnamed(5, String::new())
+    );
+    assert_eq!(Tagged::consensus_decode(&mut reader).unwrap(), Tagged::Unit);
+    let mut writer = Vec::new();
+    assert_eq!(Tagged::Unit.consensus_encode(&mut writer).unwrap(), 1);
+}
+
+// Encodings of the syncer tasks and events captured with the hand-written implementations replaced
+// by the derived ones.
+const CAPTURED_ABORT_TASK: &str = "0107000000";
+const CAPTURED_WATCH_HEIGHT_TASK: &str = "020100000000350c000000000002000102";
+const CAPTURED_WATCH_ADDRESS_TASK: &str = "030200000064350c00000000000300ababab01";
+const CAPTURED_WATCH_TRANSACTION_TASK: &str =
+    "0403000000c8350c00000000002000abababababababababababababababababababababababababababababababab\
+     0600";
+const CAPTURED_BROADCAST_TRANSACTION_TASK: &str = "05ffffffff0400deadbeef";
+const CAPTURED_HEIGHT_CHANGED_EVENT: &str =
+    "01010000002000cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd00350c0000000000";
+const CAPTURED_ADDRESS_TRANSACTION_EVENT: &str =
+    "02020000002000ababababababababababababababababababababababababababababababababa086010000000000\
+     2000cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd0400deadbeef";
+const CAPTURED_TRANSACTION_CONFIRMATIONS_EVENT: &str = "03030000000000ffffffff";
+const CAPTURED_TRANSACTION_BROADCASTED_EVENT: &str = "040400000004000400deadbeef01000000";
+const CAPTURED_TASK_ABORTED_EVENT: &str = "050700000001000000";
+
+#[test]
+fn derived_syncer_encoding_matches_captured_encoding() {
+    let tasks = [
+        (Task::Abort(syncer::Abort { id: 7 }), CAPTURED_ABORT_TASK),
+        (
+            Task::WatchHeight(WatchHeight {
+                id: 1,
+                lifetime: 800_000,
+                addendum: vec![0x01, 0x02],
+            }),
+            CAPTURED_WATCH_HEIGHT_TASK,
+        ),
+        (
+            Task::WatchAddress(WatchAddress {
+                id: 2,
+                lifetime: 800_100,
+                addendum: vec![0xab; 3],
+                include_tx: Boolean::True,
+            }),
+            CAPTURED_WATCH_ADDRESS_TASK,
+        ),
+        (
+            Task::WatchTransaction(WatchTransaction {
+                id: 3,
+                lifetime: 800_200,
+                hash: vec![0xab; 32],
+                confirmation_bound: 6,
+            }),
+            CAPTURED_WATCH_TRANSACTION_TASK,
+        ),
+        (
+            Task::BroadcastTransaction(BroadcastTransaction {
+                id: -1,
+                tx: vec![0xde, 0xad, 0xbe, 0xef],
+            }),
+            CAPTURED_BROADCAST_TRANSACTION_TASK,
+        ),
+    ];
+    for (task, captured) in tasks.iter() {
+        let bytes = serialize(task);
+        assert_eq!(hex::encode(&bytes), *captured);
+        assert_eq!(serialize(&deserialize::<Task>(&bytes).unwrap()), bytes);
+    }
+
+    let events = [
+        (
+            Event::HeightChanged(HeightChanged {
+                id: 1,
+                block: vec![0xcd; 32],
+                height: 800_000,
+            }),
+            CAPTURED_HEIGHT_CHANGED_EVENT,
+        ),
+        (
+            Event::AddressTransaction(AddressTransaction {
+                id: 2,
+                hash: vec![0xab; 32],
+                amount: 100_000,
+                block: vec![0xcd; 32],
+                tx: vec![0xde, 0xad, 0xbe, 0xef],
+            }),
+            CAPTURED_ADDRESS_TRANSACTION_EVENT,
+        ),
+        (
+            Event::TransactionConfirmations(TransactionConfirmations {
+                id: 3,
+                block: vec![],
+                confirmations: -1,
+            }),
+            CAPTURED_TRANSACTION_CONFIRMATIONS_EVENT,
+        ),
+        (
+            Event::TransactionBroadcasted(TransactionBroadcasted {
+                id: 4,
+                tx_len: 4,
+                tx: vec![0xde, 0xad, 0xbe, 0xef],
+                success_broadcast: 1,
+            }),
+            CAPTURED_TRANSACTION_BROADCASTED_EVENT,
+        ),
+        (
+            Event::TaskAborted(TaskAborted {
+                id: 7,
+                success_abort: 1,
+            }),
+            CAPTURED_TASK_ABORTED_EVENT,
+        ),
+    ];
+    for (event, captured) in events.iter() {
+        let bytes = serialize(event);
+        assert_eq!(hex::encode(&bytes), *captured);
+        assert_eq!(serialize(&deserialize::<Event>(&bytes).unwrap()), bytes);
+    }
+}
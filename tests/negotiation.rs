@@ -6,8 +6,13 @@ use farcaster_core::swap::btcxmr::BtcXmr;
 
 use farcaster_core::blockchain::{FeeStrategy, Network};
 use farcaster_core::consensus::{self, deserialize, serialize_hex};
-use farcaster_core::negotiation::{Buy, Offer, OfferId, PublicOffer, PublicOfferId, Sell};
+use farcaster_core::negotiation::{
+    self, AmountRange, Buy, Offer, OfferId, Price, PublicOffer, PublicOfferId, Sell,
+    ValidationPolicy, Version,
+};
+use farcaster_core::protocol_message::TakerAmount;
 use farcaster_core::role::SwapRole;
+use farcaster_core::swap::SwapId;
 
 use bitcoin::Amount;
 
@@ -73,6 +78,72 @@ fn maker_sell_arbitrating_assets_offer() {
     assert_eq!(offer.expect("an offer").maker_role, SwapRole::Bob);
 }
 
+#[test]
+fn maker_valid_offers() {
+    let policy = ValidationPolicy::default();
+    let offer = Buy::<BtcXmr>::some(BitcoinSegwitV0::new(), Amount::from_sat(100000))
+        .with(Monero, monero::Amount::from_pico(200))
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(10))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .to_valid_offer(&policy);
+    assert!(offer.is_ok());
+
+    let offer = Sell::<BtcXmr>::some(BitcoinSegwitV0::new(), Amount::from_sat(100000))
+        .for_some(Monero, monero::Amount::from_pico(200))
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(5))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .to_valid_offer(&policy);
+    assert!(matches!(
+        offer,
+        Err(negotiation::Error::PunishTimelockBeforeCancel)
+    ));
+
+    let offer = Sell::<BtcXmr>::some(BitcoinSegwitV0::new(), Amount::from_sat(100000))
+        .for_some(Monero, monero::Amount::from_pico(200))
+        .on(Network::Testnet)
+        .to_valid_offer(&policy);
+    assert!(matches!(offer, Err(negotiation::Error::IncompleteOffer)));
+}
+
+#[test]
+fn maker_offers_at_price() {
+    // 3 piconeros per 2 satoshis
+    let price = Price::new(3, 2).unwrap();
+    let offer: Offer<BtcXmr> = Buy::some(BitcoinSegwitV0::new(), Amount::from_sat(100001))
+        .at_price(Monero, price)
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(10))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .to_offer()
+        .unwrap();
+    assert_eq!(offer.maker_role, SwapRole::Alice);
+    assert_eq!(offer.accordant_amount, monero::Amount::from_pico(150001));
+    assert_eq!(offer.price(), Some(Price::new(150001, 100001).unwrap()));
+
+    let offer: Offer<BtcXmr> = Sell::some(BitcoinSegwitV0::new(), Amount::from_sat(100000))
+        .at_price(Monero, price)
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(10))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .to_offer()
+        .unwrap();
+    assert_eq!(offer.maker_role, SwapRole::Bob);
+    assert_eq!(offer.accordant_amount, monero::Amount::from_pico(150000));
+    assert_eq!(offer.price(), Some(price));
+
+    // Overflowing amounts do not produce an offer
+    let offer: Option<Offer<BtcXmr>> =
+        Sell::some(BitcoinSegwitV0::new(), Amount::from_sat(u64::MAX))
+            .at_price(Monero, price)
+            .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(10))
+            .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+            .on(Network::Testnet)
+            .to_offer();
+    assert!(offer.is_none());
+}
+
 #[test]
 fn serialize_public_offer() {
     let hex = "46435357415001000200000080800000800800a0860100000000000800c80000000000000004000\
@@ -150,3 +221,118 @@ fn parse_public_offer() {
         assert!(res.is_ok());
     }
 }
+
+fn range_public_offer() -> PublicOffer<BtcXmr> {
+    let offer: Offer<BtcXmr> = Sell::some(BitcoinSegwitV0::new(), Amount::from_sat(100000))
+        .for_some(Monero, monero::Amount::from_pico(200))
+        .with_timelocks(CSVTimelock::new(10), CSVTimelock::new(10))
+        .with_fee(FeeStrategy::Fixed(SatPerVByte::from_sat(20)))
+        .on(Network::Testnet)
+        .to_offer()
+        .unwrap();
+    let ip = FromStr::from_str("0.0.0.0").unwrap();
+    let port = FromStr::from_str("9735").unwrap();
+
+    let secp = secp256k1::Secp256k1::new();
+    let sk = bitcoin::PrivateKey::from_wif("L1HKVVLHXiUhecWnwFYF6L3shkf1E12HUmuZTESvBXUdx3yqVP1D")
+        .unwrap()
+        .key;
+    let node_id = secp256k1::PublicKey::from_secret_key(&secp, &sk);
+    let peer_address = InetSocketAddr::new(ip, port);
+    let range = AmountRange::new(Amount::from_sat(50000), Amount::from_sat(100000)).unwrap();
+    offer.to_public_v2(node_id, peer_address, range).unwrap()
+}
+
+#[test]
+fn serialize_public_offer_with_amount_range() {
+    let hex = "46435357415002000200000080800000800800a0860100000000000800c80000000000000004000\
+               a00000004000a000000010800140000000000000002011400080050c30000000000000800a08601\
+               0000000000210003b31a0a70343bb46f3db3768296ac5027f9873921b37f852860c690063ff9e4c9\
+               00000000000000000000000000000000000000000000000000000000000000000000260700";
+    let public_offer = range_public_offer();
+    assert_eq!(public_offer.version(), &Version::new_v2());
+    assert_eq!(hex, serialize_hex(&public_offer));
+    let strict_ser = strict_encoding::strict_serialize(&public_offer).unwrap();
+    assert_eq!(&hex::decode(hex).unwrap(), &strict_ser);
+    let res: PublicOffer<BtcXmr> = strict_encoding::strict_deserialize(&strict_ser).unwrap();
+    assert_eq!(&public_offer, &res);
+    let res = PublicOffer::<BtcXmr>::from_str(&public_offer.to_string()).unwrap();
+    assert_eq!(&public_offer, &res);
+
+    // A range with a minimum above its maximum is rejected when decoding
+    let invalid = hex.replace("080050c3000000000000", "0800f049020000000000");
+    let res: Result<PublicOffer<BtcXmr>, _> = deserialize(&hex::decode(invalid).unwrap()[..]);
+    assert!(res.is_err());
+
+    // A version 2 public offer without range is rejected when decoding
+    let missing = hex.replace("011400080050c30000000000000800a086010000000000", "00");
+    let res: Result<PublicOffer<BtcXmr>, _> = deserialize(&hex::decode(missing).unwrap()[..]);
+    assert!(res.is_err());
+}
+
+#[test]
+fn fill_public_offer_with_amount_range() {
+    let public_offer = range_public_offer();
+    assert!(public_offer.is_range());
+    assert!(public_offer.accepts_amount(&Amount::from_sat(50000)));
+    assert!(public_offer.accepts_amount(&Amount::from_sat(100000)));
+    assert!(!public_offer.accepts_amount(&Amount::from_sat(49999)));
+    assert!(!public_offer.accepts_amount(&Amount::from_sat(100001)));
+
+    let filled = public_offer.fill(Amount::from_sat(75000)).unwrap();
+    assert!(!filled.is_range());
+    assert_eq!(filled.version(), &Version::new_v1());
+    let res: PublicOffer<BtcXmr> = deserialize(&consensus::serialize(&filled)).unwrap();
+    assert_eq!(res, filled);
+    assert_eq!(filled.offer.arbitrating_amount, Amount::from_sat(75000));
+    assert_eq!(
+        filled.offer.accordant_amount,
+        monero::Amount::from_pico(150)
+    );
+    assert_eq!(filled.offer.price(), public_offer.offer.price());
+
+    assert!(matches!(
+        public_offer.fill(Amount::from_sat(100001)),
+        Err(negotiation::Error::AmountOutOfRange)
+    ));
+    assert!(matches!(
+        AmountRange::new(Amount::from_sat(0), Amount::from_sat(1)),
+        Err(negotiation::Error::InvalidAmountRange)
+    ));
+
+    // An arbitrating amount too small to buy a single accordant base unit is rejected
+    let range = AmountRange::new(Amount::from_sat(1), Amount::from_sat(100000)).unwrap();
+    let public_offer = public_offer
+        .offer
+        .to_public_v2(public_offer.node_id, public_offer.peer_address, range)
+        .unwrap();
+    assert!(matches!(
+        public_offer.fill(Amount::from_sat(499)),
+        Err(negotiation::Error::ZeroAmount)
+    ));
+    assert!(public_offer.fill(Amount::from_sat(500)).is_ok());
+}
+
+#[test]
+fn taker_commits_to_amount() {
+    let public_offer = range_public_offer();
+    let swap_id = SwapId::random();
+
+    assert!(TakerAmount::new(swap_id, &public_offer, Amount::from_sat(10)).is_err());
+    let msg = TakerAmount::new(swap_id, &public_offer, Amount::from_sat(60000)).unwrap();
+    let strict_ser = strict_encoding::strict_serialize(&msg).unwrap();
+    let res: TakerAmount<BtcXmr> = strict_encoding::strict_deserialize(&strict_ser).unwrap();
+    assert_eq!(res.public_offer_id, public_offer.id());
+    assert_eq!(res.arbitrating_amount, Amount::from_sat(60000));
+
+    let filled = res.fill(&public_offer).unwrap();
+    assert_eq!(filled.offer.arbitrating_amount, Amount::from_sat(60000));
+    assert_eq!(
+        filled.offer.accordant_amount,
+        monero::Amount::from_pico(120)
+    );
+
+    // The message does not apply to another public offer
+    let other = public_offer.fill(Amount::from_sat(60000)).unwrap();
+    assert!(res.fill(&other).is_err());
+}
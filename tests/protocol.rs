@@ -29,6 +29,20 @@ macro_rules! test_strict_ser {
     };
 }
 
+macro_rules! test_serde_yaml {
+    ($var:ident, $type:ty) => {
+        #[cfg(feature = "serde")]
+        {
+            let s = serde_yaml::to_string(&$var).expect("Encode in yaml");
+            let res: $type = serde_yaml::from_str(&s).expect("Decode from yaml");
+            assert_eq!(
+                strict_encoding::strict_serialize(&$var).unwrap(),
+                strict_encoding::strict_serialize(&res).unwrap()
+            );
+        }
+    };
+}
+
 fn init() -> (Alice<BtcXmr>, Bob<BtcXmr>, PublicOffer<BtcXmr>) {
     let hex = "46435357415001000200000080800000800800a0860100000000000800c80000000000000004000\
                a00000004000a000000010800140000000000000002210003b31a0a70343bb46f3db3768296ac50\
@@ -36,11 +50,11 @@ fn init() -> (Alice<BtcXmr>, Bob<BtcXmr>, PublicOffer<BtcXmr>) {
                00000000000000000000000260700";
 
     let destination_address =
-        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").expect("Parsable address");
+        Address::from_str("tb1qesgvtyx9y6lax0x34napc2m7t5zdq6s7vq4jh9").expect("Parsable address");
     let fee_politic = FeePriority::Low;
     let alice: Alice<BtcXmr> = Alice::new(destination_address, fee_politic);
     let refund_address =
-        Address::from_str("bc1qesgvtyx9y6lax0x34napc2m7t5zdq6s7xxwpvk").expect("Parsable address");
+        Address::from_str("tb1qesgvtyx9y6lax0x34napc2m7t5zdq6s7vq4jh9").expect("Parsable address");
     let bob: Bob<BtcXmr> = Bob::new(refund_address, fee_politic);
 
     let pub_offer: PublicOffer<BtcXmr> =
@@ -80,22 +94,31 @@ fn execute_offline_protocol() {
     let (alice_params, _alice_proof) = alice
         .generate_parameters(&mut alice_key_manager, &pub_offer)
         .unwrap();
+    test_serde_yaml!(
+        alice_params,
+        farcaster_core::bundle::AliceParameters<BtcXmr>
+    );
     let commit_alice_params =
         CommitAliceParameters::commit_to_bundle(swap_id, &commitment_engine, alice_params.clone());
     test_strict_ser!(commit_alice_params, CommitAliceParameters<BtcXmr>);
+    test_serde_yaml!(commit_alice_params, CommitAliceParameters<BtcXmr>);
 
     let (bob_params, _bob_proof) = bob
         .generate_parameters(&mut bob_key_manager, &pub_offer)
         .unwrap();
+    test_serde_yaml!(bob_params, farcaster_core::bundle::BobParameters<BtcXmr>);
     let commit_bob_params =
         CommitBobParameters::commit_to_bundle(swap_id, &commitment_engine, bob_params.clone());
     test_strict_ser!(commit_bob_params, CommitBobParameters<BtcXmr>);
+    test_serde_yaml!(commit_bob_params, CommitBobParameters<BtcXmr>);
 
     // Reveal
     let reveal_alice_params: RevealAliceParameters<BtcXmr> = (swap_id, alice_params.clone()).into();
     test_strict_ser!(reveal_alice_params, RevealAliceParameters<BtcXmr>);
+    test_serde_yaml!(reveal_alice_params, RevealAliceParameters<BtcXmr>);
     let reveal_bob_params: RevealBobParameters<BtcXmr> = (swap_id, bob_params.clone()).into();
     test_strict_ser!(reveal_bob_params, RevealBobParameters<BtcXmr>);
+    test_serde_yaml!(reveal_bob_params, RevealBobParameters<BtcXmr>);
 
     assert!(commit_alice_params
         .verify_with_reveal(&commitment_engine, reveal_alice_params)
@@ -134,13 +157,25 @@ fn execute_offline_protocol() {
     let core = bob
         .core_arbitrating_transactions(&alice_params, &bob_params, funding, &pub_offer)
         .unwrap();
+    test_serde_yaml!(
+        core,
+        farcaster_core::bundle::CoreArbitratingTransactions<BitcoinSegwitV0>
+    );
+    // PSBTs are serialized in their standard base64 form
+    #[cfg(feature = "serde")]
+    assert!(serde_yaml::to_string(&core).unwrap().contains("cHNidP8"));
     let bob_cosign_cancel = bob
         .cosign_arbitrating_cancel(&mut bob_key_manager, &core)
         .unwrap();
+    test_serde_yaml!(
+        bob_cosign_cancel,
+        farcaster_core::bundle::CosignedArbitratingCancel<BitcoinSegwitV0>
+    );
 
     let core_arb_setup: CoreArbitratingSetup<BtcXmr> =
         (swap_id, core.clone(), bob_cosign_cancel.clone()).into();
     test_strict_ser!(core_arb_setup, CoreArbitratingSetup<BtcXmr>);
+    test_serde_yaml!(core_arb_setup, CoreArbitratingSetup<BtcXmr>);
 
     //
     // Sign the refund procedure
@@ -154,6 +189,10 @@ fn execute_offline_protocol() {
             &pub_offer,
         )
         .unwrap();
+    test_serde_yaml!(
+        adaptor_refund,
+        farcaster_core::bundle::SignedAdaptorRefund<BitcoinSegwitV0>
+    );
     let alice_cosign_cancel = alice
         .cosign_arbitrating_cancel(
             &mut alice_key_manager,
@@ -167,6 +206,7 @@ fn execute_offline_protocol() {
     let refund_proc_sig: RefundProcedureSignatures<BtcXmr> =
         (swap_id, alice_cosign_cancel.clone(), adaptor_refund.clone()).into();
     test_strict_ser!(refund_proc_sig, RefundProcedureSignatures<BtcXmr>);
+    test_serde_yaml!(refund_proc_sig, RefundProcedureSignatures<BtcXmr>);
 
     //
     // Validate the refund procedure and sign the buy procedure
@@ -188,9 +228,17 @@ fn execute_offline_protocol() {
             &pub_offer,
         )
         .unwrap();
+    test_serde_yaml!(
+        adaptor_buy,
+        farcaster_core::bundle::SignedAdaptorBuy<BitcoinSegwitV0>
+    );
     let signed_lock = bob
         .sign_arbitrating_lock(&mut bob_key_manager, &core)
         .unwrap();
+    test_serde_yaml!(
+        signed_lock,
+        farcaster_core::bundle::SignedArbitratingLock<BitcoinSegwitV0>
+    );
 
     let mut lock = LockTx::from_partial(core.lock.clone());
     lock.add_witness(funding_key, signed_lock.lock_sig).unwrap();
@@ -201,6 +249,7 @@ fn execute_offline_protocol() {
 
     let buy_proc_sig: BuyProcedureSignature<BtcXmr> = (swap_id, adaptor_buy.clone()).into();
     test_strict_ser!(buy_proc_sig, BuyProcedureSignature<BtcXmr>);
+    test_serde_yaml!(buy_proc_sig, BuyProcedureSignature<BtcXmr>);
 
     //
     // IF BUY PATH:
@@ -229,6 +278,10 @@ fn execute_offline_protocol() {
             &adaptor_buy,
         )
         .unwrap();
+    test_serde_yaml!(
+        fully_sign_buy,
+        farcaster_core::bundle::FullySignedBuy<BitcoinSegwitV0>
+    );
 
     let mut buy = BuyTx::from_partial(adaptor_buy.buy.clone());
     buy.add_witness(bob_params.buy, fully_sign_buy.buy_adapted_sig)
@@ -285,6 +338,10 @@ fn execute_offline_protocol() {
     let fully_signed_refund = bob
         .fully_sign_refund(&mut bob_key_manager, core.clone(), &adaptor_refund)
         .unwrap();
+    test_serde_yaml!(
+        fully_signed_refund,
+        farcaster_core::bundle::FullySignedRefund<BitcoinSegwitV0>
+    );
 
     let mut refund = RefundTx::from_partial(core.refund.clone());
     refund
@@ -337,6 +394,10 @@ fn execute_offline_protocol() {
             &pub_offer,
         )
         .unwrap();
+    test_serde_yaml!(
+        fully_signed_punish,
+        farcaster_core::bundle::FullySignedPunish<BitcoinSegwitV0>
+    );
 
     let mut punish = PunishTx::from_partial(fully_signed_punish.punish);
     punish
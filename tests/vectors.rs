@@ -0,0 +1,1126 @@
+//! Test vectors of the consensus encoding of the wire formats, for implementations in other
+//! languages and to catch accidental format changes.
+//!
+//! Each vector lists the encoding of the fields of a value in declaration order, the encoded
+//! value is their concatenation. The vectors are written in `tests/vectors/<name>.json` with:
+//!
+//! ```text
+//! cargo test --test vectors -- --ignored generate_test_vectors
+//! ```
+//!
+//! and `check_test_vectors` asserts that the encoder and the decoder match the committed files
+//! byte for byte.
+
+use farcaster_core::bitcoin::BitcoinSegwitV0;
+use farcaster_core::bundle::{
+    AliceParameters, BobParameters, CoreArbitratingTransactions, CosignedArbitratingCancel,
+    FullySignedBuy, FullySignedPunish, FullySignedRefund, FundingTransaction, Proof,
+    SignedAdaptorBuy, SignedAdaptorRefund, SignedArbitratingLock,
+};
+use farcaster_core::consensus::{
+    self, deserialize, serialize, CanonicalBytes, Decodable, Encodable,
+};
+use farcaster_core::crypto::dleq::{DLEQProof, DLEQProofFormat, DLEQ_BITS};
+use farcaster_core::negotiation::PublicOffer;
+use farcaster_core::protocol_message::{
+    Abort, BuyProcedureSignature, CommitAliceParameters, CommitBobParameters, CoreArbitratingSetup,
+    Handshake, MessageFrame, ProtocolMessage, RefundProcedureSignatures, RevealAliceParameters,
+    RevealBobParameters, RevealProof, TakerAmount,
+};
+use farcaster_core::swap::btcxmr::{BtcXmr, SaltedBtcXmr};
+use farcaster_core::syncer::{Event, Task};
+use farcaster_core::transaction::TxLabel;
+
+use bitcoin::secp256k1::{PublicKey, SecretKey};
+use ecdsa_fun::adaptor::{Adaptor, HashTranscript};
+use ecdsa_fun::fun::{Point, Scalar};
+use ecdsa_fun::nonce;
+use rand_chacha::ChaCha20Rng;
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const SWAP_ID: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+const PUBLIC_OFFER_ID: &str = "202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f";
+
+const ED25519_BASEPOINT: &str = "5866666666666666666666666666666666666666666666666666666666666666";
+const ED25519_ONE: &str = "0100000000000000000000000000000000000000000000000000000000000000";
+const SECP256K1_GENERATOR_X: &str =
+    "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+const SECP256K1_ONE: &str = "0000000000000000000000000000000000000000000000000000000000000001";
+
+struct Vector {
+    description: &'static str,
+    // name and hex encoding of each field
+    inputs: Vec<(&'static str, String)>,
+}
+
+fn vector(description: &'static str, inputs: &[(&'static str, &str)]) -> Vector {
+    Vector {
+        description,
+        inputs: inputs
+            .iter()
+            .map(|(name, hex)| (*name, hex.to_string()))
+            .collect(),
+    }
+}
+
+impl Vector {
+    fn encoded(&self) -> String {
+        self.inputs.iter().map(|(_, hex)| hex.as_str()).collect()
+    }
+}
+
+struct VectorSet {
+    name: &'static str,
+    type_name: &'static str,
+    // decode the value and encode it again
+    roundtrip: fn(&[u8]) -> Result<Vec<u8>, consensus::Error>,
+    vectors: Vec<Vector>,
+}
+
+fn roundtrip<T: Decodable + Encodable + std::fmt::Debug>(
+    bytes: &[u8],
+) -> Result<Vec<u8>, consensus::Error> {
+    Ok(serialize(&deserialize::<T>(bytes)?))
+}
+
+impl VectorSet {
+    fn path(&self) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("vectors")
+            .join(format!("{}.json", self.name))
+    }
+
+    // Assert the encoded value of each vector round-trips before serializing the set.
+    fn to_json(&self) -> Value {
+        let vectors: Vec<Value> = self
+            .vectors
+            .iter()
+            .map(|vector| {
+                let encoded = vector.encoded();
+                self.assert_roundtrip(vector.description, &encoded);
+                let inputs: Vec<Value> = vector
+                    .inputs
+                    .iter()
+                    .map(|(name, hex)| json!({ "name": name, "hex": hex }))
+                    .collect();
+                json!({
+                    "description": vector.description,
+                    "inputs": inputs,
+                    "encoded": encoded,
+                })
+            })
+            .collect();
+        json!({ "type": self.type_name, "vectors": vectors })
+    }
+
+    fn assert_roundtrip(&self, description: &str, encoded: &str) {
+        let bytes = hex::decode(encoded).expect("Valid hex");
+        match (self.roundtrip)(&bytes) {
+            Ok(res) => assert_eq!(
+                hex::encode(res),
+                encoded,
+                "{}: {} is not re-encoded identically",
+                self.type_name,
+                description
+            ),
+            Err(e) => panic!(
+                "{}: {} cannot be decoded: {}",
+                self.type_name, description, e
+            ),
+        }
+    }
+}
+
+// Fields of a well formed proof in the compact format, the commitments are the generators and
+// the scalars are one. Decoding parses the elements but does not verify the proof.
+fn compact_proof() -> Vec<(&'static str, String)> {
+    let ring_signature = [
+        "2a".repeat(32).as_str(),
+        ED25519_ONE,
+        SECP256K1_ONE,
+        ED25519_ONE,
+        SECP256K1_ONE,
+    ]
+    .concat();
+    vec![
+        ("proof_length", "21dd".to_string()),
+        ("version", "01".to_string()),
+        ("c_g", ED25519_BASEPOINT.repeat(DLEQ_BITS)),
+        ("c_h_parities", "00".repeat(32)),
+        ("c_h", SECP256K1_GENERATOR_X.repeat(DLEQ_BITS)),
+        ("ring_signatures", ring_signature.repeat(DLEQ_BITS)),
+        ("pok_0", [ED25519_BASEPOINT, ED25519_ONE].concat()),
+        ("pok_1", [SECP256K1_ONE, SECP256K1_ONE].concat()),
+    ]
+}
+
+fn reveal_proof() -> VectorSet {
+    let mut inputs = vec![("swap_id", SWAP_ID.to_string())];
+    inputs.extend(compact_proof());
+    VectorSet {
+        name: "reveal_proof",
+        type_name: "RevealProof",
+        roundtrip: roundtrip::<RevealProof<BtcXmr>>,
+        vectors: vec![Vector {
+            description: "compact cross group discrete logarithm proof",
+            inputs,
+        }],
+    }
+}
+
+fn proof() -> VectorSet {
+    VectorSet {
+        name: "proof",
+        type_name: "Proof",
+        roundtrip: roundtrip::<Proof<BtcXmr>>,
+        vectors: vec![Vector {
+            description: "compact cross group discrete logarithm proof",
+            inputs: compact_proof(),
+        }],
+    }
+}
+
+// Decode a proof in any format and encode it again in the legacy format.
+fn legacy_roundtrip(bytes: &[u8]) -> Result<Vec<u8>, consensus::Error> {
+    Ok(deserialize::<DLEQProof>(bytes)?.to_bytes_with_format(DLEQProofFormat::Legacy))
+}
+
+fn legacy_dleq_proof() -> VectorSet {
+    // The proof of the compact vector in the legacy format, the ring signature challenge is
+    // encoded with its reductions modulo the order of each group.
+    let e_g = "50823e70f563057a7df03ae46c366c002a2a2a2a2a2a2a2a2a2a2a2a2a2a2a0a";
+    let ring_signature = [
+        e_g,
+        "2a".repeat(32).as_str(),
+        ED25519_ONE,
+        SECP256K1_ONE,
+        ED25519_ONE,
+        SECP256K1_ONE,
+    ]
+    .concat();
+    let c_h = ["02", SECP256K1_GENERATOR_X].concat();
+    // vectors are prefixed with their number of elements
+    let bits = hex::encode(serialize(&(DLEQ_BITS as u16)));
+    VectorSet {
+        name: "legacy_dleq_proof",
+        type_name: "DLEQProof",
+        roundtrip: legacy_roundtrip,
+        vectors: vec![vector(
+            "legacy cross group discrete logarithm proof",
+            &[
+                (
+                    "c_g",
+                    &[bits.as_str(), &ED25519_BASEPOINT.repeat(DLEQ_BITS)].concat(),
+                ),
+                ("c_h", &[bits.as_str(), &c_h.repeat(DLEQ_BITS)].concat()),
+                (
+                    "ring_signatures",
+                    &[bits.as_str(), &ring_signature.repeat(DLEQ_BITS)].concat(),
+                ),
+                ("pok_0", &[ED25519_BASEPOINT, ED25519_ONE].concat()),
+                ("pok_1", &[SECP256K1_ONE, SECP256K1_ONE].concat()),
+            ],
+        )],
+    }
+}
+
+// Length prefixed canonical bytes of an adaptor signature. The signature is produced with a
+// deterministic nonce so the vectors are stable.
+fn encrypted_signature() -> String {
+    let adaptor =
+        Adaptor::<HashTranscript<Sha256, ChaCha20Rng>, nonce::Deterministic<Sha256>>::default();
+    let signing_key = Scalar::from(SecretKey::from_slice(&[0x2a; 32]).unwrap());
+    let encryption_key = Point::from(
+        PublicKey::from_str("02c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5")
+            .unwrap(),
+    );
+    let sig = adaptor.encrypted_sign(&signing_key, &encryption_key, &[0x42; 32]);
+    hex::encode(serialize(&sig.as_canonical_bytes()))
+}
+
+// Length prefixed partially signed transaction spending the output `byte` repeated.
+fn psbt(byte: u8) -> String {
+    format!(
+        "5d0070736274ff0100520200000001{}0000000000ffffffff01a0860100000000001600140f0f0f0f0f0f0f0f\
+         0f0f0f0f0f0f0f0f0f0f0f0f00000000000000",
+        format!("{:02x}", byte).repeat(32)
+    )
+}
+
+// Length prefixed compact signature with `r` and `s` bytes repeated.
+fn signature(r: u8, s: u8) -> String {
+    format!(
+        "4000{}{}",
+        format!("{:02x}", r).repeat(32),
+        format!("{:02x}", s).repeat(32)
+    )
+}
+
+fn buy_procedure_signature() -> VectorSet {
+    VectorSet {
+        name: "buy_procedure_signature",
+        type_name: "BuyProcedureSignature",
+        roundtrip: roundtrip::<BuyProcedureSignature<BtcXmr>>,
+        vectors: vec![vector(
+            "buy transaction with its adaptor signature",
+            &[
+                ("swap_id", SWAP_ID),
+                ("buy", &psbt(0xdd)),
+                ("buy_adaptor_sig", &encrypted_signature()),
+            ],
+        )],
+    }
+}
+
+fn refund_procedure_signatures() -> VectorSet {
+    VectorSet {
+        name: "refund_procedure_signatures",
+        type_name: "RefundProcedureSignatures",
+        roundtrip: roundtrip::<RefundProcedureSignatures<BtcXmr>>,
+        vectors: vec![vector(
+            "cancel signature and refund adaptor signature",
+            &[
+                ("swap_id", SWAP_ID),
+                ("cancel_sig", &signature(0x11, 0x22)),
+                ("refund_adaptor_sig", &encrypted_signature()),
+            ],
+        )],
+    }
+}
+
+fn tx_label() -> VectorSet {
+    VectorSet {
+        name: "tx_label",
+        type_name: "TxLabel",
+        roundtrip: roundtrip::<TxLabel>,
+        vectors: vec![
+            vector("Funding label", &[("tag", "0100")]),
+            vector("Lock label", &[("tag", "0200")]),
+            vector("Buy label", &[("tag", "0300")]),
+            vector("Cancel label", &[("tag", "0400")]),
+            vector("Refund label", &[("tag", "0500")]),
+            vector("Punish label", &[("tag", "0600")]),
+            vector("AccLock label", &[("tag", "0700")]),
+        ],
+    }
+}
+
+fn syncer_task() -> VectorSet {
+    VectorSet {
+        name: "syncer_task",
+        type_name: "Task",
+        roundtrip: roundtrip::<Task>,
+        vectors: vec![
+            vector("abort task", &[("tag", "01"), ("id", "07000000")]),
+            vector(
+                "watch height task",
+                &[
+                    ("tag", "02"),
+                    ("id", "01000000"),
+                    ("lifetime", "00350c0000000000"),
+                    ("addendum", "02000102"),
+                ],
+            ),
+            vector(
+                "watch address task",
+                &[
+                    ("tag", "03"),
+                    ("id", "02000000"),
+                    ("lifetime", "64350c0000000000"),
+                    ("addendum", "150000140102030405060708090a0b0c0d0e0f10111213"),
+                    ("include_tx", "01"),
+                ],
+            ),
+            vector(
+                "watch transaction task",
+                &[
+                    ("tag", "04"),
+                    ("id", "03000000"),
+                    ("lifetime", "c8350c0000000000"),
+                    (
+                        "hash",
+                        "2000abababababababababababababababababababababababababababababababab",
+                    ),
+                    ("confirmation_bound", "0600"),
+                ],
+            ),
+            vector(
+                "broadcast transaction task",
+                &[("tag", "05"), ("id", "ffffffff"), ("tx", "0400deadbeef")],
+            ),
+        ],
+    }
+}
+
+fn syncer_event() -> VectorSet {
+    VectorSet {
+        name: "syncer_event",
+        type_name: "Event",
+        roundtrip: roundtrip::<Event>,
+        vectors: vec![
+            vector(
+                "height changed event",
+                &[
+                    ("tag", "01"),
+                    ("id", "01000000"),
+                    (
+                        "block",
+                        "2000cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd",
+                    ),
+                    ("height", "00350c0000000000"),
+                ],
+            ),
+            vector(
+                "address transaction event",
+                &[
+                    ("tag", "02"),
+                    ("id", "02000000"),
+                    (
+                        "hash",
+                        "2000abababababababababababababababababababababababababababababababab",
+                    ),
+                    ("amount", "a086010000000000"),
+                    (
+                        "block",
+                        "2000cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd",
+                    ),
+                    ("tx", "0400deadbeef"),
+                ],
+            ),
+            vector(
+                "transaction confirmations event",
+                &[
+                    ("tag", "03"),
+                    ("id", "03000000"),
+                    ("block", "0000"),
+                    ("confirmations", "ffffffff"),
+                ],
+            ),
+            vector(
+                "transaction broadcasted event",
+                &[
+                    ("tag", "04"),
+                    ("id", "04000000"),
+                    ("tx_len", "0400"),
+                    ("tx", "0400deadbeef"),
+                    ("success_broadcast", "01000000"),
+                ],
+            ),
+            vector(
+                "task aborted event",
+                &[
+                    ("tag", "05"),
+                    ("id", "07000000"),
+                    ("success_abort", "01000000"),
+                ],
+            ),
+        ],
+    }
+}
+
+// Fields of a public offer up to the maker role, shared by all the versions.
+const PUBLIC_OFFER_FIELDS: [(&str, &str); 9] = [
+    ("network", "02"),
+    ("arbitrating_blockchain", "00000080"),
+    ("accordant_blockchain", "80000080"),
+    ("arbitrating_amount", "0800a086010000000000"),
+    ("accordant_amount", "0800c800000000000000"),
+    ("cancel_timelock", "04000a000000"),
+    ("punish_timelock", "04000a000000"),
+    ("fee_strategy", "0108001400000000000000"),
+    ("maker_role", "02"),
+];
+
+const NODE_ID: &str = "210003b31a0a70343bb46f3db3768296ac5027f9873921b37f852860c690063ff9e4c9";
+
+const PEER_ADDRESS: &str = "00000000000000000000000000000000000000000000000000000000000000000000\
+                            260700";
+
+fn public_offer_vector(
+    description: &'static str,
+    version: &'static str,
+    amount_range: Option<&'static str>,
+) -> Vector {
+    let mut inputs = vec![("magic_bytes", "464353574150"), ("version", version)];
+    inputs.extend_from_slice(&PUBLIC_OFFER_FIELDS);
+    if let Some(amount_range) = amount_range {
+        inputs.push(("amount_range", amount_range));
+    }
+    inputs.extend_from_slice(&[("node_id", NODE_ID), ("peer_address", PEER_ADDRESS)]);
+    vector(description, &inputs)
+}
+
+fn public_offer() -> VectorSet {
+    VectorSet {
+        name: "public_offer",
+        type_name: "PublicOffer",
+        roundtrip: roundtrip::<PublicOffer<BtcXmr>>,
+        vectors: vec![
+            public_offer_vector("version 1 public offer", "0100", None),
+            public_offer_vector(
+                "version 2 public offer with an amount range",
+                "0200",
+                Some("011400080050c30000000000000800a086010000000000"),
+            ),
+        ],
+    }
+}
+
+fn handshake() -> VectorSet {
+    VectorSet {
+        name: "handshake",
+        type_name: "Handshake",
+        roundtrip: roundtrip::<Handshake>,
+        vectors: vec![
+            vector(
+                "handshake with the supported features",
+                &[
+                    ("swap_id", SWAP_ID),
+                    ("versions", "01000100"),
+                    ("features", "0200000000000000"),
+                ],
+            ),
+            vector(
+                "handshake with several versions and a required feature",
+                &[
+                    ("swap_id", SWAP_ID),
+                    ("versions", "020001000200"),
+                    ("features", "0100000000000000"),
+                ],
+            ),
+        ],
+    }
+}
+
+fn taker_amount() -> VectorSet {
+    VectorSet {
+        name: "taker_amount",
+        type_name: "TakerAmount",
+        roundtrip: roundtrip::<TakerAmount<BtcXmr>>,
+        vectors: vec![vector(
+            "taker amount",
+            &[
+                ("swap_id", SWAP_ID),
+                ("public_offer_id", PUBLIC_OFFER_ID),
+                ("arbitrating_amount", "0800a086010000000000"),
+            ],
+        )],
+    }
+}
+
+// Length prefixed commitment of `byte` repeated.
+fn commitment(byte: u8) -> String {
+    format!("2000{}", format!("{:02x}", byte).repeat(32))
+}
+
+// Vector of a commit message, only Alice commits to a punish key.
+fn commit_vector(description: &'static str, punish: bool, extra_keys: bool) -> Vector {
+    let mut inputs = vec![
+        ("swap_id", SWAP_ID.to_string()),
+        ("buy", commitment(0x01)),
+        ("cancel", commitment(0x02)),
+        ("refund", commitment(0x03)),
+    ];
+    if punish {
+        inputs.push(("punish", commitment(0x04)));
+    }
+    inputs.push(("adaptor", commitment(0x05)));
+    if extra_keys {
+        inputs.extend(vec![
+            (
+                "extra_arbitrating_keys",
+                ["01000700", &commitment(0x06)].concat(),
+            ),
+            (
+                "arbitrating_shared_keys",
+                ["01000100", &commitment(0x07)].concat(),
+            ),
+            ("spend", commitment(0x08)),
+            (
+                "extra_accordant_keys",
+                ["01000700", &commitment(0x09)].concat(),
+            ),
+            (
+                "accordant_shared_keys",
+                ["01000100", &commitment(0x0a)].concat(),
+            ),
+        ]);
+    } else {
+        inputs.extend(vec![
+            ("extra_arbitrating_keys", "0000".to_string()),
+            ("arbitrating_shared_keys", "0000".to_string()),
+            ("spend", commitment(0x08)),
+            ("extra_accordant_keys", "0000".to_string()),
+            ("accordant_shared_keys", "0000".to_string()),
+        ]);
+    }
+    Vector {
+        description,
+        inputs,
+    }
+}
+
+fn commit_alice_parameters() -> VectorSet {
+    VectorSet {
+        name: "commit_alice_parameters",
+        type_name: "CommitAliceParameters",
+        roundtrip: roundtrip::<CommitAliceParameters<BtcXmr>>,
+        vectors: vec![
+            commit_vector("commitments without extra keys", true, false),
+            commit_vector("commitments with extra and shared keys", true, true),
+        ],
+    }
+}
+
+fn commit_bob_parameters() -> VectorSet {
+    VectorSet {
+        name: "commit_bob_parameters",
+        type_name: "CommitBobParameters",
+        roundtrip: roundtrip::<CommitBobParameters<BtcXmr>>,
+        vectors: vec![
+            commit_vector("commitments without extra keys", false, false),
+            commit_vector("commitments with extra and shared keys", false, true),
+        ],
+    }
+}
+
+const REVEAL_ADDRESS: &str = "2a006263317177353038643671656a7874646734793572337a6172766172793063\
+                              357877376b763866337434";
+
+// Fields of the public keys revealed by a participant, only Alice has a punish key.
+fn key_inputs(punish: bool, extra_keys: bool) -> Vec<(&'static str, &'static str)> {
+    let mut inputs = vec![
+        (
+            "buy",
+            "21000279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+        ),
+        (
+            "cancel",
+            "210002c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee5",
+        ),
+        (
+            "refund",
+            "210002f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+        ),
+    ];
+    if punish {
+        inputs.push((
+            "punish",
+            "210002e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13",
+        ));
+    }
+    inputs.push((
+        "adaptor",
+        "2100022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4",
+    ));
+    let spend = "20005866666666666666666666666666666666666666666666666666666666666666";
+    if extra_keys {
+        inputs.extend_from_slice(&[
+            (
+                "extra_arbitrating_keys",
+                "01000700210003fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556",
+            ),
+            (
+                "arbitrating_shared_keys",
+                "010001002000000000000000000000000000000000000000000000000000000000000000002a",
+            ),
+            ("spend", spend),
+            (
+                "extra_accordant_keys",
+                "0100070020005866666666666666666666666666666666666666666666666666666666666666",
+            ),
+            (
+                "accordant_shared_keys",
+                "0100010020002a00000000000000000000000000000000000000000000000000000000000000",
+            ),
+        ]);
+    } else {
+        inputs.extend_from_slice(&[
+            ("extra_arbitrating_keys", "0000"),
+            ("arbitrating_shared_keys", "0000"),
+            ("spend", spend),
+            ("extra_accordant_keys", "0000"),
+            ("accordant_shared_keys", "0000"),
+        ]);
+    }
+    inputs
+}
+
+// Vector of a reveal message, Alice reveals her punish key and the nonces are only encoded with
+// salted commitments.
+fn reveal_vector(
+    description: &'static str,
+    punish: bool,
+    extra_keys: bool,
+    nonces: Option<&'static str>,
+) -> Vector {
+    let mut inputs = vec![("swap_id", SWAP_ID)];
+    inputs.extend(key_inputs(punish, extra_keys));
+    inputs.push(("address", REVEAL_ADDRESS));
+    if let Some(nonces) = nonces {
+        inputs.push(("nonces", nonces));
+    }
+    vector(description, &inputs)
+}
+
+// Nonces of a salted reveal, a nonce for the buy key and one for the spend key.
+const REVEAL_NONCES: &str = "02000120001111111111111111111111111111111111111111111111111111111111\
+                             11111108200088888888888888888888888888888888888888888888888888888888\
+                             88888888";
+
+fn reveal_alice_parameters() -> VectorSet {
+    VectorSet {
+        name: "reveal_alice_parameters",
+        type_name: "RevealAliceParameters",
+        roundtrip: roundtrip::<RevealAliceParameters<BtcXmr>>,
+        vectors: vec![
+            reveal_vector("reveal without extra keys", true, false, None),
+            reveal_vector("reveal with extra and shared keys", true, true, None),
+        ],
+    }
+}
+
+fn salted_reveal_alice_parameters() -> VectorSet {
+    VectorSet {
+        name: "salted_reveal_alice_parameters",
+        type_name: "RevealAliceParameters",
+        roundtrip: roundtrip::<RevealAliceParameters<SaltedBtcXmr>>,
+        vectors: vec![reveal_vector(
+            "salted reveal with extra and shared keys",
+            true,
+            true,
+            Some(REVEAL_NONCES),
+        )],
+    }
+}
+
+fn reveal_bob_parameters() -> VectorSet {
+    VectorSet {
+        name: "reveal_bob_parameters",
+        type_name: "RevealBobParameters",
+        roundtrip: roundtrip::<RevealBobParameters<BtcXmr>>,
+        vectors: vec![
+            reveal_vector("reveal without extra keys", false, false, None),
+            reveal_vector("reveal with extra and shared keys", false, true, None),
+        ],
+    }
+}
+
+fn salted_reveal_bob_parameters() -> VectorSet {
+    VectorSet {
+        name: "salted_reveal_bob_parameters",
+        type_name: "RevealBobParameters",
+        roundtrip: roundtrip::<RevealBobParameters<SaltedBtcXmr>>,
+        vectors: vec![reveal_vector(
+            "salted reveal with extra and shared keys",
+            false,
+            true,
+            Some(REVEAL_NONCES),
+        )],
+    }
+}
+
+fn core_arbitrating_setup() -> VectorSet {
+    VectorSet {
+        name: "core_arbitrating_setup",
+        type_name: "CoreArbitratingSetup",
+        roundtrip: roundtrip::<CoreArbitratingSetup<BtcXmr>>,
+        vectors: vec![vector(
+            "core arbitrating transactions",
+            &[
+                ("swap_id", SWAP_ID),
+                ("lock", &psbt(0xaa)),
+                ("cancel", &psbt(0xbb)),
+                ("refund", &psbt(0xcc)),
+                ("cancel_sig", &signature(0x11, 0x22)),
+            ],
+        )],
+    }
+}
+
+fn alice_parameters() -> VectorSet {
+    let mut inputs = key_inputs(true, true);
+    inputs.extend_from_slice(&[
+        ("destination_address", REVEAL_ADDRESS),
+        ("cancel_timelock", "0104000a000000"),
+        ("punish_timelock", "01040014000000"),
+        ("fee_strategy", "010b000108001400000000000000"),
+    ]);
+    VectorSet {
+        name: "alice_parameters",
+        type_name: "AliceParameters",
+        roundtrip: roundtrip::<AliceParameters<BtcXmr>>,
+        vectors: vec![vector(
+            "parameters with extra and shared keys and a fixed fee",
+            &inputs,
+        )],
+    }
+}
+
+fn bob_parameters() -> VectorSet {
+    let mut inputs = key_inputs(false, false);
+    inputs.extend_from_slice(&[
+        ("refund_address", REVEAL_ADDRESS),
+        ("cancel_timelock", "00"),
+        ("punish_timelock", "00"),
+        (
+            "fee_strategy",
+            "0115000208000a0000000000000008001e00000000000000",
+        ),
+    ]);
+    VectorSet {
+        name: "bob_parameters",
+        type_name: "BobParameters",
+        roundtrip: roundtrip::<BobParameters<BtcXmr>>,
+        vectors: vec![vector(
+            "parameters without timelocks and with a fee range",
+            &inputs,
+        )],
+    }
+}
+
+fn cosigned_arbitrating_cancel() -> VectorSet {
+    VectorSet {
+        name: "cosigned_arbitrating_cancel",
+        type_name: "CosignedArbitratingCancel",
+        roundtrip: roundtrip::<CosignedArbitratingCancel<BitcoinSegwitV0>>,
+        vectors: vec![vector(
+            "cancel signature",
+            &[("cancel_sig", &signature(0x11, 0x12))],
+        )],
+    }
+}
+
+fn funding_transaction() -> VectorSet {
+    VectorSet {
+        name: "funding_transaction",
+        type_name: "FundingTransaction",
+        roundtrip: roundtrip::<FundingTransaction<BitcoinSegwitV0>>,
+        vectors: vec![vector(
+            "funding transaction",
+            &[(
+                "funding",
+                &format!(
+                    "52000200000001{}0000000000ffffffff01a0860100000000001600140f0f0f0f0f0f0f0f0f0f\
+                     0f0f0f0f0f0f0f0f0f0f00000000",
+                    "ee".repeat(32)
+                ),
+            )],
+        )],
+    }
+}
+
+fn core_arbitrating_transactions() -> VectorSet {
+    VectorSet {
+        name: "core_arbitrating_transactions",
+        type_name: "CoreArbitratingTransactions",
+        roundtrip: roundtrip::<CoreArbitratingTransactions<BitcoinSegwitV0>>,
+        vectors: vec![vector(
+            "lock, cancel, and refund transactions",
+            &[
+                ("lock", &psbt(0xaa)),
+                ("cancel", &psbt(0xbb)),
+                ("refund", &psbt(0xcc)),
+            ],
+        )],
+    }
+}
+
+fn signed_adaptor_buy() -> VectorSet {
+    VectorSet {
+        name: "signed_adaptor_buy",
+        type_name: "SignedAdaptorBuy",
+        roundtrip: roundtrip::<SignedAdaptorBuy<BitcoinSegwitV0>>,
+        vectors: vec![vector(
+            "buy transaction with its adaptor signature",
+            &[
+                ("buy", &psbt(0xdd)),
+                ("buy_adaptor_sig", &encrypted_signature()),
+            ],
+        )],
+    }
+}
+
+fn fully_signed_buy() -> VectorSet {
+    VectorSet {
+        name: "fully_signed_buy",
+        type_name: "FullySignedBuy",
+        roundtrip: roundtrip::<FullySignedBuy<BitcoinSegwitV0>>,
+        vectors: vec![vector(
+            "buy signature and adapted signature",
+            &[
+                ("buy_sig", &signature(0x11, 0x12)),
+                ("buy_adapted_sig", &signature(0x33, 0x34)),
+            ],
+        )],
+    }
+}
+
+fn signed_adaptor_refund() -> VectorSet {
+    VectorSet {
+        name: "signed_adaptor_refund",
+        type_name: "SignedAdaptorRefund",
+        roundtrip: roundtrip::<SignedAdaptorRefund<BitcoinSegwitV0>>,
+        vectors: vec![vector(
+            "refund adaptor signature",
+            &[("refund_adaptor_sig", &encrypted_signature())],
+        )],
+    }
+}
+
+fn fully_signed_refund() -> VectorSet {
+    VectorSet {
+        name: "fully_signed_refund",
+        type_name: "FullySignedRefund",
+        roundtrip: roundtrip::<FullySignedRefund<BitcoinSegwitV0>>,
+        vectors: vec![vector(
+            "refund signature and adapted signature",
+            &[
+                ("refund_sig", &signature(0x11, 0x12)),
+                ("refund_adapted_sig", &signature(0x33, 0x34)),
+            ],
+        )],
+    }
+}
+
+fn signed_arbitrating_lock() -> VectorSet {
+    VectorSet {
+        name: "signed_arbitrating_lock",
+        type_name: "SignedArbitratingLock",
+        roundtrip: roundtrip::<SignedArbitratingLock<BitcoinSegwitV0>>,
+        vectors: vec![vector(
+            "lock signature",
+            &[("lock_sig", &signature(0x55, 0x56))],
+        )],
+    }
+}
+
+fn fully_signed_punish() -> VectorSet {
+    VectorSet {
+        name: "fully_signed_punish",
+        type_name: "FullySignedPunish",
+        roundtrip: roundtrip::<FullySignedPunish<BitcoinSegwitV0>>,
+        vectors: vec![vector(
+            "punish transaction with its signature",
+            &[
+                ("punish", &psbt(0xff)),
+                ("punish_sig", &signature(0x55, 0x56)),
+            ],
+        )],
+    }
+}
+
+// Payloads of the framed messages, a handshake, a taker amount, and an abort.
+const HANDSHAKE_PAYLOAD: [(&str, &str); 4] = [
+    ("payload_length", "2c000000"),
+    ("swap_id", SWAP_ID),
+    ("versions", "01000100"),
+    ("features", "0200000000000000"),
+];
+
+const TAKER_AMOUNT_PAYLOAD: [(&str, &str); 4] = [
+    ("payload_length", "4a000000"),
+    ("swap_id", SWAP_ID),
+    ("public_offer_id", PUBLIC_OFFER_ID),
+    ("arbitrating_amount", "0800a086010000000000"),
+];
+
+const ABORT_ERROR_BODY: &str = "011100696e76616c6964207369676e6174757265";
+
+const ABORT_PAYLOAD: [(&str, &str); 3] = [
+    ("payload_length", "34000000"),
+    ("swap_id", SWAP_ID),
+    ("error_body", ABORT_ERROR_BODY),
+];
+
+// Vector of a protocol message, frames end with their extensions.
+fn message_vector(
+    description: &'static str,
+    type_id: &'static str,
+    payload: &[(&'static str, &'static str)],
+    extensions: Option<&'static str>,
+) -> Vector {
+    let mut inputs = vec![("type_id", type_id)];
+    inputs.extend_from_slice(payload);
+    if let Some(extensions) = extensions {
+        inputs.push(("extensions", extensions));
+    }
+    vector(description, &inputs)
+}
+
+fn protocol_message() -> VectorSet {
+    VectorSet {
+        name: "protocol_message",
+        type_name: "ProtocolMessage",
+        roundtrip: roundtrip::<ProtocolMessage<BtcXmr>>,
+        vectors: vec![
+            message_vector("handshake message", "ab83", &HANDSHAKE_PAYLOAD, None),
+            message_vector("taker amount message", "aa83", &TAKER_AMOUNT_PAYLOAD, None),
+        ],
+    }
+}
+
+fn message_frame() -> VectorSet {
+    VectorSet {
+        name: "message_frame",
+        type_name: "MessageFrame",
+        roundtrip: roundtrip::<MessageFrame<BtcXmr>>,
+        vectors: vec![
+            message_vector(
+                "handshake frame without extension",
+                "ab83",
+                &HANDSHAKE_PAYLOAD,
+                Some("0000"),
+            ),
+            message_vector(
+                "taker amount frame without extension",
+                "aa83",
+                &TAKER_AMOUNT_PAYLOAD,
+                Some("0000"),
+            ),
+            message_vector(
+                "abort frame without code",
+                "fe83",
+                &ABORT_PAYLOAD,
+                Some("0000"),
+            ),
+            message_vector(
+                "abort frame with an invalid signature code extension",
+                "fe83",
+                &ABORT_PAYLOAD,
+                Some("0100010002002c01"),
+            ),
+            message_vector(
+                "abort frame with an unregistered code extension",
+                "fe83",
+                &ABORT_PAYLOAD,
+                Some("010001000200e803"),
+            ),
+        ],
+    }
+}
+
+fn abort() -> VectorSet {
+    VectorSet {
+        name: "abort",
+        type_name: "Abort",
+        roundtrip: roundtrip::<Abort>,
+        vectors: vec![
+            vector(
+                "abort without error body",
+                &[("swap_id", SWAP_ID), ("error_body", "00")],
+            ),
+            vector(
+                "abort with an error body",
+                &[("swap_id", SWAP_ID), ("error_body", ABORT_ERROR_BODY)],
+            ),
+            vector(
+                "abort with an invalid signature code",
+                &[
+                    ("swap_id", SWAP_ID),
+                    ("error_body", ABORT_ERROR_BODY),
+                    ("abort_code", "2c01"),
+                ],
+            ),
+        ],
+    }
+}
+
+fn vector_sets() -> Vec<VectorSet> {
+    vec![
+        tx_label(),
+        syncer_task(),
+        syncer_event(),
+        public_offer(),
+        alice_parameters(),
+        bob_parameters(),
+        proof(),
+        legacy_dleq_proof(),
+        cosigned_arbitrating_cancel(),
+        funding_transaction(),
+        core_arbitrating_transactions(),
+        signed_adaptor_buy(),
+        fully_signed_buy(),
+        signed_adaptor_refund(),
+        fully_signed_refund(),
+        signed_arbitrating_lock(),
+        fully_signed_punish(),
+        taker_amount(),
+        handshake(),
+        commit_alice_parameters(),
+        commit_bob_parameters(),
+        reveal_alice_parameters(),
+        salted_reveal_alice_parameters(),
+        reveal_bob_parameters(),
+        salted_reveal_bob_parameters(),
+        reveal_proof(),
+        core_arbitrating_setup(),
+        refund_procedure_signatures(),
+        buy_procedure_signature(),
+        abort(),
+        protocol_message(),
+        message_frame(),
+    ]
+}
+
+#[test]
+#[ignore]
+fn generate_test_vectors() {
+    for set in vector_sets() {
+        let json = serde_json::to_string_pretty(&set.to_json()).unwrap();
+        fs::write(set.path(), json + "\n").unwrap();
+    }
+}
+
+#[test]
+fn check_test_vectors() {
+    let sets = vector_sets();
+    for set in sets.iter() {
+        let generated = set.to_json();
+        let file = fs::read_to_string(set.path()).unwrap_or_else(|e| {
+            panic!(
+                "Missing test vectors {}, run generate_test_vectors: {}",
+                set.path().display(),
+                e
+            )
+        });
+        let file: Value = serde_json::from_str(&file).expect("Valid JSON test vectors");
+        assert_eq!(file["type"], set.type_name);
+        for vector in file["vectors"].as_array().expect("Array of vectors") {
+            let description = vector["description"].as_str().unwrap();
+            let encoded = vector["encoded"].as_str().unwrap();
+            let inputs: String = vector["inputs"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|input| input["hex"].as_str().unwrap())
+                .collect();
+            assert_eq!(inputs, encoded, "{}: {}", set.type_name, description);
+            set.assert_roundtrip(description, encoded);
+        }
+        assert_eq!(
+            file, generated,
+            "{} vectors are out of date, run generate_test_vectors",
+            set.name
+        );
+    }
+
+    // every committed file belongs to a set
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("vectors");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        assert!(
+            sets.iter().any(|set| set.path() == path),
+            "Unknown test vector file {}",
+            path.display()
+        );
+    }
+}
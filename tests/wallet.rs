@@ -5,7 +5,7 @@ use std::str::FromStr;
 
 use farcaster_core::crypto::{
     AccordantKeyId, ArbitratingKeyId, GenerateKey, GenerateSharedKey, ProveCrossGroupDleq,
-    SharedKeyId, Sign,
+    SharedKeyId, Sign, REDACTED,
 };
 use farcaster_core::monero::SHARED_VIEW_KEY_ID;
 use farcaster_core::swap::btcxmr::*;
@@ -240,3 +240,99 @@ fn can_create_accordant_address() {
         accordant_address
     );
 }
+
+#[test]
+fn key_manager_generates_deterministic_proofs() {
+    let seed: [u8; 32] =
+        hex::decode("000102030405060708090a0b0c0d0e0f000102030405060708090a0b0c0d0e0f")
+            .unwrap()
+            .try_into()
+            .unwrap();
+    let mut key_manager = KeyManager::new(seed, 0).expect("Can create a key manager");
+    assert_eq!(
+        hex::encode(key_manager.dleq_proof_rng().get_seed()),
+        "2d7ef5d1e346180ed8ff96c7749c15c8feb783a1316243f4c5ce32b9832e23be"
+    );
+    let (spend, encryption_key, proof) = key_manager.generate_proof().unwrap();
+
+    // re-generating the proof, e.g. after a crash, yields the same proof
+    let mut key_manager_bis = KeyManager::new(seed, 0).expect("Can create a key manager");
+    let (spend_bis, encryption_key_bis, proof_bis) = key_manager_bis.generate_proof().unwrap();
+    assert_eq!(spend, spend_bis);
+    assert_eq!(encryption_key, encryption_key_bis);
+    assert_eq!(proof.as_canonical_bytes(), proof_bis.as_canonical_bytes());
+    assert!(key_manager
+        .verify_proof(&spend, &encryption_key, proof_bis)
+        .is_ok());
+
+    // another swap index yields another random number generator
+    let key_manager_other = KeyManager::new(seed, 1).expect("Can create a key manager");
+    assert_eq!(
+        hex::encode(key_manager_other.dleq_proof_rng().get_seed()),
+        "0998359d21e49ace6a1b294e9f86f004f485b90b586eb0c249d9aaeda2900f57"
+    );
+
+    // an injected random number generator is used instead of the derived one
+    let mut rng = key_manager.dleq_proof_rng();
+    let (_, _, proof_with_rng) = key_manager.generate_proof_with_rng(&mut rng).unwrap();
+    assert_eq!(
+        proof.as_canonical_bytes(),
+        proof_with_rng.as_canonical_bytes()
+    );
+    let (_, _, proof_random) = key_manager
+        .generate_proof_with_rng(&mut rand::thread_rng())
+        .unwrap();
+    assert_ne!(
+        proof.as_canonical_bytes(),
+        proof_random.as_canonical_bytes()
+    );
+    assert!(key_manager
+        .verify_proof(&spend, &encryption_key, proof_random)
+        .is_ok());
+}
+
+#[test]
+fn key_manager_debug_redacts_secrets() {
+    let seed: [u8; 32] =
+        hex::decode("000102030405060708090a0b0c0d0e0f000102030405060708090a0b0c0d0e0f")
+            .unwrap()
+            .try_into()
+            .unwrap();
+    let mut key_manager = KeyManager::new(seed, 0).expect("Can create a key manager");
+    let bitcoin_key = key_manager
+        .get_or_derive_bitcoin_key(ArbitratingKeyId::Lock)
+        .unwrap();
+    let monero_key = key_manager
+        .get_or_derive_monero_key(AccordantKeyId::Spend)
+        .unwrap();
+
+    let debug = format!("{:?}", key_manager);
+    assert!(debug.contains(REDACTED));
+    // derivation paths are public
+    assert!(debug.contains("bitcoin_derivations"));
+    for secret in [
+        format!("{:?}", seed),
+        hex::encode(seed),
+        bitcoin_key.to_string(),
+        monero_key.to_string(),
+        format!("{:?}", monero_key),
+        format!("{:?}", monero_key.to_bytes()),
+    ] {
+        assert!(!debug.contains(&secret), "{} leaks {}", debug, secret);
+    }
+}
+
+#[test]
+#[cfg(feature = "taproot")]
+fn taproot_shared_key_is_redacted_and_zeroized() {
+    use farcaster_core::bitcoin::taproot::SecretSharedKey;
+    use zeroize::Zeroize;
+
+    let mut key = SecretSharedKey::from_canonical_bytes(&[0x42; 32]).unwrap();
+    let debug = format!("{:?}", key);
+    assert!(debug.contains(REDACTED));
+    assert!(!debug.contains("66"));
+
+    key.zeroize();
+    assert_eq!(key.as_bytes(), &[0u8; 32]);
+}